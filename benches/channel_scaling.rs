@@ -0,0 +1,69 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const WIDTH      : i32 = 1024;
+const HEIGHT     : i32 = 1024;
+const ITERATIONS : u32 = 20;
+
+// NOTE(erick): What map_zero_based used to do, kept here to compare.
+fn float_map_zero_based(value: &mut u8, from: u32, to: u32) {
+    if from == to || from == 0 { return; }
+
+    let t = (*value as f32) / from as f32;
+    *value = (to as f32 * t).round() as u8;
+}
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0 .. ITERATIONS {
+        f();
+    }
+
+    start.elapsed() / ITERATIONS
+}
+
+fn report(name: &str, elapsed: Duration) {
+    let n_pixels = (WIDTH * HEIGHT) as f64;
+    println!("{:<32} {:>10.3} ms {:>10.1} Mpixels/s", name,
+             elapsed.as_secs_f64() * 1000.0, n_pixels / elapsed.as_secs_f64() / 1e6);
+}
+
+fn scaling_bench(name: &str, scale: fn(&mut u8, u32, u32)) {
+    let elapsed = time(|| {
+        for index in 0 .. (WIDTH * HEIGHT) as u32 {
+            // NOTE(erick): Three 5 bits channels, like a 16 bits pixel.
+            for channel in 0 .. 3 {
+                let mut value = ((index >> (channel * 5)) & 0x1f) as u8;
+                scale(&mut value, 0x1f, 0xff);
+                black_box(value);
+            }
+        }
+    });
+
+    report(name, elapsed);
+}
+
+fn decode_bench(name: &str, bits_per_pixel: u16, compression: CompressionType) {
+    let mut image = Bitmap::new(WIDTH, HEIGHT, bits_per_pixel, compression);
+    image.map_pixels(|x, y, _| BitmapPixel::rgb(x as u8, y as u8, (x ^ y) as u8));
+    let data = image.into_data();
+
+    let elapsed = time(|| {
+        black_box(Bitmap::from_slice(&data).unwrap());
+    });
+
+    report(name, elapsed);
+}
+
+fn main() {
+    scaling_bench("float channel scaling", float_map_zero_based);
+    scaling_bench("fixed-point channel scaling", map_zero_based);
+
+    decode_bench("decode 16 bits (x1r5g5b5)", 16, CompressionType::Uncompressed);
+    decode_bench("decode 16 bits (bitfields)", 16, CompressionType::BitFields);
+    decode_bench("decode 24 bits", 24, CompressionType::Uncompressed);
+}
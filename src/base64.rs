@@ -0,0 +1,77 @@
+// NOTE(erick): Standard base64 (RFC 4648) with '=' padding. Just enough
+// for data URLs, so we don't need to pull a crate for it.
+const ALPHABET : &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let b0 = chunk[0] as u32;
+        let b1 = chunk.get(1).cloned().unwrap_or(0) as u32;
+        let b2 = chunk.get(2).cloned().unwrap_or(0) as u32;
+        let group = b0 << 16 | b1 << 8 | b2;
+
+        result.push(ALPHABET[(group >> 18) as usize & 0x3f] as char);
+        result.push(ALPHABET[(group >> 12) as usize & 0x3f] as char);
+        if chunk.len() > 1 {
+            result.push(ALPHABET[(group >> 6) as usize & 0x3f] as char);
+        } else {
+            result.push('=');
+        }
+        if chunk.len() > 2 {
+            result.push(ALPHABET[group as usize & 0x3f] as char);
+        } else {
+            result.push('=');
+        }
+    }
+
+    result
+}
+
+fn decode_symbol(symbol: u8) -> Option<u32> {
+    let value = match symbol {
+        b'A' ..= b'Z' => symbol - b'A',
+        b'a' ..= b'z' => symbol - b'a' + 26,
+        b'0' ..= b'9' => symbol - b'0' + 52,
+        b'+'          => 62,
+        b'/'          => 63,
+        _             => return None,
+    };
+
+    Some(value as u32)
+}
+
+// NOTE(erick): Whitespace is skipped, since data URLs are often wrapped.
+// Returns None on any other character outside of the alphabet or on
+// a malformed length/padding.
+pub fn decode(text: &str) -> Option<Vec<u8>> {
+    let symbols : Vec<u8> = text.bytes()
+        .filter(|symbol| !symbol.is_ascii_whitespace())
+        .collect();
+
+    if !symbols.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let mut result = Vec::with_capacity(symbols.len() / 4 * 3);
+    let n_chunks = symbols.len() / 4;
+    for (index, chunk) in symbols.chunks(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|&&symbol| symbol == b'=').count();
+        if padding > 2 || (padding > 0 && index + 1 != n_chunks) {
+            return None;
+        }
+
+        let mut group = 0u32;
+        for &symbol in &chunk[.. 4 - padding] {
+            group = group << 6 | decode_symbol(symbol)?;
+        }
+        group <<= 6 * padding as u32;
+
+        result.push((group >> 16) as u8);
+        if padding < 2 { result.push((group >> 8) as u8); }
+        if padding < 1 { result.push(group as u8); }
+    }
+
+    Some(result)
+}
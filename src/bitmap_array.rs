@@ -0,0 +1,165 @@
+use Bitmap;
+use BitmapResult;
+use BitmapError;
+use BytesWalker;
+use DecodeOptions;
+use FILE_HEADER_SIZE;
+
+use bitmap_write::push_u16;
+use bitmap_write::push_u32;
+
+use std::io::Read;
+use std::io::Write;
+
+const ARRAY_MAGIC_NUMBER : u16 = 0x41_42; // "AB": We are little-endian
+const ARRAY_HEADER_SIZE  : u32 = 14;
+
+// NOTE(erick): Offset of 'pixel_array_offset' inside the file header.
+const PIXEL_ARRAY_OFFSET_FIELD : usize = 10;
+
+// NOTE(erick): OS/2 bitmap array. Every entry is a 14 bytes header
+//   magic_number "BA", header_size, next_offset, display_width, display_height
+// followed by a complete bitmap file whose 'pixel_array_offset' is
+// relative to the start of the whole array file, not to the bitmap.
+// 'next_offset' is zero on the last entry.
+pub struct BitmapArray;
+
+impl BitmapArray {
+    pub fn from_reader<R: Read>(reader: &mut R) -> BitmapResult<Vec<Bitmap>> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        BitmapArray::from_data(&data)
+    }
+
+    pub fn from_data(data: &[u8]) -> BitmapResult<Vec<Bitmap>> {
+        BitmapArray::from_data_with_options(data, &DecodeOptions::default())
+    }
+
+    // NOTE(erick): 'options' apply to each image on its own.
+    pub fn from_data_with_options(data: &[u8],
+                                  options: &DecodeOptions) -> BitmapResult<Vec<Bitmap>> {
+        let mut result = Vec::new();
+        let mut entry_offset = 0usize;
+
+        loop {
+            let bitmap_offset = entry_offset + ARRAY_HEADER_SIZE as usize;
+            if data.len() < bitmap_offset + FILE_HEADER_SIZE as usize {
+                return Err(BitmapError::TruncatedAt(data.len()));
+            }
+
+            let mut walker = BytesWalker::new(&data[entry_offset ..]);
+            let magic_number = walker.next_u16();
+            let _header_size = walker.next_u32();
+            let next_offset  = walker.next_u32() as usize;
+            if magic_number != ARRAY_MAGIC_NUMBER {
+                return Err(BitmapError::InvalidBitmap);
+            }
+
+            // NOTE(erick): Arrays don't nest. Letting them would allow a
+            // crafted file to make us recurse once per entry.
+            if BitmapArray::is_array(&data[bitmap_offset ..]) {
+                return Err(BitmapError::InvalidBitmap);
+            }
+
+            // NOTE(erick): Make the embedded bitmap self-contained by
+            // rebasing its pixel offset.
+            let mut bitmap_data = data[bitmap_offset ..].to_vec();
+            let field = PIXEL_ARRAY_OFFSET_FIELD .. PIXEL_ARRAY_OFFSET_FIELD + 4;
+            let absolute_offset = BytesWalker::new(&bitmap_data[field.clone()]).next_u32() as usize;
+            if absolute_offset < bitmap_offset {
+                return Err(BitmapError::InconsistentField {
+                    name     : "pixel_array_offset",
+                    expected : bitmap_offset as u64,
+                    found    : absolute_offset as u64,
+                });
+            }
+            if absolute_offset > data.len() {
+                return Err(BitmapError::TruncatedAt(data.len()));
+            }
+
+            let relative_offset = (absolute_offset - bitmap_offset) as u32;
+            bitmap_data[field].copy_from_slice(&relative_offset.to_le_bytes());
+
+            // NOTE(erick): What follows the pixels are the other entries,
+            // not a trailer of this bitmap.
+            let mut bitmap = Bitmap::from_data_with_options(bitmap_data, options)?;
+            bitmap.trailer.clear();
+            result.push(bitmap);
+
+            if next_offset == 0 {
+                break;
+            }
+
+            // NOTE(erick): Entries must move forward, otherwise a bad
+            // file could keep us looping forever.
+            if next_offset <= entry_offset {
+                return Err(BitmapError::InvalidBitmap);
+            }
+            entry_offset = next_offset;
+        }
+
+        Ok(result)
+    }
+
+    // NOTE(erick): Entries are written back to back, each bitmap right
+    // after its array header. The display size is left as zero, which
+    // means "any device".
+    pub fn write<W: Write>(images: &[Bitmap], writer: &mut W) -> BitmapResult<()> {
+        writer.write_all(&BitmapArray::into_data(images)?)?;
+        Ok(())
+    }
+
+    pub fn into_data(images: &[Bitmap]) -> BitmapResult<Vec<u8>> {
+        if images.is_empty() {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        let mut result = Vec::new();
+        for (index, image) in images.iter().enumerate() {
+            let entry_offset = result.len();
+            let bitmap_offset = entry_offset + ARRAY_HEADER_SIZE as usize;
+
+            let mut bitmap_data = image.into_data();
+            let field = PIXEL_ARRAY_OFFSET_FIELD .. PIXEL_ARRAY_OFFSET_FIELD + 4;
+            let relative_offset = BytesWalker::new(&bitmap_data[field.clone()]).next_u32();
+            let absolute_offset = relative_offset + bitmap_offset as u32;
+            bitmap_data[field].copy_from_slice(&absolute_offset.to_le_bytes());
+
+            let next_offset = if index + 1 == images.len() {
+                0
+            } else {
+                (bitmap_offset + bitmap_data.len()) as u32
+            };
+
+            push_u16(&mut result, ARRAY_MAGIC_NUMBER);
+            push_u32(&mut result, ARRAY_HEADER_SIZE);
+            push_u32(&mut result, next_offset);
+            push_u16(&mut result, 0);
+            push_u16(&mut result, 0);
+            result.extend_from_slice(&bitmap_data);
+        }
+
+        Ok(result)
+    }
+
+    pub fn is_array(data: &[u8]) -> bool {
+        data.len() >= 2 && BytesWalker::new(data).next_u16() == ARRAY_MAGIC_NUMBER
+    }
+}
+
+impl Bitmap {
+    // NOTE(erick): Every image of an OS/2 bitmap array. A plain bitmap
+    // file is accepted too and gives a single image.
+    pub fn array_from_data(data: &[u8]) -> BitmapResult<Vec<Bitmap>> {
+        if BitmapArray::is_array(data) {
+            BitmapArray::from_data(data)
+        } else {
+            Ok(vec![Bitmap::from_data(data.to_vec())?])
+        }
+    }
+
+    pub fn array_into_data(images: &[Bitmap]) -> BitmapResult<Vec<u8>> {
+        BitmapArray::into_data(images)
+    }
+}
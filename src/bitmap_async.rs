@@ -0,0 +1,149 @@
+use Bitmap;
+use BitmapError;
+use BitmapResult;
+use DecodeOptions;
+
+use read_headers;
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use std::future::Future;
+use std::io::ErrorKind;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+const READ_CHUNK_SIZE : usize = 16 * 1024;
+
+// NOTE(erick): The crate is still on the 2015 edition, which has no
+// async/await, so the futures are written by hand. Both only borrow the
+// reader (or writer), so they are Unpin and can be polled in place.
+struct ReadBitmap<'a, R: 'a> {
+    reader          : &'a mut R,
+    options         : DecodeOptions,
+    data            : Vec<u8>,
+    headers_checked : bool,
+}
+
+impl<'a, R: AsyncRead + Unpin> ReadBitmap<'a, R> {
+    // NOTE(erick): Once the headers arrived we check them against the
+    // limits, so uploads with absurd sizes are rejected before we buffer
+    // the rest of their body.
+    fn check_headers(&mut self) -> BitmapResult<()> {
+        if self.headers_checked || self.data.len() < 2 {
+            return Ok(());
+        }
+
+        // NOTE(erick): Bitmap arrays start with their own header, they
+        // are checked entry by entry once they are complete. Until then
+        // only max_file_size bounds them.
+        if &self.data[.. 2] == b"BA" {
+            self.headers_checked = true;
+            return Ok(());
+        }
+
+        match read_headers(&mut &self.data[..]) {
+            Ok((_, info_header, _)) => {
+                self.options.check_limits(&info_header)?;
+                self.headers_checked = true;
+                Ok(())
+            },
+            Err(BitmapError::BitmapIOError(ref err)) if err.kind() == ErrorKind::UnexpectedEof => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl<'a, R: AsyncRead + Unpin> Future for ReadBitmap<'a, R> {
+    type Output = BitmapResult<Bitmap>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<BitmapResult<Bitmap>> {
+        let this = self.get_mut();
+
+        let mut chunk = [0u8; READ_CHUNK_SIZE];
+        loop {
+            let mut buffer = ReadBuf::new(&mut chunk);
+            match Pin::new(&mut *this.reader).poll_read(cx, &mut buffer) {
+                Poll::Pending         => return Poll::Pending,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(BitmapError::from(err))),
+                Poll::Ready(Ok(()))   => {},
+            }
+
+            if buffer.filled().is_empty() {
+                return Poll::Ready(Bitmap::from_slice_with_options(&this.data, &this.options));
+            }
+
+            this.data.extend_from_slice(buffer.filled());
+            if let Err(err) = this.options.check_file_size(this.data.len() as u64) {
+                return Poll::Ready(Err(err));
+            }
+            if let Err(err) = this.check_headers() {
+                return Poll::Ready(Err(err));
+            }
+        }
+    }
+}
+
+struct WriteBitmap<'a, W: 'a> {
+    writer  : &'a mut W,
+    data    : Vec<u8>,
+    written : usize,
+}
+
+impl<'a, W: AsyncWrite + Unpin> Future for WriteBitmap<'a, W> {
+    type Output = BitmapResult<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<BitmapResult<()>> {
+        let this = self.get_mut();
+
+        while this.written < this.data.len() {
+            match Pin::new(&mut *this.writer).poll_write(cx, &this.data[this.written ..]) {
+                Poll::Pending          => return Poll::Pending,
+                Poll::Ready(Err(err))  => return Poll::Ready(Err(BitmapError::from(err))),
+                Poll::Ready(Ok(0))     => {
+                    return Poll::Ready(Err(BitmapError::from(std::io::Error::from(ErrorKind::WriteZero))));
+                },
+                Poll::Ready(Ok(count)) => this.written += count,
+            }
+        }
+
+        match Pin::new(&mut *this.writer).poll_flush(cx) {
+            Poll::Pending         => Poll::Pending,
+            Poll::Ready(Err(err)) => Poll::Ready(Err(BitmapError::from(err))),
+            Poll::Ready(Ok(()))   => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+impl Bitmap {
+    // NOTE(erick): Like 'from_reader', reading until the end of the
+    // stream (or until we got more than max_file_size bytes) without
+    // blocking the thread.
+    pub fn from_async_reader<'a, R>(reader: &'a mut R)
+                                    -> impl Future<Output = BitmapResult<Bitmap>> + 'a
+        where R: AsyncRead + Unpin {
+        Bitmap::from_async_reader_with_options(reader, &DecodeOptions::default())
+    }
+
+    pub fn from_async_reader_with_options<'a, R>(reader: &'a mut R, options: &DecodeOptions)
+                                                 -> impl Future<Output = BitmapResult<Bitmap>> + 'a
+        where R: AsyncRead + Unpin {
+        ReadBitmap {
+            reader          : reader,
+            options         : options.clone(),
+            data            : Vec::new(),
+            headers_checked : false,
+        }
+    }
+
+    // NOTE(erick): The file is encoded up front (like 'into_file'), then
+    // written and flushed.
+    pub fn write_to_async<'a, W>(&self, writer: &'a mut W)
+                                 -> impl Future<Output = BitmapResult<()>> + 'a
+        where W: AsyncWrite + Unpin {
+        WriteBitmap {
+            writer  : writer,
+            data    : self.into_data(),
+            written : 0,
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use Bitmap;
+use BitmapPixel;
+use BitmapPalette;
+use BitmapResult;
+use BitmapError;
+use CompressionType;
+
+use palette_capacity;
+
+// NOTE(erick): Inches per meter, the headers store the resolution in
+// pixels per meter.
+const INCHES_PER_METER : f64 = 1.0 / 0.0254;
+
+// NOTE(erick): Collects what Bitmap::new and the header fields would be
+// set to. Like BitmapPaletteBuilder nothing is checked until 'build',
+// so the calls can come in any order.
+#[derive(Clone, Debug)]
+pub struct BitmapBuilder {
+    size           : Option<(u32, u32)>,
+    bits_per_pixel : u16,
+    compression    : Option<CompressionType>,
+    palette        : Option<BitmapPalette>,
+    resolution     : (i32, i32),
+    is_top_down    : bool,
+    fill           : BitmapPixel,
+}
+
+impl Default for BitmapBuilder {
+    // NOTE(erick): The same format new_default gives us.
+    fn default() -> BitmapBuilder {
+        BitmapBuilder {
+            size           : None,
+            bits_per_pixel : 32,
+            compression    : None,
+            palette        : None,
+            resolution     : (0, 0),
+            is_top_down    : false,
+            fill           : BitmapPixel::transparent(),
+        }
+    }
+}
+
+impl BitmapBuilder {
+    pub fn new() -> BitmapBuilder {
+        BitmapBuilder::default()
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> BitmapBuilder {
+        self.size = Some((width, height));
+        self
+    }
+
+    pub fn bits_per_pixel(mut self, bits_per_pixel: u16) -> BitmapBuilder {
+        self.bits_per_pixel = bits_per_pixel;
+        self
+    }
+
+    // NOTE(erick): When it is never called 16 and 32 bits images are
+    // BitFields and everything else is Uncompressed.
+    pub fn compression(mut self, compression: CompressionType) -> BitmapBuilder {
+        self.compression = Some(compression);
+        self
+    }
+
+    pub fn palette(mut self, palette: BitmapPalette) -> BitmapBuilder {
+        self.palette = Some(palette);
+        self
+    }
+
+    pub fn dpi(self, dpi: u32) -> BitmapBuilder {
+        self.dpi_xy(dpi, dpi)
+    }
+
+    pub fn dpi_xy(mut self, dpi_x: u32, dpi_y: u32) -> BitmapBuilder {
+        self.resolution = (dpi_to_pixels_per_meter(dpi_x), dpi_to_pixels_per_meter(dpi_y));
+        self
+    }
+
+    pub fn top_down(mut self, is_top_down: bool) -> BitmapBuilder {
+        self.is_top_down = is_top_down;
+        self
+    }
+
+    pub fn fill(mut self, color: BitmapPixel) -> BitmapBuilder {
+        self.fill = color;
+        self
+    }
+
+    // NOTE(erick): Fails when the size is missing or empty, when we can't
+    // write the bit depth and compression combination (RLE included) and
+    // when the palette doesn't fit the bit depth, the bit depth has no
+    // palette at all or needs one and none was given.
+    pub fn build(self) -> BitmapResult<Bitmap> {
+        let (width, height) = match self.size {
+            Some((width, height)) if width > 0 && height > 0 &&
+                width <= i32::MAX as u32 && height <= i32::MAX as u32 => (width, height),
+            _ => return Err(BitmapError::InvalidOperation),
+        };
+        let n_pixels = (width as usize).checked_mul(height as usize)
+            .ok_or(BitmapError::InvalidOperation)?;
+
+        let bits_per_pixel = self.bits_per_pixel;
+        let compression = match self.compression {
+            Some(compression) => compression,
+            None if bits_per_pixel == 16 || bits_per_pixel == 32 => CompressionType::BitFields,
+            None => CompressionType::Uncompressed,
+        };
+
+        match compression {
+            CompressionType::Uncompressed | CompressionType::BitFields => {},
+            _ => return Err(BitmapError::UnsupportedCompressionType(compression)),
+        }
+        let supported = matches!((bits_per_pixel, compression),
+                                 (1 | 2 | 4 | 8 | 16 | 24 | 32, CompressionType::Uncompressed) |
+                                 (16 | 32, CompressionType::BitFields));
+        if !supported {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        if self.palette.is_some() != (palette_capacity(bits_per_pixel) > 0) {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        let mut result = Bitmap::lazy_new(width as i32, height as i32,
+                                          bits_per_pixel, compression);
+        result.info_header.pixels_per_meter_x = self.resolution.0;
+        result.info_header.pixels_per_meter_y = self.resolution.1;
+        result.info_header.is_top_down        = self.is_top_down;
+
+        if let Some(palette) = self.palette {
+            result.set_palette(palette)?;
+        }
+
+        result.image_data = vec![self.fill; n_pixels];
+
+        Ok(result)
+    }
+}
+
+fn dpi_to_pixels_per_meter(dpi: u32) -> i32 {
+    (dpi as f64 * INCHES_PER_METER).round().min(i32::MAX as f64) as i32
+}
+
+impl Bitmap {
+    pub fn builder() -> BitmapBuilder {
+        BitmapBuilder::new()
+    }
+}
@@ -0,0 +1,349 @@
+use Bitmap;
+use BitmapPixel;
+use BitmapResult;
+use BitmapError;
+
+// NOTE(erick): Where the original content goes when the canvas grows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    // NOTE(erick): Offset of the content along each axis given the space
+    // left on it. Centered content gets the extra pixel on the far side.
+    fn offsets(&self, extra_width: u32, extra_height: u32) -> (u32, u32) {
+        let x = match *self {
+            Anchor::TopLeft  | Anchor::Left   | Anchor::BottomLeft  => 0,
+            Anchor::Top      | Anchor::Center | Anchor::Bottom      => extra_width / 2,
+            Anchor::TopRight | Anchor::Right  | Anchor::BottomRight => extra_width,
+        };
+        let y = match *self {
+            Anchor::TopLeft    | Anchor::Top    | Anchor::TopRight    => 0,
+            Anchor::Left       | Anchor::Center | Anchor::Right       => extra_height / 2,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => extra_height,
+        };
+
+        (x, y)
+    }
+}
+
+// NOTE(erick): Where smaller images go along the other axis when merging
+// (across a strip, or inside their cell of a grid).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alignment {
+    Start,
+    Center,
+    End,
+}
+
+impl Alignment {
+    fn offset(&self, extra: u32) -> u32 {
+        match *self {
+            Alignment::Start  => 0,
+            Alignment::Center => extra / 2,
+            Alignment::End    => extra,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeLayout {
+    // NOTE(erick): One row, left to right.
+    Horizontal,
+    // NOTE(erick): One column, top to bottom.
+    Vertical,
+    // NOTE(erick): Rows of this many columns, left to right and top to
+    // bottom. Every cell is as big as the biggest image.
+    Grid(u32),
+}
+
+#[derive(Clone, Debug)]
+pub struct MergeOptions {
+    pub alignment  : Alignment,
+    // NOTE(erick): Color of the area no image covers.
+    pub background : BitmapPixel,
+}
+
+impl Default for MergeOptions {
+    fn default() -> MergeOptions {
+        MergeOptions {
+            alignment  : Alignment::Start,
+            background : BitmapPixel::transparent(),
+        }
+    }
+}
+
+impl Bitmap {
+    // NOTE(erick): The result is a default (32 bits) image, like the
+    // one merge_horizontally and merge_vertically return.
+    pub fn merge(images: &[&Bitmap], layout: MergeLayout,
+                 options: &MergeOptions) -> BitmapResult<Bitmap> {
+        if images.is_empty() || layout == MergeLayout::Grid(0) {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        let sizes: Vec<(u64, u64)> = images.iter()
+            .map(|image| (image.info_header.image_width as u64,
+                          image.info_header.image_height as u64))
+            .collect();
+        let max_width  = sizes.iter().map(|size| size.0).max().unwrap();
+        let max_height = sizes.iter().map(|size| size.1).max().unwrap();
+
+        let (width, height) = match layout {
+            MergeLayout::Horizontal => (sizes.iter().map(|size| size.0).sum(), max_height),
+            MergeLayout::Vertical   => (max_width, sizes.iter().map(|size| size.1).sum()),
+            MergeLayout::Grid(columns) => {
+                let columns = (columns as u64).min(images.len() as u64);
+                let rows = (images.len() as u64).div_ceil(columns);
+                (columns * max_width, rows * max_height)
+            },
+        };
+        if width > i32::MAX as u64 || height > i32::MAX as u64 {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        let mut result = Bitmap::new_default(width as i32, height as i32);
+        result.map_pixels(|_, _, _| options.background);
+
+        let alignment = options.alignment;
+        let mut cursor = 0;
+        for (index, image) in images.iter().enumerate() {
+            let (image_width, image_height) = sizes[index];
+            let (x, y) = match layout {
+                MergeLayout::Horizontal => {
+                    let position = (cursor, alignment.offset((max_height - image_height) as u32) as u64);
+                    cursor += image_width;
+                    position
+                },
+                MergeLayout::Vertical => {
+                    let position = (alignment.offset((max_width - image_width) as u32) as u64, cursor);
+                    cursor += image_height;
+                    position
+                },
+                MergeLayout::Grid(columns) => {
+                    let column = index as u64 % columns as u64;
+                    let row    = index as u64 / columns as u64;
+                    (column * max_width + alignment.offset((max_width - image_width) as u32) as u64,
+                     row * max_height + alignment.offset((max_height - image_height) as u32) as u64)
+                },
+            };
+
+            result.replace_rect_with_rect_from(image,
+                                               0, 0,
+                                               x as u32, y as u32,
+                                               image_width as u32, image_height as u32);
+        }
+
+        Ok(result)
+    }
+
+    pub fn merge_horizontally_with(image0: &Bitmap, image1: &Bitmap,
+                                   options: &MergeOptions) -> Bitmap {
+        Bitmap::merge(&[image0, image1], MergeLayout::Horizontal, options)
+            .expect("Merged images are too wide")
+    }
+
+    pub fn merge_vertically_with(image0: &Bitmap, image1: &Bitmap,
+                                 options: &MergeOptions) -> Bitmap {
+        Bitmap::merge(&[image0, image1], MergeLayout::Vertical, options)
+            .expect("Merged images are too tall")
+    }
+
+    // NOTE(erick): Repeats 'pattern' over the whole image, starting at the
+    // top-left corner.
+    pub fn fill_tiled(&mut self, pattern: &Bitmap) -> BitmapResult<()> {
+        self.fill_tiled_with_offset(pattern, 0, 0)
+    }
+
+    // NOTE(erick): Same as fill_tiled with the pattern shifted by
+    // (offset_x, offset_y), which can be negative or bigger than the
+    // pattern.
+    pub fn fill_tiled_with_offset(&mut self, pattern: &Bitmap,
+                                  offset_x: i32, offset_y: i32) -> BitmapResult<()> {
+        let pattern_width  = pattern.info_header.image_width as i64;
+        let pattern_height = pattern.info_header.image_height as i64;
+        if pattern_width <= 0 || pattern_height <= 0 {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        let mut pattern_pixels = Vec::with_capacity((pattern_width * pattern_height) as usize);
+        for y in 0 .. pattern_height {
+            for x in 0 .. pattern_width {
+                pattern_pixels.push(pattern.get_pixel(x as u32, y as u32));
+            }
+        }
+
+        self.map_pixels(|x, y, _| {
+            let pattern_x = (x as i64 - offset_x as i64).rem_euclid(pattern_width);
+            let pattern_y = (y as i64 - offset_y as i64).rem_euclid(pattern_height);
+
+            pattern_pixels[(pattern_y * pattern_width + pattern_x) as usize]
+        });
+
+        Ok(())
+    }
+
+    // NOTE(erick): A new default (32 bits) image with 'pattern' repeated
+    // all over it.
+    pub fn tiled(pattern: &Bitmap, width: u32, height: u32) -> BitmapResult<Bitmap> {
+        if width > i32::MAX as u32 || height > i32::MAX as u32 {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        let mut result = Bitmap::new_default(width as i32, height as i32);
+        result.fill_tiled(pattern)?;
+
+        Ok(result)
+    }
+
+    // NOTE(erick): Grows the image to 'new_width' x 'new_height' keeping
+    // its format. The new area is filled with 'fill' and the old content
+    // is placed according to 'anchor'.
+    pub fn extend_canvas(&mut self, new_width: u32, new_height: u32,
+                         anchor: Anchor, fill: BitmapPixel) -> BitmapResult<()> {
+        let width  = self.info_header.image_width as u32;
+        let height = self.info_header.image_height as u32;
+        if new_width < width || new_height < height {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        let (offset_x, offset_y) = anchor.offsets(new_width - width, new_height - height);
+        self.place_on_canvas(new_width, new_height, offset_x, offset_y, fill)
+    }
+
+    // NOTE(erick): Adds borders of the given sizes, e.g., to frame an image.
+    pub fn pad(&mut self, left: u32, right: u32, top: u32, bottom: u32,
+               fill: BitmapPixel) -> BitmapResult<()> {
+        let width  = self.info_header.image_width as u64;
+        let height = self.info_header.image_height as u64;
+        let new_width  = width + left as u64 + right as u64;
+        let new_height = height + top as u64 + bottom as u64;
+        if new_width > i32::MAX as u64 || new_height > i32::MAX as u64 {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        self.place_on_canvas(new_width as u32, new_height as u32, left, top, fill)
+    }
+
+    fn place_on_canvas(&mut self, new_width: u32, new_height: u32,
+                       offset_x: u32, offset_y: u32, fill: BitmapPixel) -> BitmapResult<()> {
+        if new_width > i32::MAX as u32 || new_height > i32::MAX as u32 {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        let width  = self.info_header.image_width as u32;
+        let height = self.info_header.image_height as u32;
+
+        let mut screen_pixels = Vec::with_capacity(width as usize * height as usize);
+        for y in 0 .. height {
+            for x in 0 .. width {
+                screen_pixels.push(self.get_pixel(x, y));
+            }
+        }
+
+        // NOTE(erick): Same as resize, the format (and the palette) is
+        // kept. On palettized images the fill color is snapped to the
+        // palette when the image is written.
+        let (file_header, info_header) = self.crop_headers(new_width, new_height);
+        self.file_header = file_header;
+        self.info_header = info_header;
+        self.image_data = vec![fill; new_width as usize * new_height as usize];
+        self.palette_indices.clear();
+
+        let mut pixels_iter = screen_pixels.into_iter();
+        for y in 0 .. height {
+            for x in 0 .. width {
+                let pixel = pixels_iter.next().unwrap();
+                self.set_pixel(offset_x + x, offset_y + y, pixel);
+            }
+        }
+
+        Ok(())
+    }
+
+    // NOTE(erick): Same as crop_to_rect, but the rows are moved down
+    // inside image_data instead of copied to a new image. The orientation
+    // is kept, so the rows we keep are still contiguous in file order.
+    pub fn crop_in_place(&mut self, x0: u32, y0: u32,
+                         width: u32, height: u32) -> BitmapResult<()> {
+        let old_width  = self.info_header.image_width as u32;
+        let old_height = self.info_header.image_height as u32;
+        if x0 as u64 + width as u64 > old_width as u64 ||
+            y0 as u64 + height as u64 > old_height as u64 {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        // NOTE(erick): The first file row we keep. Every row goes to an
+        // index lower than (or equal to) the one it comes from, so copying
+        // them in order never overwrites pixels we still need.
+        let first_row = if self.info_header.is_top_down {
+            y0
+        } else {
+            old_height - y0 - height
+        } as usize;
+
+        let keeps_indices = self.palette_indices.len() == self.image_data.len();
+        for row in 0 .. height as usize {
+            let source = (first_row + row) * old_width as usize + x0 as usize;
+            let target = row * width as usize;
+
+            self.image_data.copy_within(source .. source + width as usize, target);
+            if keeps_indices {
+                self.palette_indices.copy_within(source .. source + width as usize, target);
+            }
+        }
+
+        let n_pixels = width as usize * height as usize;
+        self.image_data.truncate(n_pixels);
+        self.image_data.shrink_to_fit();
+        if keeps_indices {
+            self.palette_indices.truncate(n_pixels);
+            self.palette_indices.shrink_to_fit();
+        }
+
+        let is_top_down = self.info_header.is_top_down;
+        let (file_header, info_header) = self.crop_headers(width, height);
+        self.file_header = file_header;
+        self.info_header = info_header;
+        self.info_header.is_top_down = is_top_down;
+
+        Ok(())
+    }
+
+    // NOTE(erick): Removes the rows and columns around the image whose
+    // pixels are all within 'tolerance' (euclidean RGB distance, like
+    // flood_fill) of 'border_color'. Returns the rectangle that was kept,
+    // or None (leaving the image alone) when every pixel is border.
+    pub fn trim(&mut self, border_color: BitmapPixel,
+                tolerance: u32) -> Option<(u32, u32, u32, u32)> {
+        let width  = self.info_header.image_width as u32;
+        let height = self.info_header.image_height as u32;
+        let max_distance_squared = tolerance.saturating_mul(tolerance);
+        let is_border = |bitmap: &Bitmap, x: u32, y: u32| {
+            bitmap.get_pixel(x, y).distance_squared(&border_color) <= max_distance_squared
+        };
+        let row_is_border = |y: u32| (0 .. width).all(|x| is_border(self, x, y));
+
+        let y0 = (0 .. height).find(|&y| !row_is_border(y))?;
+        let y1 = (0 .. height).rev().find(|&y| !row_is_border(y))?;
+
+        let column_is_border = |x: u32| (y0 ..= y1).all(|y| is_border(self, x, y));
+        let x0 = (0 .. width).find(|&x| !column_is_border(x))?;
+        let x1 = (0 .. width).rev().find(|&x| !column_is_border(x))?;
+
+        let rect = (x0, y0, x1 - x0 + 1, y1 - y0 + 1);
+        self.crop_in_place(rect.0, rect.1, rect.2, rect.3)
+            .expect("The trimmed rectangle is inside the image");
+
+        Some(rect)
+    }
+}
@@ -0,0 +1,191 @@
+use Bitmap;
+use BitmapPixel;
+use BitmapResult;
+use BitmapError;
+
+use std::cmp::max;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Add,
+    Subtract,
+    Darken,
+    Lighten,
+}
+
+// NOTE(erick): How the overlapping columns of a stitch are combined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blend {
+    // NOTE(erick): The second image simply covers the first one.
+    None,
+    // NOTE(erick): Cross-fade from the first to the second image.
+    Linear,
+}
+
+impl BlendMode {
+    // NOTE(erick): The blend function B(src, dest) on a single
+    // channel, both in [0, 255].
+    fn blend_channel(&self, src: u8, dest: u8) -> u8 {
+        let s = src as u32;
+        let d = dest as u32;
+
+        match *self {
+            BlendMode::Normal   => src,
+            BlendMode::Multiply => ((s * d + 127) / 255) as u8,
+            BlendMode::Screen   => (s + d - (s * d + 127) / 255) as u8,
+            BlendMode::Add      => src.saturating_add(dest),
+            BlendMode::Subtract => dest.saturating_sub(src),
+            BlendMode::Darken   => src.min(dest),
+            BlendMode::Lighten  => src.max(dest),
+        }
+    }
+
+    // NOTE(erick): Follows the W3C compositing model: the blended color
+    // replaces the source color where the destination is opaque and
+    // the result is then composited with source_over.
+    pub fn composite(&self, src: &BitmapPixel, dest: &BitmapPixel) -> BitmapPixel {
+        if *self == BlendMode::Normal {
+            return source_over(src, dest);
+        }
+
+        let dest_alpha = dest.alpha as f32 / 255.0;
+        let mix = |s: u8, d: u8| {
+            let blended = self.blend_channel(s, d) as f32;
+            ((1.0 - dest_alpha) * s as f32 + dest_alpha * blended).round() as u8
+        };
+
+        let mixed = BitmapPixel::rgba(mix(src.red, dest.red),
+                                      mix(src.green, dest.green),
+                                      mix(src.blue, dest.blue),
+                                      src.alpha);
+
+        source_over(&mixed, dest)
+    }
+}
+
+// NOTE(erick): Porter-Duff "source over" with straight (not
+// premultiplied) alpha, which is what BitmapPixel stores.
+pub fn source_over(src: &BitmapPixel, dest: &BitmapPixel) -> BitmapPixel {
+    if src.alpha == 0xff { return *src; }
+    if src.alpha == 0x00 { return *dest; }
+
+    let src_alpha  = src.alpha as f32 / 255.0;
+    let dest_alpha = dest.alpha as f32 / 255.0 * (1.0 - src_alpha);
+    let out_alpha  = src_alpha + dest_alpha;
+
+    let blend = |s: u8, d: u8| {
+        ((s as f32 * src_alpha + d as f32 * dest_alpha) / out_alpha).round() as u8
+    };
+
+    BitmapPixel::rgba(blend(src.red, dest.red),
+                      blend(src.green, dest.green),
+                      blend(src.blue, dest.blue),
+                      (out_alpha * 255.0).round() as u8)
+}
+
+// NOTE(erick): Intersects the rectangle of 'other' placed at (dest_x, dest_y)
+// with 'image'. Returns (src_x0, src_y0, dest_x0, dest_y0, width, height)
+// or None when nothing is visible.
+pub fn clip_rect(image: &Bitmap, other: &Bitmap,
+                 dest_x: i32, dest_y: i32) -> Option<(u32, u32, u32, u32, u32, u32)> {
+    let dest_width  = image.info_header.image_width as i64;
+    let dest_height = image.info_header.image_height as i64;
+    let src_width   = other.info_header.image_width as i64;
+    let src_height  = other.info_header.image_height as i64;
+
+    let x0 = (dest_x as i64).max(0);
+    let y0 = (dest_y as i64).max(0);
+    let x1 = (dest_x as i64 + src_width).min(dest_width);
+    let y1 = (dest_y as i64 + src_height).min(dest_height);
+
+    if x0 >= x1 || y0 >= y1 {
+        return None;
+    }
+
+    Some(((x0 - dest_x as i64) as u32, (y0 - dest_y as i64) as u32,
+          x0 as u32, y0 as u32,
+          (x1 - x0) as u32, (y1 - y0) as u32))
+}
+
+impl Bitmap {
+    // NOTE(erick): Composites 'other' on top of this image with its
+    // top-left corner at (dest_x, dest_y). Parts falling outside of
+    // this image are clipped.
+    pub fn overlay(&mut self, other: &Bitmap, dest_x: i32, dest_y: i32) {
+        self.overlay_with_mode(other, dest_x, dest_y, BlendMode::Normal);
+    }
+
+    pub fn overlay_with_mode(&mut self, other: &Bitmap,
+                             dest_x: i32, dest_y: i32, mode: BlendMode) {
+        let (src_x0, src_y0, dest_x0, dest_y0, width, height) =
+            match clip_rect(self, other, dest_x, dest_y) {
+                Some(rect) => rect,
+                None       => return,
+            };
+
+        for y in 0 .. height {
+            for x in 0 .. width {
+                let src  = other.get_pixel(src_x0 + x, src_y0 + y);
+                let dest = self.get_pixel(dest_x0 + x, dest_y0 + y);
+
+                self.set_pixel(dest_x0 + x, dest_y0 + y, mode.composite(&src, &dest));
+            }
+        }
+    }
+
+    // NOTE(erick): Like 'merge_horizontally' but the last 'overlap'
+    // columns of 'image0' and the first ones of 'image1' cover the same
+    // area. Where only one image has pixels (images of different
+    // heights) that image is used as is.
+    pub fn stitch_horizontally(image0: &Bitmap, image1: &Bitmap,
+                               overlap: u32, blend: Blend) -> BitmapResult<Bitmap> {
+        let width0  = image0.info_header.image_width as u32;
+        let height0 = image0.info_header.image_height as u32;
+        let width1  = image1.info_header.image_width as u32;
+        let height1 = image1.info_header.image_height as u32;
+
+        if overlap > width0 || overlap > width1 {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        let result_width  = width0 + width1 - overlap;
+        let result_height = max(height0, height1);
+        let offset1 = width0 - overlap;
+
+        let mut result = Bitmap::new_default(result_width as i32, result_height as i32);
+        for y in 0 .. result_height {
+            for x in 0 .. result_width {
+                let pixel0 = if x < width0 && y < height0 {
+                    Some(image0.get_pixel(x, y))
+                } else {
+                    None
+                };
+                let pixel1 = if x >= offset1 && y < height1 {
+                    Some(image1.get_pixel(x - offset1, y))
+                } else {
+                    None
+                };
+
+                let pixel = match (pixel0, pixel1) {
+                    (Some(p0), Some(p1)) => match blend {
+                        Blend::None   => p1,
+                        Blend::Linear => {
+                            let t = ((x - offset1) as f32 + 0.5) / overlap as f32;
+                            p0.lerp(&p1, t)
+                        },
+                    },
+                    (Some(p0), None) => p0,
+                    (None, Some(p1)) => p1,
+                    (None, None)     => continue,
+                };
+
+                result.set_pixel(x, y, pixel);
+            }
+        }
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,170 @@
+use Bitmap;
+use BitmapPixel;
+use BitmapResult;
+use BitmapError;
+
+// NOTE(erick): How much of the heatmap color covers the original
+// image on pixels that differ.
+const HEATMAP_OPACITY : f32 = 0.6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorMap {
+    Grayscale,
+    // NOTE(erick): black -> red -> yellow -> white
+    Heat,
+    // NOTE(erick): blue -> cyan -> green -> yellow -> red
+    Jet,
+}
+
+impl ColorMap {
+    // NOTE(erick): 't' goes from 0.0 (no difference) to 1.0
+    // (largest difference possible).
+    pub fn color(&self, t: f32) -> BitmapPixel {
+        let t = t.clamp(0.0, 1.0);
+
+        match *self {
+            ColorMap::Grayscale => {
+                let value = (t * 255.0) as u8;
+                BitmapPixel::rgb(value, value, value)
+            },
+            ColorMap::Heat => {
+                let red   = (t * 3.0).min(1.0);
+                let green = (t * 3.0 - 1.0).clamp(0.0, 1.0);
+                let blue  = (t * 3.0 - 2.0).clamp(0.0, 1.0);
+                BitmapPixel::rgb((red * 255.0) as u8,
+                                 (green * 255.0) as u8,
+                                 (blue * 255.0) as u8)
+            },
+            ColorMap::Jet => {
+                let red   = (1.5 - (4.0 * t - 3.0).abs()).clamp(0.0, 1.0);
+                let green = (1.5 - (4.0 * t - 2.0).abs()).clamp(0.0, 1.0);
+                let blue  = (1.5 - (4.0 * t - 1.0).abs()).clamp(0.0, 1.0);
+                BitmapPixel::rgb((red * 255.0) as u8,
+                                 (green * 255.0) as u8,
+                                 (blue * 255.0) as u8)
+            },
+        }
+    }
+}
+
+// NOTE(erick): Euclidean distance over the four channels
+// normalized to [0, 1].
+pub fn difference_magnitude(p0: &BitmapPixel, p1: &BitmapPixel) -> f32 {
+    let alpha_distance = p0.alpha as i32 - p1.alpha as i32;
+    let distance_squared = p0.distance_squared(p1) as f32 +
+        (alpha_distance * alpha_distance) as f32;
+
+    distance_squared.sqrt() / (4.0 * 255.0 * 255.0f32).sqrt()
+}
+
+// NOTE(erick): Summary of Bitmap::diff. Every metric covers the four
+// channels, so a change in alpha alone counts as a difference.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffStats {
+    pub differing_pixels   : u64,
+    pub max_channel_delta  : u8,
+    pub mean_squared_error : f64,
+    // NOTE(erick): In dB, infinite when the images are identical.
+    pub psnr               : f64,
+}
+
+impl DiffStats {
+    pub fn is_identical(&self) -> bool {
+        self.differing_pixels == 0
+    }
+}
+
+pub struct BitmapDiff {
+    // NOTE(erick): Absolute difference of red, green and blue at each
+    // pixel (black where they match). Opaque, so viewers show it as it is.
+    pub image : Bitmap,
+    pub stats : DiffStats,
+}
+
+impl Bitmap {
+    pub fn diff(&self, other: &Bitmap) -> BitmapResult<BitmapDiff> {
+        let width  = self.info_header.image_width;
+        let height = self.info_header.image_height;
+
+        if width != other.info_header.image_width ||
+            height != other.info_header.image_height {
+                return Err(BitmapError::InvalidOperation);
+            }
+
+        let mut image = Bitmap::new_default(width, height);
+        let mut differing_pixels = 0;
+        let mut max_channel_delta = 0;
+        let mut squared_error_sum = 0u64;
+        for y in 0 .. height as u32 {
+            for x in 0 .. width as u32 {
+                let p0 = self.get_pixel(x, y);
+                let p1 = other.get_pixel(x, y);
+                let deltas = [p0.red.abs_diff(p1.red), p0.green.abs_diff(p1.green),
+                              p0.blue.abs_diff(p1.blue), p0.alpha.abs_diff(p1.alpha)];
+
+                if deltas.iter().any(|&delta| delta > 0) {
+                    differing_pixels += 1;
+                }
+                for &delta in &deltas {
+                    max_channel_delta = max_channel_delta.max(delta);
+                    squared_error_sum += delta as u64 * delta as u64;
+                }
+
+                image.set_pixel(x, y, BitmapPixel::rgb(deltas[0], deltas[1], deltas[2]));
+            }
+        }
+
+        let n_samples = width as u64 * height as u64 * 4;
+        let mean_squared_error = if n_samples > 0 {
+            squared_error_sum as f64 / n_samples as f64
+        } else {
+            0.0
+        };
+        let psnr = if mean_squared_error > 0.0 {
+            10.0 * (255.0 * 255.0 / mean_squared_error).log10()
+        } else {
+            f64::INFINITY
+        };
+
+        Ok(BitmapDiff {
+            image : image,
+            stats : DiffStats {
+                differing_pixels   : differing_pixels,
+                max_channel_delta  : max_channel_delta,
+                mean_squared_error : mean_squared_error,
+                psnr               : psnr,
+            },
+        })
+    }
+
+    pub fn diff_heatmap(&self, other: &Bitmap,
+                        color_map: ColorMap) -> BitmapResult<Bitmap> {
+        let width  = self.info_header.image_width;
+        let height = self.info_header.image_height;
+
+        if width != other.info_header.image_width ||
+            height != other.info_header.image_height {
+                return Err(BitmapError::InvalidOperation);
+            }
+
+        let mut result = Bitmap::new_default(width, height);
+        for y in 0 .. height as u32 {
+            for x in 0 .. width as u32 {
+                let original = self.get_pixel(x, y);
+                let magnitude = difference_magnitude(&original,
+                                                     &other.get_pixel(x, y));
+
+                let mut pixel = original;
+                if magnitude > 0.0 {
+                    pixel = original.lerp(&color_map.color(magnitude),
+                                          HEATMAP_OPACITY);
+                    pixel.alpha = original.alpha;
+                }
+
+                result.set_pixel(x, y, pixel);
+            }
+        }
+
+        Ok(result)
+    }
+}
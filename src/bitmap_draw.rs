@@ -0,0 +1,507 @@
+use Bitmap;
+use BitmapPixel;
+
+use bitmap_compose::source_over;
+use bitmap_font::BitmapFont;
+
+// NOTE(erick): f32::fract keeps the sign, we want x - floor(x).
+fn fractional_part(value: f32) -> f32 {
+    value - value.floor()
+}
+
+// NOTE(erick): Liang-Barsky clipping of the segment against the
+// rectangle from 'min' to 'max', both included. Segments that miss the
+// rectangle return None.
+fn clip_segment_f64(x0: f64, y0: f64, x1: f64, y1: f64,
+                    min: (f64, f64), max: (f64, f64)) -> Option<(f64, f64, f64, f64)> {
+    let (x_min, y_min) = min;
+    let (x_max, y_max) = max;
+    let dx = x1 - x0;
+    let dy = y1 - y0;
+    let mut t_enter = 0.0f64;
+    let mut t_exit  = 1.0f64;
+
+    let edges = [(-dx, x0 - x_min),
+                 ( dx, x_max - x0),
+                 (-dy, y0 - y_min),
+                 ( dy, y_max - y0)];
+    for &(p, q) in edges.iter() {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t_enter = t_enter.max(t);
+            } else {
+                t_exit = t_exit.min(t);
+            }
+        }
+    }
+    if t_enter > t_exit {
+        return None;
+    }
+
+    // NOTE(erick): End points that don't move are kept as they are,
+    // x0 + 1.0 * dx is not always exactly x1.
+    let (clipped_x0, clipped_y0) = if t_enter == 0.0 {
+        (x0, y0)
+    } else {
+        (x0 + t_enter * dx, y0 + t_enter * dy)
+    };
+    let (clipped_x1, clipped_y1) = if t_exit == 1.0 {
+        (x1, y1)
+    } else {
+        (x0 + t_exit * dx, y0 + t_exit * dy)
+    };
+
+    Some((clipped_x0, clipped_y0, clipped_x1, clipped_y1))
+}
+
+// NOTE(erick): Clips the segment against the image rectangle. The clipped
+// end points are rounded to the closest pixel, which is at most half a
+// pixel away from the ideal line, the same error Bresenham already has.
+fn clip_segment(x0: i64, y0: i64, x1: i64, y1: i64,
+                width: i64, height: i64) -> Option<(i64, i64, i64, i64)> {
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+
+    let inside = |x: i64, y: i64| x >= 0 && y >= 0 && x < width && y < height;
+    if inside(x0, y0) && inside(x1, y1) {
+        return Some((x0, y0, x1, y1));
+    }
+
+    let (clipped_x0, clipped_y0, clipped_x1, clipped_y1) =
+        clip_segment_f64(x0 as f64, y0 as f64, x1 as f64, y1 as f64,
+                         (0.0, 0.0), ((width - 1) as f64, (height - 1) as f64))?;
+
+    let to_pixel = |x: f64, y: f64| {
+        ((x.round() as i64).clamp(0, width - 1), (y.round() as i64).clamp(0, height - 1))
+    };
+    let (clipped_x0, clipped_y0) = if inside(x0, y0) { (x0, y0) } else { to_pixel(clipped_x0, clipped_y0) };
+    let (clipped_x1, clipped_y1) = if inside(x1, y1) { (x1, y1) } else { to_pixel(clipped_x1, clipped_y1) };
+
+    Some((clipped_x0, clipped_y0, clipped_x1, clipped_y1))
+}
+
+// NOTE(erick): Every primitive takes screen coordinates as i32 so shapes
+// can be partially (or completely) outside of the image. Pixels that
+// fall outside are just skipped.
+impl Bitmap {
+    fn put_pixel_clipped(&mut self, x: i64, y: i64, color: BitmapPixel) {
+        if x < 0 || y < 0 ||
+            x >= self.info_header.image_width as i64 ||
+            y >= self.info_header.image_height as i64 {
+                return;
+            }
+
+        self.set_pixel(x as u32, y as u32, color);
+    }
+
+    // NOTE(erick): 'coverage' is how much of the pixel the shape covers,
+    // from 0.0 to 1.0. It scales the alpha of 'color' before blending.
+    fn blend_pixel_clipped(&mut self, x: i64, y: i64,
+                           color: BitmapPixel, coverage: f32) {
+        if x < 0 || y < 0 ||
+            x >= self.info_header.image_width as i64 ||
+            y >= self.info_header.image_height as i64 {
+                return;
+            }
+
+        let coverage = coverage.clamp(0.0, 1.0);
+        if coverage <= 0.0 {
+            return;
+        }
+
+        let mut src = color;
+        src.alpha = (color.alpha as f32 * coverage).round() as u8;
+
+        let dest = self.get_pixel(x as u32, y as u32);
+        self.set_pixel(x as u32, y as u32, source_over(&src, &dest));
+    }
+
+    fn draw_horizontal_span(&mut self, x0: i64, x1: i64, y: i64, color: BitmapPixel) {
+        if y < 0 || y >= self.info_header.image_height as i64 {
+            return;
+        }
+
+        let x0 = x0.max(0);
+        let x1 = x1.min(self.info_header.image_width as i64 - 1);
+        for x in x0 ..= x1 {
+            self.set_pixel(x as u32, y as u32, color);
+        }
+    }
+
+    // NOTE(erick): Bresenham's line algorithm, both end points included.
+    // End points far outside of the image are moved to the image border
+    // first, so we never walk (or overflow on) the part we can't see.
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32,
+                     color: BitmapPixel) {
+        let (x0, y0, x1, y1) = match clip_segment(x0 as i64, y0 as i64, x1 as i64, y1 as i64,
+                                                  self.info_header.image_width as i64,
+                                                  self.info_header.image_height as i64) {
+            Some(segment) => segment,
+            None => return,
+        };
+
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let step_x = if x0 < x1 { 1 } else { -1 };
+        let step_y = if y0 < y1 { 1 } else { -1 };
+
+        let mut error = dx + dy;
+        let mut x = x0;
+        let mut y = y0;
+
+        loop {
+            self.put_pixel_clipped(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+
+            let error2 = 2 * error;
+            if error2 >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if error2 <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    pub fn draw_rect(&mut self, x: i32, y: i32, width: u32, height: u32,
+                     color: BitmapPixel) {
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        // NOTE(erick): The far edges may not fit in an i32, those are
+        // outside of the image anyway.
+        let x1 = (x as i64 + width as i64 - 1).min(i32::MAX as i64) as i32;
+        let y1 = (y as i64 + height as i64 - 1).min(i32::MAX as i64) as i32;
+        let x1_visible = x1 as i64 == x as i64 + width as i64 - 1;
+        let y1_visible = y1 as i64 == y as i64 + height as i64 - 1;
+
+        self.draw_horizontal_span(x as i64, x1 as i64, y as i64, color);
+        if y1_visible {
+            self.draw_horizontal_span(x as i64, x1 as i64, y1 as i64, color);
+        }
+
+        let span_start = y.saturating_add(1).max(0);
+        let span_end   = y1.min(self.info_header.image_height);
+        for current_y in span_start .. span_end {
+            self.put_pixel_clipped(x as i64, current_y as i64, color);
+            if x1_visible {
+                self.put_pixel_clipped(x1 as i64, current_y as i64, color);
+            }
+        }
+    }
+
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32,
+                     color: BitmapPixel) {
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let x1 = (x as i64 + width as i64 - 1).min(i32::MAX as i64) as i32;
+        let y_start = (y as i64).max(0);
+        let y_end   = (y as i64 + height as i64).min(self.info_header.image_height as i64);
+        for current_y in y_start .. y_end {
+            self.draw_horizontal_span(x as i64, x1 as i64, current_y, color);
+        }
+    }
+
+    // NOTE(erick): Returns false when the bounding box of the ellipse
+    // doesn't touch the image, so there is nothing to draw.
+    fn ellipse_is_visible(&self, center_x: i64, center_y: i64,
+                          rx: i64, ry: i64) -> bool {
+        center_x + rx >= 0 && center_y + ry >= 0 &&
+            center_x - rx < self.info_header.image_width as i64 &&
+            center_y - ry < self.info_header.image_height as i64
+    }
+
+    // NOTE(erick): Midpoint ellipse algorithm. (center_x, center_y) is the
+    // center and radius_x, radius_y are the semi-axes. Coordinates are
+    // i64 so the center plus a radius can't overflow, and the decision
+    // variables are i128 because they grow with the fourth power of the
+    // radii.
+    pub fn draw_ellipse(&mut self, center_x: i32, center_y: i32,
+                        radius_x: u32, radius_y: u32, color: BitmapPixel) {
+        let center_x = center_x as i64;
+        let center_y = center_y as i64;
+        if !self.ellipse_is_visible(center_x, center_y, radius_x as i64, radius_y as i64) {
+            return;
+        }
+
+        let rx = radius_x as i128;
+        let ry = radius_y as i128;
+        let rx2 = rx * rx;
+        let ry2 = ry * ry;
+
+        let plot_symmetric = |bitmap: &mut Bitmap, x: i128, y: i128| {
+            let (x, y) = (x as i64, y as i64);
+            bitmap.put_pixel_clipped(center_x + x, center_y + y, color);
+            bitmap.put_pixel_clipped(center_x - x, center_y + y, color);
+            bitmap.put_pixel_clipped(center_x + x, center_y - y, color);
+            bitmap.put_pixel_clipped(center_x - x, center_y - y, color);
+        };
+
+        let mut x = 0;
+        let mut y = ry;
+
+        // NOTE(erick): Region 1, where the slope is less than one.
+        // Everything is multiplied by 4 to stay in integers.
+        let mut decision = (4 * ry2).saturating_sub((4 * rx2).saturating_mul(ry)) + rx2;
+        while ry2 * x <= rx2 * y {
+            plot_symmetric(self, x, y);
+
+            if decision < 0 {
+                decision = decision.saturating_add(4 * ry2 * (2 * x + 3));
+            } else {
+                decision = decision.saturating_add(4 * ry2 * (2 * x + 3))
+                    .saturating_sub((8 * rx2).saturating_mul(y - 1));
+                y -= 1;
+            }
+            x += 1;
+        }
+
+        // NOTE(erick): Region 2, where the slope is greater than one.
+        let mut decision = ry2.saturating_mul((2 * x + 1) * (2 * x + 1))
+            .saturating_add((4 * rx2).saturating_mul((y - 1) * (y - 1)))
+            .saturating_sub((4 * rx2).saturating_mul(ry2));
+        while y >= 0 {
+            plot_symmetric(self, x, y);
+
+            if decision > 0 {
+                decision = decision.saturating_add((4 * rx2).saturating_mul(3 - 2 * y));
+            } else {
+                decision = decision.saturating_add(8 * ry2 * (x + 1))
+                    .saturating_add((4 * rx2).saturating_mul(3 - 2 * y));
+                x += 1;
+            }
+            y -= 1;
+        }
+    }
+
+    pub fn fill_ellipse(&mut self, center_x: i32, center_y: i32,
+                        radius_x: u32, radius_y: u32, color: BitmapPixel) {
+        let center_x = center_x as i64;
+        let center_y = center_y as i64;
+        if !self.ellipse_is_visible(center_x, center_y, radius_x as i64, radius_y as i64) {
+            return;
+        }
+
+        let rx = radius_x as f64;
+        let ry = radius_y as f64;
+
+        // NOTE(erick): Only the rows inside of the image.
+        let y_start = (center_y - radius_y as i64).max(0);
+        let y_end   = (center_y + radius_y as i64).min(self.info_header.image_height as i64 - 1);
+        for y in y_start ..= y_end {
+            let dy = y - center_y;
+            let half_width = if radius_y == 0 {
+                rx
+            } else {
+                let t = dy as f64 / ry;
+                rx * (1.0 - t * t).max(0.0).sqrt()
+            };
+            let half_width = half_width.round() as i64;
+
+            self.draw_horizontal_span(center_x - half_width, center_x + half_width,
+                                      y, color);
+        }
+    }
+
+    // NOTE(erick): Xiaolin Wu's line algorithm. Each step along the major
+    // axis covers two pixels whose coverage depends on the distance to
+    // the ideal line. End points are at pixel centers. The segment is
+    // clipped first to the image plus a two pixel margin, which is far
+    // enough that the clipped end points don't change the coverage of
+    // any pixel we can see.
+    pub fn draw_line_aa(&mut self, x0: f32, y0: f32, x1: f32, y1: f32,
+                        color: BitmapPixel) {
+        if !(x0.is_finite() && y0.is_finite() && x1.is_finite() && y1.is_finite()) {
+            return;
+        }
+
+        let (x0, y0, x1, y1) =
+            match clip_segment_f64(x0 as f64, y0 as f64, x1 as f64, y1 as f64,
+                                   (-2.0, -2.0),
+                                   (self.info_header.image_width as f64 + 1.0,
+                                    self.info_header.image_height as f64 + 1.0)) {
+                Some((x0, y0, x1, y1)) => (x0 as f32, y0 as f32, x1 as f32, y1 as f32),
+                None => return,
+            };
+
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+
+        let (mut x0, mut y0, mut x1, mut y1) = if steep {
+            (y0, x0, y1, x1)
+        } else {
+            (x0, y0, x1, y1)
+        };
+        if x0 > x1 {
+            std::mem::swap(&mut x0, &mut x1);
+            std::mem::swap(&mut y0, &mut y1);
+        }
+
+        let dx = x1 - x0;
+        let gradient = if dx == 0.0 { 1.0 } else { (y1 - y0) / dx };
+
+        let plot = |bitmap: &mut Bitmap, x: i64, y: i64, coverage: f32| {
+            if steep {
+                bitmap.blend_pixel_clipped(y, x, color, coverage);
+            } else {
+                bitmap.blend_pixel_clipped(x, y, color, coverage);
+            }
+        };
+
+        // NOTE(erick): First end point.
+        let x_end = x0.round();
+        let y_end = y0 + gradient * (x_end - x0);
+        let x_gap = 1.0 - fractional_part(x0 + 0.5);
+        let x_start = x_end as i64;
+        plot(self, x_start, y_end.floor() as i64, (1.0 - fractional_part(y_end)) * x_gap);
+        plot(self, x_start, y_end.floor() as i64 + 1, fractional_part(y_end) * x_gap);
+        let mut intersection_y = y_end + gradient;
+
+        // NOTE(erick): Second end point.
+        let x_end = x1.round();
+        let y_end = y1 + gradient * (x_end - x1);
+        let x_gap = fractional_part(x1 + 0.5);
+        let x_stop = x_end as i64;
+        plot(self, x_stop, y_end.floor() as i64, (1.0 - fractional_part(y_end)) * x_gap);
+        plot(self, x_stop, y_end.floor() as i64 + 1, fractional_part(y_end) * x_gap);
+
+        for x in x_start + 1 .. x_stop {
+            let y = intersection_y.floor() as i64;
+            plot(self, x, y, 1.0 - fractional_part(intersection_y));
+            plot(self, x, y + 1, fractional_part(intersection_y));
+
+            intersection_y += gradient;
+        }
+    }
+
+    // NOTE(erick): A one pixel wide ring. The coverage of each pixel
+    // falls linearly with the distance of its center to the circle.
+    pub fn draw_circle_aa(&mut self, center_x: f32, center_y: f32,
+                          radius: f32, color: BitmapPixel) {
+        let x_min = (center_x - radius - 1.0).floor() as i32;
+        let x_max = (center_x + radius + 1.0).ceil() as i32;
+        let y_min = (center_y - radius - 1.0).floor() as i32;
+        let y_max = (center_y + radius + 1.0).ceil() as i32;
+
+        for y in y_min.max(0) ..= y_max.min(self.info_header.image_height - 1) {
+            for x in x_min.max(0) ..= x_max.min(self.info_header.image_width - 1) {
+                let dx = x as f32 - center_x;
+                let dy = y as f32 - center_y;
+                let distance = (dx * dx + dy * dy).sqrt();
+
+                self.blend_pixel_clipped(x as i64, y as i64, color, 1.0 - (distance - radius).abs());
+            }
+        }
+    }
+
+    // NOTE(erick): Fills the 4-connected region around (x, y) whose colors
+    // are within 'tolerance' (euclidean RGB distance) of the seed color.
+    // Uses an explicit stack of spans so big regions can't overflow the
+    // call stack.
+    pub fn flood_fill(&mut self, x: u32, y: u32,
+                      new_color: BitmapPixel, tolerance: u32) {
+        let width  = self.info_header.image_width as usize;
+        let height = self.info_header.image_height as usize;
+        if x as usize >= width || y as usize >= height {
+            return;
+        }
+
+        let seed = self.get_pixel(x, y);
+        let max_distance_squared = tolerance.saturating_mul(tolerance);
+
+        // NOTE(erick): The new color may itself be similar to the seed,
+        // so we remember which pixels were already filled.
+        let mut filled = vec![false; width * height];
+        let matches = |bitmap: &Bitmap, filled: &Vec<bool>, x: usize, y: usize| {
+            !filled[y * width + x] &&
+                bitmap.get_pixel(x as u32, y as u32)
+                .distance_squared(&seed) <= max_distance_squared
+        };
+
+        let mut stack = vec![(x as usize, y as usize)];
+        while let Some((x, y)) = stack.pop() {
+            if !matches(self, &filled, x, y) {
+                continue;
+            }
+
+            let mut x0 = x;
+            while x0 > 0 && matches(self, &filled, x0 - 1, y) {
+                x0 -= 1;
+            }
+            let mut x1 = x;
+            while x1 + 1 < width && matches(self, &filled, x1 + 1, y) {
+                x1 += 1;
+            }
+
+            for current_x in x0 ..= x1 {
+                filled[y * width + current_x] = true;
+                self.set_pixel(current_x as u32, y as u32, new_color);
+
+                if y > 0 && matches(self, &filled, current_x, y - 1) {
+                    stack.push((current_x, y - 1));
+                }
+                if y + 1 < height && matches(self, &filled, current_x, y + 1) {
+                    stack.push((current_x, y + 1));
+                }
+            }
+        }
+    }
+
+    // NOTE(erick): Draws 'text' with the built-in 8x8 font. (x, y) is the
+    // top-left corner of the first glyph and '\n' starts a new line.
+    pub fn draw_text(&mut self, x: i32, y: i32, text: &str, color: BitmapPixel) {
+        self.draw_text_with_font(x, y, text, color, &BitmapFont::builtin());
+    }
+
+    // NOTE(erick): The pen moves in i64 so text starting anywhere in i32
+    // can't overflow, and we stop once it passes the bottom of the image.
+    pub fn draw_text_with_font(&mut self, x: i32, y: i32, text: &str,
+                               color: BitmapPixel, font: &BitmapFont) {
+        let glyph_width  = font.glyph_width() as i64;
+        let glyph_height = font.glyph_height() as i64;
+        let image_width  = self.info_header.image_width as i64;
+        let image_height = self.info_header.image_height as i64;
+
+        let mut pen_x = x as i64;
+        let mut pen_y = y as i64;
+        for c in text.chars() {
+            if pen_y >= image_height {
+                break;
+            }
+
+            if c == '\n' {
+                pen_x = x as i64;
+                pen_y += glyph_height;
+                continue;
+            }
+
+            // NOTE(erick): Characters the font doesn't have still take
+            // their space so the rest of the line stays aligned.
+            let visible = pen_x < image_width && pen_x + glyph_width > 0 &&
+                pen_y + glyph_height > 0;
+            if let Some(mask) = font.glyph(c).filter(|_| visible) {
+                for glyph_y in 0 .. glyph_height {
+                    for glyph_x in 0 .. glyph_width {
+                        if mask[(glyph_y * glyph_width + glyph_x) as usize] {
+                            self.put_pixel_clipped(pen_x + glyph_x, pen_y + glyph_y, color);
+                        }
+                    }
+                }
+            }
+
+            pen_x += glyph_width;
+        }
+    }
+}
@@ -0,0 +1,88 @@
+// NOTE(erick): Lets embedded-graphics draw into a Bitmap (DrawTarget)
+// and lets a Bitmap be drawn onto any other target (ImageDrawable).
+// Colors are Rgb888, alpha is always opaque.
+
+use Bitmap;
+use BitmapPixel;
+
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{Dimensions, OriginDimensions, Point, Size};
+use embedded_graphics_core::image::{GetPixel, ImageDrawable};
+use embedded_graphics_core::pixelcolor::{Rgb888, RgbColor};
+use embedded_graphics_core::primitives::Rectangle;
+use embedded_graphics_core::Pixel;
+
+use std::convert::Infallible;
+
+fn to_rgb888(pixel: BitmapPixel) -> Rgb888 {
+    Rgb888::new(pixel.red, pixel.green, pixel.blue)
+}
+
+fn from_rgb888(color: Rgb888) -> BitmapPixel {
+    BitmapPixel::rgb(color.r(), color.g(), color.b())
+}
+
+impl OriginDimensions for Bitmap {
+    fn size(&self) -> Size {
+        Size::new(self.info_header.image_width as u32,
+                  self.info_header.image_height as u32)
+    }
+}
+
+impl DrawTarget for Bitmap {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where I: IntoIterator<Item = Pixel<Self::Color>> {
+        let bounding_box = self.bounding_box();
+
+        for Pixel(point, color) in pixels {
+            // NOTE(erick): Pixels outside the image are silently dropped,
+            // as required by DrawTarget.
+            if bounding_box.contains(point) {
+                self.set_pixel(point.x as u32, point.y as u32, from_rgb888(color));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl GetPixel for Bitmap {
+    type Color = Rgb888;
+
+    fn pixel(&self, point: Point) -> Option<Self::Color> {
+        if self.bounding_box().contains(point) {
+            Some(to_rgb888(self.get_pixel(point.x as u32, point.y as u32)))
+        } else {
+            None
+        }
+    }
+}
+
+impl ImageDrawable for Bitmap {
+    type Color = Rgb888;
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+        where D: DrawTarget<Color = Self::Color> {
+        self.draw_sub_image(target, &self.bounding_box())
+    }
+
+    fn draw_sub_image<D>(&self, target: &mut D, area: &Rectangle) -> Result<(), D::Error>
+        where D: DrawTarget<Color = Self::Color> {
+        let visible = area.intersection(&self.bounding_box());
+        if visible.size.width == 0 || visible.size.height == 0 {
+            return Ok(());
+        }
+
+        let colors = visible.rows().flat_map(|y| {
+            visible.columns().map(move |x| {
+                to_rgb888(self.get_pixel(x as u32, y as u32))
+            })
+        });
+
+        let dest_area = Rectangle::new(visible.top_left - area.top_left, visible.size);
+        target.fill_contiguous(&dest_area, colors)
+    }
+}
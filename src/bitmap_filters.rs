@@ -0,0 +1,231 @@
+use Bitmap;
+use BitmapResult;
+use BitmapError;
+
+use bitmap_resize::FloatPixel;
+use bitmap_resize::clamp_channel;
+
+use parallel;
+
+// NOTE(erick): What the kernel sees past the image borders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeMode {
+    // NOTE(erick): Repeats the border pixel.
+    Clamp,
+    // NOTE(erick): Continues from the opposite border (tiling).
+    Wrap,
+    // NOTE(erick): Reflects the image without repeating the border
+    // pixel: ... 2 1 | 0 1 2 ... n-1 | n-2 ...
+    Mirror,
+}
+
+impl EdgeMode {
+    fn resolve(&self, index: i64, size: usize) -> usize {
+        let size = size as i64;
+        let resolved = match *self {
+            EdgeMode::Clamp  => index.clamp(0, size - 1),
+            EdgeMode::Wrap   => index.rem_euclid(size),
+            EdgeMode::Mirror => {
+                if size == 1 {
+                    0
+                } else {
+                    let period = 2 * (size - 1);
+                    let folded = index.rem_euclid(period);
+                    if folded < size { folded } else { period - folded }
+                }
+            },
+        };
+
+        resolved as usize
+    }
+}
+
+// NOTE(erick): Pixels in screen order as floats.
+fn float_pixels(image: &Bitmap) -> Vec<FloatPixel> {
+    let width  = image.info_header.image_width as u32;
+    let height = image.info_header.image_height as u32;
+
+    let mut result = Vec::with_capacity((width * height) as usize);
+    for y in 0 .. height {
+        for x in 0 .. width {
+            result.push(FloatPixel::from_pixel(image.get_pixel(x, y)));
+        }
+    }
+
+    result
+}
+
+// NOTE(erick): Writes the color channels back, alpha is kept.
+fn store_colors(image: &mut Bitmap, pixels: &[FloatPixel]) {
+    let width  = image.info_header.image_width as u32;
+    let height = image.info_header.image_height as u32;
+
+    for y in 0 .. height {
+        for x in 0 .. width {
+            let filtered = pixels[(y * width + x) as usize];
+            let mut pixel = image.get_pixel(x, y);
+
+            pixel.red   = clamp_channel(filtered.red);
+            pixel.green = clamp_channel(filtered.green);
+            pixel.blue  = clamp_channel(filtered.blue);
+            image.set_pixel(x, y, pixel);
+        }
+    }
+}
+
+// NOTE(erick): The kernel is centered on each pixel and applied as
+// written (no flipping), which is what image editors do. Each row only
+// reads 'pixels', so rows are filtered in parallel.
+fn convolve_pixels(pixels: &[FloatPixel], width: usize, height: usize,
+                   kernel: &[f32], kernel_width: usize, kernel_height: usize,
+                   edge: EdgeMode) -> Vec<FloatPixel> {
+    let rows : Vec<i64> = (0 .. height as i64).collect();
+    parallel::map_items(&rows, |&y| {
+        convolve_row(pixels, width, height, y, kernel, kernel_width, kernel_height, edge)
+    }).concat()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn convolve_row(pixels: &[FloatPixel], width: usize, height: usize, y: i64,
+                kernel: &[f32], kernel_width: usize, kernel_height: usize,
+                edge: EdgeMode) -> Vec<FloatPixel> {
+    let half_width  = (kernel_width / 2) as i64;
+    let half_height = (kernel_height / 2) as i64;
+
+    let mut result = Vec::with_capacity(width);
+    for x in 0 .. width as i64 {
+        let mut accum = FloatPixel::default();
+
+        for kernel_y in 0 .. kernel_height {
+            let source_y = edge.resolve(y + kernel_y as i64 - half_height, height);
+            for kernel_x in 0 .. kernel_width {
+                let weight = kernel[kernel_y * kernel_width + kernel_x];
+                if weight == 0.0 {
+                    continue;
+                }
+
+                let source_x = edge.resolve(x + kernel_x as i64 - half_width, width);
+                accum.accumulate(pixels[source_y * width + source_x], weight);
+            }
+        }
+
+        result.push(accum);
+    }
+
+    result
+}
+
+const SHARPEN_KERNEL : [f32; 9] = [ 0.0, -1.0,  0.0,
+                                   -1.0,  5.0, -1.0,
+                                    0.0, -1.0,  0.0];
+
+const EMBOSS_KERNEL  : [f32; 9] = [-2.0, -1.0,  0.0,
+                                   -1.0,  1.0,  1.0,
+                                    0.0,  1.0,  2.0];
+
+const SOBEL_X_KERNEL : [f32; 9] = [-1.0,  0.0,  1.0,
+                                   -2.0,  0.0,  2.0,
+                                   -1.0,  0.0,  1.0];
+
+const SOBEL_Y_KERNEL : [f32; 9] = [-1.0, -2.0, -1.0,
+                                    0.0,  0.0,  0.0,
+                                    1.0,  2.0,  1.0];
+
+impl Bitmap {
+    // NOTE(erick): 'kernel' is row-major with 'kernel_width' *
+    // 'kernel_height' entries and both dimensions must be odd so the
+    // kernel has a center. Only the color channels are filtered.
+    pub fn convolve(&mut self, kernel: &[f32], kernel_width: usize, kernel_height: usize,
+                    edge: EdgeMode) -> BitmapResult<()> {
+        if kernel_width.is_multiple_of(2) || kernel_height.is_multiple_of(2) ||
+            kernel.len() != kernel_width * kernel_height {
+                return Err(BitmapError::InvalidOperation);
+            }
+
+        let width  = self.info_header.image_width as usize;
+        let height = self.info_header.image_height as usize;
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        let filtered = convolve_pixels(&float_pixels(self), width, height,
+                                       kernel, kernel_width, kernel_height, edge);
+        store_colors(self, &filtered);
+
+        Ok(())
+    }
+
+    pub fn box_blur(&mut self, radius: u32) {
+        let size = 2 * radius as usize + 1;
+        let kernel = vec![1.0 / (size * size) as f32; size * size];
+
+        self.convolve(&kernel, size, size, EdgeMode::Clamp).unwrap();
+    }
+
+    // NOTE(erick): The Gaussian is separable, so instead of one k x k
+    // kernel we do a horizontal and a vertical pass with a k wide one.
+    // The kernel is cut at 3 sigma, and at the image size, past which
+    // every tap would read the same clamped border pixels. Non-positive
+    // sigmas do nothing.
+    pub fn gaussian_blur(&mut self, sigma: f32) {
+        let width  = self.info_header.image_width as usize;
+        let height = self.info_header.image_height as usize;
+        if sigma.is_nan() || sigma <= 0.0 || width == 0 || height == 0 {
+            return;
+        }
+
+        let radius = ((3.0 * sigma).ceil() as i64).min(width.max(height) as i64);
+        let mut kernel : Vec<f32> = (-radius ..= radius)
+            .map(|offset| {
+                let offset = offset as f32;
+                (-(offset * offset) / (2.0 * sigma * sigma)).exp()
+            })
+            .collect();
+        let kernel_sum : f32 = kernel.iter().sum();
+        for weight in &mut kernel {
+            *weight /= kernel_sum;
+        }
+
+        let size = kernel.len();
+        let horizontal = convolve_pixels(&float_pixels(self), width, height,
+                                         &kernel, size, 1, EdgeMode::Clamp);
+        let blurred = convolve_pixels(&horizontal, width, height,
+                                      &kernel, 1, size, EdgeMode::Clamp);
+        store_colors(self, &blurred);
+    }
+
+    pub fn sharpen(&mut self) {
+        self.convolve(&SHARPEN_KERNEL, 3, 3, EdgeMode::Clamp).unwrap();
+    }
+
+    pub fn emboss(&mut self) {
+        self.convolve(&EMBOSS_KERNEL, 3, 3, EdgeMode::Clamp).unwrap();
+    }
+
+    // NOTE(erick): Gradient magnitude of the Sobel operator on each color
+    // channel. Flat areas become black and edges bright.
+    pub fn sobel_edges(&mut self) {
+        let width  = self.info_header.image_width as usize;
+        let height = self.info_header.image_height as usize;
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let pixels = float_pixels(self);
+        let gradient_x = convolve_pixels(&pixels, width, height,
+                                         &SOBEL_X_KERNEL, 3, 3, EdgeMode::Clamp);
+        let gradient_y = convolve_pixels(&pixels, width, height,
+                                         &SOBEL_Y_KERNEL, 3, 3, EdgeMode::Clamp);
+
+        let magnitude : Vec<FloatPixel> = gradient_x.iter().zip(&gradient_y)
+            .map(|(gx, gy)| FloatPixel {
+                red   : gx.red.hypot(gy.red),
+                green : gx.green.hypot(gy.green),
+                blue  : gx.blue.hypot(gy.blue),
+                alpha : 0.0,
+            })
+            .collect();
+
+        store_colors(self, &magnitude);
+    }
+}
@@ -0,0 +1,206 @@
+use Bitmap;
+use BitmapResult;
+use BitmapError;
+
+use bitmap_resize::FloatPixel;
+
+// NOTE(erick): Mertens et al. use sigma = 0.2 on [0, 1] intensities.
+const WELL_EXPOSED_SIGMA : f32 = 0.2;
+// NOTE(erick): Keeps the normalization well defined when every
+// measure is zero (flat, fully clipped regions).
+const WEIGHT_EPSILON     : f32 = 1e-6;
+// NOTE(erick): We don't build Laplacian pyramids, so weight maps are
+// smoothed with a box of this radius instead to avoid hard seams.
+const WEIGHT_SMOOTH_RADIUS : i32 = 2;
+// NOTE(erick): Size of the neighbourhood whose Laplacian energy decides
+// which frame is sharpest. Bigger windows are less noisy but blur the
+// transition between frames.
+const FOCUS_WINDOW_RADIUS  : i32 = 3;
+
+// NOTE(erick): All images of a stack must share the same dimensions.
+fn check_stack(images: &[&Bitmap]) -> BitmapResult<(u32, u32)> {
+    let first = match images.first() {
+        Some(first) => first,
+        None        => return Err(BitmapError::InvalidOperation),
+    };
+
+    let width  = first.info_header.image_width;
+    let height = first.info_header.image_height;
+    for image in images {
+        if image.info_header.image_width != width ||
+            image.info_header.image_height != height {
+                return Err(BitmapError::InvalidOperation);
+            }
+    }
+
+    Ok((width as u32, height as u32))
+}
+
+// NOTE(erick): Luma in [0, 1], in screen order.
+fn luma_plane(image: &Bitmap, width: u32, height: u32) -> Vec<f32> {
+    let mut result = Vec::with_capacity((width * height) as usize);
+    for y in 0 .. height {
+        for x in 0 .. width {
+            result.push(image.get_pixel(x, y).luma() as f32 / 255.0);
+        }
+    }
+
+    result
+}
+
+// NOTE(erick): Absolute response of the 4-neighbour Laplacian, with the
+// borders clamped.
+fn laplacian_magnitude(plane: &[f32], width: u32, height: u32) -> Vec<f32> {
+    let w = width as i64;
+    let h = height as i64;
+    let at = |x: i64, y: i64| {
+        plane[(y.clamp(0, h - 1) * w + x.clamp(0, w - 1)) as usize]
+    };
+
+    let mut result = Vec::with_capacity(plane.len());
+    for y in 0 .. h {
+        for x in 0 .. w {
+            let response = at(x - 1, y) + at(x + 1, y) + at(x, y - 1) + at(x, y + 1)
+                - 4.0 * at(x, y);
+            result.push(response.abs());
+        }
+    }
+
+    result
+}
+
+// NOTE(erick): Separable box filter with clamped borders.
+fn box_smooth(plane: &[f32], width: u32, height: u32, radius: i32) -> Vec<f32> {
+    let w = width as i32;
+    let h = height as i32;
+    let window = (2 * radius + 1) as f32;
+
+    let mut horizontal = vec![0.0; plane.len()];
+    for y in 0 .. h {
+        for x in 0 .. w {
+            let mut sum = 0.0;
+            for dx in -radius ..= radius {
+                sum += plane[(y * w + (x + dx).clamp(0, w - 1)) as usize];
+            }
+            horizontal[(y * w + x) as usize] = sum / window;
+        }
+    }
+
+    let mut result = vec![0.0; plane.len()];
+    for y in 0 .. h {
+        for x in 0 .. w {
+            let mut sum = 0.0;
+            for dy in -radius ..= radius {
+                sum += horizontal[((y + dy).clamp(0, h - 1) * w + x) as usize];
+            }
+            result[(y * w + x) as usize] = sum / window;
+        }
+    }
+
+    result
+}
+
+// NOTE(erick): Blends the stack with per-pixel weights. The weights of
+// each pixel are normalized so they add up to one.
+fn weighted_blend(images: &[&Bitmap], weights: &[Vec<f32>],
+                  width: u32, height: u32) -> Bitmap {
+    let mut result = Bitmap::new_default(width as i32, height as i32);
+
+    for y in 0 .. height {
+        for x in 0 .. width {
+            let index = (y * width + x) as usize;
+            let total : f32 = weights.iter().map(|w| w[index]).sum();
+
+            let mut accum = FloatPixel::default();
+            for (image, weight) in images.iter().zip(weights) {
+                let pixel = FloatPixel::from_pixel(image.get_pixel(x, y));
+                accum.accumulate(pixel, weight[index] / total);
+            }
+
+            result.set_pixel(x, y, accum.to_pixel());
+        }
+    }
+
+    result
+}
+
+impl Bitmap {
+    // NOTE(erick): Exposure fusion as described by Mertens, Kautz and
+    // Van Reeth. Each pixel of each capture is weighted by its contrast
+    // (Laplacian), saturation (standard deviation of RGB) and
+    // well-exposedness (closeness to mid-gray), and the captures are
+    // blended with those weights. Instead of pyramid blending we smooth
+    // the weight maps, which is cheaper but can leave mild halos.
+    pub fn exposure_fuse(images: &[&Bitmap]) -> BitmapResult<Bitmap> {
+        let (width, height) = check_stack(images)?;
+
+        let mut weights = Vec::with_capacity(images.len());
+        for image in images {
+            let contrast = laplacian_magnitude(&luma_plane(image, width, height),
+                                               width, height);
+
+            let mut weight = Vec::with_capacity(contrast.len());
+            for y in 0 .. height {
+                for x in 0 .. width {
+                    let pixel = image.get_pixel(x, y);
+                    let r = pixel.red   as f32 / 255.0;
+                    let g = pixel.green as f32 / 255.0;
+                    let b = pixel.blue  as f32 / 255.0;
+
+                    let mean = (r + g + b) / 3.0;
+                    let saturation = (((r - mean).powi(2) +
+                                       (g - mean).powi(2) +
+                                       (b - mean).powi(2)) / 3.0).sqrt();
+
+                    let exposedness = |c: f32| {
+                        (-(c - 0.5).powi(2) /
+                         (2.0 * WELL_EXPOSED_SIGMA * WELL_EXPOSED_SIGMA)).exp()
+                    };
+                    let well_exposed = exposedness(r) * exposedness(g) * exposedness(b);
+
+                    let index = (y * width + x) as usize;
+                    weight.push(contrast[index] * saturation * well_exposed
+                                + WEIGHT_EPSILON);
+                }
+            }
+
+            weights.push(box_smooth(&weight, width, height, WEIGHT_SMOOTH_RADIUS));
+        }
+
+        Ok(weighted_blend(images, &weights, width, height))
+    }
+
+    // NOTE(erick): Picks, for every pixel, the frame with the highest
+    // local contrast (Laplacian energy averaged over a small window).
+    // Ties go to the first frame.
+    pub fn focus_stack(images: &[&Bitmap]) -> BitmapResult<Bitmap> {
+        let (width, height) = check_stack(images)?;
+
+        let sharpness : Vec<Vec<f32>> = images.iter()
+            .map(|image| {
+                let contrast = laplacian_magnitude(&luma_plane(image, width, height),
+                                                   width, height);
+                let energy : Vec<f32> = contrast.iter().map(|c| c * c).collect();
+                box_smooth(&energy, width, height, FOCUS_WINDOW_RADIUS)
+            })
+            .collect();
+
+        let mut result = Bitmap::new_default(width as i32, height as i32);
+        for y in 0 .. height {
+            for x in 0 .. width {
+                let index = (y * width + x) as usize;
+
+                let mut sharpest = 0;
+                for frame in 1 .. images.len() {
+                    if sharpness[frame][index] > sharpness[sharpest][index] {
+                        sharpest = frame;
+                    }
+                }
+
+                result.set_pixel(x, y, images[sharpest].get_pixel(x, y));
+            }
+        }
+
+        Ok(result)
+    }
+}
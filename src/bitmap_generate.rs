@@ -0,0 +1,172 @@
+use Bitmap;
+use BitmapPixel;
+use BitmapResult;
+use BitmapError;
+
+// NOTE(erick): Generated images are default (32 bits) images, so the
+// colors (and their alpha) are kept exactly.
+fn new_generated(width: u32, height: u32) -> BitmapResult<Bitmap> {
+    if width > i32::MAX as u32 || height > i32::MAX as u32 {
+        return Err(BitmapError::InvalidOperation);
+    }
+
+    Ok(Bitmap::new_default(width as i32, height as i32))
+}
+
+// NOTE(erick): SplitMix64. We don't use the rand crate for seeded output
+// because its streams are allowed to change between versions, and noise
+// images are used as fixtures that must stay the same forever.
+struct SplitMix64 {
+    state : u64,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> SplitMix64 {
+        SplitMix64 { state : seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+// NOTE(erick): Value in [0, 1] of the lattice point (x, y), the same
+// every time for a given seed.
+#[cfg(feature = "noise")]
+fn lattice_value(seed: u64, x: i64, y: i64) -> f32 {
+    let hash = SplitMix64::new(seed ^
+                               (x as u64).wrapping_mul(0x9e3779b97f4a7c15) ^
+                               (y as u64).wrapping_mul(0xc2b2ae3d27d4eb4f)).next_u64();
+
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+#[cfg(feature = "noise")]
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+// NOTE(erick): Value noise at (x, y) for a lattice of 'cell_size' pixels,
+// in [0, 1].
+#[cfg(feature = "noise")]
+fn value_noise_at(seed: u64, x: f32, y: f32, cell_size: f32) -> f32 {
+    let x = x / cell_size;
+    let y = y / cell_size;
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let tx = smoothstep(x - x0);
+    let ty = smoothstep(y - y0);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+
+    let top    = lattice_value(seed, x0, y0) * (1.0 - tx) + lattice_value(seed, x0 + 1, y0) * tx;
+    let bottom = lattice_value(seed, x0, y0 + 1) * (1.0 - tx) + lattice_value(seed, x0 + 1, y0 + 1) * tx;
+
+    top * (1.0 - ty) + bottom * ty
+}
+
+impl Bitmap {
+    // NOTE(erick): Uniform, opaque RGB noise. The same seed always gives
+    // the same image (pixels are generated top to bottom, left to right).
+    pub fn noise(width: u32, height: u32, seed: u64) -> BitmapResult<Bitmap> {
+        let mut result = new_generated(width, height)?;
+
+        let mut generator = SplitMix64::new(seed);
+        result.map_pixels(|_, _, _| {
+            let value = generator.next_u64();
+            BitmapPixel::rgb(value as u8, (value >> 8) as u8, (value >> 16) as u8)
+        });
+
+        Ok(result)
+    }
+
+    // NOTE(erick): Smooth gray value noise with features about 'cell_size'
+    // pixels big. Each extra octave adds details half the size with half
+    // the weight. Like 'noise', it only depends on the seed.
+    #[cfg(feature = "noise")]
+    pub fn value_noise(width: u32, height: u32, seed: u64,
+                       cell_size: f32, octaves: u32) -> BitmapResult<Bitmap> {
+        if cell_size <= 0.0 || octaves == 0 {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        let mut result = new_generated(width, height)?;
+        result.map_pixels(|x, y, _| {
+            let mut value = 0.0;
+            let mut weight = 1.0;
+            let mut total_weight = 0.0;
+            let mut octave_cell_size = cell_size;
+            for octave in 0 .. octaves as u64 {
+                let octave_seed = seed.wrapping_add(octave.wrapping_mul(0x632be59bd9b4e019));
+                value += value_noise_at(octave_seed, x as f32, y as f32, octave_cell_size) * weight;
+                total_weight += weight;
+                weight *= 0.5;
+                octave_cell_size = (octave_cell_size * 0.5).max(1.0);
+            }
+
+            let gray = (value / total_weight * 255.0).round().clamp(0.0, 255.0) as u8;
+            BitmapPixel::rgb(gray, gray, gray)
+        });
+
+        Ok(result)
+    }
+
+    // NOTE(erick): 'angle' is in degrees, clockwise on the screen: 0 goes
+    // from left (start_color) to right (end_color), 90 from top to bottom.
+    // The gradient spans the whole image along that direction, so the
+    // first and last pixels get the exact colors.
+    pub fn linear_gradient(width: u32, height: u32,
+                           start_color: BitmapPixel, end_color: BitmapPixel,
+                           angle: f32) -> BitmapResult<Bitmap> {
+        let mut result = new_generated(width, height)?;
+
+        let (direction_y, direction_x) = angle.to_radians().sin_cos();
+        let project = |x: f32, y: f32| x * direction_x + y * direction_y;
+
+        // NOTE(erick): The centers of the corner pixels are the ends of
+        // the gradient.
+        let last_x = width.saturating_sub(1) as f32;
+        let last_y = height.saturating_sub(1) as f32;
+        let corners = [project(0.0, 0.0), project(last_x, 0.0),
+                       project(0.0, last_y), project(last_x, last_y)];
+        let min = corners.iter().cloned().fold(f32::INFINITY, f32::min);
+        let max = corners.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let span = max - min;
+
+        result.map_pixels(|x, y, _| {
+            let t = if span > f32::EPSILON {
+                (project(x as f32, y as f32) - min) / span
+            } else {
+                0.0
+            };
+
+            start_color.lerp(&end_color, t.clamp(0.0, 1.0))
+        });
+
+        Ok(result)
+    }
+
+    // NOTE(erick): 'inner_color' at (center_x, center_y), fading to
+    // 'outer_color' at 'radius' pixels from it and beyond.
+    pub fn radial_gradient(width: u32, height: u32,
+                           inner_color: BitmapPixel, outer_color: BitmapPixel,
+                           center_x: f32, center_y: f32,
+                           radius: f32) -> BitmapResult<Bitmap> {
+        let mut result = new_generated(width, height)?;
+
+        result.map_pixels(|x, y, _| {
+            let dx = x as f32 - center_x;
+            let dy = y as f32 - center_y;
+            let distance = (dx * dx + dy * dy).sqrt();
+            let t = if radius > 0.0 { distance / radius } else { 1.0 };
+
+            inner_color.lerp(&outer_color, t.clamp(0.0, 1.0))
+        });
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,101 @@
+use Bitmap;
+
+// NOTE(erick): Tiles of a sprite sheet, left to right and top to bottom.
+// The first tile starts 'margin' pixels from the top-left corner and the
+// tiles are 'spacing' pixels apart. Tiles that don't fit completely are
+// left out.
+pub struct GridTiles<'a> {
+    bitmap      : &'a Bitmap,
+    tile_width  : u32,
+    tile_height : u32,
+    spacing     : u32,
+    margin      : u32,
+    columns     : u32,
+    rows        : u32,
+    next        : u64,
+}
+
+fn tiles_along(size: u32, tile_size: u32, spacing: u32, margin: u32) -> u32 {
+    if tile_size == 0 {
+        return 0;
+    }
+
+    let available = size.saturating_sub(margin) as u64;
+    ((available + spacing as u64) / (tile_size as u64 + spacing as u64)) as u32
+}
+
+impl<'a> GridTiles<'a> {
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    fn n_tiles(&self) -> u64 {
+        self.columns as u64 * self.rows as u64
+    }
+}
+
+impl<'a> Iterator for GridTiles<'a> {
+    type Item = Bitmap;
+
+    fn next(&mut self) -> Option<Bitmap> {
+        if self.next >= self.n_tiles() {
+            return None;
+        }
+
+        let column = self.next % self.columns as u64;
+        let row    = self.next / self.columns as u64;
+        self.next += 1;
+
+        // NOTE(erick): Like in tiles_along the steps can overflow a u32
+        // even though the positions of the tiles that fit never do.
+        let x = self.margin as u64 + column * (self.tile_width as u64 + self.spacing as u64);
+        let y = self.margin as u64 + row * (self.tile_height as u64 + self.spacing as u64);
+
+        Some(self.bitmap.cropped(x as u32, y as u32, self.tile_width, self.tile_height))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.n_tiles() - self.next) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for GridTiles<'a> {}
+
+impl Bitmap {
+    // NOTE(erick): Slices a sprite sheet into tiles, which keep the
+    // sheet's format like crop_to_rect does.
+    pub fn split_grid(&self, tile_width: u32, tile_height: u32,
+                      spacing: u32, margin: u32) -> Vec<Bitmap> {
+        self.grid_tiles(tile_width, tile_height, spacing, margin).collect()
+    }
+
+    // NOTE(erick): Same as split_grid, one tile at a time.
+    pub fn grid_tiles(&self, tile_width: u32, tile_height: u32,
+                      spacing: u32, margin: u32) -> GridTiles<'_> {
+        let width  = self.info_header.image_width as u32;
+        let height = self.info_header.image_height as u32;
+
+        let mut columns = tiles_along(width, tile_width, spacing, margin);
+        let mut rows    = tiles_along(height, tile_height, spacing, margin);
+        if columns == 0 || rows == 0 {
+            columns = 0;
+            rows    = 0;
+        }
+
+        GridTiles {
+            bitmap      : self,
+            tile_width  : tile_width,
+            tile_height : tile_height,
+            spacing     : spacing,
+            margin      : margin,
+            columns     : columns,
+            rows        : rows,
+            next        : 0,
+        }
+    }
+}
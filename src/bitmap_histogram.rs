@@ -0,0 +1,78 @@
+use Bitmap;
+use BitmapPixel;
+
+const N_LEVELS : usize = 256;
+
+fn channel_histogram<F>(data: &[BitmapPixel], channel: F) -> [u64; N_LEVELS]
+    where F: Fn(&BitmapPixel) -> u8 {
+    let mut histogram = [0; N_LEVELS];
+    for pixel in data {
+        histogram[channel(pixel) as usize] += 1;
+    }
+
+    histogram
+}
+
+fn cumulative(histogram: &[u64; N_LEVELS]) -> [u64; N_LEVELS] {
+    let mut result = [0; N_LEVELS];
+    let mut accum = 0;
+    for level in 0 .. N_LEVELS {
+        accum += histogram[level];
+        result[level] = accum;
+    }
+
+    result
+}
+
+// NOTE(erick): Maps each level of 'source' to the first level of
+// 'reference' whose normalized cumulative count reaches the source one.
+// The CDFs are compared with cross multiplication so we don't need floats.
+fn matching_table<F>(source: &[BitmapPixel], reference: &[BitmapPixel],
+                     channel: F) -> [u8; N_LEVELS]
+    where F: Fn(&BitmapPixel) -> u8 {
+    let source_cdf    = cumulative(&channel_histogram(source, &channel));
+    let reference_cdf = cumulative(&channel_histogram(reference, &channel));
+
+    let source_total    = source.len() as u64;
+    let reference_total = reference.len() as u64;
+
+    let mut table = [0; N_LEVELS];
+    let mut reference_level = 0;
+    for level in 0 .. N_LEVELS {
+        // NOTE(erick): Both CDFs are monotonic, so the matched level
+        // never goes back.
+        while reference_level < N_LEVELS - 1 &&
+            reference_cdf[reference_level] * source_total <
+            source_cdf[level] * reference_total {
+                reference_level += 1;
+            }
+
+        table[level] = reference_level as u8;
+    }
+
+    table
+}
+
+impl Bitmap {
+    // NOTE(erick): Classic histogram specification done independently on
+    // red, green and blue. Alpha is left untouched. The images don't need
+    // to have the same dimensions.
+    pub fn match_histogram(&mut self, reference: &Bitmap) {
+        if self.image_data.is_empty() || reference.image_data.is_empty() {
+            return;
+        }
+
+        let red_table   = matching_table(&self.image_data, &reference.image_data,
+                                         |p| p.red);
+        let green_table = matching_table(&self.image_data, &reference.image_data,
+                                         |p| p.green);
+        let blue_table  = matching_table(&self.image_data, &reference.image_data,
+                                         |p| p.blue);
+
+        for pixel in &mut self.image_data {
+            pixel.red   = red_table[pixel.red as usize];
+            pixel.green = green_table[pixel.green as usize];
+            pixel.blue  = blue_table[pixel.blue as usize];
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use Bitmap;
+use BitmapResult;
+use BitmapError;
+
+use std::fs::File;
+use std::io::Read;
+
+// NOTE(erick): A 3D color lookup table with 'size' samples per axis.
+// Entries are RGB triples in [0, 1] stored with red changing fastest,
+// which is the order used by both .cube files and Hald CLUT images.
+// The domain is the range of input values the cube spans, per channel.
+#[derive(Clone, Debug)]
+pub struct Lut3d {
+    size       : usize,
+    table      : Vec<[f32; 3]>,
+    domain_min : [f32; 3],
+    domain_max : [f32; 3],
+}
+
+impl Lut3d {
+    pub fn new(size: usize, table: Vec<[f32; 3]>) -> BitmapResult<Lut3d> {
+        let n_entries = n_entries(size)?;
+        if size < 2 || table.len() != n_entries {
+            return Err(BitmapError::InvalidLut(
+                format!("expected {} entries, got {}", n_entries, table.len())));
+        }
+
+        Ok(Lut3d {
+            size       : size,
+            table      : table,
+            domain_min : [0.0; 3],
+            domain_max : [1.0; 3],
+        })
+    }
+
+    pub fn with_domain(mut self, domain_min: [f32; 3],
+                       domain_max: [f32; 3]) -> BitmapResult<Lut3d> {
+        for channel in 0 .. 3 {
+            let range = domain_max[channel] - domain_min[channel];
+            if range <= 0.0 || !range.is_finite() {
+                return Err(BitmapError::InvalidLut(
+                    format!("invalid domain {:?} to {:?}", domain_min, domain_max)));
+            }
+        }
+
+        self.domain_min = domain_min;
+        self.domain_max = domain_max;
+        Ok(self)
+    }
+
+    pub fn identity(size: usize) -> BitmapResult<Lut3d> {
+        let mut table = Vec::with_capacity(n_entries(size)?);
+        let max = (size.max(2) - 1) as f32;
+        for b in 0 .. size {
+            for g in 0 .. size {
+                for r in 0 .. size {
+                    table.push([r as f32 / max, g as f32 / max, b as f32 / max]);
+                }
+            }
+        }
+
+        Lut3d::new(size, table)
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn domain(&self) -> ([f32; 3], [f32; 3]) {
+        (self.domain_min, self.domain_max)
+    }
+
+    // NOTE(erick): Adobe .cube text format. Only 3D tables are supported.
+    pub fn from_cube_str(text: &str) -> BitmapResult<Lut3d> {
+        let mut size = 0;
+        let mut domain_min = [0.0f32; 3];
+        let mut domain_max = [1.0f32; 3];
+        let mut table = Vec::new();
+
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid_line = || BitmapError::InvalidLut(
+                format!("invalid line {}: {}", line_index + 1, line));
+
+            let mut tokens = line.split_whitespace();
+            let keyword = tokens.next().unwrap();
+            let parse_triple = |tokens: &mut ::std::str::SplitWhitespace| -> BitmapResult<[f32; 3]> {
+                let mut result = [0.0; 3];
+                for value in &mut result {
+                    *value = tokens.next()
+                        .and_then(|token| token.parse().ok())
+                        .ok_or_else(invalid_line)?;
+                }
+                Ok(result)
+            };
+
+            match keyword {
+                "TITLE" => {},
+                "LUT_1D_SIZE" => {
+                    return Err(BitmapError::InvalidLut(
+                        "1D LUTs are not supported".to_string()));
+                },
+                "LUT_3D_SIZE" => {
+                    size = tokens.next()
+                        .and_then(|token| token.parse().ok())
+                        .ok_or_else(invalid_line)?;
+                },
+                "DOMAIN_MIN" => domain_min = parse_triple(&mut tokens)?,
+                "DOMAIN_MAX" => domain_max = parse_triple(&mut tokens)?,
+                // NOTE(erick): Same as DOMAIN_MIN and DOMAIN_MAX with
+                // every channel sharing the range.
+                "LUT_3D_INPUT_RANGE" => {
+                    let mut range = [0.0f32; 2];
+                    for value in &mut range {
+                        *value = tokens.next()
+                            .and_then(|token| token.parse().ok())
+                            .ok_or_else(invalid_line)?;
+                    }
+                    domain_min = [range[0]; 3];
+                    domain_max = [range[1]; 3];
+                },
+                _ => {
+                    let mut tokens = line.split_whitespace();
+                    table.push(parse_triple(&mut tokens)?);
+                },
+            }
+        }
+
+        // NOTE(erick): The domain only changes how inputs are mapped to
+        // the cube, the entries themselves are the output colors.
+        Lut3d::new(size, table)?.with_domain(domain_min, domain_max)
+    }
+
+    pub fn from_cube_file(file: &mut File) -> BitmapResult<Lut3d> {
+        let mut text = String::new();
+        file.read_to_string(&mut text)?;
+
+        Lut3d::from_cube_str(&text)
+    }
+
+    // NOTE(erick): A Hald CLUT of level L is an L^3 x L^3 image holding
+    // a cube with L^2 samples per axis, read left to right, top to bottom.
+    pub fn from_hald(image: &Bitmap) -> BitmapResult<Lut3d> {
+        let width  = image.info_header.image_width as usize;
+        let height = image.info_header.image_height as usize;
+
+        let level = (1 ..= 16).find(|level| level * level * level == width);
+        let level = match level {
+            Some(level) if width == height => level,
+            _ => {
+                return Err(BitmapError::InvalidLut(
+                    format!("{}x{} is not a Hald CLUT size", width, height)));
+            },
+        };
+
+        let mut table = Vec::with_capacity(width * height);
+        for y in 0 .. height as u32 {
+            for x in 0 .. width as u32 {
+                let pixel = image.get_pixel(x, y);
+                table.push([pixel.red   as f32 / 255.0,
+                            pixel.green as f32 / 255.0,
+                            pixel.blue  as f32 / 255.0]);
+            }
+        }
+
+        Lut3d::new(level * level, table)
+    }
+
+    fn entry(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        self.table[(b * self.size + g) * self.size + r]
+    }
+
+    // NOTE(erick): Trilinear interpolation. Inputs are in the domain of
+    // the table, which is [0, 1] unless it says otherwise, and are
+    // clamped to it.
+    pub fn lookup(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let max_index = (self.size - 1) as f32;
+
+        let mut base = [0; 3];
+        let mut t = [0.0f32; 3];
+        for channel in 0 .. 3 {
+            let range = self.domain_max[channel] - self.domain_min[channel];
+            let normalized = (rgb[channel] - self.domain_min[channel]) / range;
+            let position = normalized.clamp(0.0, 1.0) * max_index;
+            let index = (position.floor() as usize).min(self.size - 2);
+            base[channel] = index;
+            t[channel] = position - index as f32;
+        }
+
+        let mut result = [0.0f32; 3];
+        for corner in 0 .. 8 {
+            let dr = corner & 1;
+            let dg = (corner >> 1) & 1;
+            let db = (corner >> 2) & 1;
+
+            let weight = (if dr == 1 { t[0] } else { 1.0 - t[0] }) *
+                (if dg == 1 { t[1] } else { 1.0 - t[1] }) *
+                (if db == 1 { t[2] } else { 1.0 - t[2] });
+            if weight == 0.0 {
+                continue;
+            }
+
+            let entry = self.entry(base[0] + dr, base[1] + dg, base[2] + db);
+            for channel in 0 .. 3 {
+                result[channel] += entry[channel] * weight;
+            }
+        }
+
+        result
+    }
+}
+
+// NOTE(erick): size^3, which overflows for sizes no file should have.
+fn n_entries(size: usize) -> BitmapResult<usize> {
+    size.checked_mul(size)
+        .and_then(|square| square.checked_mul(size))
+        .ok_or_else(|| BitmapError::InvalidLut(format!("{} samples per axis is too many", size)))
+}
+
+impl Bitmap {
+    pub fn apply_lut3d(&mut self, lut: &Lut3d) {
+        let to_channel = |value: f32| (value * 255.0).round().clamp(0.0, 255.0) as u8;
+
+        for pixel in &mut self.image_data {
+            let rgb = lut.lookup([pixel.red   as f32 / 255.0,
+                                  pixel.green as f32 / 255.0,
+                                  pixel.blue  as f32 / 255.0]);
+
+            pixel.red   = to_channel(rgb[0]);
+            pixel.green = to_channel(rgb[1]);
+            pixel.blue  = to_channel(rgb[2]);
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use Bitmap;
+use BitmapPixel;
+
+// NOTE(erick): Pixels in screen order, so the inner loops don't need
+// to go through 'get_pixel'.
+fn screen_pixels(image: &Bitmap) -> Vec<BitmapPixel> {
+    let width  = image.info_header.image_width as u32;
+    let height = image.info_header.image_height as u32;
+
+    let mut result = Vec::with_capacity((width * height) as usize);
+    for y in 0 .. height {
+        for x in 0 .. width {
+            result.push(image.get_pixel(x, y));
+        }
+    }
+
+    result
+}
+
+fn absolute_difference(p0: &BitmapPixel, p1: &BitmapPixel) -> u64 {
+    (p0.red   as i32 - p1.red   as i32).unsigned_abs() as u64 +
+    (p0.green as i32 - p1.green as i32).unsigned_abs() as u64 +
+    (p0.blue  as i32 - p1.blue  as i32).unsigned_abs() as u64
+}
+
+// NOTE(erick): Per channel sums of every rectangle starting at the top
+// left corner, with an extra row and column of zeros so window sums
+// don't need to special case the borders.
+struct SummedAreaTable {
+    stride : usize,
+    sums   : Vec<[u64; 3]>,
+}
+
+impl SummedAreaTable {
+    fn new(pixels: &[BitmapPixel], width: usize, height: usize) -> SummedAreaTable {
+        let stride = width + 1;
+        let mut sums = vec![[0u64; 3]; stride * (height + 1)];
+
+        for y in 0 .. height {
+            let mut row = [0u64; 3];
+            for x in 0 .. width {
+                let pixel = &pixels[y * width + x];
+                row[0] += pixel.red   as u64;
+                row[1] += pixel.green as u64;
+                row[2] += pixel.blue  as u64;
+
+                let above = sums[y * stride + x + 1];
+                sums[(y + 1) * stride + x + 1] = [above[0] + row[0],
+                                                  above[1] + row[1],
+                                                  above[2] + row[2]];
+            }
+        }
+
+        SummedAreaTable {
+            stride : stride,
+            sums   : sums,
+        }
+    }
+
+    fn window_sum(&self, x: usize, y: usize, width: usize, height: usize) -> [u64; 3] {
+        let top_left     = self.sums[y * self.stride + x];
+        let top_right    = self.sums[y * self.stride + x + width];
+        let bottom_left  = self.sums[(y + height) * self.stride + x];
+        let bottom_right = self.sums[(y + height) * self.stride + x + width];
+
+        let mut result = [0u64; 3];
+        for channel in 0 .. 3 {
+            result[channel] = bottom_right[channel] + top_left[channel] -
+                top_right[channel] - bottom_left[channel];
+        }
+
+        result
+    }
+}
+
+impl Bitmap {
+    // NOTE(erick): Sum of absolute differences over RGB, normalized to
+    // [0, 1] (0 is a perfect match). Returns the top-left corner, in
+    // screen coordinates, of the best match whose score is not above
+    // 'tolerance'. Each candidate stops accumulating as soon as it can't
+    // beat the best one so far, which keeps the search usable on
+    // screenshots where most positions fail within a few rows.
+    // Before that, the difference between the channel sums of the window
+    // (from a summed-area table) and of the needle is a lower bound of
+    // the SAD, so most candidates are rejected in constant time.
+    pub fn find_subimage(&self, needle: &Bitmap, tolerance: f32) -> Option<(u32, u32, f32)> {
+        let width         = self.info_header.image_width as usize;
+        let height        = self.info_header.image_height as usize;
+        let needle_width  = needle.info_header.image_width as usize;
+        let needle_height = needle.info_header.image_height as usize;
+
+        if needle_width == 0 || needle_height == 0 ||
+            needle_width > width || needle_height > height {
+                return None;
+            }
+
+        let haystack = screen_pixels(self);
+        let needle   = screen_pixels(needle);
+
+        let haystack_sums = SummedAreaTable::new(&haystack, width, height);
+        let needle_sum = SummedAreaTable::new(&needle, needle_width, needle_height)
+            .window_sum(0, 0, needle_width, needle_height);
+
+        let max_sad = (needle_width * needle_height * 3 * 255) as f64;
+        let mut budget = (tolerance.max(0.0) as f64 * max_sad).floor() as u64;
+        let mut best = None;
+
+        for y in 0 ..= height - needle_height {
+            for x in 0 ..= width - needle_width {
+                let window_sum = haystack_sums.window_sum(x, y, needle_width, needle_height);
+                let lower_bound: u64 = (0 .. 3)
+                    .map(|channel| window_sum[channel].abs_diff(needle_sum[channel]))
+                    .sum();
+                if lower_bound > budget {
+                    continue;
+                }
+
+                let mut sad = 0u64;
+
+                'rows: for needle_y in 0 .. needle_height {
+                    let haystack_row = &haystack[(y + needle_y) * width + x ..];
+                    let needle_row   = &needle[needle_y * needle_width ..];
+
+                    for needle_x in 0 .. needle_width {
+                        sad += absolute_difference(&haystack_row[needle_x],
+                                                   &needle_row[needle_x]);
+                    }
+
+                    if sad > budget {
+                        break 'rows;
+                    }
+                }
+
+                if sad <= budget && best.is_none_or(|(_, _, best_sad)| sad < best_sad) {
+                    best = Some((x as u32, y as u32, sad));
+                    if sad == 0 {
+                        return Some((x as u32, y as u32, 0.0));
+                    }
+                    budget = sad;
+                }
+            }
+        }
+
+        best.map(|(x, y, sad)| (x, y, (sad as f64 / max_sad) as f32))
+    }
+
+    // NOTE(erick): Returns (dx, dy) such that 'other.shift(dx, dy, ..)'
+    // lines up with 'self'. The search is coarse-to-fine: the shift is
+    // found exhaustively on a small version of both images and then
+    // refined one pixel at a time at every level of the pyramid.
+    pub fn estimate_translation(&self, other: &Bitmap, max_shift: u32) -> (i32, i32) {
+        let mut pyramid = vec![(LumaPlane::from_bitmap(self), LumaPlane::from_bitmap(other))];
+        // NOTE(erick): Larger shifts leave no overlap, so they can't win
+        // and would only make the coarse search longer.
+        let max_size = [self.info_header.image_width, self.info_header.image_height,
+                        other.info_header.image_width, other.info_header.image_height]
+            .iter().map(|&size| size.max(0) as u32).min().unwrap();
+        let max_shift = max_shift.min(max_size) as i32;
+
+        while (max_shift >> (pyramid.len() - 1)) > COARSE_SEARCH_RADIUS {
+            let next = {
+                let (ref current, ref current_other) = pyramid[pyramid.len() - 1];
+                if current.width.min(current_other.width) / 2 < MIN_PYRAMID_SIZE ||
+                    current.height.min(current_other.height) / 2 < MIN_PYRAMID_SIZE {
+                        break;
+                    }
+
+                (current.downsampled(), current_other.downsampled())
+            };
+            pyramid.push(next);
+        }
+
+        let coarsest_level = pyramid.len() - 1;
+        let coarsest_max_shift = max_shift >> coarsest_level;
+        let (ref coarse, ref coarse_other) = pyramid[coarsest_level];
+        let mut shift = coarse.best_shift_around(coarse_other, (0, 0),
+                                                 coarsest_max_shift, coarsest_max_shift);
+
+        for level in (0 .. coarsest_level).rev() {
+            let (ref plane, ref plane_other) = pyramid[level];
+            shift = plane.best_shift_around(plane_other, (shift.0 * 2, shift.1 * 2),
+                                            1, max_shift >> level);
+        }
+
+        shift
+    }
+
+    // NOTE(erick): Moves the image content by (dx, dy) in screen
+    // coordinates. Uncovered pixels get 'fill'.
+    pub fn shift(&mut self, dx: i32, dy: i32, fill: BitmapPixel) {
+        let width  = self.info_header.image_width as i64;
+        let height = self.info_header.image_height as i64;
+        let source = screen_pixels(self);
+
+        for y in 0 .. height {
+            for x in 0 .. width {
+                let source_x = x - dx as i64;
+                let source_y = y - dy as i64;
+
+                let pixel = if source_x >= 0 && source_x < width &&
+                    source_y >= 0 && source_y < height {
+                        source[(source_y * width + source_x) as usize]
+                    } else {
+                        fill
+                    };
+
+                self.set_pixel(x as u32, y as u32, pixel);
+            }
+        }
+    }
+}
+
+// NOTE(erick): Shifts smaller than this at the coarsest pyramid level
+// are searched exhaustively.
+const COARSE_SEARCH_RADIUS : i32 = 4;
+// NOTE(erick): Below this size downsampling loses too much detail.
+const MIN_PYRAMID_SIZE     : usize = 16;
+
+struct LumaPlane {
+    width  : usize,
+    height : usize,
+    data   : Vec<f32>,
+}
+
+impl LumaPlane {
+    fn from_bitmap(image: &Bitmap) -> LumaPlane {
+        LumaPlane {
+            width  : image.info_header.image_width as usize,
+            height : image.info_header.image_height as usize,
+            data   : screen_pixels(image).iter().map(|p| p.luma() as f32).collect(),
+        }
+    }
+
+    // NOTE(erick): 2x2 box average; an odd last row/column is dropped.
+    fn downsampled(&self) -> LumaPlane {
+        let width  = self.width / 2;
+        let height = self.height / 2;
+
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0 .. height {
+            for x in 0 .. width {
+                let top    = (2 * y) * self.width + 2 * x;
+                let bottom = top + self.width;
+                data.push((self.data[top] + self.data[top + 1] +
+                           self.data[bottom] + self.data[bottom + 1]) / 4.0);
+            }
+        }
+
+        LumaPlane {
+            width  : width,
+            height : height,
+            data   : data,
+        }
+    }
+
+    // NOTE(erick): Mean absolute difference between self(x, y) and
+    // other(x - dx, y - dy) over the overlapping area. Overlaps smaller
+    // than a quarter of the images are rejected since a handful of
+    // pixels can match by accident.
+    fn shifted_difference(&self, other: &LumaPlane, dx: i32, dy: i32) -> Option<f32> {
+        let x0 = dx.max(0) as i64;
+        let y0 = dy.max(0) as i64;
+        let x1 = (self.width as i64).min(other.width as i64 + dx as i64);
+        let y1 = (self.height as i64).min(other.height as i64 + dy as i64);
+
+        if x1 <= x0 || y1 <= y0 {
+            return None;
+        }
+
+        let overlap = ((x1 - x0) * (y1 - y0)) as usize;
+        let smallest_area = (self.width * self.height).min(other.width * other.height);
+        if overlap * 4 < smallest_area {
+            return None;
+        }
+
+        let mut sum = 0.0;
+        for y in y0 .. y1 {
+            let other_y = (y - dy as i64) as usize;
+            for x in x0 .. x1 {
+                let other_x = (x - dx as i64) as usize;
+                sum += (self.data[y as usize * self.width + x as usize] -
+                        other.data[other_y * other.width + other_x]).abs();
+            }
+        }
+
+        Some(sum / overlap as f32)
+    }
+
+    fn best_shift_around(&self, other: &LumaPlane, center: (i32, i32),
+                         radius: i32, max_shift: i32) -> (i32, i32) {
+        let mut best = (center, None);
+
+        for dy in (center.1 - radius).max(-max_shift) ..= (center.1 + radius).min(max_shift) {
+            for dx in (center.0 - radius).max(-max_shift) ..= (center.0 + radius).min(max_shift) {
+                if let Some(difference) = self.shifted_difference(other, dx, dy) {
+                    // NOTE(erick): Ties prefer the smallest shift.
+                    let better = match best.1 {
+                        None => true,
+                        Some(best_difference) => {
+                            difference < best_difference ||
+                                (difference == best_difference &&
+                                 dx.abs() + dy.abs() < (best.0).0.abs() + (best.0).1.abs())
+                        },
+                    };
+
+                    if better {
+                        best = ((dx, dy), Some(difference));
+                    }
+                }
+            }
+        }
+
+        best.0
+    }
+}
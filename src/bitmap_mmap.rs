@@ -0,0 +1,33 @@
+use Bitmap;
+use BitmapResult;
+
+use memmap2::Mmap;
+
+use std::fs::File;
+use std::path::Path;
+
+// NOTE(erick): Maps the whole file. Mapping is only unsafe if someone
+// else truncates or writes to the file while we read it, in which case
+// we may read garbage (or get a SIGBUS on truncation); we never hand
+// out references to the mapped memory.
+fn map_file<P: AsRef<Path>>(path: P) -> BitmapResult<Mmap> {
+    let file = File::open(path)?;
+    let map = unsafe { Mmap::map(&file)? };
+
+    Ok(map)
+}
+
+impl Bitmap {
+    // NOTE(erick): Like 'from_file' without reading the file into memory
+    // first, only the decoded pixels are allocated.
+    pub fn from_mmap<P: AsRef<Path>>(path: P) -> BitmapResult<Bitmap> {
+        Bitmap::from_slice(&map_file(path)?)
+    }
+
+    // NOTE(erick): See 'decode_region'. Only the pages holding the
+    // rectangle's rows are read from disk.
+    pub fn region_from_mmap<P: AsRef<Path>>(path: P, x: u32, y: u32,
+                                            width: u32, height: u32) -> BitmapResult<Bitmap> {
+        Bitmap::decode_region(&map_file(path)?, x, y, width, height)
+    }
+}
@@ -0,0 +1,243 @@
+use Bitmap;
+use BitmapPixel;
+use BitmapResult;
+use BitmapError;
+use CompressionType;
+
+use std::io::Read;
+use std::io::Write;
+
+// NOTE(erick): Netpbm lines should not be longer than 70 characters.
+const MAX_ASCII_LINE_LENGTH : usize = 70;
+
+// NOTE(erick): Luma threshold below which a PBM pixel is black.
+const PBM_THRESHOLD : u8 = 128;
+
+// NOTE(erick): Every Netpbm format has a plain (ASCII) and a raw
+// (binary) flavor, which only differ in how the samples are stored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetpbmEncoding {
+    Ascii,
+    Binary,
+}
+
+// NOTE(erick): Writes the samples of one row at a time as decimal numbers,
+// wrapping long rows.
+fn write_ascii_samples<W: Write>(writer: &mut W, samples: &[u8],
+                                 samples_per_row: usize) -> BitmapResult<()> {
+    for row in samples.chunks(samples_per_row.max(1)) {
+        let mut line = String::new();
+        for sample in row {
+            let text = sample.to_string();
+            if !line.is_empty() && line.len() + 1 + text.len() > MAX_ASCII_LINE_LENGTH {
+                writeln!(writer, "{}", line)?;
+                line.clear();
+            }
+
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&text);
+        }
+        writeln!(writer, "{}", line)?;
+    }
+
+    Ok(())
+}
+
+// NOTE(erick): Walks the header (and the samples of ASCII files), which
+// are decimal numbers separated by whitespace and '#' comments.
+struct NetpbmTokens<'a> {
+    data          : &'a [u8],
+    current_index : usize,
+}
+
+impl<'a> NetpbmTokens<'a> {
+    fn skip_separators(&mut self) {
+        while let Some(&byte) = self.data.get(self.current_index) {
+            if byte == b'#' {
+                while self.data.get(self.current_index).is_some_and(|&b| b != b'\n') {
+                    self.current_index += 1;
+                }
+            } else if byte.is_ascii_whitespace() {
+                self.current_index += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next_number(&mut self) -> BitmapResult<u32> {
+        self.skip_separators();
+
+        let start = self.current_index;
+        while self.data.get(self.current_index).is_some_and(|b| b.is_ascii_digit()) {
+            self.current_index += 1;
+        }
+
+        std::str::from_utf8(&self.data[start .. self.current_index]).ok()
+            .and_then(|digits| digits.parse().ok())
+            .ok_or(BitmapError::InvalidBitmap)
+    }
+}
+
+impl Bitmap {
+    // NOTE(erick): Reads plain (P3) and raw (P6) PPM files. Samples are
+    // scaled from [0, maxval] to [0, 255], raw files with a maxval above
+    // 255 store two big-endian bytes per sample.
+    pub fn from_ppm_data(data: &[u8]) -> BitmapResult<Bitmap> {
+        let is_binary = match data.get(.. 2) {
+            Some(b"P3") => false,
+            Some(b"P6") => true,
+            _           => return Err(BitmapError::InvalidBitmap),
+        };
+
+        let mut tokens = NetpbmTokens { data : data, current_index : 2 };
+        let width  = tokens.next_number()?;
+        let height = tokens.next_number()?;
+        let maxval = tokens.next_number()?;
+
+        if width == 0 || height == 0 || width > i32::MAX as u32 || height > i32::MAX as u32 ||
+            maxval == 0 || maxval > 0xffff {
+            return Err(BitmapError::InvalidBitmap);
+        }
+
+        let n_samples = width as usize * height as usize * 3;
+        let mut samples = Vec::with_capacity(n_samples.min(data.len()));
+        if is_binary {
+            // NOTE(erick): A single whitespace byte separates the header
+            // from the samples.
+            let start = tokens.current_index + 1;
+            let bytes_per_sample = if maxval > 0xff { 2 } else { 1 };
+            let end = n_samples.checked_mul(bytes_per_sample)
+                .and_then(|size| size.checked_add(start))
+                .filter(|&end| end <= data.len())
+                .ok_or(BitmapError::InvalidBitmap)?;
+
+            for sample in data[start .. end].chunks(bytes_per_sample) {
+                samples.push(sample.iter().fold(0u32, |value, &byte| value << 8 | byte as u32));
+            }
+        } else {
+            for _ in 0 .. n_samples {
+                samples.push(tokens.next_number()?);
+            }
+        }
+
+        if samples.iter().any(|&sample| sample > maxval) {
+            return Err(BitmapError::InvalidBitmap);
+        }
+        let scale = |sample: u32| ((sample * 255 + maxval / 2) / maxval) as u8;
+
+        let mut result = Bitmap::new(width as i32, height as i32,
+                                     24, CompressionType::Uncompressed);
+        for (index, rgb) in samples.chunks(3).enumerate() {
+            let x = (index % width as usize) as u32;
+            let y = (index / width as usize) as u32;
+            result.set_pixel(x, y, BitmapPixel::rgb(scale(rgb[0]), scale(rgb[1]), scale(rgb[2])));
+        }
+
+        Ok(result)
+    }
+
+    pub fn from_ppm_reader<R: Read>(reader: &mut R) -> BitmapResult<Bitmap> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        Bitmap::from_ppm_data(&data)
+    }
+
+    fn netpbm_size(&self) -> (usize, usize) {
+        (self.info_header.image_width as usize, self.info_header.image_height as usize)
+    }
+
+    // NOTE(erick): Netpbm rows go top to bottom, we always go through
+    // get_pixel so the file order of the bitmap doesn't matter. Alpha is
+    // dropped.
+    pub fn write_ppm<W: Write>(&self, writer: &mut W,
+                               encoding: NetpbmEncoding) -> BitmapResult<()> {
+        let (width, height) = self.netpbm_size();
+
+        let mut samples = Vec::with_capacity(width * height * 3);
+        for y in 0 .. height as u32 {
+            for x in 0 .. width as u32 {
+                let pixel = self.get_pixel(x, y);
+                samples.extend_from_slice(&[pixel.red, pixel.green, pixel.blue]);
+            }
+        }
+
+        match encoding {
+            NetpbmEncoding::Ascii => {
+                write!(writer, "P3\n{} {}\n255\n", width, height)?;
+                write_ascii_samples(writer, &samples, width * 3)?;
+            },
+            NetpbmEncoding::Binary => {
+                write!(writer, "P6\n{} {}\n255\n", width, height)?;
+                writer.write_all(&samples)?;
+            },
+        }
+
+        Ok(())
+    }
+
+    // NOTE(erick): Gray levels are the pixels' luma.
+    pub fn write_pgm<W: Write>(&self, writer: &mut W,
+                               encoding: NetpbmEncoding) -> BitmapResult<()> {
+        let (width, height) = self.netpbm_size();
+
+        let mut samples = Vec::with_capacity(width * height);
+        for y in 0 .. height as u32 {
+            for x in 0 .. width as u32 {
+                samples.push(self.get_pixel(x, y).luma());
+            }
+        }
+
+        match encoding {
+            NetpbmEncoding::Ascii => {
+                write!(writer, "P2\n{} {}\n255\n", width, height)?;
+                write_ascii_samples(writer, &samples, width)?;
+            },
+            NetpbmEncoding::Binary => {
+                write!(writer, "P5\n{} {}\n255\n", width, height)?;
+                writer.write_all(&samples)?;
+            },
+        }
+
+        Ok(())
+    }
+
+    // NOTE(erick): Pixels with a luma below PBM_THRESHOLD are black. In
+    // PBM files 1 means black.
+    pub fn write_pbm<W: Write>(&self, writer: &mut W,
+                               encoding: NetpbmEncoding) -> BitmapResult<()> {
+        let (width, height) = self.netpbm_size();
+
+        let mut bits = Vec::with_capacity(width * height);
+        for y in 0 .. height as u32 {
+            for x in 0 .. width as u32 {
+                bits.push((self.get_pixel(x, y).luma() < PBM_THRESHOLD) as u8);
+            }
+        }
+
+        match encoding {
+            NetpbmEncoding::Ascii => {
+                write!(writer, "P1\n{} {}\n", width, height)?;
+                write_ascii_samples(writer, &bits, width)?;
+            },
+            NetpbmEncoding::Binary => {
+                write!(writer, "P4\n{} {}\n", width, height)?;
+
+                // NOTE(erick): Rows are packed MSB first and padded to
+                // a whole byte.
+                for row in bits.chunks(width.max(1)) {
+                    let mut row_data = vec![0u8; width.div_ceil(8)];
+                    for (x, &bit) in row.iter().enumerate() {
+                        row_data[x / 8] |= bit << (7 - x % 8);
+                    }
+                    writer.write_all(&row_data)?;
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
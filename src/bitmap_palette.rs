@@ -0,0 +1,119 @@
+use Bitmap;
+use BitmapPixel;
+use BitmapPalette;
+use BitmapResult;
+use BitmapError;
+
+use palette_capacity;
+
+// NOTE(erick): Levels of each channel in the web safe palette.
+const WEB_SAFE_LEVELS : [u8; 6] = [0x00, 0x33, 0x66, 0x99, 0xcc, 0xff];
+
+// NOTE(erick): Collects the colors of a palette. Nothing is checked
+// until 'build', when we know which bit depth the palette is for.
+#[derive(Clone, Debug, Default)]
+pub struct BitmapPaletteBuilder {
+    colors : Vec<BitmapPixel>,
+}
+
+impl BitmapPaletteBuilder {
+    pub fn new() -> BitmapPaletteBuilder {
+        BitmapPaletteBuilder::default()
+    }
+
+    // NOTE(erick): 'n_levels' evenly spaced grays from black to white.
+    pub fn grayscale(n_levels: u16) -> BitmapPaletteBuilder {
+        let colors = (0 .. n_levels as u32)
+            .map(|level| {
+                let value = if n_levels > 1 {
+                    ((level * 255 + (n_levels as u32 - 1) / 2) / (n_levels as u32 - 1)) as u8
+                } else {
+                    0
+                };
+                BitmapPixel::rgb(value, value, value)
+            })
+            .collect();
+
+        BitmapPaletteBuilder {
+            colors : colors,
+        }
+    }
+
+    // NOTE(erick): The 216 colors of the 6x6x6 cube, blue changes fastest.
+    pub fn web_safe() -> BitmapPaletteBuilder {
+        let mut colors = Vec::with_capacity(216);
+        for &red in &WEB_SAFE_LEVELS {
+            for &green in &WEB_SAFE_LEVELS {
+                for &blue in &WEB_SAFE_LEVELS {
+                    colors.push(BitmapPixel::rgb(red, green, blue));
+                }
+            }
+        }
+
+        BitmapPaletteBuilder {
+            colors : colors,
+        }
+    }
+
+    pub fn black_and_white() -> BitmapPaletteBuilder {
+        BitmapPaletteBuilder {
+            colors : vec![BitmapPixel::black(), BitmapPixel::rgb(0xff, 0xff, 0xff)],
+        }
+    }
+
+    pub fn from_colors(colors: &[BitmapPixel]) -> BitmapPaletteBuilder {
+        BitmapPaletteBuilder {
+            colors : colors.to_vec(),
+        }
+    }
+
+    pub fn push(mut self, color: BitmapPixel) -> BitmapPaletteBuilder {
+        self.colors.push(color);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    // NOTE(erick): Fails when the colors don't fit a 'bits_per_pixel'
+    // palette or when that depth doesn't use a palette at all.
+    // Palettes don't have alpha, so it is forced to opaque.
+    pub fn build(self, bits_per_pixel: u16) -> BitmapResult<BitmapPalette> {
+        check_palette_size(self.colors.len(), bits_per_pixel)?;
+
+        Ok(self.colors.into_iter()
+           .map(|color| BitmapPixel::rgb(color.red, color.green, color.blue))
+           .collect())
+    }
+}
+
+fn check_palette_size(n_colors: usize, bits_per_pixel: u16) -> BitmapResult<()> {
+    let capacity = palette_capacity(bits_per_pixel);
+    if n_colors == 0 || n_colors > capacity {
+        return Err(BitmapError::InvalidOperation);
+    }
+
+    Ok(())
+}
+
+impl Bitmap {
+    // NOTE(erick): The palette must fit the current bit depth. Shorter
+    // palettes are padded with their last color since the file always
+    // stores every entry. Pixels are not touched, they are snapped to
+    // the palette when the image is written.
+    pub fn set_palette(&mut self, mut palette: BitmapPalette) -> BitmapResult<()> {
+        let bits_per_pixel = self.info_header.bits_per_pixel;
+        check_palette_size(palette.len(), bits_per_pixel)?;
+
+        let last_color = *palette.last().unwrap();
+        palette.resize(palette_capacity(bits_per_pixel), last_color);
+        self.palette = Some(palette);
+
+        Ok(())
+    }
+}
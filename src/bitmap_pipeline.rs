@@ -0,0 +1,191 @@
+use Bitmap;
+use BitmapResult;
+#[cfg(feature = "serde")]
+use BitmapError;
+use CompressionType;
+use ResizeFilter;
+
+#[cfg(feature = "encode-palettized")]
+use bitmap_quantize::{PaletteQuantizer, Dither};
+
+// NOTE(erick): One operation of a Pipeline. Each step maps to the
+// Bitmap method of the same name.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PipelineStep {
+    Resize { width: u32, height: u32, filter: ResizeFilter },
+    Grayscale,
+    Invert,
+    Brightness { offset: i16 },
+    Contrast { factor: f32 },
+    Gamma { gamma: f32 },
+    Saturation { factor: f32 },
+    MirrorHorizontally,
+    MirrorVertically,
+    #[cfg(feature = "filters")]
+    GaussianBlur { sigma: f32 },
+    #[cfg(feature = "filters")]
+    Sharpen,
+    // NOTE(erick): Builds a palette of 'colors' entries for each image and
+    // remaps it with the pipeline's dither.
+    #[cfg(feature = "encode-palettized")]
+    Quantize { colors: u16 },
+    ConvertTo { bits_per_pixel: u16, compression: CompressionType },
+}
+
+// NOTE(erick): A list of steps applied in order, so the same job can be
+// run on many images and stored (as JSON, with the serde feature) next
+// to the images it produced.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Pipeline {
+    steps  : Vec<PipelineStep>,
+    // NOTE(erick): Used by every Quantize step and by ConvertTo steps
+    // with a palettized target.
+    #[cfg(feature = "encode-palettized")]
+    dither : Dither,
+}
+
+impl Default for Pipeline {
+    fn default() -> Pipeline {
+        Pipeline::new()
+    }
+}
+
+impl Pipeline {
+    pub fn new() -> Pipeline {
+        Pipeline {
+            steps  : Vec::new(),
+            #[cfg(feature = "encode-palettized")]
+            dither : Dither::None,
+        }
+    }
+
+    pub fn steps(&self) -> &[PipelineStep] {
+        &self.steps
+    }
+
+    pub fn then(mut self, step: PipelineStep) -> Pipeline {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn resize(self, width: u32, height: u32, filter: ResizeFilter) -> Pipeline {
+        self.then(PipelineStep::Resize { width : width, height : height, filter : filter })
+    }
+
+    pub fn grayscale(self) -> Pipeline {
+        self.then(PipelineStep::Grayscale)
+    }
+
+    pub fn invert(self) -> Pipeline {
+        self.then(PipelineStep::Invert)
+    }
+
+    pub fn brightness(self, offset: i16) -> Pipeline {
+        self.then(PipelineStep::Brightness { offset : offset })
+    }
+
+    pub fn contrast(self, factor: f32) -> Pipeline {
+        self.then(PipelineStep::Contrast { factor : factor })
+    }
+
+    pub fn gamma(self, gamma: f32) -> Pipeline {
+        self.then(PipelineStep::Gamma { gamma : gamma })
+    }
+
+    pub fn saturation(self, factor: f32) -> Pipeline {
+        self.then(PipelineStep::Saturation { factor : factor })
+    }
+
+    pub fn mirror_horizontally(self) -> Pipeline {
+        self.then(PipelineStep::MirrorHorizontally)
+    }
+
+    pub fn mirror_vertically(self) -> Pipeline {
+        self.then(PipelineStep::MirrorVertically)
+    }
+
+    #[cfg(feature = "filters")]
+    pub fn gaussian_blur(self, sigma: f32) -> Pipeline {
+        self.then(PipelineStep::GaussianBlur { sigma : sigma })
+    }
+
+    #[cfg(feature = "filters")]
+    pub fn sharpen(self) -> Pipeline {
+        self.then(PipelineStep::Sharpen)
+    }
+
+    #[cfg(feature = "encode-palettized")]
+    pub fn quantize(self, colors: u16) -> Pipeline {
+        self.then(PipelineStep::Quantize { colors : colors })
+    }
+
+    #[cfg(feature = "encode-palettized")]
+    pub fn dither(mut self, dither: Dither) -> Pipeline {
+        self.dither = dither;
+        self
+    }
+
+    pub fn convert_to(self, bits_per_pixel: u16, compression: CompressionType) -> Pipeline {
+        self.then(PipelineStep::ConvertTo {
+            bits_per_pixel : bits_per_pixel,
+            compression    : compression,
+        })
+    }
+
+    // NOTE(erick): Stops at the first step that fails, leaving the image
+    // with the steps before it applied.
+    pub fn apply(&self, image: &mut Bitmap) -> BitmapResult<()> {
+        for step in &self.steps {
+            match *step {
+                PipelineStep::Resize { width, height, filter } =>
+                    image.resize(width, height, filter)?,
+                PipelineStep::Grayscale             => image.to_grayscale(),
+                PipelineStep::Invert                => image.invert_colors(),
+                PipelineStep::Brightness { offset } => image.adjust_brightness(offset),
+                PipelineStep::Contrast { factor }   => image.adjust_contrast(factor),
+                PipelineStep::Gamma { gamma }       => image.apply_gamma(gamma),
+                PipelineStep::Saturation { factor } => image.adjust_saturation(factor),
+                PipelineStep::MirrorHorizontally    => image.mirror_horizontally(),
+                PipelineStep::MirrorVertically      => image.mirror_vertically(),
+                #[cfg(feature = "filters")]
+                PipelineStep::GaussianBlur { sigma } => image.gaussian_blur(sigma),
+                #[cfg(feature = "filters")]
+                PipelineStep::Sharpen               => image.sharpen(),
+                #[cfg(feature = "encode-palettized")]
+                PipelineStep::Quantize { colors } => {
+                    let quantizer = PaletteQuantizer::train(&[&*image], colors)?;
+                    quantizer.remap(image, self.dither);
+                },
+                #[cfg(feature = "encode-palettized")]
+                PipelineStep::ConvertTo { bits_per_pixel, compression } =>
+                    image.convert_to_with_dither(bits_per_pixel, compression, self.dither)?,
+                #[cfg(not(feature = "encode-palettized"))]
+                PipelineStep::ConvertTo { bits_per_pixel, compression } =>
+                    image.convert_to(bits_per_pixel, compression)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn apply_all(&self, images: &mut [Bitmap]) -> BitmapResult<()> {
+        for image in images {
+            self.apply(image)?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Pipelines are always serializable")
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> BitmapResult<Pipeline> {
+        serde_json::from_str(json)
+            .map_err(|err| BitmapError::InvalidPipeline(err.to_string()))
+    }
+}
@@ -0,0 +1,266 @@
+use Bitmap;
+use BitmapPixel;
+use BitmapResult;
+use BitmapError;
+use CompressionType;
+use DecodeOptions;
+
+use read_headers;
+
+// NOTE(erick): Byte order of the pixels in a PixelBuffer. Bgra8 is what
+// 32 bits BMPs store (with the right masks), so those can be copied row
+// by row in both directions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    Bgra8,
+    Rgba8,
+    Bgr8,
+    Rgb8,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match *self {
+            PixelFormat::Bgra8 | PixelFormat::Rgba8 => 4,
+            PixelFormat::Bgr8  | PixelFormat::Rgb8  => 3,
+        }
+    }
+
+    fn read(&self, bytes: &[u8]) -> BitmapPixel {
+        match *self {
+            PixelFormat::Bgra8 => BitmapPixel::rgba(bytes[2], bytes[1], bytes[0], bytes[3]),
+            PixelFormat::Rgba8 => BitmapPixel::rgba(bytes[0], bytes[1], bytes[2], bytes[3]),
+            PixelFormat::Bgr8  => BitmapPixel::rgb(bytes[2], bytes[1], bytes[0]),
+            PixelFormat::Rgb8  => BitmapPixel::rgb(bytes[0], bytes[1], bytes[2]),
+        }
+    }
+
+    fn write(&self, bytes: &mut [u8], pixel: BitmapPixel) {
+        match *self {
+            PixelFormat::Bgra8 => bytes.copy_from_slice(&[pixel.blue, pixel.green, pixel.red, pixel.alpha]),
+            PixelFormat::Rgba8 => bytes.copy_from_slice(&[pixel.red, pixel.green, pixel.blue, pixel.alpha]),
+            PixelFormat::Bgr8  => bytes.copy_from_slice(&[pixel.blue, pixel.green, pixel.red]),
+            PixelFormat::Rgb8  => bytes.copy_from_slice(&[pixel.red, pixel.green, pixel.blue]),
+        }
+    }
+}
+
+// NOTE(erick): 32 bits masks that put the channels in Bgra8 order.
+const BGRA_RED_MASK   : u32 = 0x00ff0000;
+const BGRA_GREEN_MASK : u32 = 0x0000ff00;
+const BGRA_BLUE_MASK  : u32 = 0x000000ff;
+const BGRA_ALPHA_MASK : u32 = 0xff000000;
+
+// NOTE(erick): Pixels as one contiguous byte buffer, rows top to bottom
+// with no padding. Unlike Bitmap::image_data the bytes can be handed
+// as they are to whatever wants a raw surface (GPU textures, display
+// buffers, ...).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PixelBuffer {
+    width  : u32,
+    height : u32,
+    format : PixelFormat,
+    data   : Vec<u8>,
+}
+
+impl PixelBuffer {
+    pub fn new(width: u32, height: u32, format: PixelFormat) -> PixelBuffer {
+        let size = width as usize * height as usize * format.bytes_per_pixel();
+
+        PixelBuffer {
+            width  : width,
+            height : height,
+            format : format,
+            data   : vec![0; size],
+        }
+    }
+
+    // NOTE(erick): 'data' must hold exactly 'width' * 'height' pixels.
+    pub fn from_bytes(width: u32, height: u32, format: PixelFormat,
+                      data: Vec<u8>) -> BitmapResult<PixelBuffer> {
+        if data.len() != width as usize * height as usize * format.bytes_per_pixel() {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        Ok(PixelBuffer {
+            width  : width,
+            height : height,
+            format : format,
+            data   : data,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    // NOTE(erick): Bytes from the start of a row to the start of the next.
+    pub fn stride(&self) -> usize {
+        self.width as usize * self.format.bytes_per_pixel()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn row(&self, y: u32) -> &[u8] {
+        let stride = self.stride();
+        &self.data[y as usize * stride .. (y as usize + 1) * stride]
+    }
+
+    fn pixel_range(&self, x: u32, y: u32) -> std::ops::Range<usize> {
+        let bytes_per_pixel = self.format.bytes_per_pixel();
+        let start = y as usize * self.stride() + x as usize * bytes_per_pixel;
+
+        start .. start + bytes_per_pixel
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> BitmapPixel {
+        self.format.read(&self.data[self.pixel_range(x, y)])
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, pixel: BitmapPixel) {
+        let range = self.pixel_range(x, y);
+        self.format.write(&mut self.data[range], pixel);
+    }
+
+    // NOTE(erick): Going to a format without alpha drops it, coming from
+    // one makes every pixel opaque.
+    pub fn convert_to(&self, format: PixelFormat) -> PixelBuffer {
+        if format == self.format {
+            return self.clone();
+        }
+
+        let mut result = PixelBuffer::new(self.width, self.height, format);
+        let source_size = self.format.bytes_per_pixel();
+        let target_size = format.bytes_per_pixel();
+        for (source, target) in self.data.chunks(source_size).zip(result.data.chunks_mut(target_size)) {
+            format.write(target, self.format.read(source));
+        }
+
+        result
+    }
+
+    // NOTE(erick): 32 bits files whose channels are already in Bgra8
+    // order (uncompressed or with Bgra8 masks) are copied row by row.
+    // Everything else goes through Bitmap.
+    pub fn from_bmp_data(data: &[u8]) -> BitmapResult<PixelBuffer> {
+        if let Some(result) = PixelBuffer::copy_bgra_rows(data)? {
+            return Ok(result);
+        }
+
+        Ok(Bitmap::from_slice(data)?.to_pixel_buffer(PixelFormat::Bgra8))
+    }
+
+    fn copy_bgra_rows(data: &[u8]) -> BitmapResult<Option<PixelBuffer>> {
+        if data.len() < 2 || &data[.. 2] != b"BM" {
+            return Ok(None);
+        }
+
+        let (f_header, info_header, _) = read_headers(&mut &data[..])?;
+        DecodeOptions::default().check_limits(&info_header)?;
+
+        let has_bgra_masks = info_header.red_mask == BGRA_RED_MASK &&
+            info_header.green_mask == BGRA_GREEN_MASK && info_header.blue_mask == BGRA_BLUE_MASK &&
+            (info_header.alpha_mask == BGRA_ALPHA_MASK || info_header.alpha_mask == 0);
+        let is_bgra = info_header.bits_per_pixel == 32 &&
+            (info_header.compression_type == CompressionType::Uncompressed as u32 ||
+             (info_header.compression_type == CompressionType::BitFields as u32 && has_bgra_masks));
+
+        let width  = info_header.image_width as u32;
+        let height = info_header.image_height as u32;
+        let stride = width as usize * 4;
+        let pixels_start = f_header.pixel_array_offset as usize;
+        // NOTE(erick): Truncated files take the slow path, which knows
+        // what to do with the missing rows.
+        if !is_bgra || pixels_start.saturating_add(stride * height as usize) > data.len() {
+            return Ok(None);
+        }
+
+        let keeps_alpha = info_header.compression_type == CompressionType::BitFields as u32 &&
+            info_header.alpha_mask == BGRA_ALPHA_MASK;
+
+        let mut result = PixelBuffer::new(width, height, PixelFormat::Bgra8);
+        for (y, row) in result.data.chunks_mut(stride.max(1)).enumerate() {
+            let file_row = if info_header.is_top_down { y } else { height as usize - y - 1 };
+            let row_start = pixels_start + file_row * stride;
+            row.copy_from_slice(&data[row_start .. row_start + stride]);
+
+            if !keeps_alpha {
+                for pixel in row.chunks_mut(4) {
+                    pixel[3] = 0xff;
+                }
+            }
+        }
+
+        Ok(Some(result))
+    }
+
+    // NOTE(erick): Writes a top-down 32 bits BitFields file with Bgra8
+    // masks, so Bgra8 buffers are copied as they are.
+    pub fn to_bmp_data(&self) -> Vec<u8> {
+        if self.format != PixelFormat::Bgra8 {
+            return self.convert_to(PixelFormat::Bgra8).to_bmp_data();
+        }
+
+        let (file_header, mut info_header) = Bitmap::create_headers(self.width as i32,
+                                                                   self.height as i32,
+                                                                   32, CompressionType::BitFields);
+        info_header.red_mask   = BGRA_RED_MASK;
+        info_header.green_mask = BGRA_GREEN_MASK;
+        info_header.blue_mask  = BGRA_BLUE_MASK;
+        info_header.alpha_mask = BGRA_ALPHA_MASK;
+        info_header.is_top_down = true;
+
+        let mut result = Vec::with_capacity(file_header.file_size as usize);
+        file_header.into_data(&mut result);
+        info_header.into_data(&mut result);
+        result.extend_from_slice(&self.data);
+
+        result
+    }
+}
+
+impl Bitmap {
+    pub fn to_pixel_buffer(&self, format: PixelFormat) -> PixelBuffer {
+        let width  = self.info_header.image_width as u32;
+        let height = self.info_header.image_height as u32;
+
+        let mut result = PixelBuffer::new(width, height, format);
+        for y in 0 .. height {
+            for x in 0 .. width {
+                result.set_pixel(x, y, self.get_pixel(x, y));
+            }
+        }
+
+        result
+    }
+
+    // NOTE(erick): The result is a default (32 bits BitFields) image.
+    pub fn from_pixel_buffer(buffer: &PixelBuffer) -> Bitmap {
+        let mut result = Bitmap::new_default(buffer.width as i32, buffer.height as i32);
+        for y in 0 .. buffer.height {
+            for x in 0 .. buffer.width {
+                result.set_pixel(x, y, buffer.get_pixel(x, y));
+            }
+        }
+
+        result
+    }
+}
@@ -0,0 +1,197 @@
+use Bitmap;
+use BitmapPixel;
+use BitmapResult;
+use BitmapError;
+use CompressionType;
+
+// NOTE(erick): "Quite OK Image" format, see https://qoiformat.org.
+// Everything is big-endian. The header is the magic, width, height,
+// number of channels and the color space.
+const QOI_MAGIC       : &[u8; 4] = b"qoif";
+const QOI_HEADER_SIZE : usize = 14;
+const QOI_END_MARKER  : [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+
+const QOI_OP_INDEX : u8 = 0x00;
+const QOI_OP_DIFF  : u8 = 0x40;
+const QOI_OP_LUMA  : u8 = 0x80;
+const QOI_OP_RUN   : u8 = 0xc0;
+const QOI_OP_RGB   : u8 = 0xfe;
+const QOI_OP_RGBA  : u8 = 0xff;
+const QOI_OP_MASK  : u8 = 0xc0;
+
+const QOI_MAX_RUN_LENGTH : u8 = 62;
+
+fn qoi_hash(pixel: &BitmapPixel) -> usize {
+    (pixel.red as usize * 3 + pixel.green as usize * 5 +
+     pixel.blue as usize * 7 + pixel.alpha as usize * 11) % 64
+}
+
+fn same_rgba(a: &BitmapPixel, b: &BitmapPixel) -> bool {
+    (a.red, a.green, a.blue, a.alpha) == (b.red, b.green, b.blue, b.alpha)
+}
+
+impl Bitmap {
+    // NOTE(erick): Images with any translucent pixel get 4 channels,
+    // the others 3. Pixels go top to bottom.
+    pub fn to_qoi(&self) -> Vec<u8> {
+        let width  = self.info_header.image_width as u32;
+        let height = self.info_header.image_height as u32;
+        let has_alpha = self.image_data.iter().any(|pixel| pixel.alpha != 0xff);
+
+        let mut result = Vec::with_capacity(QOI_HEADER_SIZE + self.image_data.len() * 2);
+        result.extend_from_slice(QOI_MAGIC);
+        result.extend_from_slice(&width.to_be_bytes());
+        result.extend_from_slice(&height.to_be_bytes());
+        result.push(if has_alpha { 4 } else { 3 });
+        result.push(0); // sRGB with linear alpha
+
+        let mut seen = [BitmapPixel::rgba(0, 0, 0, 0); 64];
+        let mut previous = BitmapPixel::black();
+        let mut run_length = 0u8;
+
+        for y in 0 .. height {
+            for x in 0 .. width {
+                let mut pixel = self.get_pixel(x, y);
+                if !has_alpha {
+                    pixel.alpha = 0xff;
+                }
+
+                if same_rgba(&pixel, &previous) {
+                    run_length += 1;
+                    if run_length == QOI_MAX_RUN_LENGTH {
+                        result.push(QOI_OP_RUN | (run_length - 1));
+                        run_length = 0;
+                    }
+                    continue;
+                }
+
+                if run_length > 0 {
+                    result.push(QOI_OP_RUN | (run_length - 1));
+                    run_length = 0;
+                }
+
+                let hash = qoi_hash(&pixel);
+                if same_rgba(&seen[hash], &pixel) {
+                    result.push(QOI_OP_INDEX | hash as u8);
+                } else if pixel.alpha == previous.alpha {
+                    let dr = pixel.red.wrapping_sub(previous.red) as i8;
+                    let dg = pixel.green.wrapping_sub(previous.green) as i8;
+                    let db = pixel.blue.wrapping_sub(previous.blue) as i8;
+                    let dr_dg = dr.wrapping_sub(dg);
+                    let db_dg = db.wrapping_sub(dg);
+
+                    if (-2 .. 2).contains(&dr) && (-2 .. 2).contains(&dg) && (-2 .. 2).contains(&db) {
+                        result.push(QOI_OP_DIFF | ((dr + 2) as u8) << 4 |
+                                    ((dg + 2) as u8) << 2 | (db + 2) as u8);
+                    } else if (-32 .. 32).contains(&dg) &&
+                        (-8 .. 8).contains(&dr_dg) && (-8 .. 8).contains(&db_dg) {
+                        result.push(QOI_OP_LUMA | (dg + 32) as u8);
+                        result.push(((dr_dg + 8) as u8) << 4 | (db_dg + 8) as u8);
+                    } else {
+                        result.extend_from_slice(&[QOI_OP_RGB, pixel.red, pixel.green, pixel.blue]);
+                    }
+                } else {
+                    result.extend_from_slice(&[QOI_OP_RGBA, pixel.red, pixel.green,
+                                               pixel.blue, pixel.alpha]);
+                }
+
+                seen[hash] = pixel;
+                previous = pixel;
+            }
+        }
+
+        if run_length > 0 {
+            result.push(QOI_OP_RUN | (run_length - 1));
+        }
+        result.extend_from_slice(&QOI_END_MARKER);
+
+        result
+    }
+
+    // NOTE(erick): 4 channels images come out as 32 bits bitmaps, 3
+    // channels ones as 24 bits bitmaps.
+    pub fn from_qoi(data: &[u8]) -> BitmapResult<Bitmap> {
+        if data.len() < QOI_HEADER_SIZE + QOI_END_MARKER.len() || &data[.. 4] != QOI_MAGIC {
+            return Err(BitmapError::InvalidBitmap);
+        }
+
+        let read_u32 = |offset: usize| {
+            u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+        };
+        let width    = read_u32(4);
+        let height   = read_u32(8);
+        let channels = data[12];
+
+        // NOTE(erick): No op gives more than QOI_MAX_RUN_LENGTH pixels,
+        // so bigger images can't be in this data.
+        let n_pixels = width as u64 * height as u64;
+        let max_pixels = (data.len() - QOI_HEADER_SIZE) as u64 * QOI_MAX_RUN_LENGTH as u64;
+        if width == 0 || height == 0 || width > i32::MAX as u32 || height > i32::MAX as u32 ||
+            !matches!(channels, 3 | 4) || n_pixels > max_pixels {
+            return Err(BitmapError::InvalidBitmap);
+        }
+
+        let mut result = if channels == 4 {
+            Bitmap::new_default(width as i32, height as i32)
+        } else {
+            Bitmap::new(width as i32, height as i32, 24, CompressionType::Uncompressed)
+        };
+
+        let chunks_end = data.len() - QOI_END_MARKER.len();
+        let mut index = QOI_HEADER_SIZE;
+        let mut next_byte = || -> BitmapResult<u8> {
+            let byte = *data[.. chunks_end].get(index).ok_or(BitmapError::InvalidBitmap)?;
+            index += 1;
+            Ok(byte)
+        };
+
+        let mut seen = [BitmapPixel::rgba(0, 0, 0, 0); 64];
+        let mut pixel = BitmapPixel::black();
+        let mut run_length = 0;
+
+        for y in 0 .. height {
+            for x in 0 .. width {
+                if run_length > 0 {
+                    run_length -= 1;
+                } else {
+                    let op = next_byte()?;
+                    if op == QOI_OP_RGB {
+                        pixel.red   = next_byte()?;
+                        pixel.green = next_byte()?;
+                        pixel.blue  = next_byte()?;
+                    } else if op == QOI_OP_RGBA {
+                        pixel.red   = next_byte()?;
+                        pixel.green = next_byte()?;
+                        pixel.blue  = next_byte()?;
+                        pixel.alpha = next_byte()?;
+                    } else {
+                        match op & QOI_OP_MASK {
+                            QOI_OP_INDEX => pixel = seen[op as usize],
+                            QOI_OP_DIFF => {
+                                pixel.red   = pixel.red.wrapping_add((op >> 4) & 0x03).wrapping_sub(2);
+                                pixel.green = pixel.green.wrapping_add((op >> 2) & 0x03).wrapping_sub(2);
+                                pixel.blue  = pixel.blue.wrapping_add(op & 0x03).wrapping_sub(2);
+                            },
+                            QOI_OP_LUMA => {
+                                let second = next_byte()?;
+                                let dg = (op & 0x3f).wrapping_sub(32);
+                                pixel.red   = pixel.red.wrapping_add(dg)
+                                    .wrapping_add(second >> 4).wrapping_sub(8);
+                                pixel.green = pixel.green.wrapping_add(dg);
+                                pixel.blue  = pixel.blue.wrapping_add(dg)
+                                    .wrapping_add(second & 0x0f).wrapping_sub(8);
+                            },
+                            _ => run_length = op & 0x3f,
+                        }
+                    }
+
+                    seen[qoi_hash(&pixel)] = pixel;
+                }
+
+                result.set_pixel(x, y, pixel);
+            }
+        }
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,318 @@
+use Bitmap;
+use BitmapPixel;
+use BitmapPalette;
+use BitmapResult;
+use BitmapError;
+use CompressionType;
+
+use palette_capacity;
+use parallel;
+
+use std::collections::HashMap;
+
+// NOTE(erick): A box of the RGB cube holding the distinct colors
+// of the image together with how many times each one appears.
+struct ColorBox {
+    colors : Vec<(BitmapPixel, u32)>,
+}
+
+impl ColorBox {
+    fn channel(pixel: &BitmapPixel, channel: usize) -> u8 {
+        match channel {
+            0 => pixel.red,
+            1 => pixel.green,
+            _ => pixel.blue,
+        }
+    }
+
+    // NOTE(erick): Returns the channel with the widest range and its range.
+    fn widest_channel(&self) -> (usize, u8) {
+        let mut result = (0, 0);
+        for channel in 0 .. 3 {
+            let values = self.colors.iter().map(|c| ColorBox::channel(&c.0, channel));
+            let min = values.clone().min().unwrap_or(0);
+            let max = values.max().unwrap_or(0);
+
+            if max - min > result.1 {
+                result = (channel, max - min);
+            }
+        }
+
+        result
+    }
+
+    // NOTE(erick): Splits the box at the weighted median of its widest
+    // channel. Both halves are guaranteed to be non-empty.
+    fn split(mut self) -> (ColorBox, ColorBox) {
+        let (channel, _) = self.widest_channel();
+        self.colors.sort_by_key(|c| ColorBox::channel(&c.0, channel));
+
+        let total : u64 = self.colors.iter().map(|c| c.1 as u64).sum();
+        let mut accumulated = 0;
+        let mut split_index = 1;
+        for (index, color) in self.colors.iter().enumerate() {
+            accumulated += color.1 as u64;
+            if accumulated * 2 >= total {
+                split_index = index + 1;
+                break;
+            }
+        }
+        let split_index = split_index.clamp(1, self.colors.len() - 1);
+
+        let upper = self.colors.split_off(split_index);
+        (ColorBox { colors : self.colors }, ColorBox { colors : upper })
+    }
+
+    fn mean(&self) -> BitmapPixel {
+        let mut accum = [0u64; 3];
+        let mut count = 0u64;
+        for &(pixel, weight) in &self.colors {
+            accum[0] += pixel.red   as u64 * weight as u64;
+            accum[1] += pixel.green as u64 * weight as u64;
+            accum[2] += pixel.blue  as u64 * weight as u64;
+            count += weight as u64;
+        }
+
+        let average = |sum: u64| ((sum + count / 2) / count) as u8;
+        BitmapPixel::rgb(average(accum[0]), average(accum[1]), average(accum[2]))
+    }
+}
+
+// NOTE(erick): Heckbert's median cut. Keeps splitting the box with the
+// widest channel range until we have 'palette_desired_size' boxes, then
+// uses the mean color of each box. Images with fewer distinct colors
+// than that get their exact colors back.
+pub fn median_cut(colors: &[BitmapPixel], palette_desired_size: u16) -> BitmapPalette {
+    let mut histogram = HashMap::new();
+    for pixel in colors {
+        let key = (pixel.red as u32) << 16 | (pixel.green as u32) << 8 | pixel.blue as u32;
+        *histogram.entry(key).or_insert(0u32) += 1;
+    }
+
+    let mut distinct_colors : Vec<_> = histogram.into_iter()
+        .map(|(key, count)| (BitmapPixel::rgb_u32(key << 8), count))
+        .collect();
+    // NOTE(erick): HashMap order is random, sorting keeps the result stable.
+    distinct_colors.sort_by_key(|c| (c.0.red, c.0.green, c.0.blue));
+
+    if distinct_colors.is_empty() {
+        return vec![BitmapPixel::black()];
+    }
+
+    let mut boxes = vec![ColorBox { colors : distinct_colors }];
+    while boxes.len() < palette_desired_size as usize {
+        let widest = boxes.iter()
+            .enumerate()
+            .filter(|&(_, color_box)| color_box.colors.len() > 1)
+            .max_by_key(|&(_, color_box)| color_box.widest_channel().1)
+            .map(|(index, _)| index);
+
+        let widest = match widest {
+            Some(index) => index,
+            None        => break,
+        };
+
+        let (lower, upper) = boxes.swap_remove(widest).split();
+        boxes.push(lower);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|color_box| color_box.mean()).collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Dither {
+    None,
+    FloydSteinberg,
+    // NOTE(erick): Ordered dithering. Unlike error diffusion each pixel
+    // is handled on its own, so the result is repeatable and tiles.
+    Bayer4x4,
+    Bayer8x8,
+}
+
+// NOTE(erick): A palette shared by a whole set of images, e.g., the
+// frames of an animation. Using one palette for every frame avoids
+// the flickering we get when each frame picks its own colors.
+#[derive(Clone, Debug)]
+pub struct PaletteQuantizer {
+    palette : BitmapPalette,
+}
+
+impl Bitmap {
+    pub fn build_palette(&self, n_colors: u16) -> BitmapPalette {
+        median_cut(&self.image_data, n_colors)
+    }
+}
+
+impl PaletteQuantizer {
+    pub fn train(images: &[&Bitmap], n_colors: u16) -> BitmapResult<PaletteQuantizer> {
+        if n_colors == 0 || n_colors as usize > palette_capacity(8) {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        let mut colors = Vec::new();
+        for image in images {
+            colors.extend_from_slice(&image.image_data);
+        }
+
+        if colors.is_empty() {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        Ok(PaletteQuantizer {
+            palette : median_cut(&colors, n_colors),
+        })
+    }
+
+    pub fn from_palette(palette: BitmapPalette) -> BitmapResult<PaletteQuantizer> {
+        if palette.is_empty() || palette.len() > palette_capacity(8) {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        Ok(PaletteQuantizer {
+            palette : palette,
+        })
+    }
+
+    pub fn palette(&self) -> &BitmapPalette {
+        &self.palette
+    }
+
+    // NOTE(erick): Converts 'image' to the smallest palettized format that
+    // fits the palette and snaps every pixel to a palette color, so
+    // writing the image doesn't have to guess anything.
+    pub fn remap(&self, image: &mut Bitmap, dither: Dither) {
+        let bits_per_pixel = [1, 2, 4, 8].iter()
+            .cloned()
+            .find(|&bpp| palette_capacity(bpp) >= self.palette.len())
+            .unwrap();
+
+        image.replace_headers(bits_per_pixel, CompressionType::Uncompressed);
+
+        // NOTE(erick): The file always stores the whole palette, so we
+        // fill the unused entries with the last color.
+        let mut palette = self.palette.clone();
+        let last_color = *palette.last().unwrap();
+        palette.resize(palette_capacity(bits_per_pixel), last_color);
+
+        match dither {
+            Dither::None => {
+                image.image_data = parallel::map_items(&image.image_data, |pixel| {
+                    self.palette[pixel.find_closest_by_index(&self.palette)]
+                });
+            },
+            Dither::FloydSteinberg => floyd_steinberg(image, &self.palette),
+            Dither::Bayer4x4       => ordered_dither(image, &self.palette, 4),
+            Dither::Bayer8x8       => ordered_dither(image, &self.palette, 8),
+        }
+
+        image.palette = Some(palette);
+    }
+}
+
+fn floyd_steinberg(image: &mut Bitmap, palette: &BitmapPalette) {
+    let width  = image.info_header.image_width as usize;
+    let height = image.info_header.image_height as usize;
+
+    // NOTE(erick): Errors of the current and the next row, one entry
+    // per pixel with one extra entry on each side to avoid bounds checks.
+    let mut current_errors = vec![[0.0f32; 3]; width + 2];
+    let mut next_errors    = vec![[0.0f32; 3]; width + 2];
+
+    for y in 0 .. height {
+        for x in 0 .. width {
+            let original = image.get_pixel(x as u32, y as u32);
+            let error = current_errors[x + 1];
+
+            let wanted = [original.red   as f32 + error[0],
+                          original.green as f32 + error[1],
+                          original.blue  as f32 + error[2]];
+            let wanted_pixel = BitmapPixel::rgb(wanted[0].round().clamp(0.0, 255.0) as u8,
+                                                wanted[1].round().clamp(0.0, 255.0) as u8,
+                                                wanted[2].round().clamp(0.0, 255.0) as u8);
+
+            let chosen = palette[wanted_pixel.find_closest_by_index(palette)];
+            image.set_pixel(x as u32, y as u32, chosen);
+
+            let chosen = [chosen.red as f32, chosen.green as f32, chosen.blue as f32];
+            for channel in 0 .. 3 {
+                let quantization_error = wanted[channel] - chosen[channel];
+
+                current_errors[x + 2][channel] += quantization_error * 7.0 / 16.0;
+                next_errors[x][channel]        += quantization_error * 3.0 / 16.0;
+                next_errors[x + 1][channel]    += quantization_error * 5.0 / 16.0;
+                next_errors[x + 2][channel]    += quantization_error * 1.0 / 16.0;
+            }
+        }
+
+        current_errors = next_errors;
+        next_errors = vec![[0.0f32; 3]; width + 2];
+    }
+}
+
+// NOTE(erick): Builds the n x n Bayer index matrix (n a power of two)
+// recursively: M(2n) = [4M, 4M + 2; 4M + 3, 4M + 1].
+fn bayer_matrix(size: usize) -> Vec<u32> {
+    let mut matrix = vec![0u32];
+    let mut current_size = 1;
+
+    while current_size < size {
+        let next_size = current_size * 2;
+        let mut next = vec![0u32; next_size * next_size];
+
+        for y in 0 .. current_size {
+            for x in 0 .. current_size {
+                let value = 4 * matrix[y * current_size + x];
+                next[y * next_size + x]                                 = value;
+                next[y * next_size + x + current_size]                  = value + 2;
+                next[(y + current_size) * next_size + x]                = value + 3;
+                next[(y + current_size) * next_size + x + current_size] = value + 1;
+            }
+        }
+
+        matrix = next;
+        current_size = next_size;
+    }
+
+    matrix
+}
+
+fn ordered_dither(image: &mut Bitmap, palette: &BitmapPalette, size: usize) {
+    let width  = image.info_header.image_width as u32;
+    let height = image.info_header.image_height as u32;
+
+    let matrix = bayer_matrix(size);
+    let n_cells = (size * size) as f32;
+
+    // NOTE(erick): The threshold offsets should span roughly the distance
+    // between neighbouring palette colors. We estimate it as if the
+    // palette were a regular grid of the RGB cube, so a two color palette
+    // gets the whole range.
+    let levels_per_channel = (palette.len() as f32).cbrt();
+    let spread = (255.0 / (levels_per_channel - 1.0).max(1e-3)).min(255.0);
+
+    // NOTE(erick): Each pixel only depends on its position, so the rows
+    // are dithered in parallel and stored afterwards.
+    let rows : Vec<u32> = (0 .. height).collect();
+    let dithered_rows = parallel::map_items(&rows, |&y| {
+        (0 .. width).map(|x| {
+            let cell = matrix[(y as usize % size) * size + x as usize % size];
+            let offset = ((cell as f32 + 0.5) / n_cells - 0.5) * spread;
+
+            let original = image.get_pixel(x, y);
+            let shift = |channel: u8| (channel as f32 + offset).round().clamp(0.0, 255.0) as u8;
+            let wanted = BitmapPixel::rgb(shift(original.red),
+                                          shift(original.green),
+                                          shift(original.blue));
+
+            palette[wanted.find_closest_by_index(palette)]
+        }).collect::<Vec<_>>()
+    });
+
+    for (y, row) in dithered_rows.into_iter().enumerate() {
+        for (x, pixel) in row.into_iter().enumerate() {
+            image.set_pixel(x as u32, y as u32, pixel);
+        }
+    }
+}
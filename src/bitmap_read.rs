@@ -1,9 +1,9 @@
 use BytesWalker;
 use BitmapPixel;
 use BitmapPalette;
-use mask_offset_and_shifted;
+use BitfieldChannel;
 
-use map_zero_based;
+use ChannelLut;
 
 #[macro_export]
 macro_rules! pad_to_align {
@@ -12,260 +12,402 @@ macro_rules! pad_to_align {
     )
 }
 
+// NOTE(erick): Things that were wrong with a file but didn't stop us
+// from decoding it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeWarning {
+    // NOTE(erick): 'count' pixels used an index past the end of the
+    // palette and got DecodeOptions::palette_fallback instead.
+    PaletteIndexOutOfRange { count: usize },
+}
+
+// NOTE(erick): Files can have palettes shorter than their indices need
+// (colors_used = 16 with a stray index of 200). Instead of panicking we
+// hand out a fallback color and count how many times that happened.
+pub struct PaletteLookup<'a> {
+    palette      : &'a [BitmapPixel],
+    fallback     : BitmapPixel,
+    out_of_range : usize,
+    // NOTE(erick): The raw indices read by 'indexed_color', in file
+    // order. Only kept when asked for, streaming decodes don't need them.
+    indices      : Option<Vec<u8>>,
+}
+
+impl<'a> PaletteLookup<'a> {
+    pub fn new(palette: &'a Option<BitmapPalette>, fallback: BitmapPixel) -> PaletteLookup<'a> {
+        PaletteLookup {
+            palette      : palette.as_ref().map_or(&[], |p| p.as_slice()),
+            fallback     : fallback,
+            out_of_range : 0,
+            indices      : None,
+        }
+    }
+
+    pub fn keeping_indices(mut self) -> PaletteLookup<'a> {
+        self.indices = Some(Vec::new());
+        self
+    }
+
+    // NOTE(erick): An empty lookup over the same palette for decoding
+    // part of the image on its own. Its results go back with 'merge'.
+    pub fn for_step(&self) -> PaletteLookup<'a> {
+        PaletteLookup {
+            palette      : self.palette,
+            fallback     : self.fallback,
+            out_of_range : 0,
+            indices      : self.indices.as_ref().map(|_| Vec::new()),
+        }
+    }
+
+    // NOTE(erick): Steps have to be merged in file order.
+    pub fn merge(&mut self, step: PaletteLookup) {
+        self.out_of_range += step.out_of_range;
+        if let (Some(indices), Some(step_indices)) = (self.indices.as_mut(), step.indices) {
+            indices.extend(step_indices);
+        }
+    }
+
+    pub fn color(&mut self, index: usize) -> BitmapPixel {
+        match self.palette.get(index) {
+            Some(&color) => color,
+            None         => {
+                self.out_of_range += 1;
+                self.fallback
+            },
+        }
+    }
+
+    pub fn indexed_color(&mut self, index: usize) -> BitmapPixel {
+        if let Some(ref mut indices) = self.indices {
+            indices.push(index as u8);
+        }
+
+        self.color(index)
+    }
+
+    pub fn take_indices(&mut self) -> Vec<u8> {
+        self.indices.take().unwrap_or_default()
+    }
+
+    // NOTE(erick): The color of pixels the file doesn't set at all.
+    #[cfg(feature = "decode-rle")]
+    fn background(&self) -> BitmapPixel {
+        self.palette.first().cloned().unwrap_or(self.fallback)
+    }
+
+    pub fn out_of_range(&self) -> usize {
+        self.out_of_range
+    }
+}
+
+// NOTE(erick): All the readers below go row by row and decode exactly
+// 'image_height' rows of 'image_width' pixels, skipping the row padding.
+// Whatever comes after the last row is not ours to interpret, and a
+// truncated last row is dropped.
+fn padded_row_size(image_width: i32, bytes_per_pixel: usize) -> usize {
+    (image_width as usize * bytes_per_pixel).div_ceil(4) * 4
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn read_32_bitfield(data_walker: &mut BytesWalker,
                     result: &mut Vec<BitmapPixel>,
+                    image_width: i32, image_height: i32,
                     red_mask: u32,
                     green_mask: u32,
                     blue_mask: u32,
                     alpha_mask: u32) {
-    let (red_offset,   _)   = mask_offset_and_shifted(red_mask);
-    let (green_offset, _) = mask_offset_and_shifted(green_mask);
-    let (blue_offset,  _)  = mask_offset_and_shifted(blue_mask);
-    let (alpha_offset, _) = mask_offset_and_shifted(alpha_mask);
-
-    while data_walker.has_data() {
-        let pixel_value = data_walker.next_u32();
-
-        let mut pixel = BitmapPixel {
-            blue  : ((pixel_value  >> blue_offset)  & 0xff) as u8,
-            green : ((pixel_value  >> green_offset) & 0xff) as u8,
-            red   : ((pixel_value  >> red_offset)   & 0xff) as u8,
-            alpha : ((pixel_value  >> alpha_offset) & 0xff) as u8,
-        };
-
-        if alpha_mask == 0x00 {
-            // NOTE(erick): We are in XRGB mode.
-            pixel.alpha = 0xff;
+    let red   = BitfieldChannel::for_reading(red_mask);
+    let green = BitfieldChannel::for_reading(green_mask);
+    let blue  = BitfieldChannel::for_reading(blue_mask);
+    let alpha = BitfieldChannel::for_reading(alpha_mask);
+
+    let bytes_per_row = padded_row_size(image_width, 4);
+    for _ in 0 .. image_height {
+        if data_walker.remaining() < bytes_per_row {
+            break;
         }
 
-        result.push(pixel);
+        for _ in 0 .. image_width {
+            let pixel_value = data_walker.next_u32();
+
+            let mut pixel = BitmapPixel {
+                blue  : blue.read(pixel_value),
+                green : green.read(pixel_value),
+                red   : red.read(pixel_value),
+                alpha : alpha.read(pixel_value),
+            };
+
+            if alpha_mask == 0x00 {
+                // NOTE(erick): We are in XRGB mode.
+                pixel.alpha = 0xff;
+            }
+
+            result.push(pixel);
+        }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn read_16_bitfield(data_walker: &mut BytesWalker,
                         result: &mut Vec<BitmapPixel>,
-                        image_width: i32,
+                        image_width: i32, image_height: i32,
                         red_mask: u32,
                         green_mask: u32,
                         blue_mask: u32,
                         alpha_mask: u32) {
-    let (red_offset,   red_shifted)   = mask_offset_and_shifted(red_mask);
-    let (green_offset, green_shifted) = mask_offset_and_shifted(green_mask);
-    let (blue_offset,  blue_shifted)  = mask_offset_and_shifted(blue_mask);
-    let (alpha_offset, alpha_shifted) = mask_offset_and_shifted(alpha_mask);
-
-    let mut column_index = 0;
-    while data_walker.has_data() {
-        if column_index == image_width {
-            // NOTE(erick): We have to align rows to
-            // 4 bytes values.
-            column_index = 0;
-            data_walker.align_with_u32();
-        }
+    let red   = BitfieldChannel::for_reading(red_mask);
+    let green = BitfieldChannel::for_reading(green_mask);
+    let blue  = BitfieldChannel::for_reading(blue_mask);
+    let alpha = BitfieldChannel::for_reading(alpha_mask);
 
-        // NOTE(erick): The file can have some padding at the end.
-        if !data_walker.has_data() {
+    let bytes_per_row = padded_row_size(image_width, 2);
+    for _ in 0 .. image_height {
+        if data_walker.remaining() < bytes_per_row {
             break;
         }
 
-        let pixel_value = data_walker.next_u16() as u32;
+        for _ in 0 .. image_width {
+            let pixel_value = data_walker.next_u16() as u32;
 
-        let mut pixel = BitmapPixel {
-            blue  : ((pixel_value & blue_mask)  >> blue_offset)  as u8,
-            green : ((pixel_value & green_mask) >> green_offset) as u8,
-            red   : ((pixel_value & red_mask)   >> red_offset)   as u8,
-            alpha : ((pixel_value & alpha_mask) >> alpha_offset) as u8,
-        };
+            let mut pixel = BitmapPixel {
+                blue  : blue.read(pixel_value),
+                green : green.read(pixel_value),
+                red   : red.read(pixel_value),
+                alpha : alpha.read(pixel_value),
+            };
 
-        map_zero_based(&mut pixel.red   , red_shifted, 0xff);
-        map_zero_based(&mut pixel.green , green_shifted, 0xff);
-        map_zero_based(&mut pixel.blue  , blue_shifted, 0xff);
-        map_zero_based(&mut pixel.alpha , alpha_shifted, 0xff);
+            if alpha_mask == 0x00 {
+                // NOTE(erick): We are in XRGB mode.
+                pixel.alpha = 0xff;
+            }
 
-        if alpha_mask == 0x00 {
-            // NOTE(erick): We are in XRGB mode.
-            pixel.alpha = 0xff;
+            result.push(pixel);
         }
 
-        result.push(pixel);
-        column_index += 1;
+        // NOTE(erick): We have to align rows to 4 bytes values.
+        data_walker.align_with_u32();
     }
 }
 
 pub fn read_32_uncompressed(data_walker: &mut BytesWalker,
-                            result: &mut Vec<BitmapPixel>) {
-    // NOTE(erick): We only have alpha when the
-    // compression_type is BitFields. The last byte is
-    // here only for padding.
-    while data_walker.has_data() {
-        let pixel = BitmapPixel {
-            blue  : data_walker.next_u8(),
-            green : data_walker.next_u8(),
-            red   : data_walker.next_u8(),
-            alpha : 0xff,
-        };
-        // NOTE(erick): We have to discard the padding byte.
-        data_walker.next_u8();
-        result.push(pixel);
+                            result: &mut Vec<BitmapPixel>,
+                            image_width: i32, image_height: i32) {
+    let bytes_per_row = padded_row_size(image_width, 4);
+    for _ in 0 .. image_height {
+        if data_walker.remaining() < bytes_per_row {
+            break;
+        }
+
+        // NOTE(erick): We only have alpha when the
+        // compression_type is BitFields. The last byte is
+        // here only for padding.
+        for _ in 0 .. image_width {
+            let pixel = BitmapPixel {
+                blue  : data_walker.next_u8(),
+                green : data_walker.next_u8(),
+                red   : data_walker.next_u8(),
+                alpha : 0xff,
+            };
+            // NOTE(erick): We have to discard the padding byte.
+            data_walker.next_u8();
+            result.push(pixel);
+        }
     }
 }
 
 pub fn read_24_uncompressed(data_walker: &mut BytesWalker,
                             result: &mut Vec<BitmapPixel>,
-                            image_width: i32) {
-    let mut column_index = 0;
-    while data_walker.has_data() {
-        if column_index == image_width {
-            // NOTE(erick): We have to align rows to
-            // 4 bytes values.
-            column_index = 0;
-            data_walker.align_with_u32();
-
-            // NOTE(erick): The file can have some padding at the end.
-            if !data_walker.has_data() {
-                break;
-            }
+                            image_width: i32, image_height: i32) {
+    let bytes_per_row = padded_row_size(image_width, 3);
+    for _ in 0 .. image_height {
+        if data_walker.remaining() < bytes_per_row {
+            break;
         }
 
-        let pixel = BitmapPixel {
-            blue  : data_walker.next_u8(),
-            green : data_walker.next_u8(),
-            red   : data_walker.next_u8(),
-            alpha : 0xff,
-        };
+        for _ in 0 .. image_width {
+            let pixel = BitmapPixel {
+                blue  : data_walker.next_u8(),
+                green : data_walker.next_u8(),
+                red   : data_walker.next_u8(),
+                alpha : 0xff,
+            };
 
-        result.push(pixel);
-        column_index += 1;
+            result.push(pixel);
+        }
+
+        // NOTE(erick): We have to align rows to 4 bytes values.
+        data_walker.align_with_u32();
     }
 }
 
 pub fn read_16_uncompressed(data_walker: &mut BytesWalker,
                             result: &mut Vec<BitmapPixel>,
-                            image_width: i32) {
-    let mut column_index = 0;
-    while data_walker.has_data() {
-        if column_index == image_width {
-            // NOTE(erick): We have to align rows to
-            // 4 bytes values.
-            column_index = 0;
-            data_walker.align_with_u32();
-
-            // NOTE(erick): The file can have some padding at the end.
-            if !data_walker.has_data() {
-                break;
-            }
-        }
+                            image_width: i32, image_height: i32) {
+    let channel_lut = ChannelLut::new(0x1f, 0xff);
 
-        let pixel_data = data_walker.next_u16();
-        let mut pixel = BitmapPixel {
-            blue  : (pixel_data & 0x1f) as u8,
-            green : ((pixel_data  >>  5) & 0x1f) as u8,
-            red   : ((pixel_data  >> 10) & 0x1f) as u8,
-            alpha : 0xff,
-        };
-
-        map_zero_based(&mut pixel.red, 0x1f, 0xff);
-        map_zero_based(&mut pixel.green, 0x1f, 0xff);
-        map_zero_based(&mut pixel.blue, 0x1f, 0xff);
+    let bytes_per_row = padded_row_size(image_width, 2);
+    for _ in 0 .. image_height {
+        if data_walker.remaining() < bytes_per_row {
+            break;
+        }
 
-        result.push(pixel);
-        column_index += 1;
-    }
-}
+        for _ in 0 .. image_width {
+            let pixel_data = data_walker.next_u16() as u32;
+            let pixel = BitmapPixel {
+                blue  : channel_lut.get(pixel_data & 0x1f) as u8,
+                green : channel_lut.get((pixel_data  >>  5) & 0x1f) as u8,
+                red   : channel_lut.get((pixel_data  >> 10) & 0x1f) as u8,
+                alpha : 0xff,
+            };
 
-pub fn read_8_uncompressed(data_walker: &mut BytesWalker,
-                           result: &mut Vec<BitmapPixel>,
-                           image_width: i32,
-                           image_palette: &BitmapPalette) {
-    let mut column_index = 0;
-    while data_walker.has_data() {
-        if column_index == image_width {
-            column_index = 0;
-            data_walker.align_with_u32();
-
-            if !data_walker.has_data() {
-                break;
-            }
+            result.push(pixel);
         }
-        let pixel_index = data_walker.next_u8() as usize;
-        let pixel = image_palette[pixel_index];
 
-        result.push(pixel);
-        column_index += 1;
+        // NOTE(erick): We have to align rows to 4 bytes values.
+        data_walker.align_with_u32();
     }
 }
 
-pub fn read_4_uncompressed(data_walker: &mut BytesWalker,
-                           result: &mut Vec<BitmapPixel>,
-                           image_width: i32,
-                           image_palette: &BitmapPalette) {
-    let mut column_index = 0;
-    while data_walker.has_data() {
-        if column_index >= image_width {
-            column_index = 0;
-            data_walker.align_with_u32();
-
-            if !data_walker.has_data() {
-                break;
-            }
-        }
-        let pixels_indexes = data_walker.next_u8();
-        let p0_index = (pixels_indexes >> 4) as usize;
-        let p1_index = (pixels_indexes & 0x0f) as usize;
+// NOTE(erick): 1, 2, 4 and 8 bits per pixel. Rows are handled one at
+// a time using the padded row size, so odd widths can't shift the
+// following rows. Pixels are packed starting from the most significant
+// bits. A truncated last row is dropped.
+pub fn read_palettized_uncompressed(data_walker: &mut BytesWalker,
+                                    result: &mut Vec<BitmapPixel>,
+                                    image_width: i32, image_height: i32,
+                                    bits_per_pixel: u16,
+                                    image_palette: &mut PaletteLookup) {
+    let width = image_width as usize;
+    let bits_per_pixel = bits_per_pixel as usize;
+    let bytes_per_row = (width * bits_per_pixel).div_ceil(32) * 4;
+    let index_mask = (1u16 << bits_per_pixel) as usize - 1;
 
-        let pixel0 = image_palette[p0_index];
-        let pixel1 = image_palette[p1_index];
+    for _ in 0 .. image_height {
+        if data_walker.remaining() < bytes_per_row {
+            break;
+        }
 
-        result.push(pixel0);
-        column_index += 1;
+        let row = data_walker.next_bytes(bytes_per_row);
+        for column_index in 0 .. width {
+            let bit_offset = column_index * bits_per_pixel;
+            let shift = 8 - bits_per_pixel - bit_offset % 8;
+            let color_index = (row[bit_offset / 8] as usize >> shift) & index_mask;
 
-        if column_index < image_width {
-            result.push(pixel1);
-            column_index += 1;
+            result.push(image_palette.indexed_color(color_index));
         }
     }
 }
 
-pub fn read_1_uncompressed(data_walker: &mut BytesWalker,
-                           result: &mut Vec<BitmapPixel>,
-                           image_width: i32, image_height: i32,
-                           image_palette: &BitmapPalette) {
-    for _ in 0 .. image_height {
-        let mut column_index = 0;
-        for _ in 0 .. image_width / 8 {
-            let pixels_byte = data_walker.next_u8();
-            append_pixels_from_byte(&image_palette,
-                                    result,
-                                    pixels_byte, 8);
-
-            column_index += 8;
+// NOTE(erick): RLE images may skip pixels using the delta escape
+// or by ending a line early. Those pixels are left with the first
+// palette entry, which is what most decoders do.
+#[cfg(feature = "decode-rle")]
+pub fn read_rle(data_walker: &mut BytesWalker,
+                result: &mut Vec<BitmapPixel>,
+                image_width: i32, image_height: i32,
+                image_palette: &mut PaletteLookup,
+                bits_per_pixel: u16) {
+    let width  = image_width as usize;
+    let height = image_height as usize;
+
+    result.clear();
+    result.resize(width * height, image_palette.background());
+
+    let mut x = 0;
+    let mut y = 0;
+
+    while data_walker.has_data() && y < height {
+        let count = data_walker.next_u8() as usize;
+        if !data_walker.has_data() {
+            break;
         }
-
-        let remaining_pixels = image_width - column_index;
-        if remaining_pixels > 0 {
-            let pixels_byte = data_walker.next_u8();
-            append_pixels_from_byte(&image_palette,
-                                    result,
-                                    pixels_byte,
-                                    remaining_pixels);
+        let value = data_walker.next_u8();
+
+        if count > 0 {
+            // NOTE(erick): Encoded mode, `value` is repeated `count` times.
+            for pixel_index in 0 .. count {
+                let color_index = rle_color_index(value, pixel_index,
+                                                  bits_per_pixel);
+                put_rle_pixel(result, image_palette, width, x, y, color_index);
+                x += 1;
+            }
+            continue;
         }
 
-        data_walker.align_with_u32()
+        match value {
+            0 => {
+                // NOTE(erick): End of line.
+                x = 0;
+                y += 1;
+            },
+            1 => {
+                // NOTE(erick): End of bitmap.
+                break;
+            },
+            2 => {
+                // NOTE(erick): Delta, the next two bytes are a
+                // displacement to the right and up.
+                if !data_walker.has_data() { break; }
+                x += data_walker.next_u8() as usize;
+                if !data_walker.has_data() { break; }
+                y += data_walker.next_u8() as usize;
+            },
+            n_pixels => {
+                // NOTE(erick): Absolute mode, `n_pixels` literal indexes
+                // follow, padded to a 16-bit boundary.
+                let n_pixels = n_pixels as usize;
+                let n_bytes = if bits_per_pixel == 4 {
+                    n_pixels.div_ceil(2)
+                } else {
+                    n_pixels
+                };
+
+                let mut byte = 0;
+                for pixel_index in 0 .. n_pixels {
+                    if bits_per_pixel != 4 || pixel_index.is_multiple_of(2) {
+                        if !data_walker.has_data() { break; }
+                        byte = data_walker.next_u8();
+                    }
+
+                    let color_index = rle_color_index(byte, pixel_index,
+                                                      bits_per_pixel);
+                    put_rle_pixel(result, image_palette, width, x, y, color_index);
+                    x += 1;
+                }
+
+                if !n_bytes.is_multiple_of(2) && data_walker.has_data() {
+                    data_walker.next_u8();
+                }
+            },
+        }
     }
 }
 
-fn append_pixels_from_byte(palette: &BitmapPalette,
-                        vec:&mut Vec<BitmapPixel>,
-                        byte: u8, n_bits: i32) {
-    let mut mask = 0x80;
-
-    for _ in 0 .. n_bits {
-        if byte & mask == 0 {
-            vec.push(palette[0]);
+#[cfg(feature = "decode-rle")]
+fn rle_color_index(byte: u8, pixel_index: usize, bits_per_pixel: u16) -> usize {
+    if bits_per_pixel == 4 {
+        if pixel_index.is_multiple_of(2) {
+            (byte >> 4) as usize
         } else {
-            vec.push(palette[1]);
+            (byte & 0x0f) as usize
         }
+    } else {
+        byte as usize
+    }
+}
+
+#[cfg(feature = "decode-rle")]
+#[allow(clippy::ptr_arg)]
+fn put_rle_pixel(result: &mut Vec<BitmapPixel>, palette: &mut PaletteLookup,
+                 width: usize, x: usize, y: usize, color_index: usize) {
+    // NOTE(erick): Broken files can run past the end of a row or of
+    // the image. We just drop those pixels.
+    if x >= width {
+        return;
+    }
 
-        mask = mask >> 1;
+    let data_index = y * width + x;
+    if data_index < result.len() {
+        result[data_index] = palette.color(color_index);
     }
 }
@@ -0,0 +1,332 @@
+use Bitmap;
+use BitmapPixel;
+use BitmapResult;
+use BitmapError;
+use CompressionType;
+
+use std::f32::consts::PI;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ResizeFilter {
+    NearestNeighbor,
+    Bilinear,
+    Lanczos3,
+}
+
+impl ResizeFilter {
+    fn radius(&self) -> f32 {
+        match *self {
+            ResizeFilter::NearestNeighbor => 0.5,
+            ResizeFilter::Bilinear        => 1.0,
+            ResizeFilter::Lanczos3        => 3.0,
+        }
+    }
+
+    fn weight(&self, x: f32) -> f32 {
+        let x = x.abs();
+        match *self {
+            ResizeFilter::NearestNeighbor => {
+                if x <= 0.5 { 1.0 } else { 0.0 }
+            },
+            ResizeFilter::Bilinear => {
+                if x < 1.0 { 1.0 - x } else { 0.0 }
+            },
+            ResizeFilter::Lanczos3 => {
+                if x < 1e-6 {
+                    1.0
+                } else if x < 3.0 {
+                    let pi_x = PI * x;
+                    3.0 * pi_x.sin() * (pi_x / 3.0).sin() / (pi_x * pi_x)
+                } else {
+                    0.0
+                }
+            },
+        }
+    }
+}
+
+// NOTE(erick): Channels are kept as f32 in [0, 255] while resampling
+// so the intermediate pass doesn't lose precision.
+#[derive(Clone, Copy, Default)]
+pub struct FloatPixel {
+    pub red   : f32,
+    pub green : f32,
+    pub blue  : f32,
+    pub alpha : f32,
+}
+
+impl FloatPixel {
+    pub fn from_pixel(pixel: BitmapPixel) -> FloatPixel {
+        FloatPixel {
+            red   : pixel.red   as f32,
+            green : pixel.green as f32,
+            blue  : pixel.blue  as f32,
+            alpha : pixel.alpha as f32,
+        }
+    }
+
+    pub fn to_pixel(self) -> BitmapPixel {
+        BitmapPixel::rgba(clamp_channel(self.red),
+                          clamp_channel(self.green),
+                          clamp_channel(self.blue),
+                          clamp_channel(self.alpha))
+    }
+
+    pub fn accumulate(&mut self, other: FloatPixel, weight: f32) {
+        self.red   += other.red   * weight;
+        self.green += other.green * weight;
+        self.blue  += other.blue  * weight;
+        self.alpha += other.alpha * weight;
+    }
+}
+
+pub fn clamp_channel(value: f32) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+// NOTE(erick): Samples 'image' at a fractional position in screen
+// coordinates, pixel centers are at integer positions. Positions
+// outside the image are clamped to the border.
+pub fn sample_bilinear(image: &Bitmap, x: f32, y: f32) -> BitmapPixel {
+    let max_x = image.info_header.image_width  as i64 - 1;
+    let max_y = image.info_header.image_height as i64 - 1;
+
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let tx = x - x0;
+    let ty = y - y0;
+
+    let at = |xi: i64, yi: i64| {
+        FloatPixel::from_pixel(image.get_pixel(xi.clamp(0, max_x) as u32,
+                                               yi.clamp(0, max_y) as u32))
+    };
+
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let mut result = FloatPixel::default();
+    result.accumulate(at(x0,     y0),     (1.0 - tx) * (1.0 - ty));
+    result.accumulate(at(x0 + 1, y0),     tx         * (1.0 - ty));
+    result.accumulate(at(x0,     y0 + 1), (1.0 - tx) * ty);
+    result.accumulate(at(x0 + 1, y0 + 1), tx         * ty);
+
+    result.to_pixel()
+}
+
+// NOTE(erick): Point sampling with any of the resize filters, same
+// conventions as 'sample_bilinear'. The kernel is not stretched since
+// we don't know the local scale of the mapping.
+pub fn sample(image: &Bitmap, x: f32, y: f32, filter: ResizeFilter) -> BitmapPixel {
+    let max_x = image.info_header.image_width  as i64 - 1;
+    let max_y = image.info_header.image_height as i64 - 1;
+
+    match filter {
+        ResizeFilter::NearestNeighbor => {
+            let xi = (x.round() as i64).clamp(0, max_x);
+            let yi = (y.round() as i64).clamp(0, max_y);
+            image.get_pixel(xi as u32, yi as u32)
+        },
+        ResizeFilter::Bilinear => sample_bilinear(image, x, y),
+        _ => {
+            let radius = filter.radius();
+            let first_x = (x - radius).ceil() as i64;
+            let last_x  = (x + radius).floor() as i64;
+            let first_y = (y - radius).ceil() as i64;
+            let last_y  = (y + radius).floor() as i64;
+
+            let mut result = FloatPixel::default();
+            let mut weights_sum = 0.0;
+            for yi in first_y ..= last_y {
+                let weight_y = filter.weight(yi as f32 - y);
+                for xi in first_x ..= last_x {
+                    let weight = weight_y * filter.weight(xi as f32 - x);
+                    let pixel = image.get_pixel(xi.clamp(0, max_x) as u32,
+                                                yi.clamp(0, max_y) as u32);
+
+                    result.accumulate(FloatPixel::from_pixel(pixel), weight);
+                    weights_sum += weight;
+                }
+            }
+
+            if weights_sum != 0.0 {
+                result.red   /= weights_sum;
+                result.green /= weights_sum;
+                result.blue  /= weights_sum;
+                result.alpha /= weights_sum;
+            }
+
+            result.to_pixel()
+        },
+    }
+}
+
+// NOTE(erick): For each destination index we store the first source
+// index and the normalized weights of the source samples.
+fn axis_weights(src_size: u32, dest_size: u32,
+                filter: ResizeFilter) -> Vec<(i64, Vec<f32>)> {
+    // NOTE(erick): Nearest neighbor takes the one source pixel under the
+    // destination center, even when shrinking. Integer math keeps exact
+    // ratios from landing on the wrong side of a pixel.
+    if filter == ResizeFilter::NearestNeighbor {
+        return (0 .. dest_size as u64)
+            .map(|dest_index| {
+                let src_index = (2 * dest_index + 1) * src_size as u64 / (2 * dest_size as u64);
+                (src_index.min(src_size as u64 - 1) as i64, vec![1.0])
+            })
+            .collect();
+    }
+
+    let scale = src_size as f32 / dest_size as f32;
+    // NOTE(erick): When shrinking we stretch the kernel so every source
+    // pixel contributes to the result.
+    let filter_scale = scale.max(1.0);
+    let support = filter.radius() * filter_scale;
+
+    let mut result = Vec::with_capacity(dest_size as usize);
+    for dest_index in 0 .. dest_size {
+        let center = (dest_index as f32 + 0.5) * scale - 0.5;
+        let first = (center - support).floor() as i64;
+        let last  = (center + support).ceil() as i64;
+
+        let mut weights = Vec::with_capacity((last - first + 1) as usize);
+        let mut weights_sum = 0.0;
+        for src_index in first ..= last {
+            let weight = filter.weight((src_index as f32 - center) / filter_scale);
+            weights.push(weight);
+            weights_sum += weight;
+        }
+
+        if weights_sum != 0.0 {
+            for weight in &mut weights {
+                *weight /= weights_sum;
+            }
+        }
+
+        result.push((first, weights));
+    }
+
+    result
+}
+
+fn resample(src: &[FloatPixel], src_width: u32, src_height: u32,
+            dest_width: u32, dest_height: u32,
+            filter: ResizeFilter) -> Vec<FloatPixel> {
+    let clamp = |index: i64, size: u32| index.clamp(0, size as i64 - 1) as usize;
+
+    // NOTE(erick): Horizontal pass first, then vertical.
+    let x_weights = axis_weights(src_width, dest_width, filter);
+    let mut horizontal = Vec::with_capacity((dest_width * src_height) as usize);
+    for y in 0 .. src_height as usize {
+        let row = &src[y * src_width as usize .. (y + 1) * src_width as usize];
+        for &(first, ref weights) in &x_weights {
+            let mut pixel = FloatPixel::default();
+            for (offset, weight) in weights.iter().enumerate() {
+                pixel.accumulate(row[clamp(first + offset as i64, src_width)], *weight);
+            }
+            horizontal.push(pixel);
+        }
+    }
+
+    let y_weights = axis_weights(src_height, dest_height, filter);
+    let mut result = Vec::with_capacity((dest_width * dest_height) as usize);
+    for &(first, ref weights) in &y_weights {
+        for x in 0 .. dest_width as usize {
+            let mut pixel = FloatPixel::default();
+            for (offset, weight) in weights.iter().enumerate() {
+                let y = clamp(first + offset as i64, src_height);
+                pixel.accumulate(horizontal[y * dest_width as usize + x], *weight);
+            }
+            result.push(pixel);
+        }
+    }
+
+    result
+}
+
+impl Bitmap {
+    pub fn resize(&mut self, new_width: u32, new_height: u32,
+                  filter: ResizeFilter) -> BitmapResult<()> {
+        let width  = self.info_header.image_width as u32;
+        let height = self.info_header.image_height as u32;
+
+        // NOTE(erick): Empty images have no pixels to sample from.
+        if new_width == 0 || new_height == 0 || width == 0 || height == 0 {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        let mut screen_pixels = Vec::with_capacity((width * height) as usize);
+        for y in 0 .. height {
+            for x in 0 .. width {
+                screen_pixels.push(FloatPixel::from_pixel(self.get_pixel(x, y)));
+            }
+        }
+
+        let resized = resample(&screen_pixels, width, height,
+                               new_width, new_height, filter);
+
+        // NOTE(erick): Same headers as a crop to the new size, which also
+        // turns RLE into Uncompressed since we can't write RLE.
+        let (file_header, info_header) = self.crop_headers(new_width, new_height);
+        self.file_header = file_header;
+        self.info_header = info_header;
+        self.image_data = vec![BitmapPixel::transparent();
+                               (new_width * new_height) as usize];
+        self.palette_indices.clear();
+
+        let mut resized_iter = resized.into_iter();
+        for y in 0 .. new_height {
+            for x in 0 .. new_width {
+                let pixel = resized_iter.next().unwrap().to_pixel();
+                self.set_pixel(x, y, pixel);
+            }
+        }
+
+        Ok(())
+    }
+
+    // NOTE(erick): Shrinks the image to fit inside max_width x max_height
+    // keeping its aspect ratio. Images that already fit are not enlarged.
+    // When a letterbox color is given the result is exactly
+    // max_width x max_height with the thumbnail centered on it.
+    pub fn thumbnail(&self, max_width: u32, max_height: u32,
+                     letterbox: Option<BitmapPixel>) -> BitmapResult<Bitmap> {
+        let width  = self.info_header.image_width as u32;
+        let height = self.info_header.image_height as u32;
+
+        if max_width == 0 || max_height == 0 || width == 0 || height == 0 {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        let scale = (max_width as f32 / width as f32)
+            .min(max_height as f32 / height as f32)
+            .min(1.0);
+        let thumb_width  = ((width as f32 * scale).round() as u32).clamp(1, max_width);
+        let thumb_height = ((height as f32 * scale).round() as u32).clamp(1, max_height);
+
+        let mut thumb = self.clone();
+        if thumb_width != width || thumb_height != height {
+            thumb.resize(thumb_width, thumb_height, ResizeFilter::Bilinear)?;
+        }
+
+        let background = match letterbox {
+            Some(color) => color,
+            None        => return Ok(thumb),
+        };
+
+        let compression = CompressionType::from(self.info_header.compression_type);
+        let mut result = Bitmap::new(max_width as i32, max_height as i32,
+                                     self.info_header.bits_per_pixel,
+                                     compression);
+        result.palette = thumb.palette.clone();
+        for pixel in &mut result.image_data {
+            *pixel = background;
+        }
+
+        result.replace_rect_with_rect_from(&thumb,
+                                           0, 0,
+                                           (max_width - thumb_width) / 2,
+                                           (max_height - thumb_height) / 2,
+                                           thumb_width, thumb_height);
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,319 @@
+use Bitmap;
+use BitmapPixel;
+use BitmapResult;
+use BitmapError;
+use BytesWalker;
+use CompressionType;
+
+use bitmap_write::push_u16;
+
+use std::io::Read;
+use std::io::Write;
+
+const TGA_HEADER_SIZE : usize = 18;
+
+// NOTE(erick): Image types we handle. Adding 8 to a type gives its
+// RLE compressed version.
+const TGA_COLOR_MAPPED : u8 = 1;
+const TGA_TRUE_COLOR   : u8 = 2;
+const TGA_GRAYSCALE    : u8 = 3;
+const TGA_RLE_FLAG     : u8 = 8;
+
+// NOTE(erick): Bits of the image descriptor.
+const TGA_ALPHA_BITS_MASK  : u8 = 0x0f;
+const TGA_RIGHT_TO_LEFT    : u8 = 0x10;
+const TGA_TOP_TO_BOTTOM    : u8 = 0x20;
+
+const TGA_MAX_PACKET_LENGTH : usize = 128;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TgaCompression {
+    Uncompressed,
+    Rle,
+}
+
+struct TgaHeader {
+    id_length       : u8,
+    color_map_type  : u8,
+    image_type      : u8,
+    color_map_first : u16,
+    color_map_size  : u16,
+    color_map_depth : u8,
+    width           : u16,
+    height          : u16,
+    pixel_depth     : u8,
+    descriptor      : u8,
+}
+
+impl TgaHeader {
+    fn from_data(data: &[u8]) -> TgaHeader {
+        let mut data_walker = BytesWalker::new(data);
+
+        let id_length       = data_walker.next_u8();
+        let color_map_type  = data_walker.next_u8();
+        let image_type      = data_walker.next_u8();
+        let color_map_first = data_walker.next_u16();
+        let color_map_size  = data_walker.next_u16();
+        let color_map_depth = data_walker.next_u8();
+        let _x_origin       = data_walker.next_u16();
+        let _y_origin       = data_walker.next_u16();
+
+        TgaHeader {
+            id_length       : id_length,
+            color_map_type  : color_map_type,
+            image_type      : image_type,
+            color_map_first : color_map_first,
+            color_map_size  : color_map_size,
+            color_map_depth : color_map_depth,
+            width           : data_walker.next_u16(),
+            height          : data_walker.next_u16(),
+            pixel_depth     : data_walker.next_u8(),
+            descriptor      : data_walker.next_u8(),
+        }
+    }
+
+    fn has_alpha(&self) -> bool {
+        self.descriptor & TGA_ALPHA_BITS_MASK != 0
+    }
+}
+
+// NOTE(erick): TGA stores colors as BGR(A) like BMP. 15 and 16 bits
+// pixels are A1R5G5B5.
+fn read_tga_color(bytes: &[u8], has_alpha: bool) -> BitmapPixel {
+    let alpha = |value: u8| if has_alpha { value } else { 0xff };
+
+    match bytes.len() {
+        1 => BitmapPixel::rgb(bytes[0], bytes[0], bytes[0]),
+        2 => {
+            let value = bytes[0] as u16 | (bytes[1] as u16) << 8;
+            let expand = |channel: u16| ((channel << 3) | (channel >> 2)) as u8;
+            BitmapPixel::rgba(expand((value >> 10) & 0x1f),
+                              expand((value >> 5) & 0x1f),
+                              expand(value & 0x1f),
+                              alpha(if value & 0x8000 != 0 { 0xff } else { 0x00 }))
+        },
+        3 => BitmapPixel::rgb(bytes[2], bytes[1], bytes[0]),
+        _ => BitmapPixel::rgba(bytes[2], bytes[1], bytes[0], alpha(bytes[3])),
+    }
+}
+
+// NOTE(erick): Reads 'n_pixels' raw pixel values of 'bytes_per_pixel'
+// bytes each, expanding RLE packets. Packets are allowed to cross rows.
+fn read_tga_values(data_walker: &mut BytesWalker, n_pixels: usize,
+                   bytes_per_pixel: usize, is_rle: bool) -> BitmapResult<Vec<u8>> {
+    let total_size = n_pixels * bytes_per_pixel;
+
+    if !is_rle {
+        if data_walker.remaining() < total_size {
+            return Err(BitmapError::InvalidBitmap);
+        }
+        return Ok(data_walker.next_bytes(total_size).to_vec());
+    }
+
+    let max_size = data_walker.remaining() * TGA_MAX_PACKET_LENGTH;
+    let mut result = Vec::with_capacity(total_size.min(max_size));
+    while result.len() < total_size {
+        if !data_walker.has_data() {
+            return Err(BitmapError::InvalidBitmap);
+        }
+
+        let packet_header = data_walker.next_u8();
+        let count = (packet_header & 0x7f) as usize + 1;
+        let is_run = packet_header & 0x80 != 0;
+
+        let n_bytes = if is_run { bytes_per_pixel } else { count * bytes_per_pixel };
+        if data_walker.remaining() < n_bytes {
+            return Err(BitmapError::InvalidBitmap);
+        }
+
+        let values = data_walker.next_bytes(n_bytes);
+        if is_run {
+            for _ in 0 .. count {
+                result.extend_from_slice(values);
+            }
+        } else {
+            result.extend_from_slice(values);
+        }
+    }
+
+    result.truncate(total_size);
+    Ok(result)
+}
+
+// NOTE(erick): Run packets for repeated pixels, raw packets for the rest.
+// Packets never cross rows, which some old readers require.
+fn write_tga_rle_row(data: &mut Vec<u8>, row: &[[u8; 4]], bytes_per_pixel: usize) {
+    let mut index = 0;
+    while index < row.len() {
+        let mut run_length = 1;
+        while index + run_length < row.len() && run_length < TGA_MAX_PACKET_LENGTH &&
+            row[index + run_length] == row[index] {
+            run_length += 1;
+        }
+
+        if run_length > 1 {
+            data.push(0x80 | (run_length - 1) as u8);
+            data.extend_from_slice(&row[index][.. bytes_per_pixel]);
+            index += run_length;
+            continue;
+        }
+
+        // NOTE(erick): The raw packet stops right before the next run.
+        let start = index;
+        while index < row.len() && index - start < TGA_MAX_PACKET_LENGTH &&
+            !(index + 1 < row.len() && row[index + 1] == row[index]) {
+            index += 1;
+        }
+
+        data.push((index - start - 1) as u8);
+        for pixel in &row[start .. index] {
+            data.extend_from_slice(&pixel[.. bytes_per_pixel]);
+        }
+    }
+}
+
+impl Bitmap {
+    // NOTE(erick): Reads color mapped, true color and grayscale Targa
+    // files, uncompressed or RLE. Images with alpha come out as 32 bits
+    // bitmaps, the others as 24 bits ones.
+    pub fn from_tga_data(data: &[u8]) -> BitmapResult<Bitmap> {
+        if data.len() < TGA_HEADER_SIZE {
+            return Err(BitmapError::InvalidBitmap);
+        }
+
+        let header = TgaHeader::from_data(data);
+        let is_rle = header.image_type & TGA_RLE_FLAG != 0;
+        let base_type = header.image_type & !TGA_RLE_FLAG;
+
+        let valid_depth = match base_type {
+            TGA_COLOR_MAPPED => header.pixel_depth == 8 && header.color_map_type == 1,
+            TGA_TRUE_COLOR   => matches!(header.pixel_depth, 15 | 16 | 24 | 32),
+            TGA_GRAYSCALE    => header.pixel_depth == 8,
+            _                => false,
+        };
+        if !valid_depth || header.width == 0 || header.height == 0 {
+            return Err(BitmapError::InvalidBitmap);
+        }
+
+        let mut data_walker = BytesWalker::new(data);
+        data_walker.next_bytes(TGA_HEADER_SIZE);
+        if data_walker.remaining() < header.id_length as usize {
+            return Err(BitmapError::InvalidBitmap);
+        }
+        data_walker.next_bytes(header.id_length as usize);
+
+        let mut color_map = Vec::new();
+        if header.color_map_type == 1 {
+            let entry_size = (header.color_map_depth as usize).div_ceil(8);
+            let color_map_bytes = entry_size * header.color_map_size as usize;
+            if !matches!(entry_size, 2 ..= 4) || data_walker.remaining() < color_map_bytes {
+                return Err(BitmapError::InvalidBitmap);
+            }
+
+            color_map = data_walker.next_bytes(color_map_bytes)
+                .chunks(entry_size)
+                .map(|entry| read_tga_color(entry, header.has_alpha()))
+                .collect();
+        }
+
+        let width  = header.width as usize;
+        let height = header.height as usize;
+        let bytes_per_pixel = (header.pixel_depth as usize).div_ceil(8);
+        let values = read_tga_values(&mut data_walker, width * height, bytes_per_pixel, is_rle)?;
+
+        let mut result = if header.has_alpha() {
+            Bitmap::new_default(width as i32, height as i32)
+        } else {
+            Bitmap::new(width as i32, height as i32, 24, CompressionType::Uncompressed)
+        };
+
+        for (index, value) in values.chunks(bytes_per_pixel).enumerate() {
+            let pixel = if base_type == TGA_COLOR_MAPPED {
+                let entry = (value[0] as usize).checked_sub(header.color_map_first as usize);
+                match entry.and_then(|entry| color_map.get(entry)) {
+                    Some(&color) => color,
+                    None         => return Err(BitmapError::InvalidBitmap),
+                }
+            } else {
+                read_tga_color(value, header.has_alpha())
+            };
+
+            let mut x = index % width;
+            let mut y = index / width;
+            if header.descriptor & TGA_RIGHT_TO_LEFT != 0 {
+                x = width - x - 1;
+            }
+            if header.descriptor & TGA_TOP_TO_BOTTOM == 0 {
+                y = height - y - 1;
+            }
+
+            result.set_pixel(x as u32, y as u32, pixel);
+        }
+
+        Ok(result)
+    }
+
+    pub fn from_tga_reader<R: Read>(reader: &mut R) -> BitmapResult<Bitmap> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        Bitmap::from_tga_data(&data)
+    }
+
+    // NOTE(erick): Writes a true color image, with 32 bits when any pixel
+    // is not opaque and 24 bits otherwise. Rows are stored top-down.
+    pub fn write_tga<W: Write>(&self, writer: &mut W,
+                               compression: TgaCompression) -> BitmapResult<()> {
+        let width  = self.info_header.image_width as u32;
+        let height = self.info_header.image_height as u32;
+        if width > u16::MAX as u32 || height > u16::MAX as u32 {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        let has_alpha = self.image_data.iter().any(|pixel| pixel.alpha != 0xff);
+        let bytes_per_pixel = if has_alpha { 4 } else { 3 };
+
+        let mut image_type = TGA_TRUE_COLOR;
+        if compression == TgaCompression::Rle {
+            image_type |= TGA_RLE_FLAG;
+        }
+
+        let mut descriptor = TGA_TOP_TO_BOTTOM;
+        if has_alpha {
+            descriptor |= 8;
+        }
+
+        let mut data = Vec::new();
+        data.push(0); // id_length
+        data.push(0); // color_map_type
+        data.push(image_type);
+        data.extend_from_slice(&[0; 5]); // color map specification
+        push_u16(&mut data, 0); // x_origin
+        push_u16(&mut data, 0); // y_origin
+        push_u16(&mut data, width as u16);
+        push_u16(&mut data, height as u16);
+        data.push(bytes_per_pixel as u8 * 8);
+        data.push(descriptor);
+
+        for y in 0 .. height {
+            let row : Vec<[u8; 4]> = (0 .. width)
+                .map(|x| {
+                    let pixel = self.get_pixel(x, y);
+                    [pixel.blue, pixel.green, pixel.red, pixel.alpha]
+                })
+                .collect();
+
+            match compression {
+                TgaCompression::Uncompressed => {
+                    for pixel in &row {
+                        data.extend_from_slice(&pixel[.. bytes_per_pixel]);
+                    }
+                },
+                TgaCompression::Rle => write_tga_rle_row(&mut data, &row, bytes_per_pixel),
+            }
+        }
+
+        writer.write_all(&data)?;
+        Ok(())
+    }
+}
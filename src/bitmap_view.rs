@@ -0,0 +1,229 @@
+use Bitmap;
+use BitmapPixel;
+use BitmapResult;
+use BitmapError;
+use BlendMode;
+
+// NOTE(erick): A rectangle of a parent Bitmap, borrowed instead of copied
+// like crop_to_rect does. Coordinates passed to a view are relative to
+// its top-left corner (in screen order, like Bitmap's).
+#[derive(Clone, Copy)]
+pub struct BitmapView<'a> {
+    parent : &'a Bitmap,
+    x      : u32,
+    y      : u32,
+    width  : u32,
+    height : u32,
+}
+
+// NOTE(erick): Same as BitmapView, with write access to the rectangle.
+pub struct BitmapViewMut<'a> {
+    parent : &'a mut Bitmap,
+    x      : u32,
+    y      : u32,
+    width  : u32,
+    height : u32,
+}
+
+fn check_rect(width: u32, height: u32, x: u32, y: u32,
+              view_width: u32, view_height: u32) -> BitmapResult<()> {
+    if x as u64 + view_width as u64 > width as u64 ||
+        y as u64 + view_height as u64 > height as u64 {
+        return Err(BitmapError::InvalidOperation);
+    }
+
+    Ok(())
+}
+
+impl<'a> BitmapView<'a> {
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // NOTE(erick): Pixels between the start of a row of the view and the
+    // start of the next one in the parent.
+    pub fn stride(&self) -> u32 {
+        self.parent.info_header.image_width as u32
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> BitmapPixel {
+        assert!(x < self.width && y < self.height, "Pixel outside of the view");
+        self.parent.get_pixel(self.x + x, self.y + y)
+    }
+
+    // NOTE(erick): A view of part of this view, with coordinates relative
+    // to this view.
+    pub fn view(&self, x: u32, y: u32, width: u32, height: u32) -> BitmapResult<BitmapView<'a>> {
+        check_rect(self.width, self.height, x, y, width, height)?;
+
+        Ok(BitmapView {
+            parent : self.parent,
+            x      : self.x + x,
+            y      : self.y + y,
+            width  : width,
+            height : height,
+        })
+    }
+
+    // NOTE(erick): The copy crop_to_rect would have made, in the parent's
+    // format.
+    pub fn to_bitmap(&self) -> Bitmap {
+        self.parent.cropped(self.x, self.y, self.width, self.height)
+    }
+
+    pub fn into_data(&self) -> Vec<u8> {
+        self.to_bitmap().into_data()
+    }
+}
+
+impl<'a> BitmapViewMut<'a> {
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn stride(&self) -> u32 {
+        self.parent.info_header.image_width as u32
+    }
+
+    pub fn as_view(&self) -> BitmapView<'_> {
+        BitmapView {
+            parent : self.parent,
+            x      : self.x,
+            y      : self.y,
+            width  : self.width,
+            height : self.height,
+        }
+    }
+
+    pub fn view_mut(&mut self, x: u32, y: u32,
+                    width: u32, height: u32) -> BitmapResult<BitmapViewMut<'_>> {
+        check_rect(self.width, self.height, x, y, width, height)?;
+
+        Ok(BitmapViewMut {
+            parent : self.parent,
+            x      : self.x + x,
+            y      : self.y + y,
+            width  : width,
+            height : height,
+        })
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> BitmapPixel {
+        self.as_view().get_pixel(x, y)
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, pixel: BitmapPixel) {
+        assert!(x < self.width && y < self.height, "Pixel outside of the view");
+        self.parent.set_pixel(self.x + x, self.y + y, pixel);
+    }
+
+    pub fn map_pixels<F>(&mut self, mut f: F)
+        where F: FnMut(u32, u32, BitmapPixel) -> BitmapPixel {
+        for y in 0 .. self.height {
+            for x in 0 .. self.width {
+                let pixel = self.get_pixel(x, y);
+                self.set_pixel(x, y, f(x, y, pixel));
+            }
+        }
+    }
+
+    pub fn fill(&mut self, pixel: BitmapPixel) {
+        self.map_pixels(|_, _, _| pixel);
+    }
+
+    // NOTE(erick): Copies 'source' with its top-left corner at (dest_x,
+    // dest_y) of this view. Parts falling outside of the view are
+    // clipped.
+    pub fn blit(&mut self, source: &BitmapView, dest_x: i32, dest_y: i32) {
+        self.draw_view(source, dest_x, dest_y, None);
+    }
+
+    // NOTE(erick): Like Bitmap::overlay_with_mode, clipped to the view.
+    pub fn overlay(&mut self, source: &BitmapView, dest_x: i32, dest_y: i32, mode: BlendMode) {
+        self.draw_view(source, dest_x, dest_y, Some(mode));
+    }
+
+    fn draw_view(&mut self, source: &BitmapView,
+                 dest_x: i32, dest_y: i32, mode: Option<BlendMode>) {
+        let x0 = (dest_x as i64).max(0);
+        let y0 = (dest_y as i64).max(0);
+        let x1 = (dest_x as i64 + source.width as i64).min(self.width as i64);
+        let y1 = (dest_y as i64 + source.height as i64).min(self.height as i64);
+
+        for y in y0 .. y1 {
+            for x in x0 .. x1 {
+                let src = source.get_pixel((x - dest_x as i64) as u32, (y - dest_y as i64) as u32);
+                let pixel = match mode {
+                    Some(mode) => mode.composite(&src, &self.get_pixel(x as u32, y as u32)),
+                    None       => src,
+                };
+
+                self.set_pixel(x as u32, y as u32, pixel);
+            }
+        }
+    }
+
+    pub fn to_bitmap(&self) -> Bitmap {
+        self.as_view().to_bitmap()
+    }
+
+    pub fn into_data(&self) -> Vec<u8> {
+        self.as_view().into_data()
+    }
+}
+
+impl Bitmap {
+    pub fn view(&self, x: u32, y: u32, width: u32, height: u32) -> BitmapResult<BitmapView<'_>> {
+        self.as_view().view(x, y, width, height)
+    }
+
+    pub fn view_mut(&mut self, x: u32, y: u32,
+                    width: u32, height: u32) -> BitmapResult<BitmapViewMut<'_>> {
+        check_rect(self.info_header.image_width as u32, self.info_header.image_height as u32,
+                   x, y, width, height)?;
+
+        Ok(BitmapViewMut {
+            parent : self,
+            x      : x,
+            y      : y,
+            width  : width,
+            height : height,
+        })
+    }
+
+    // NOTE(erick): The whole image as a view, e.g., to blit it into a
+    // view of another image.
+    pub fn as_view(&self) -> BitmapView<'_> {
+        BitmapView {
+            parent : self,
+            x      : 0,
+            y      : 0,
+            width  : self.info_header.image_width as u32,
+            height : self.info_header.image_height as u32,
+        }
+    }
+}
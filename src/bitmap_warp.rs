@@ -0,0 +1,151 @@
+use Bitmap;
+use BitmapPixel;
+use BitmapResult;
+use BitmapError;
+
+use ResizeFilter;
+
+use bitmap_resize::sample;
+
+use std::f32::consts::PI;
+
+// NOTE(erick): Distance from 'center' to the farthest corner, the
+// largest radius the polar transforms represent.
+fn max_radius(image: &Bitmap, center: (f32, f32)) -> f32 {
+    let width  = image.info_header.image_width as f32;
+    let height = image.info_header.image_height as f32;
+
+    let dx = center.0.max(width - 1.0 - center.0);
+    let dy = center.1.max(height - 1.0 - center.1);
+    dx.hypot(dy)
+}
+
+impl Bitmap {
+    // NOTE(erick): Backward mapping: 'source_position' maps the screen
+    // coordinates of each destination pixel to the (fractional) position
+    // of the original image it should come from. Pixel centers are at
+    // integer positions and positions outside the image get 'fill'.
+    pub fn remap<F>(&mut self, source_position: F, filter: ResizeFilter, fill: BitmapPixel)
+        where F: Fn(f32, f32) -> (f32, f32) {
+        let width  = self.info_header.image_width as u32;
+        let height = self.info_header.image_height as u32;
+        let source = self.clone();
+
+        for y in 0 .. height {
+            for x in 0 .. width {
+                let (source_x, source_y) = source_position(x as f32, y as f32);
+
+                let inside = source_x > -0.5 && source_x < width as f32 - 0.5 &&
+                    source_y > -0.5 && source_y < height as f32 - 0.5;
+                let pixel = if inside {
+                    sample(&source, source_x, source_y, filter)
+                } else {
+                    fill
+                };
+
+                self.set_pixel(x, y, pixel);
+            }
+        }
+    }
+
+    // NOTE(erick): Affine transform in one resampling pass. 'matrix' is
+    // [a, b, c, d, e, f], taking a pixel at (x, y) of the original image
+    // to (a x + b y + c, d x + e y + f) on screen. The size is kept and
+    // uncovered pixels get 'background'. Matrices that squash the image
+    // to a line or a point can't be inverted and are rejected.
+    pub fn transform(&mut self, matrix: [f32; 6], filter: ResizeFilter,
+                     background: BitmapPixel) -> BitmapResult<()> {
+        let [a, b, c, d, e, f] = matrix;
+        let determinant = a * e - b * d;
+        if determinant.abs() <= f32::EPSILON || !determinant.is_finite() {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        self.remap(|x, y| {
+            let x = x - c;
+            let y = y - f;
+
+            ((e * x - b * y) / determinant,
+             (a * y - d * x) / determinant)
+        }, filter, background);
+
+        Ok(())
+    }
+
+    // NOTE(erick): Rotates around the center of the image, clockwise on
+    // screen, keeping its size. Corners that leave the image are lost.
+    pub fn rotate(&mut self, degrees: f32, filter: ResizeFilter, background: BitmapPixel) {
+        let center_x = (self.info_header.image_width as f32 - 1.0) / 2.0;
+        let center_y = (self.info_header.image_height as f32 - 1.0) / 2.0;
+        let (sin, cos) = degrees.to_radians().sin_cos();
+
+        let matrix = [cos, -sin, center_x - cos * center_x + sin * center_y,
+                      sin,  cos, center_y - sin * center_x - cos * center_y];
+        self.transform(matrix, filter, background)
+            .expect("Rotations can always be inverted");
+    }
+
+    // NOTE(erick): Brown-Conrady radial model. A pixel at distance r
+    // from 'center' in the corrected image comes from distance
+    // r * (1 + k1 r^2 + k2 r^4) in the captured one. Distances are
+    // normalized by half of the image diagonal so the coefficients don't
+    // depend on the resolution. Barrel lenses have negative k1,
+    // pincushion lenses positive k1. Uncovered pixels become transparent.
+    pub fn undistort(&mut self, k1: f32, k2: f32, center: (f32, f32)) {
+        let width  = self.info_header.image_width as f32;
+        let height = self.info_header.image_height as f32;
+        let normalization = (width * width + height * height).sqrt() / 2.0;
+
+        if normalization == 0.0 {
+            return;
+        }
+
+        self.remap(|x, y| {
+            let dx = (x - center.0) / normalization;
+            let dy = (y - center.1) / normalization;
+            let r2 = dx * dx + dy * dy;
+            let factor = 1.0 + k1 * r2 + k2 * r2 * r2;
+
+            (center.0 + dx * factor * normalization,
+             center.1 + dy * factor * normalization)
+        }, ResizeFilter::Bilinear, BitmapPixel::rgba(0, 0, 0, 0));
+    }
+
+    // NOTE(erick): Unwraps the image around 'center', keeping its size.
+    // Columns are angles (clockwise on screen, starting at the positive
+    // x axis) and rows are radii, from the center at the top to the
+    // farthest corner at the bottom. A disc becomes a band of rows.
+    pub fn to_polar(&mut self, center: (f32, f32)) {
+        let width  = self.info_header.image_width as f32;
+        let height = self.info_header.image_height as f32;
+        let max_radius = max_radius(self, center);
+
+        self.remap(|x, y| {
+            let angle  = (x + 0.5) / width * 2.0 * PI;
+            let radius = (y + 0.5) / height * max_radius;
+
+            (center.0 + radius * angle.cos(),
+             center.1 + radius * angle.sin())
+        }, ResizeFilter::Bilinear, BitmapPixel::rgba(0, 0, 0, 0));
+    }
+
+    // NOTE(erick): Inverse of 'to_polar'. The image must have the size it
+    // had when it was unwrapped and 'center' must be the same.
+    pub fn from_polar(&mut self, center: (f32, f32)) {
+        let width  = self.info_header.image_width as f32;
+        let height = self.info_header.image_height as f32;
+        let max_radius = max_radius(self, center);
+
+        self.remap(|x, y| {
+            let dx = x - center.0;
+            let dy = y - center.1;
+            let angle = dy.atan2(dx).rem_euclid(2.0 * PI);
+            let radius = dx.hypot(dy);
+
+            // NOTE(erick): Angles within half a column of zero are clamped
+            // instead of wrapping around, good enough for one pixel.
+            ((angle / (2.0 * PI) * width - 0.5).clamp(0.0, width - 1.0),
+             (radius / max_radius * height - 0.5).clamp(0.0, height - 1.0))
+        }, ResizeFilter::Bilinear, BitmapPixel::rgba(0, 0, 0, 0));
+    }
+}
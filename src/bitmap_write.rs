@@ -1,62 +1,58 @@
 use BitmapPixel;
 use BitmapPalette;
-use mask_offset_and_shifted;
+use BitfieldChannel;
 
-use map_zero_based;
+use ChannelLut;
 
-pub fn write_32_bitfield(data: &mut Vec<u8>, pixels: &Vec<BitmapPixel>,
+pub fn write_32_bitfield(data: &mut Vec<u8>, pixels: &[BitmapPixel],
                          red_mask: u32, green_mask: u32,
                          blue_mask: u32, alpha_mask: u32) {
-    let (red_offset, _)   = mask_offset_and_shifted(red_mask);
-    let (green_offset, _) = mask_offset_and_shifted(green_mask);
-    let (blue_offset, _)  = mask_offset_and_shifted(blue_mask);
-    let (alpha_offset, _) = mask_offset_and_shifted(alpha_mask);
+    let red   = BitfieldChannel::for_writing(red_mask);
+    let green = BitfieldChannel::for_writing(green_mask);
+    let blue  = BitfieldChannel::for_writing(blue_mask);
+    let alpha = BitfieldChannel::for_writing(alpha_mask);
 
     for pixel in pixels {
-        let pixel_value : u32 =
-            (pixel.red as u32)   << red_offset   |
-        (pixel.green as u32) << green_offset |
-        (pixel.blue  as u32) << blue_offset  |
-        (pixel.alpha as u32) << alpha_offset & alpha_mask;
-        // NOTE(erick): we and with alpha_mask so we can support argb and
-        // xrgb at the same time.
+        // NOTE(erick): Channels are and-ed with their masks, so a zero
+        // alpha_mask gives us XRGB.
+        let pixel_value =
+            red.write(pixel.red)     |
+            green.write(pixel.green) |
+            blue.write(pixel.blue)   |
+            alpha.write(pixel.alpha);
 
         push_u32(data, pixel_value);
     }
 }
 
-pub fn write_16_bitfield(data: &mut Vec<u8>, pixels: &Vec<BitmapPixel>,
+#[allow(clippy::too_many_arguments)]
+pub fn write_16_bitfield(data: &mut Vec<u8>, pixels: &[BitmapPixel],
                          image_width: i32, image_height: i32,
                          red_mask: u32, green_mask: u32,
                          blue_mask: u32, alpha_mask: u32) {
-    let (red_offset, red_shifted)   = mask_offset_and_shifted(red_mask);
-    let (green_offset, green_shifted) = mask_offset_and_shifted(green_mask);
-    let (blue_offset, blue_shifted)  = mask_offset_and_shifted(blue_mask);
-    let (alpha_offset, alpha_shifted) = mask_offset_and_shifted(alpha_mask);
+    let red   = BitfieldChannel::for_writing(red_mask);
+    let green = BitfieldChannel::for_writing(green_mask);
+    let blue  = BitfieldChannel::for_writing(blue_mask);
+    let alpha = BitfieldChannel::for_writing(alpha_mask);
 
-    let mut pixel_iter = pixels.into_iter();
+    let mut pixel_iter = pixels.iter();
 
     let bytes_per_row = image_width * 2;
     let n_padding_bytes = pad_to_align!(bytes_per_row, 4);
 
     for _ in 0 .. image_height {
         for _ in 0 .. image_width {
-            let mut pixel = pixel_iter.next().unwrap().clone();
-
-            map_zero_based(&mut pixel.red, 0xff, red_shifted);
-            map_zero_based(&mut pixel.green, 0xff, green_shifted);
-            map_zero_based(&mut pixel.blue, 0xff, blue_shifted);
-            map_zero_based(&mut pixel.alpha, 0xff, alpha_shifted);
+            let pixel = pixel_iter.next().unwrap();
 
-            let pixel_value : u16 =
-                (pixel.red as u16)   << red_offset   |
-            (pixel.green as u16) << green_offset |
-            (pixel.blue  as u16) << blue_offset  |
-            (pixel.alpha as u16) << alpha_offset & alpha_mask as u16;
-            // NOTE(erick): We and with alpha_mask so we can support ARGB and
-            // XRGB at the same time.
+            // NOTE(erick): Channels are and-ed with their masks, so a zero
+            // alpha_mask gives us XRGB.
+            let pixel_value =
+                red.write(pixel.red)     |
+                green.write(pixel.green) |
+                blue.write(pixel.blue)   |
+                alpha.write(pixel.alpha);
 
-            push_u16(data, pixel_value);
+            push_u16(data, pixel_value as u16);
         }
 
         for _ in 0 .. n_padding_bytes {
@@ -65,7 +61,7 @@ pub fn write_16_bitfield(data: &mut Vec<u8>, pixels: &Vec<BitmapPixel>,
     }
 }
 
-pub fn write_32_uncompressed(data: &mut Vec<u8>, pixels: &Vec<BitmapPixel>) {
+pub fn write_32_uncompressed(data: &mut Vec<u8>, pixels: &[BitmapPixel]) {
     for pixel in pixels {
         data.push(pixel.blue);
         data.push(pixel.green);
@@ -74,9 +70,9 @@ pub fn write_32_uncompressed(data: &mut Vec<u8>, pixels: &Vec<BitmapPixel>) {
     }
 }
 
-pub fn write_24_uncompressed(data: &mut Vec<u8>, pixels: &Vec<BitmapPixel>,
+pub fn write_24_uncompressed(data: &mut Vec<u8>, pixels: &[BitmapPixel],
                              image_width: i32, image_height: i32) {
-    let mut pixel_iter = pixels.into_iter();
+    let mut pixel_iter = pixels.iter();
 
     let bytes_per_row = image_width * 3;
     let n_padding_bytes = pad_to_align!(bytes_per_row, 4);
@@ -96,82 +92,22 @@ pub fn write_24_uncompressed(data: &mut Vec<u8>, pixels: &Vec<BitmapPixel>,
     }
 }
 
-pub fn write_16_uncompressed(data: &mut Vec<u8>, pixels: &Vec<BitmapPixel>,
+pub fn write_16_uncompressed(data: &mut Vec<u8>, pixels: &[BitmapPixel],
                              image_width: i32, image_height: i32) {
-    let mut pixel_iter = pixels.into_iter();
+    let channel_lut = ChannelLut::new(0xff, 0x1f);
+    let mut pixel_iter = pixels.iter();
 
     let bytes_per_row = image_width * 2;
     let n_padding_bytes = pad_to_align!(bytes_per_row, 4);
 
     for _ in 0 .. image_height {
         for _ in 0 .. image_width {
-            let mut pixel = pixel_iter.next().unwrap().clone();
-            map_zero_based(&mut pixel.red  , 0xff, 0x1f);
-            map_zero_based(&mut pixel.green, 0xff, 0x1f);
-            map_zero_based(&mut pixel.blue , 0xff, 0x1f);
-
-            let pixel_data = (pixel.red   as u16) <<  10 |
-                             (pixel.green as u16) <<   5 |
-                             (pixel.blue  as u16);
-
-            push_u16(data, pixel_data);
-        }
-
-        for _ in 0 .. n_padding_bytes {
-            data.push(0x00);
-        }
-    }
-}
-
-pub fn write_8_uncompressed(data: &mut Vec<u8>, pixels: &Vec<BitmapPixel>,
-                             image_palette: &BitmapPalette,
-                             image_width: i32, image_height: i32) {
-    let mut pixel_iter = pixels.into_iter();
-    let n_padding_bytes = pad_to_align!(image_width, 4);
-
-    for _ in 0 .. image_height {
-        for _ in 0 .. image_width {
-            let pixel = pixel_iter.next().unwrap();
-
-            let palette_index = pixel.find_closest_by_index(image_palette) as u8;
-            data.push(palette_index);
-        }
-
-        for _ in 0 .. n_padding_bytes {
-            data.push(0x00);
-        }
-    }
-}
-
-pub fn write_4_uncompressed(data: &mut Vec<u8>, pixels: &Vec<BitmapPixel>,
-                            image_palette: &BitmapPalette,
-                            image_width: i32, image_height: i32) {
-    let mut pixel_iter = pixels.into_iter();
-
-    let bytes_per_row = (image_width + 1) / 2;
-    let n_padding_bytes = pad_to_align!(bytes_per_row, 4);
-
-    for _ in 0 .. image_height {
-        let mut pixels_written = 0;
-        for _ in 0 .. image_width / 2 {
-            let pixel0 = pixel_iter.next().unwrap();
-            let pixel1 = pixel_iter.next().unwrap();
-
-            let p0_index = pixel0.find_closest_by_index(image_palette) as u8;
-            let p1_index = pixel1.find_closest_by_index(image_palette) as u8;
-
-            let pixel_data = (p0_index << 4) | (p1_index & 0x0f);
-            data.push(pixel_data);
-            pixels_written += 2;
-        }
-
-        // NOTE(erick): We still have one pixel to write.
-        if pixels_written < image_width {
             let pixel = pixel_iter.next().unwrap();
-            let p_index = pixel.find_closest_by_index(image_palette) as u8;
+            let pixel_data = channel_lut.get(pixel.red as u32)   <<  10 |
+                             channel_lut.get(pixel.green as u32) <<   5 |
+                             channel_lut.get(pixel.blue as u32);
 
-            let pixel_data = p_index << 4;
-            data.push(pixel_data);
+            push_u16(data, pixel_data as u16);
         }
 
         for _ in 0 .. n_padding_bytes {
@@ -180,48 +116,48 @@ pub fn write_4_uncompressed(data: &mut Vec<u8>, pixels: &Vec<BitmapPixel>,
     }
 }
 
-pub fn write_1_uncompressed(data: &mut Vec<u8>, pixels: &Vec<BitmapPixel>,
-                            image_palette: &BitmapPalette,
-                            image_width: i32, image_height: i32) {
-    let pixels_slice = pixels.as_slice();
-
-    let remaining_pixels_per_row = (image_width -
-                                    (image_width / 8) * 8) as usize;
-
-    let bits_per_row =
-        image_width + pad_to_align!(image_width, 8);
-    let bytes_per_row = bits_per_row / 8;
-    let n_padding_bytes = pad_to_align!(bytes_per_row, 4);
-
-    let mut total_pixels_written = 0;
-    for _ in 0 .. image_height {
-        for _ in 0 .. image_width / 8 {
-            let pixels_block = &pixels_slice[total_pixels_written ..
-                                             total_pixels_written + 8];
-
-            let byte_data = byte_from_pixels(image_palette, pixels_block);
-            data.push(byte_data);
-
-            total_pixels_written += 8;
-        }
-
-        if remaining_pixels_per_row != 0 {
-            let pixels_block = &pixels_slice[total_pixels_written ..
-                                             total_pixels_written +
-                                             remaining_pixels_per_row];
-
-            let byte_data = byte_from_pixels(image_palette, pixels_block);
-            data.push(byte_data);
-            total_pixels_written += remaining_pixels_per_row;
-        }
-
-        for _ in 0 .. n_padding_bytes {
-            data.push(0x00);
+// NOTE(erick): 1, 2, 4 and 8 bits per pixel, packed starting from the
+// most significant bits. Each row is padded to 4 bytes. 'indices' are
+// the palette indices the pixels were read with (possibly stale or
+// empty). An index is reused whenever its palette entry still has the
+// pixel's color, so load -> save keeps the file's indices even when the
+// palette has repeated colors.
+pub fn write_palettized_uncompressed(data: &mut Vec<u8>, pixels: &[BitmapPixel],
+                                     indices: &[u8],
+                                     image_palette: &BitmapPalette,
+                                     image_width: i32, image_height: i32,
+                                     bits_per_pixel: u16) {
+    let width = image_width as usize;
+    let bits_per_pixel = bits_per_pixel as usize;
+    let bytes_per_row = (width * bits_per_pixel).div_ceil(32) * 4;
+    let index_mask = ((1u16 << bits_per_pixel) - 1) as u8;
+
+    let same_color = |a: &BitmapPixel, b: &BitmapPixel| {
+        (a.red, a.green, a.blue) == (b.red, b.green, b.blue)
+    };
+
+    for (row_index, row_pixels) in pixels.chunks(width.max(1))
+        .take(image_height as usize)
+        .enumerate() {
+        let row_start = data.len();
+        data.resize(row_start + bytes_per_row, 0x00);
+
+        for (column_index, pixel) in row_pixels.iter().enumerate() {
+            let original_index = indices.get(row_index * width + column_index)
+                .map(|&index| index as usize)
+                .filter(|&index| image_palette.get(index).is_some_and(|color| same_color(color, pixel)));
+
+            let palette_index = original_index
+                .unwrap_or_else(|| pixel.find_closest_by_index(image_palette)) as u8 & index_mask;
+
+            let bit_offset = column_index * bits_per_pixel;
+            let shift = 8 - bits_per_pixel - bit_offset % 8;
+            data[row_start + bit_offset / 8] |= palette_index << shift;
         }
     }
-
 }
 
+#[allow(clippy::identity_op)]
 pub fn push_u32(v: &mut Vec<u8>, value: u32) {
     // NOTE(erick): Little-endian.
     v.push((value >>  0) as u8);
@@ -229,6 +165,7 @@ pub fn push_u32(v: &mut Vec<u8>, value: u32) {
     v.push((value >> 16) as u8);
     v.push((value >> 24) as u8);
 }
+#[allow(clippy::identity_op)]
 pub fn push_i32(v: &mut Vec<u8>, value: i32) {
     // NOTE(erick): Little-endian.
     v.push((value >>  0) as u8);
@@ -236,24 +173,10 @@ pub fn push_i32(v: &mut Vec<u8>, value: i32) {
     v.push((value >> 16) as u8);
     v.push((value >> 24) as u8);
 }
+#[allow(clippy::identity_op)]
 pub fn push_u16(v: &mut Vec<u8>, value: u16) {
     // NOTE(erick): Little-endian.
     v.push((value >>  0) as u8);
     v.push((value >>  8) as u8);
 }
 
-fn byte_from_pixels(palette: &BitmapPalette, pixels: &[BitmapPixel]) -> u8 {
-    let mut mask = 0x80;
-    let mut result = 0;
-
-    for pixel in pixels {
-        let p_index = pixel.find_closest_by_index(palette);
-        if p_index != 0 {
-            result |= mask;
-        }
-
-        mask = mask >> 1;
-    }
-
-    result
-}
@@ -0,0 +1,54 @@
+// NOTE(erick): CRC-32 as used by zlib and PNG (reflected, polynomial
+// 0xedb88320). The table is built on every call, which is fine for the
+// sizes we checksum.
+fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    for (index, entry) in table.iter_mut().enumerate() {
+        let mut value = index as u32;
+        for _ in 0 .. 8 {
+            value = if value & 1 != 0 {
+                0xedb88320 ^ (value >> 1)
+            } else {
+                value >> 1
+            };
+        }
+        *entry = value;
+    }
+
+    table
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let table = crc32_table();
+
+    let mut crc = 0xffffffffu32;
+    for &byte in data {
+        crc = table[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+
+    !crc
+}
+
+// NOTE(erick): 64-bit FNV-1a. Not cryptographic, but stable across
+// versions and platforms (unlike std's DefaultHasher), so it can be
+// stored as a cache key.
+pub struct Fnv1a64 {
+    state : u64,
+}
+
+impl Fnv1a64 {
+    pub fn new() -> Fnv1a64 {
+        Fnv1a64 { state : 0xcbf29ce484222325 }
+    }
+
+    pub fn write(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.state ^= byte as u64;
+            self.state = self.state.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.state
+    }
+}
@@ -1,8 +1,108 @@
+// NOTE(erick): Struct literals spell every field out (aligned 'field :
+// value' pairs), even when the value has the same name. The other lints
+// we don't follow are allowed where they fire.
+#![allow(clippy::redundant_field_names)]
+
 extern crate rand;
 
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+
+#[cfg(feature = "serde")]
+extern crate serde_json;
+
+#[cfg(feature = "embedded-graphics")]
+extern crate embedded_graphics_core;
+
+#[cfg(feature = "mmap")]
+extern crate memmap2;
+
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+
+#[cfg(feature = "rayon")]
+extern crate rayon;
+
+#[cfg(feature = "tokio")]
+extern crate tokio;
+
 #[macro_use]
 mod bitmap_read;
 mod bitmap_write;
+mod bitmap_netpbm;
+mod bitmap_tga;
+#[cfg(feature = "qoi")]
+mod bitmap_qoi;
+#[cfg(feature = "mmap")]
+mod bitmap_mmap;
+#[cfg(feature = "tokio")]
+mod bitmap_async;
+mod bitmap_resize;
+mod bitmap_diff;
+mod bitmap_compose;
+mod bitmap_match;
+mod bitmap_warp;
+mod bitmap_array;
+mod bitmap_palette;
+mod bitmap_builder;
+mod bitmap_pipeline;
+mod bitmap_pixel_buffer;
+mod bitmap_view;
+mod bitmap_canvas;
+mod bitmap_grid;
+#[cfg(feature = "draw")]
+mod bitmap_draw;
+#[cfg(feature = "draw")]
+mod bitmap_font;
+#[cfg(feature = "draw")]
+mod bitmap_generate;
+#[cfg(feature = "encode-palettized")]
+mod bitmap_quantize;
+#[cfg(feature = "filters")]
+mod bitmap_lut;
+#[cfg(feature = "filters")]
+mod bitmap_histogram;
+#[cfg(feature = "filters")]
+mod bitmap_fusion;
+#[cfg(feature = "filters")]
+mod bitmap_filters;
+#[cfg(feature = "embedded-graphics")]
+mod bitmap_embedded_graphics;
+mod profiling;
+mod parallel;
+mod checksum;
+mod base64;
+
+pub use bitmap_read::DecodeWarning;
+pub use bitmap_netpbm::NetpbmEncoding;
+pub use bitmap_tga::TgaCompression;
+pub use bitmap_resize::ResizeFilter;
+pub use bitmap_diff::{ColorMap, DiffStats, BitmapDiff};
+pub use bitmap_compose::{BlendMode, Blend};
+pub use bitmap_array::BitmapArray;
+pub use bitmap_palette::BitmapPaletteBuilder;
+pub use bitmap_builder::BitmapBuilder;
+pub use bitmap_pipeline::{Pipeline, PipelineStep};
+pub use bitmap_pixel_buffer::{PixelBuffer, PixelFormat};
+pub use bitmap_view::{BitmapView, BitmapViewMut};
+pub use bitmap_canvas::{Anchor, Alignment, MergeLayout, MergeOptions};
+pub use bitmap_grid::GridTiles;
+#[cfg(feature = "encode-palettized")]
+pub use bitmap_quantize::{PaletteQuantizer, Dither};
+#[cfg(feature = "filters")]
+pub use bitmap_lut::Lut3d;
+#[cfg(feature = "filters")]
+pub use bitmap_filters::EdgeMode;
+#[cfg(feature = "draw")]
+pub use bitmap_font::{BitmapFont, BUILTIN_GLYPH_WIDTH, BUILTIN_GLYPH_HEIGHT};
+
+#[cfg(feature = "profiling")]
+pub use profiling::DecodeTimings;
+use profiling::DecodeTimer;
+
+use bitmap_read::PaletteLookup;
 
 use bitmap_write::push_u32;
 use bitmap_write::push_i32;
@@ -15,16 +115,51 @@ use std::io::Write;
 use std::io::Read;
 use std::fs::File;
 
-use std::cmp::max;
 use std::ops::Range;
 
 use std::convert;
 
-use std::intrinsics::transmute;
-
 const BMP_MAGIC_NUMBER : u16 = 0x4d_42; // "MB": We are little-endian
-
-const FILE_HEADER_SIZE : u32 = 14;
+// NOTE(erick): Marks the CRC record at the very end of the trailer.
+const CRC_TAG          : &[u8; 4] = b"bCRC";
+const CRC_RECORD_SIZE  : usize = 8;
+const DATA_URL_PREFIX  : &str = "data:image/bmp;base64,";
+
+pub const FILE_HEADER_SIZE : u32 = 14;
+
+// NOTE(erick): Default decode limits. A 2^28 pixels image takes 1GiB
+// once decoded.
+pub const DEFAULT_MAX_DIMENSION : u32 = 1 << 16;
+pub const DEFAULT_MAX_PIXELS    : u64 = 1 << 28;
+// NOTE(erick): Room for DEFAULT_MAX_PIXELS 32 bits pixels.
+pub const DEFAULT_MAX_FILE_SIZE : u64 = 1 << 30;
+
+// NOTE(erick): Sizes of the known info header versions. The version
+// of the header is identified by its size.
+pub const CORE_HEADER_SIZE    : u32 = 12;  // BITMAPCOREHEADER (OS/2 1.x)
+pub const INFO_HEADER_SIZE    : u32 = 40;  // BITMAPINFOHEADER
+pub const V2_INFO_HEADER_SIZE : u32 = 52;  // + RGB masks
+pub const V3_INFO_HEADER_SIZE : u32 = 56;  // + alpha mask
+pub const V4_HEADER_SIZE      : u32 = 108; // BITMAPV4HEADER
+pub const V5_HEADER_SIZE      : u32 = 124; // BITMAPV5HEADER
+
+pub const PALETTE_ENTRY_SIZE : u32 = 4;
+
+pub const MAX_PALETTE_ENTRIES_1BPP : usize = 2;
+pub const MAX_PALETTE_ENTRIES_2BPP : usize = 4;
+pub const MAX_PALETTE_ENTRIES_4BPP : usize = 16;
+pub const MAX_PALETTE_ENTRIES_8BPP : usize = 256;
+
+// NOTE(erick): Returns zero for formats that don't use a palette.
+pub fn palette_capacity(bits_per_pixel: u16) -> usize {
+    match bits_per_pixel {
+        1 => MAX_PALETTE_ENTRIES_1BPP,
+        2 => MAX_PALETTE_ENTRIES_2BPP,
+        4 => MAX_PALETTE_ENTRIES_4BPP,
+        8 => MAX_PALETTE_ENTRIES_8BPP,
+        _ => 0,
+    }
+}
 
 #[derive(Debug)]
 pub enum BitmapError {
@@ -33,9 +168,55 @@ pub enum BitmapError {
     UnsupportedNumberOfPlanes(u16),
     UnsupportedCompressionType(CompressionType),
     InvalidOperation,
+    InvalidLut(String),
+    InvalidPipeline(String),
+    // NOTE(erick): The headers ask for more than DecodeOptions allows.
+    DecodeLimitExceeded(String),
+    // NOTE(erick): A progress hook asked us to stop.
+    Cancelled,
+    // NOTE(erick): The data ends at this offset, in the middle of
+    // something we still had to read.
+    TruncatedAt(usize),
+    // NOTE(erick): A header field disagrees with the rest of the file.
+    // 'expected' is what the rest of the file implies (the smallest valid
+    // value for offsets, which can have gaps before them).
+    InconsistentField { name: &'static str, expected: u64, found: u64 },
     BitmapIOError(std::io::Error),
 }
 
+impl Display for BitmapError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match *self {
+            BitmapError::InvalidBitmap => write!(f, "invalid bitmap"),
+            BitmapError::UnsupportedInfoHeaderSize(size) =>
+                write!(f, "unsupported info header size: {} bytes", size),
+            BitmapError::UnsupportedNumberOfPlanes(n_planes) =>
+                write!(f, "unsupported number of planes: {}", n_planes),
+            BitmapError::UnsupportedCompressionType(ref compression) =>
+                write!(f, "unsupported compression type: {:?}", compression),
+            BitmapError::InvalidOperation => write!(f, "invalid operation"),
+            BitmapError::InvalidLut(ref message) => write!(f, "invalid LUT: {}", message),
+            BitmapError::InvalidPipeline(ref message) => write!(f, "invalid pipeline: {}", message),
+            BitmapError::DecodeLimitExceeded(ref message) =>
+                write!(f, "decode limit exceeded: {}", message),
+            BitmapError::Cancelled => write!(f, "cancelled"),
+            BitmapError::TruncatedAt(offset) => write!(f, "data truncated at byte {}", offset),
+            BitmapError::InconsistentField { name, expected, found } =>
+                write!(f, "inconsistent {}: expected {}, found {}", name, expected, found),
+            BitmapError::BitmapIOError(ref err) => write!(f, "I/O error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for BitmapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            BitmapError::BitmapIOError(ref err) => Some(err),
+            _                                   => None,
+        }
+    }
+}
+
 impl convert::From<std::io::Error> for BitmapError {
     fn from(err: std::io::Error) -> BitmapError {
         BitmapError::BitmapIOError(err)
@@ -44,7 +225,114 @@ impl convert::From<std::io::Error> for BitmapError {
 
 type BitmapResult<T> = Result<T, BitmapError>;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
+pub struct DecodeOptions {
+    // NOTE(erick): When set, rows handed to row callbacks are numbered
+    // in screen order (0 is the top row) instead of file order.
+    pub screen_row_order : bool,
+    // NOTE(erick): Color used for palette indices past the end of the
+    // palette. Each time it is used we record a DecodeWarning.
+    pub palette_fallback : BitmapPixel,
+    // NOTE(erick): Limits checked against the headers before anything
+    // is allocated, so crafted files can't make us run out of memory.
+    pub max_width        : u32,
+    pub max_height       : u32,
+    pub max_pixels       : u64,
+    pub max_palette_size : usize,
+    // NOTE(erick): Streams are buffered before they are decoded, the
+    // readers give up once they get more bytes than this.
+    pub max_file_size    : u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EncodeOptions {
+    // NOTE(erick): Appends CRC_TAG and the CRC-32 of the pixel array
+    // after the pixels, so Bitmap::verify_crc can detect corruption when
+    // the file is read back. Other decoders just ignore it.
+    pub append_crc32 : bool,
+}
+
+// NOTE(erick): What Bitmap::probe finds out from the headers alone.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitmapInfo {
+    pub width          : u32,
+    pub height         : u32,
+    pub bits_per_pixel : u16,
+    pub compression    : CompressionType,
+    // NOTE(erick): Number of palette entries, zero for formats
+    // without a palette.
+    pub palette_size   : usize,
+    pub is_top_down    : bool,
+    // NOTE(erick): Memory taken by 'image_data' once decoded.
+    pub decoded_size   : usize,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> DecodeOptions {
+        DecodeOptions {
+            screen_row_order : true,
+            palette_fallback : BitmapPixel::black(),
+            max_width        : DEFAULT_MAX_DIMENSION,
+            max_height       : DEFAULT_MAX_DIMENSION,
+            max_pixels       : DEFAULT_MAX_PIXELS,
+            max_palette_size : MAX_PALETTE_ENTRIES_8BPP,
+            max_file_size    : DEFAULT_MAX_FILE_SIZE,
+        }
+    }
+}
+
+impl DecodeOptions {
+    fn check_limits(&self, info_header: &BitmapInfoHeader) -> BitmapResult<()> {
+        if info_header.image_width < 0 {
+            return Err(BitmapError::InvalidBitmap);
+        }
+
+        let width  = info_header.image_width as u32;
+        let height = info_header.image_height as u32;
+        if width > self.max_width || height > self.max_height {
+            return Err(BitmapError::DecodeLimitExceeded(
+                format!("{}x{} image is larger than {}x{}",
+                        width, height, self.max_width, self.max_height)));
+        }
+
+        let n_pixels = width as u64 * height as u64;
+        if n_pixels > self.max_pixels {
+            return Err(BitmapError::DecodeLimitExceeded(
+                format!("{} pixels are more than {}", n_pixels, self.max_pixels)));
+        }
+
+        let palette_size = info_header.palette_entries();
+        if palette_size > self.max_palette_size {
+            return Err(BitmapError::DecodeLimitExceeded(
+                format!("{} palette entries are more than {}",
+                        palette_size, self.max_palette_size)));
+        }
+
+        Ok(())
+    }
+
+    fn check_file_size(&self, size: u64) -> BitmapResult<()> {
+        if size > self.max_file_size {
+            return Err(BitmapError::DecodeLimitExceeded(
+                format!("the file is larger than {} bytes", self.max_file_size)));
+        }
+
+        Ok(())
+    }
+}
+
+// NOTE(erick): What color_to_alpha_with does to the pixels within the
+// tolerance. Hard makes them fully transparent, Feathered scales their
+// alpha by how far they are from the color, so anti-aliased edges fade
+// out instead of keeping a halo.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlphaFalloff {
+    Hard,
+    Feathered,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BitmapFileHeader {
     pub magic_number       : u16,
     pub file_size          : u32,
@@ -55,6 +343,7 @@ pub struct BitmapFileHeader {
 
 #[allow(dead_code)]
 impl Display for BitmapFileHeader {
+    #[allow(clippy::write_with_newline)]
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         write!(f, "BitmapFileheader: {{\n")?;
         write!(f, "\t magic_number: 0x{:x},\n", self.magic_number)?;
@@ -96,6 +385,7 @@ impl BitmapFileHeader {
         }
     }
 
+    #[allow(clippy::wrong_self_convention)]
     fn into_data(&self, data: &mut Vec<u8>) {
         push_u16(data, self.magic_number);
         push_u32(data, self.file_size);
@@ -106,7 +396,8 @@ impl BitmapFileHeader {
 }
 
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CompressionType {
     Uncompressed = 0x0000,
     Rle8         = 0x0001,
@@ -138,12 +429,23 @@ impl convert::From<u32> for CompressionType {
     }
 }
 
+// NOTE(erick): Red, green, blue and alpha masks of the BitFields images
+// we create. Copying gimp here.
+fn default_masks(bits_per_pixel: u16) -> (u32, u32, u32, u32) {
+    if bits_per_pixel == 32 {
+        (0xff000000, 0x00ff0000, 0x0000ff00, 0x000000ff)
+    } else {
+        (0x00007c00, 0x000003e0, 0x0000001f, 0x00008000)
+    }
+}
+
 // NOTE(erick): This is the simplest ImageInfoHeader possible.
 // We will probably find BitmapInfoV5Header in the wild and
 // should _probably_ handle then. The type of header can
 // theoretically be determined my looking at the header size
 // a.k.a., the first four bytes.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BitmapInfoHeader {
     pub info_header_size   : u32,
     pub image_width        : i32,
@@ -169,6 +471,7 @@ pub struct BitmapInfoHeader {
 
 #[allow(dead_code)]
 impl Display for BitmapInfoHeader {
+    #[allow(clippy::write_with_newline)]
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         write!(f, "BitmapFileheader: {{\n")?;
 
@@ -199,8 +502,8 @@ impl BitmapInfoHeader {
            bits_per_pixel: u16,
            compression: CompressionType) -> BitmapInfoHeader {
         let h_size = match compression {
-            CompressionType::BitFields => 56,
-            _                         => 40,
+            CompressionType::BitFields => V3_INFO_HEADER_SIZE,
+            _                         => INFO_HEADER_SIZE,
         };
 
         let mut bits_per_row = i_width as u32 * bits_per_pixel as u32;
@@ -213,25 +516,10 @@ impl BitmapInfoHeader {
 
         let i_size = bytes_per_row * i_height as u32;
 
-        let mut red_mask    = 0x00000000;
-        let mut green_mask  = 0x00000000;
-        let mut blue_mask   = 0x00000000;
-        let mut alpha_mask  = 0x00000000;
-
-        if let CompressionType::BitFields = compression {
-            // NOTE(erick): Copying gimp here.
-            if bits_per_pixel == 32 {
-                red_mask   = 0xff000000;
-                green_mask = 0x00ff0000;
-                blue_mask  = 0x0000ff00;
-                alpha_mask = 0x000000ff;
-            } else {
-                red_mask   = 0x00007c00;
-                green_mask = 0x000003e0;
-                blue_mask  = 0x0000001f;
-                alpha_mask = 0x00008000;
-            }
-        }
+        let (red_mask, green_mask, blue_mask, alpha_mask) = match compression {
+            CompressionType::BitFields => default_masks(bits_per_pixel),
+            _                          => (0, 0, 0, 0),
+        };
 
         BitmapInfoHeader {
             info_header_size   : h_size,
@@ -255,7 +543,7 @@ impl BitmapInfoHeader {
         }
     }
 
-    fn from_data(data: &[u8]) -> BitmapInfoHeader {
+    fn from_data(data: &[u8]) -> BitmapResult<BitmapInfoHeader> {
         let mut data_walker = BytesWalker::new(data);
 
         let mut result = BitmapInfoHeader {
@@ -281,24 +569,41 @@ impl BitmapInfoHeader {
 
         if result.image_height < 0 {
             result.is_top_down = true;
-            result.image_height *= -1;
+            // NOTE(erick): i32::MIN has no positive counterpart.
+            result.image_height = match result.image_height.checked_neg() {
+                Some(height) => height,
+                None         => return Err(BitmapError::InvalidBitmap),
+            };
         }
 
-        if result.info_header_size > 40 {
+        if result.info_header_size > INFO_HEADER_SIZE {
             // NOTE(erick): We have masks to read
             result.red_mask   = data_walker.next_u32();
             result.green_mask = data_walker.next_u32();
             result.blue_mask  = data_walker.next_u32();
             result.alpha_mask = data_walker.next_u32();
+        } else if result.extra_masks_size() > 0 {
+            // NOTE(erick): Basic headers with BitFields are followed
+            // by the red, green and blue masks, there is no alpha mask.
+            result.red_mask   = data_walker.next_u32();
+            result.green_mask = data_walker.next_u32();
+            result.blue_mask  = data_walker.next_u32();
         }
 
-        result
+        Ok(result)
     }
 
+    #[allow(clippy::wrong_self_convention)]
     fn into_data(&self, data: &mut Vec<u8>) {
         push_u32(data, self.info_header_size);
         push_i32(data, self.image_width);
-        push_i32(data, self.image_height);
+        // NOTE(erick): A negative height is how the file tells
+        // us that the rows are stored top-down.
+        if self.is_top_down {
+            push_i32(data, -self.image_height);
+        } else {
+            push_i32(data, self.image_height);
+        }
         push_u16(data, self.n_planes);
         push_u16(data, self.bits_per_pixel);
         push_u32(data, self.compression_type);
@@ -308,18 +613,102 @@ impl BitmapInfoHeader {
         push_u32(data, self.colors_used);
         push_u32(data, self.colors_important);
 
-        if self.info_header_size > 40 {
+        if self.info_header_size > INFO_HEADER_SIZE {
             push_u32(data, self.red_mask);
             push_u32(data, self.green_mask);
             push_u32(data, self.blue_mask);
             push_u32(data, self.alpha_mask);
+        } else if self.extra_masks_size() > 0 {
+            push_u32(data, self.red_mask);
+            push_u32(data, self.green_mask);
+            push_u32(data, self.blue_mask);
+        }
+    }
+
+    // NOTE(erick): Size of the masks stored right after a basic header
+    // (they are not counted in 'info_header_size').
+    fn extra_masks_size(&self) -> u32 {
+        if self.info_header_size == INFO_HEADER_SIZE &&
+            self.compression_type == CompressionType::BitFields as u32 {
+            3 * 4
+        } else {
+            0
+        }
+    }
+
+    fn validate(&self) -> BitmapResult<()> {
+        // NOTE(erick): We only support the basic header so far.
+        let i_header_size = self.info_header_size;
+        if i_header_size != INFO_HEADER_SIZE && i_header_size != V3_INFO_HEADER_SIZE {
+            return Err(BitmapError::
+                       UnsupportedInfoHeaderSize(i_header_size))
+        }
+
+        let compression_type = CompressionType::from(self.compression_type);
+        match compression_type {
+            CompressionType::Uncompressed | CompressionType::BitFields => {},
+            #[cfg(feature = "decode-rle")]
+            CompressionType::Rle8 if self.bits_per_pixel == 8 => {},
+            #[cfg(feature = "decode-rle")]
+            CompressionType::Rle4 if self.bits_per_pixel == 4 => {},
+            _ => {
+                return Err(BitmapError::
+                           UnsupportedCompressionType(compression_type))
+            },
+        }
+
+        if self.n_planes != 1 {
+            return Err(BitmapError::
+                       UnsupportedNumberOfPlanes(self.n_planes));
+        }
+
+        // NOTE(erick): The pixel readers only handle these depths.
+        let valid_depth = match compression_type {
+            CompressionType::BitFields => matches!(self.bits_per_pixel, 16 | 32),
+            _ => matches!(self.bits_per_pixel, 1 | 2 | 4 | 8 | 16 | 24 | 32),
+        };
+        if !valid_depth {
+            return Err(BitmapError::InvalidBitmap);
+        }
+
+        Ok(())
+    }
+
+    // NOTE(erick): Size of a stored row, padding included. Only
+    // meaningful for images that are not RLE compressed.
+    fn bytes_per_row(&self) -> usize {
+        let mut bits_per_row = self.image_width as usize
+            * self.bits_per_pixel as usize;
+        let bits_pad = pad_to_align!(bits_per_row, 8);
+        bits_per_row += bits_pad;
+
+        // NOTE(erick): We need to add the padding bytes.
+        let mut bytes_per_row = bits_per_row / 8;
+        let bytes_pad = pad_to_align!(bytes_per_row, 4);
+        bytes_per_row += bytes_pad;
+
+        bytes_per_row
+    }
+
+    // NOTE(erick): Number of entries the file stores in its palette.
+    // Zero means "as many as the format can index" and so does any
+    // value that wouldn't fit, which we treat as garbage.
+    fn palette_entries(&self) -> usize {
+        let capacity = palette_capacity(self.bits_per_pixel);
+        let colors_used = self.colors_used as usize;
+
+        if colors_used > 0 && colors_used <= capacity {
+            colors_used
+        } else {
+            capacity
         }
     }
 }
 
-type BitmapPalette = Vec<BitmapPixel>;
+pub type BitmapPalette = Vec<BitmapPixel>;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BitmapPixel {
     pub blue  : u8,
     pub green : u8,
@@ -393,6 +782,104 @@ impl BitmapPixel {
                         rand::random::<u8>())
     }
 
+    // NOTE(erick): Rec. 601 luma, the usual weights for 8-bit RGB.
+    pub fn luma(&self) -> u8 {
+        (0.299 * self.red   as f32 +
+         0.587 * self.green as f32 +
+         0.114 * self.blue  as f32).round() as u8
+    }
+
+    // NOTE(erick): Linear interpolation of every channel, 't' in [0, 1].
+    pub fn lerp(&self, other: &BitmapPixel, t: f32) -> BitmapPixel {
+        let lerp = |a: u8, b: u8| {
+            (a as f32 + (b as f32 - a as f32) * t).round().clamp(0.0, 255.0) as u8
+        };
+
+        BitmapPixel::rgba(lerp(self.red, other.red),
+                          lerp(self.green, other.green),
+                          lerp(self.blue, other.blue),
+                          lerp(self.alpha, other.alpha))
+    }
+
+    // NOTE(erick): Hue is in degrees in [0, 360), saturation and value
+    // in [0, 1]. Alpha is not part of the conversion.
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let (hue, max, min) = self.hue_max_min();
+        let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+
+        (hue, saturation, max)
+    }
+
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> BitmapPixel {
+        let saturation = saturation.clamp(0.0, 1.0);
+        let value      = value.clamp(0.0, 1.0);
+
+        let chroma = value * saturation;
+        BitmapPixel::from_hue_chroma(hue, chroma, value - chroma)
+    }
+
+    // NOTE(erick): Same ranges as the HSV functions.
+    pub fn to_hsl(&self) -> (f32, f32, f32) {
+        let (hue, max, min) = self.hue_max_min();
+        let lightness = (max + min) / 2.0;
+        let saturation = if max - min > 0.0 {
+            (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+        } else {
+            0.0
+        };
+
+        (hue, saturation, lightness)
+    }
+
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> BitmapPixel {
+        let saturation = saturation.clamp(0.0, 1.0);
+        let lightness  = lightness.clamp(0.0, 1.0);
+
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        BitmapPixel::from_hue_chroma(hue, chroma, lightness - chroma / 2.0)
+    }
+
+    // NOTE(erick): Hue in degrees and the largest and smallest of the
+    // normalized channels, the parts shared by HSV and HSL.
+    fn hue_max_min(&self) -> (f32, f32, f32) {
+        let r = self.red   as f32 / 255.0;
+        let g = self.green as f32 / 255.0;
+        let b = self.blue  as f32 / 255.0;
+
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+
+        (hue.rem_euclid(360.0), max, min)
+    }
+
+    fn from_hue_chroma(hue: f32, chroma: f32, min: f32) -> BitmapPixel {
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+
+        let (r, g, b) = match sector as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+
+        let to_channel = |c: f32| ((c + min) * 255.0).round().clamp(0.0, 255.0) as u8;
+        BitmapPixel::rgb(to_channel(r), to_channel(g), to_channel(b))
+    }
+
     pub fn distance_squared(&self, other: &BitmapPixel) -> u32 {
         let red_distance   = self.red as i32 - other.red as i32;
         let green_distance = self.green as i32 - other.green as i32;
@@ -403,6 +890,7 @@ impl BitmapPixel {
          blue_distance  * blue_distance) as u32
     }
 
+    #[allow(clippy::explicit_counter_loop)]
     pub fn find_closest_by_index(&self, palette: &BitmapPalette) -> usize {
         let mut result_distance = self.distance_squared(&palette[0]);
         let mut result = 0;
@@ -424,6 +912,7 @@ impl BitmapPixel {
     }
 }
 
+#[allow(clippy::assign_op_pattern)]
 fn mask_offset_and_shifted(mut mask: u32) -> (u8, u32) {
     if mask == 0 {
         // Early-out
@@ -440,9 +929,140 @@ fn mask_offset_and_shifted(mut mask: u32) -> (u8, u32) {
     (offset, mask)
 }
 
+fn decode_warnings(palette_lookup: &PaletteLookup) -> Vec<DecodeWarning> {
+    let mut warnings = Vec::new();
+    if palette_lookup.out_of_range() > 0 {
+        warnings.push(DecodeWarning::PaletteIndexOutOfRange {
+            count : palette_lookup.out_of_range(),
+        });
+    }
+
+    #[cfg(feature = "log")]
+    for warning in &warnings {
+        warn!("{:?}", warning);
+    }
+
+    warnings
+}
+
+// NOTE(erick): Pixel arrays are decoded and encoded this many bytes at a
+// time, with progress hooks called after each step.
+const PROGRESS_STEP_SIZE : usize = 1 << 16;
+
+fn rows_per_progress_step(info_header: &BitmapInfoHeader) -> usize {
+    (PROGRESS_STEP_SIZE / info_header.bytes_per_row().max(1)).max(1)
+}
+
+// NOTE(erick): Progress hooks get the rows processed so far and the total
+// number of rows and return false to cancel.
+fn report_progress<P>(progress: &mut P, rows_done: u32, total_rows: u32) -> BitmapResult<()>
+    where P: FnMut(u32, u32) -> bool {
+    if progress(rows_done, total_rows) {
+        Ok(())
+    } else {
+        Err(BitmapError::Cancelled)
+    }
+}
+
+// NOTE(erick): RLE images can't be split in rows without decoding them,
+// so they report all of their rows at once.
+fn interpret_image_rows<P>(data: &[u8], info_header: &BitmapInfoHeader,
+                           palette: &mut PaletteLookup,
+                           progress: &mut P) -> BitmapResult<Vec<BitmapPixel>>
+    where P: FnMut(u32, u32) -> bool {
+    let total_rows = info_header.image_height as u32;
+    if info_header.compression_type != CompressionType::Uncompressed as u32 &&
+        info_header.compression_type != CompressionType::BitFields as u32 {
+        let result = interpret_image_data(data, info_header, palette);
+        report_progress(progress, total_rows, total_rows)?;
+        return Ok(result);
+    }
+
+    // NOTE(erick): Rows don't depend on each other, so a batch of steps
+    // can be decoded in parallel with a PaletteLookup each.
+    let bytes_per_row = info_header.bytes_per_row().max(1);
+    let steps : Vec<&[u8]> = data.chunks(bytes_per_row * rows_per_progress_step(info_header))
+        .collect();
+
+    let mut result = Vec::with_capacity(data.len());
+    let mut rows_done = 0;
+    for batch in steps.chunks(parallel::batch_size()) {
+        let decoded_steps = parallel::map_items(batch, |step_data| {
+            // NOTE(erick): The last step of a truncated file ends with a
+            // partial row, just like the whole pixel array would.
+            let mut step_header = info_header.clone();
+            step_header.image_height = step_data.len().div_ceil(bytes_per_row) as i32;
+
+            let mut step_palette = palette.for_step();
+            let pixels = interpret_image_data(step_data, &step_header, &mut step_palette);
+            (step_header.image_height as u32, pixels, step_palette)
+        });
+
+        for (step_rows, pixels, step_palette) in decoded_steps {
+            result.extend(pixels);
+            palette.merge(step_palette);
+
+            rows_done += step_rows;
+            report_progress(progress, rows_done, total_rows)?;
+        }
+    }
+
+    // NOTE(erick): Rows missing from truncated files count as done.
+    if rows_done < total_rows {
+        report_progress(progress, total_rows, total_rows)?;
+    }
+
+    Ok(result)
+}
+
+fn pixels_into_data_rows<P>(pixels: &[BitmapPixel], data: &mut Vec<u8>,
+                            info_header: &BitmapInfoHeader,
+                            palette: &Option<BitmapPalette>,
+                            palette_indices: &[u8],
+                            progress: &mut P) -> BitmapResult<()>
+    where P: FnMut(u32, u32) -> bool {
+    let total_rows = info_header.image_height as u32;
+    if info_header.compression_type != CompressionType::Uncompressed as u32 &&
+        info_header.compression_type != CompressionType::BitFields as u32 {
+        pixels_into_data(pixels, data, info_header, palette, palette_indices);
+        return report_progress(progress, total_rows, total_rows);
+    }
+
+    let width = (info_header.image_width as usize).max(1);
+    let pixels_per_step = width * rows_per_progress_step(info_header);
+    let steps : Vec<(usize, &[BitmapPixel])> = pixels.chunks(pixels_per_step)
+        .enumerate()
+        .map(|(step_index, step_pixels)| (step_index * pixels_per_step, step_pixels))
+        .collect();
+
+    let mut rows_done = 0;
+    for batch in steps.chunks(parallel::batch_size()) {
+        let encoded_steps = parallel::map_items(batch, |&(start, step_pixels)| {
+            let step_indices = palette_indices.get(start .. start + step_pixels.len())
+                .unwrap_or(&[]);
+
+            let mut step_header = info_header.clone();
+            step_header.image_height = step_pixels.len().div_ceil(width) as i32;
+
+            let mut step_data = Vec::new();
+            pixels_into_data(step_pixels, &mut step_data, &step_header, palette, step_indices);
+            (step_header.image_height as u32, step_data)
+        });
+
+        for (step_rows, step_data) in encoded_steps {
+            data.extend_from_slice(&step_data);
+
+            rows_done += step_rows;
+            report_progress(progress, rows_done, total_rows)?;
+        }
+    }
+
+    Ok(())
+}
+
 fn interpret_image_data(data: &[u8],
                         info_header: &BitmapInfoHeader,
-                        palette: &Option<BitmapPalette>) -> Vec<BitmapPixel> {
+                        palette: &mut PaletteLookup) -> Vec<BitmapPixel> {
     let bits_per_pixel   = info_header.bits_per_pixel;
     let compression_type = info_header.compression_type;
 
@@ -458,12 +1078,15 @@ fn interpret_image_data(data: &[u8],
 
         if bits_per_pixel == 32 {
             bitmap_read::read_32_bitfield(&mut data_walker, &mut result,
+                                         info_header.image_width,
+                                         info_header.image_height,
                                          red_mask, green_mask,
                                          blue_mask, alpha_mask);
 
         } else if bits_per_pixel == 16 {
             bitmap_read::read_16_bitfield(&mut data_walker, &mut result,
                                          info_header.image_width,
+                                         info_header.image_height,
                                          red_mask, green_mask,
                                          blue_mask, alpha_mask);
 
@@ -473,35 +1096,39 @@ fn interpret_image_data(data: &[u8],
         }
     } else if compression_type == CompressionType::Uncompressed as u32 {
         if bits_per_pixel == 32 {
-            bitmap_read::read_32_uncompressed(&mut data_walker, &mut result);
+            bitmap_read::read_32_uncompressed(&mut data_walker, &mut result,
+                                             info_header.image_width,
+                                             info_header.image_height);
 
         } else if bits_per_pixel == 24 {
             bitmap_read::read_24_uncompressed(&mut data_walker, &mut result,
-                                             info_header.image_width);
+                                             info_header.image_width,
+                                             info_header.image_height);
 
         } else if bits_per_pixel == 16 {
             bitmap_read::read_16_uncompressed(&mut data_walker, &mut result,
-                                             info_header.image_width);
+                                             info_header.image_width,
+                                             info_header.image_height);
 
-        } else if bits_per_pixel == 8 {
-            bitmap_read::read_8_uncompressed(&mut data_walker, &mut result,
-                                            info_header.image_width,
-                                            palette.as_ref().unwrap());
-
-        }else if bits_per_pixel == 4 {
-            bitmap_read::read_4_uncompressed(&mut data_walker, &mut result,
-                                            info_header.image_width,
-                                            palette.as_ref().unwrap());
-
-        } else if bits_per_pixel == 1 {
-            bitmap_read::read_1_uncompressed(&mut data_walker, &mut result,
-                                            info_header.image_width,
-                                            info_header.image_height,
-                                            palette.as_ref().unwrap());
+        } else if palette_capacity(bits_per_pixel) > 0 {
+            bitmap_read::read_palettized_uncompressed(&mut data_walker, &mut result,
+                                                      info_header.image_width,
+                                                      info_header.image_height,
+                                                      bits_per_pixel,
+                                                      palette);
 
         } else {
             panic!("Error: {} bits is not a valid format.", bits_per_pixel);
         }
+    } else if cfg!(feature = "decode-rle") &&
+        (compression_type == CompressionType::Rle8 as u32 ||
+         compression_type == CompressionType::Rle4 as u32) {
+            #[cfg(feature = "decode-rle")]
+            bitmap_read::read_rle(&mut data_walker, &mut result,
+                                  info_header.image_width,
+                                  info_header.image_height,
+                                  palette,
+                                  bits_per_pixel);
     } else {
         panic!("We don't support {:?} compression yet",
                CompressionType::from(compression_type));
@@ -510,9 +1137,25 @@ fn interpret_image_data(data: &[u8],
     result
 }
 
-fn pixels_into_data(pixels: &Vec<BitmapPixel>, data: &mut Vec<u8>,
+// NOTE(erick): Returns the palette size of the target format, or an
+// error when convert_to can't produce it.
+fn check_conversion(bits_per_pixel: u16, compression: &CompressionType) -> BitmapResult<usize> {
+    let palette_size = palette_capacity(bits_per_pixel);
+    let supported = matches!((bits_per_pixel, compression),
+                             (1 | 2 | 4 | 8 | 16 | 24 | 32, &CompressionType::Uncompressed) |
+                             (16 | 32, &CompressionType::BitFields));
+
+    if !supported || (palette_size > 0 && !cfg!(feature = "encode-palettized")) {
+        return Err(BitmapError::InvalidOperation);
+    }
+
+    Ok(palette_size)
+}
+
+fn pixels_into_data(pixels: &[BitmapPixel], data: &mut Vec<u8>,
                     bitmap_info: &BitmapInfoHeader,
-                    palette: &Option<BitmapPalette>) {
+                    palette: &Option<BitmapPalette>,
+                    palette_indices: &[u8]) {
     if bitmap_info.compression_type == CompressionType::BitFields as u32 {
         let red_mask = bitmap_info.red_mask;
         let green_mask = bitmap_info.green_mask;
@@ -548,23 +1191,16 @@ fn pixels_into_data(pixels: &Vec<BitmapPixel>, data: &mut Vec<u8>,
                                                bitmap_info.image_width,
                                                bitmap_info.image_height);
 
-        } else if bitmap_info.bits_per_pixel == 8 {
-            bitmap_write::write_8_uncompressed(data, pixels,
-                                              palette.as_ref().unwrap(),
-                                              bitmap_info.image_width,
-                                              bitmap_info.image_height);
-
-        } else if bitmap_info.bits_per_pixel == 4 {
-            bitmap_write::write_4_uncompressed(data, pixels,
-                                              palette.as_ref().unwrap(),
-                                              bitmap_info.image_width,
-                                              bitmap_info.image_height);
-
-        } else if bitmap_info.bits_per_pixel == 1 {
-            bitmap_write::write_1_uncompressed(data, pixels,
-                                              palette.as_ref().unwrap(),
-                                              bitmap_info.image_width,
-                                              bitmap_info.image_height);
+        } else if palette_capacity(bitmap_info.bits_per_pixel) > 0 {
+            // NOTE(erick): encode_normalized writes images without a
+            // palette as 24-bit, so we always have one here.
+            let palette = palette.as_ref().expect("pixels_to_data: No palette found!");
+            bitmap_write::write_palettized_uncompressed(data, pixels,
+                                                        palette_indices,
+                                                        palette,
+                                                        bitmap_info.image_width,
+                                                        bitmap_info.image_height,
+                                                        bitmap_info.bits_per_pixel);
 
         } else {
             panic!("pixels_to_data: Error: {} bits is not a valid format.",
@@ -576,13 +1212,66 @@ fn pixels_into_data(pixels: &Vec<BitmapPixel>, data: &mut Vec<u8>,
     }
 }
 
+// NOTE(erick): Same as BitmapInfoHeader::extra_masks_size but works on
+// the raw header, so we know how much to read before parsing it.
+fn extra_masks_size(info_header_data: &[u8]) -> usize {
+    let info_header_size = BytesWalker::new(info_header_data).next_u32();
+    let compression_type = BytesWalker::new(&info_header_data[16 ..]).next_u32();
+
+    if info_header_size == INFO_HEADER_SIZE &&
+        compression_type == CompressionType::BitFields as u32 {
+        3 * 4
+    } else {
+        0
+    }
+}
+
+// NOTE(erick): Reads and validates the file and info headers, and the
+// masks following a basic header, leaving 'reader' right after them.
+// Also returns how many bytes were read.
+fn read_headers<R: Read>(reader: &mut R)
+                         -> BitmapResult<(BitmapFileHeader, BitmapInfoHeader, u64)> {
+    let mut header_data = vec![0; FILE_HEADER_SIZE as usize + 4];
+    reader.read_exact(&mut header_data)?;
+
+    let f_header =
+        BitmapFileHeader::from_data(&header_data[0..FILE_HEADER_SIZE as usize]);
+    if !f_header.validate() {
+        return Err(BitmapError::InvalidBitmap);
+    }
+
+    let i_header_size = BytesWalker::new(&header_data[FILE_HEADER_SIZE as usize ..])
+        .next_u32();
+    if i_header_size != INFO_HEADER_SIZE && i_header_size != V3_INFO_HEADER_SIZE {
+        return Err(BitmapError::UnsupportedInfoHeaderSize(i_header_size));
+    }
+    header_data.resize((FILE_HEADER_SIZE + i_header_size) as usize, 0);
+    reader.read_exact(&mut header_data[FILE_HEADER_SIZE as usize + 4 ..])?;
+
+    let masks_size = extra_masks_size(&header_data[FILE_HEADER_SIZE as usize ..]);
+    if masks_size > 0 {
+        let headers_end = header_data.len();
+        header_data.resize(headers_end + masks_size, 0);
+        reader.read_exact(&mut header_data[headers_end ..])?;
+    }
+
+    let info_header =
+        BitmapInfoHeader::from_data(&header_data[FILE_HEADER_SIZE as usize ..])?;
+    info_header.validate()?;
+
+    Ok((f_header, info_header, header_data.len() as u64))
+}
+
 // TODO(erick): This is very similar to decoding a
 // 32-bit uncompressed image. Maybe we can generalize it.
-fn read_palette(data: &[u8]) -> BitmapPalette {
+// NOTE(erick): Reads at most 'n_entries' colors. Whatever follows them
+// (gap bytes some writers leave before the pixel data) is ignored.
+fn read_palette(data: &[u8], n_entries: usize) -> BitmapPalette {
+    let n_entries = n_entries.min(data.len() / PALETTE_ENTRY_SIZE as usize);
     let mut data_walker = BytesWalker::new(data);
-    let mut result = Vec::with_capacity(data.len() / 4);
+    let mut result = Vec::with_capacity(n_entries);
 
-    while data_walker.has_data() {
+    for _ in 0 .. n_entries {
         let pixel = BitmapPixel {
             blue  : data_walker.next_u8(),
             green : data_walker.next_u8(),
@@ -597,11 +1286,52 @@ fn read_palette(data: &[u8]) -> BitmapPalette {
     result
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub  struct Bitmap {
     pub file_header : BitmapFileHeader,
     pub info_header : BitmapInfoHeader,
     pub palette     : Option<BitmapPalette>,
     pub image_data  : Vec<BitmapPixel>,
+    // NOTE(erick): Whatever the file had after the pixel array. It is
+    // written back after the pixels.
+    pub trailer     : Vec<u8>,
+    // NOTE(erick): Whether the CRC record found when loading matched the
+    // pixel array. The record itself is not kept in the trailer, since
+    // it would be stale as soon as the pixels change.
+    crc_check       : Option<bool>,
+    // NOTE(erick): Palette indices of palettized images as read from the
+    // file, in file order. The writer reuses them where they still match
+    // the pixels, so saving an untouched image gives back the same bytes.
+    palette_indices : Vec<u8>,
+    // NOTE(erick): What was wrong with the file this image was decoded
+    // from. Kept with the image, since decoding can happen on any thread.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    decode_warnings : Vec<DecodeWarning>,
+}
+
+// NOTE(erick): Two bitmaps are equal when they have the same size and the
+// same pixels (all four channels) on screen. The format, the orientation
+// and the rest of the headers are not compared, so an image is equal to
+// itself after a lossless conversion.
+impl PartialEq for Bitmap {
+    fn eq(&self, other: &Bitmap) -> bool {
+        self.approx_eq(other, 0)
+    }
+}
+
+// NOTE(erick): Only the layout, dumping the pixels would be useless in
+// assertion messages.
+impl std::fmt::Debug for Bitmap {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.debug_struct("Bitmap")
+            .field("width", &self.info_header.image_width)
+            .field("height", &self.info_header.image_height)
+            .field("bits_per_pixel", &self.info_header.bits_per_pixel)
+            .field("compression", &CompressionType::from(self.info_header.compression_type))
+            .field("is_top_down", &self.info_header.is_top_down)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Bitmap {
@@ -609,14 +1339,7 @@ impl Bitmap {
     pub fn create_headers(width: i32, height: i32,
                           bits_per_pixel: u16, compression: CompressionType)
                           -> (BitmapFileHeader, BitmapInfoHeader) {
-        let mut palette_size = 0;
-        if bits_per_pixel == 8 ||
-            bits_per_pixel == 4 ||
-            bits_per_pixel == 1 {
-                palette_size = 1 << bits_per_pixel;
-            }
-
-        palette_size *= 4; // NOTE: Each palette entry is 4 bytes long.
+        let palette_size = palette_capacity(bits_per_pixel) as u32 * PALETTE_ENTRY_SIZE;
 
         // NOTE(erick): We create the info header first because
         // it computes the image_data_size and the info_header_size
@@ -642,6 +1365,11 @@ impl Bitmap {
             info_header : info_header,
             palette     : None,
             image_data  : Vec::new(),
+            trailer     : Vec::new(),
+            crc_check   : None,
+
+            palette_indices : Vec::new(),
+            decode_warnings : Vec::new(),
         }
     }
 
@@ -663,169 +1391,887 @@ impl Bitmap {
         Bitmap::new(width, height, 32, CompressionType::BitFields)
     }
 
-    pub fn convert_to(&mut self, bits_per_pixel: u16, compression: CompressionType) {
-        if self.info_header.is_top_down {
-            self.mirror_vertically();
-        }
-        if bits_per_pixel == 8 ||
-            bits_per_pixel == 4 ||
-            bits_per_pixel == 1 {
-                let palette_size = 1 << bits_per_pixel;
-                self.palette = Some(
-                    find_best_palette_k_means(&self.image_data, palette_size));
+    // NOTE(erick): Converts to any format we can write. Palettized
+    // targets keep the current palette when it fits, otherwise they get
+    // a median-cut palette built from the current pixels. The pixels are
+    // then packed and unpacked so image_data holds exactly what a reader
+    // of the new file will see (colors snapped to the palette, channels
+    // reduced to 5 bits on 16-bit images, no alpha on 24-bit ones, ...).
+    pub fn convert_to(&mut self, bits_per_pixel: u16,
+                      compression: CompressionType) -> BitmapResult<()> {
+        let palette_size = check_conversion(bits_per_pixel, &compression)?;
+
+        #[cfg(feature = "encode-palettized")]
+        {
+            if palette_size > 0 {
+                self.palette = Some(self.conversion_palette(palette_size));
             }
 
-        // NOTE(erick): It's easier to create new header than to
-        // try to modify the existing ones.
-        let (file_header, info_header) =
-            Bitmap::create_headers(self.info_header.image_width,
-                                  self.info_header.image_height,
-                                  bits_per_pixel, compression);
+            if let Some(ref mut palette) = self.palette {
+                // NOTE(erick): The file always stores the whole palette,
+                // so we fill the unused entries with the last color.
+                let last_color = *palette.last().unwrap();
+                palette.resize(palette_size, last_color);
+            }
+        }
 
-        self.file_header = file_header;
-        self.info_header = info_header;
-    }
+        if palette_size == 0 {
+            self.palette = None;
+        }
 
-    pub fn from_file(file: &mut File) -> BitmapResult<Bitmap> {
-        let mut data = Vec::new();
-        file.read_to_end(&mut data)?;
+        self.replace_headers(bits_per_pixel, compression);
 
-        Bitmap::from_data(data)
-    }
+        let mut data = Vec::with_capacity(self.info_header.image_size as usize);
+        pixels_into_data(&self.image_data, &mut data, &self.info_header,
+                         &self.palette, &self.palette_indices);
 
-    pub fn from_data(data: Vec<u8>) -> BitmapResult<Bitmap> {
-        let data_slice = data.as_slice();
-        let f_header =
-            BitmapFileHeader::from_data(&data_slice[0..FILE_HEADER_SIZE as usize]);
-        if !f_header.validate() {
-            return Err(BitmapError::InvalidBitmap);
-        }
+        let mut palette_lookup = PaletteLookup::new(&self.palette, BitmapPixel::black())
+            .keeping_indices();
+        self.image_data = interpret_image_data(&data, &self.info_header,
+                                               &mut palette_lookup);
+        self.palette_indices = palette_lookup.take_indices();
 
-        let info_header =
-            BitmapInfoHeader::from_data(&data_slice[FILE_HEADER_SIZE as usize ..]);
+        Ok(())
+    }
 
-        println!("{}", f_header);
-        println!("{}", info_header);
+    // NOTE(erick): Like convert_to, but palettized targets snap the pixels
+    // to the palette with 'dither' instead of picking the closest color.
+    // Targets without a palette ignore it.
+    #[cfg(feature = "encode-palettized")]
+    pub fn convert_to_with_dither(&mut self, bits_per_pixel: u16,
+                                  compression: CompressionType,
+                                  dither: Dither) -> BitmapResult<()> {
+        let palette_size = check_conversion(bits_per_pixel, &compression)?;
 
-        // NOTE(erick): We only support the basic header so far.
-        let i_header_size = info_header.info_header_size;
-        if i_header_size != 40 && i_header_size != 56 {
-            return Err(BitmapError::
-                       UnsupportedInfoHeaderSize(i_header_size))
+        if palette_size > 0 && dither != Dither::None {
+            let palette = self.conversion_palette(palette_size);
+            PaletteQuantizer::from_palette(palette)?.remap(self, dither);
         }
 
-        let compression_type = CompressionType::from(info_header.compression_type);
-        match compression_type {
-            CompressionType::Uncompressed | CompressionType::BitFields => {},
-            _ => {
-                return Err(BitmapError::
-                           UnsupportedCompressionType(compression_type))
-            },
+        self.convert_to(bits_per_pixel, compression)
+    }
+
+    // NOTE(erick): The current palette when it fits, otherwise a
+    // median-cut palette built from the current pixels.
+    #[cfg(feature = "encode-palettized")]
+    fn conversion_palette(&self, palette_size: usize) -> BitmapPalette {
+        match self.palette {
+            Some(ref palette) if !palette.is_empty() && palette.len() <= palette_size =>
+                palette.clone(),
+            _ => bitmap_quantize::median_cut(&self.image_data, palette_size as u16),
         }
+    }
 
-        if info_header.n_planes != 1 {
-            return Err(BitmapError::
-                       UnsupportedNumberOfPlanes(info_header.n_planes));
+    // NOTE(erick): Reads and validates the headers only, so it is cheap
+    // enough to triage lots of files before decoding any of them.
+    pub fn probe<R: Read>(reader: &mut R) -> BitmapResult<BitmapInfo> {
+        let (_, info_header, _) = read_headers(reader)?;
+        if info_header.image_width < 0 {
+            return Err(BitmapError::InvalidBitmap);
         }
 
-        let mut image_size_in_bytes = info_header.image_size as usize;
+        let width  = info_header.image_width as u32;
+        let height = info_header.image_height as u32;
 
-        // NOTE(erick): 'image_size' may be zero when the image is uncompressed
-        // so we calculate the size in this case.
-        if info_header.compression_type == CompressionType::Uncompressed as u32 {
-            let mut bits_per_row = info_header.image_width as usize
-                * info_header.bits_per_pixel as usize;
-            let bits_pad = pad_to_align!(bits_per_row as usize, 8);
-            bits_per_row += bits_pad;
+        Ok(BitmapInfo {
+            width          : width,
+            height         : height,
+            bits_per_pixel : info_header.bits_per_pixel,
+            compression    : CompressionType::from(info_header.compression_type),
+            palette_size   : info_header.palette_entries(),
+            is_top_down    : info_header.is_top_down,
+            decoded_size   : width as usize * height as usize * std::mem::size_of::<BitmapPixel>(),
+        })
+    }
 
-            // NOTE(erick): We need to add the padding bytes.
-            let mut bytes_per_row = bits_per_row / 8;
-            let bytes_pad = pad_to_align!(bytes_per_row, 4);
-            bytes_per_row += bytes_pad;
+    pub fn from_file(file: &mut File) -> BitmapResult<Bitmap> {
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+
+        Bitmap::from_data(data)
+    }
+
+    pub fn from_data(data: Vec<u8>) -> BitmapResult<Bitmap> {
+        Bitmap::from_slice(&data)
+    }
+
+    pub fn from_data_with_options(data: Vec<u8>,
+                                  options: &DecodeOptions) -> BitmapResult<Bitmap> {
+        Bitmap::from_slice_with_options(&data, options)
+    }
+
+    pub fn from_reader<R: Read>(reader: &mut R) -> BitmapResult<Bitmap> {
+        Bitmap::from_reader_with_options(reader, &DecodeOptions::default())
+    }
+
+    pub fn from_reader_with_options<R: Read>(reader: &mut R,
+                                             options: &DecodeOptions) -> BitmapResult<Bitmap> {
+        let mut data = Vec::new();
+        reader.take(options.max_file_size.saturating_add(1)).read_to_end(&mut data)?;
+        options.check_file_size(data.len() as u64)?;
+
+        Bitmap::from_slice_with_options(&data, options)
+    }
+
+    // NOTE(erick): Same as 'from_data' for data we don't own, e.g., a
+    // memory mapped file.
+    pub fn from_slice(data_slice: &[u8]) -> BitmapResult<Bitmap> {
+        Bitmap::from_slice_with_options(data_slice, &DecodeOptions::default())
+    }
+
+    pub fn from_slice_with_options(data_slice: &[u8],
+                                   options: &DecodeOptions) -> BitmapResult<Bitmap> {
+        Bitmap::from_slice_with_progress(data_slice, options, |_, _| true)
+    }
 
-            image_size_in_bytes = bytes_per_row * info_header.image_height as usize;
+    // NOTE(erick): 'progress' is called with the rows decoded so far and
+    // the image height every few rows, returning false cancels the decode
+    // with BitmapError::Cancelled.
+    pub fn from_slice_with_progress<P>(data_slice: &[u8], options: &DecodeOptions,
+                                       mut progress: P) -> BitmapResult<Bitmap>
+        where P: FnMut(u32, u32) -> bool {
+        // NOTE(erick): Bitmap arrays hold several versions of the same
+        // image, like most decoders we just use the first one.
+        if BitmapArray::is_array(data_slice) {
+            let image = BitmapArray::from_data_with_options(data_slice, options)
+                .map(|images| images.into_iter().next().unwrap())?;
+            let height = image.info_header.image_height as u32;
+            report_progress(&mut progress, height, height)?;
+            return Ok(image);
         }
 
-        let mut image_palette = None;
-        if info_header.bits_per_pixel == 1 ||
-            info_header.bits_per_pixel == 4 ||
-            info_header.bits_per_pixel == 8 {
-                let palette_offset = (FILE_HEADER_SIZE +
-                                      info_header.info_header_size) as usize;
-                let palette_data = &data_slice[palette_offset ..
-                                               f_header.pixel_array_offset as usize];
-
-                image_palette = Some(read_palette(palette_data));
+        let mut timer = DecodeTimer::start();
+
+        // NOTE(erick): Make sure the headers (and their masks) are there
+        // before parsing them.
+        let info_header_start = FILE_HEADER_SIZE as usize;
+        if data_slice.len() < info_header_start + 4 {
+            return Err(BitmapError::TruncatedAt(data_slice.len()));
+        }
+
+        let f_header =
+            BitmapFileHeader::from_data(&data_slice[0..FILE_HEADER_SIZE as usize]);
+        if !f_header.validate() {
+            return Err(BitmapError::InvalidBitmap);
+        }
+
+        let info_header_size = BytesWalker::new(&data_slice[info_header_start ..]).next_u32();
+        if info_header_size != INFO_HEADER_SIZE && info_header_size != V3_INFO_HEADER_SIZE {
+            return Err(BitmapError::UnsupportedInfoHeaderSize(info_header_size));
+        }
+        if data_slice.len() < info_header_start + info_header_size as usize ||
+            data_slice.len() < info_header_start + info_header_size as usize +
+            extra_masks_size(&data_slice[info_header_start ..]) {
+                return Err(BitmapError::TruncatedAt(data_slice.len()));
             }
 
+        let info_header =
+            BitmapInfoHeader::from_data(&data_slice[FILE_HEADER_SIZE as usize ..])?;
+
+        #[cfg(feature = "log")]
+        debug!("Decoding bitmap\n{}\n{}", f_header, info_header);
 
-        let image_data_slice  = &data_slice[f_header.pixel_array_offset as usize ..
-                                            f_header.pixel_array_offset as usize +
-                                            image_size_in_bytes];
+        info_header.validate()?;
+        options.check_limits(&info_header)?;
+
+        let mut image_size_in_bytes = info_header.image_size as usize;
+        if image_size_in_bytes == 0 {
+            // NOTE(erick): Be lenient with compressed images that don't
+            // tell us their size and use everything up to the end.
+            image_size_in_bytes = data_slice.len()
+                .saturating_sub(f_header.pixel_array_offset as usize);
+        }
+
+        // NOTE(erick): 'image_size' may be zero or just wrong when the image
+        // is not RLE compressed, but then we know the exact size.
+        if info_header.compression_type == CompressionType::Uncompressed as u32 ||
+            info_header.compression_type == CompressionType::BitFields as u32 {
+                image_size_in_bytes = info_header.bytes_per_row() *
+                    info_header.image_height as usize;
+            }
+
+        timer.header_done();
+
+        let mut image_palette = None;
+        if palette_capacity(info_header.bits_per_pixel) > 0 {
+            // NOTE(erick): Broken offsets give us a short (or empty)
+            // palette instead of a panic; PaletteLookup handles the rest.
+            let palette_offset = ((FILE_HEADER_SIZE +
+                                   info_header.info_header_size) as usize)
+                .min(data_slice.len());
+            let palette_end = (f_header.pixel_array_offset as usize)
+                .clamp(palette_offset, data_slice.len());
+            let palette_data = &data_slice[palette_offset .. palette_end];
+
+            image_palette = Some(read_palette(palette_data, info_header.palette_entries()));
+        }
+        timer.palette_done();
+
+
+        // NOTE(erick): Truncated files decode as many rows as they have.
+        // Bytes after the pixel array are kept as they are.
+        let pixels_start = (f_header.pixel_array_offset as usize).min(data_slice.len());
+        let pixels_end = (pixels_start + image_size_in_bytes).min(data_slice.len());
+        let image_data_slice = &data_slice[pixels_start .. pixels_end];
+        let mut trailer = data_slice[pixels_end ..].to_vec();
+
+        let mut crc_check = None;
+        let record_start = trailer.len().saturating_sub(CRC_RECORD_SIZE);
+        if trailer.len() >= CRC_RECORD_SIZE && &trailer[record_start .. record_start + 4] == CRC_TAG {
+            let stored_crc = BytesWalker::new(&trailer[record_start + 4 ..]).next_u32();
+            crc_check = Some(stored_crc == checksum::crc32(image_data_slice));
+            trailer.truncate(record_start);
+        }
 
         // TODO(erick): Decompressed the image!!!!
-        let image_data = interpret_image_data(&image_data_slice,
-                                              &info_header, &image_palette);
+        let mut palette_lookup = PaletteLookup::new(&image_palette,
+                                                    options.palette_fallback)
+            .keeping_indices();
+        let mut image_data = interpret_image_rows(image_data_slice, &info_header,
+                                                  &mut palette_lookup, &mut progress)?;
+        let decode_warnings = decode_warnings(&palette_lookup);
+        let palette_indices = palette_lookup.take_indices();
+
+        // NOTE(erick): Rows missing from truncated files are the last ones
+        // in file order and come out transparent.
+        let n_pixels = info_header.image_width as usize * info_header.image_height as usize;
+        image_data.resize(n_pixels, BitmapPixel::transparent());
+        timer.pixels_done();
+        timer.finish();
 
         let result = Bitmap {
             file_header : f_header,
             info_header : info_header,
             palette     : image_palette,
             image_data  : image_data,
+            trailer     : trailer,
+            crc_check   : crc_check,
+
+            palette_indices : palette_indices,
+            decode_warnings : decode_warnings,
         };
 
         Ok(result)
     }
 
+    // NOTE(erick): Decodes only the (x, y, width, height) rectangle (in
+    // screen coordinates) into a new 32 bits image. Only the rows inside
+    // the rectangle are touched, which makes it cheap on memory mapped
+    // files. Like 'decode_with' it doesn't handle RLE images.
+    pub fn decode_region(data: &[u8], x: u32, y: u32,
+                         width: u32, height: u32) -> BitmapResult<Bitmap> {
+        Bitmap::decode_region_with_options(data, x, y, width, height,
+                                           &DecodeOptions::default())
+    }
+
+    pub fn decode_region_with_options(data: &[u8], x: u32, y: u32,
+                                      width: u32, height: u32,
+                                      options: &DecodeOptions) -> BitmapResult<Bitmap> {
+        let (f_header, info_header, headers_size) = read_headers(&mut &data[..])?;
+        options.check_limits(&info_header)?;
+
+        let compression_type = CompressionType::from(info_header.compression_type);
+        match compression_type {
+            CompressionType::Uncompressed | CompressionType::BitFields => {},
+            _ => return Err(BitmapError::UnsupportedCompressionType(compression_type)),
+        }
+
+        let image_width  = info_header.image_width.max(0) as u64;
+        let image_height = info_header.image_height as u64;
+        if width == 0 || height == 0 ||
+            x as u64 + width as u64 > image_width || y as u64 + height as u64 > image_height {
+            return Err(BitmapError::InvalidOperation);
+        }
+
+        // NOTE(erick): The rows we need are contiguous in the file, so
+        // checking the last one is enough to know they are all there.
+        let file_row = |screen_y: u64| if info_header.is_top_down {
+            screen_y
+        } else {
+            image_height - screen_y - 1
+        };
+        let last_file_row = file_row(y as u64).max(file_row(y as u64 + height as u64 - 1));
+
+        let pixels_start = f_header.pixel_array_offset as usize;
+        let bytes_per_row = info_header.bytes_per_row();
+        let pixels_end = (last_file_row + 1).checked_mul(bytes_per_row as u64)
+            .and_then(|size| size.checked_add(pixels_start as u64));
+        if pixels_end.is_none_or(|end| end > data.len() as u64) {
+            return Err(BitmapError::TruncatedAt(data.len()));
+        }
+
+        let mut image_palette = None;
+        if palette_capacity(info_header.bits_per_pixel) > 0 {
+            let palette_offset = (headers_size as usize).min(data.len());
+            let palette_end = pixels_start.clamp(palette_offset, data.len());
+            image_palette = Some(read_palette(&data[palette_offset .. palette_end],
+                                              info_header.palette_entries()));
+        }
+
+        let mut row_header = info_header.clone();
+        row_header.image_height = 1;
+
+        let mut palette_lookup = PaletteLookup::new(&image_palette,
+                                                    options.palette_fallback);
+
+        // NOTE(erick): The rectangle is inside the image, so this fits the
+        // limits we just checked.
+        let mut result = Bitmap::lazy_new_default(width as i32, height as i32);
+        result.image_data = vec![BitmapPixel::transparent(); width as usize * height as usize];
+        for row in 0 .. height {
+            let row_start = pixels_start + file_row((y + row) as u64) as usize * bytes_per_row;
+            let row_data = &data[row_start .. row_start + bytes_per_row];
+            let pixels = interpret_image_data(row_data, &row_header, &mut palette_lookup);
+
+            for column in 0 .. width {
+                result.set_pixel(column, row, pixels[(x + column) as usize]);
+            }
+        }
+        result.decode_warnings = decode_warnings(&palette_lookup);
+
+        Ok(result)
+    }
+
+    // NOTE(erick): Decodes the image one row at a time, calling 'f' with
+    // the row index and its pixels. Only one row is kept in memory, so
+    // it can be used on images of any size. RLE images can't be decoded
+    // this way because their rows don't have a fixed size.
+    pub fn decode_with<R, F>(reader: &mut R, options: &DecodeOptions,
+                             f: F) -> BitmapResult<BitmapInfoHeader>
+        where R: Read, F: FnMut(u32, &[BitmapPixel]) {
+        Bitmap::decode_with_warnings(reader, options, f).map(|(info_header, _)| info_header)
+    }
+
+    // NOTE(erick): Same as 'decode_with', also returning what was wrong
+    // with the file since there is no Bitmap to keep it.
+    pub fn decode_with_warnings<R, F>(reader: &mut R, options: &DecodeOptions,
+                                      mut f: F)
+                                      -> BitmapResult<(BitmapInfoHeader, Vec<DecodeWarning>)>
+        where R: Read, F: FnMut(u32, &[BitmapPixel]) {
+        let (f_header, info_header, headers_size) = read_headers(reader)?;
+        #[cfg(feature = "log")]
+        debug!("Decoding bitmap rows\n{}\n{}", f_header, info_header);
+        options.check_limits(&info_header)?;
+
+        let compression_type = CompressionType::from(info_header.compression_type);
+        match compression_type {
+            CompressionType::Uncompressed | CompressionType::BitFields => {},
+            _ => return Err(BitmapError::UnsupportedCompressionType(compression_type)),
+        }
+
+        let pixel_array_offset = f_header.pixel_array_offset as u64;
+        if pixel_array_offset < headers_size {
+            return Err(BitmapError::InconsistentField {
+                name     : "pixel_array_offset",
+                expected : headers_size,
+                found    : pixel_array_offset,
+            });
+        }
+
+        let mut palette_data = Vec::new();
+        reader.by_ref()
+            .take(pixel_array_offset - headers_size)
+            .read_to_end(&mut palette_data)?;
+
+        let mut image_palette = None;
+        if palette_capacity(info_header.bits_per_pixel) > 0 {
+            image_palette = Some(read_palette(&palette_data,
+                                              info_header.palette_entries()));
+        }
+
+        // NOTE(erick): The readers take the height into account for some
+        // formats, so we describe each row as a one row image.
+        let mut row_header = info_header.clone();
+        row_header.image_height = 1;
+
+        let mut palette_lookup = PaletteLookup::new(&image_palette,
+                                                    options.palette_fallback);
+
+        let image_height = info_header.image_height as u32;
+        let mut row_data = vec![0; info_header.bytes_per_row()];
+        for file_row_index in 0 .. image_height {
+            reader.read_exact(&mut row_data)?;
+            let row = interpret_image_data(&row_data, &row_header, &mut palette_lookup);
+
+            let row_index = if !options.screen_row_order || info_header.is_top_down {
+                file_row_index
+            } else {
+                image_height - file_row_index - 1
+            };
+
+            f(row_index, &row);
+        }
+
+        Ok((info_header, decode_warnings(&palette_lookup)))
+    }
+
+    // NOTE(erick): Empty for images we didn't decode.
+    pub fn decode_warnings(&self) -> &[DecodeWarning] {
+        &self.decode_warnings
+    }
+
+    // NOTE(erick): Timings are kept per thread, so this returns
+    // the stages of the last from_data call made on this thread.
+    #[cfg(feature = "profiling")]
+    pub fn last_decode_timings() -> Option<DecodeTimings> {
+        profiling::last_decode_timings()
+    }
+
     pub fn into_data(&self) -> Vec<u8> {
+        self.into_data_with_options(&EncodeOptions::default())
+    }
+
+    // NOTE(erick): The headers are written normalized (see
+    // normalize_headers), the ones in self are left untouched.
+    pub fn into_data_with_options(&self, options: &EncodeOptions) -> Vec<u8> {
+        self.into_data_with_progress(options, |_, _| true)
+            .expect("Only progress hooks can cancel encoding")
+    }
+
+    // NOTE(erick): Like from_slice_with_progress, 'progress' gets the rows
+    // encoded so far and the image height and returns false to cancel.
+    pub fn into_data_with_progress<P>(&self, options: &EncodeOptions,
+                                      mut progress: P) -> BitmapResult<Vec<u8>>
+        where P: FnMut(u32, u32) -> bool {
+        let (file_header, info_header, pixels) = self.encode_normalized(&mut progress)?;
+
         let mut result = Vec::new();
+        file_header.into_data(&mut result);
+        info_header.into_data(&mut result);
+        self.push_palette(&mut result);
+        debug_assert_eq!(result.len(), file_header.pixel_array_offset as usize);
+
+        result.extend_from_slice(&pixels);
+        result.extend_from_slice(&self.trailer);
+        if options.append_crc32 {
+            result.extend_from_slice(CRC_TAG);
+            push_u32(&mut result, checksum::crc32(&pixels));
+        }
 
-        self.file_header.into_data(&mut result);
-        self.info_header.into_data(&mut result);
-
-        if self.info_header.bits_per_pixel == 1 ||
-            self.info_header.bits_per_pixel == 4 ||
-            self.info_header.bits_per_pixel == 8 {
-                let palette = self.palette.as_ref().expect("No palette found!");
-                for pixel in palette {
-                    result.push(pixel.blue);
-                    result.push(pixel.green);
-                    result.push(pixel.red);
-                    result.push(0x00);
-                }
+        Ok(result)
+    }
+
+    // NOTE(erick): Recomputes the fields that describe the layout of the
+    // file (sizes, offsets, colors_used and missing masks) from the pixels
+    // and the palette we actually have, so editing the headers by hand
+    // can't produce an inconsistent file.
+    pub fn normalize_headers(&mut self) {
+        let (file_header, info_header, _) = self.encode_normalized(&mut |_, _| true)
+            .expect("Only progress hooks can cancel encoding");
+        self.file_header = file_header;
+        self.info_header = info_header;
+    }
+
+    // NOTE(erick): The normalized headers along with the pixel array they
+    // describe. The masks have to be fixed before the pixels are encoded
+    // and the sizes can only be known after.
+    fn encode_normalized<P>(&self, progress: &mut P)
+                            -> BitmapResult<(BitmapFileHeader, BitmapInfoHeader, Vec<u8>)>
+        where P: FnMut(u32, u32) -> bool {
+        let mut info_header = self.info_header.clone();
+
+        // NOTE(erick): We can read RLE but can't write it, so decoded RLE
+        // images are written uncompressed.
+        match CompressionType::from(info_header.compression_type) {
+            CompressionType::Rle8 | CompressionType::Rle4 => {
+                info_header.compression_type = CompressionType::Uncompressed as u32;
+            },
+            _ => {},
+        }
+
+        let palette_entries = self.palette_entries_to_write();
+        // NOTE(erick): Indices mean nothing without a palette, so those
+        // images are written as 24-bit instead.
+        if palette_entries == 0 && palette_capacity(info_header.bits_per_pixel) > 0 {
+            info_header.bits_per_pixel = 24;
+            info_header.compression_type = CompressionType::Uncompressed as u32;
+        }
+
+        // NOTE(erick): Keep colors_used when it already describes the
+        // palette, so untouched files are written back as they were.
+        if info_header.palette_entries() != palette_entries || palette_entries == 0 {
+            let capacity = palette_capacity(info_header.bits_per_pixel);
+            info_header.colors_used = if palette_entries == capacity {
+                0
+            } else {
+                palette_entries as u32
+            };
+        }
+        info_header.colors_important = info_header.colors_important.min(info_header.colors_used);
+
+        if info_header.compression_type == CompressionType::BitFields as u32 &&
+            info_header.red_mask == 0 && info_header.green_mask == 0 && info_header.blue_mask == 0 {
+            let (red_mask, green_mask, blue_mask, alpha_mask) =
+                default_masks(info_header.bits_per_pixel);
+            info_header.red_mask   = red_mask;
+            info_header.green_mask = green_mask;
+            info_header.blue_mask  = blue_mask;
+            info_header.alpha_mask = alpha_mask;
+        }
+
+        let mut pixels = Vec::new();
+        pixels_into_data_rows(&self.image_data, &mut pixels, &info_header,
+                              &self.palette, &self.palette_indices, progress)?;
+        info_header.image_size = pixels.len() as u32;
+
+        let pixel_array_offset = FILE_HEADER_SIZE + info_header.info_header_size +
+            info_header.extra_masks_size() + palette_entries as u32 * PALETTE_ENTRY_SIZE;
+        // NOTE(erick): Like the files we create, 'file_size' ends with the
+        // pixel array. The trailer (and the CRC record) are left out, which
+        // is what readers that don't know about them expect.
+        let mut file_header = self.file_header.clone();
+        file_header.file_size = pixel_array_offset + info_header.image_size;
+        file_header.pixel_array_offset = pixel_array_offset;
+
+        Ok((file_header, info_header, pixels))
+    }
+
+    fn palette_entries_to_write(&self) -> usize {
+        let capacity = palette_capacity(self.info_header.bits_per_pixel);
+        if capacity == 0 {
+            return 0;
+        }
+
+        self.palette.as_ref().map_or(0, |palette| palette.len().min(capacity))
+    }
+
+    fn push_palette(&self, data: &mut Vec<u8>) {
+        if let Some(ref palette) = self.palette {
+            for pixel in palette.iter().take(self.palette_entries_to_write()) {
+                data.push(pixel.blue);
+                data.push(pixel.green);
+                data.push(pixel.red);
+                data.push(0x00);
             }
+        }
+    }
 
-        let data_size = result.len();
-        assert!(data_size <= self.file_header.pixel_array_offset as usize);
+    // NOTE(erick): Packed DIB as used by the Windows clipboard (CF_DIB):
+    // the info header, the masks or the palette and the pixels, with no
+    // file header and no gap before the pixels.
+    pub fn from_dib_data(data: &[u8]) -> BitmapResult<Bitmap> {
+        if data.len() < INFO_HEADER_SIZE as usize {
+            return Err(BitmapError::TruncatedAt(data.len()));
+        }
 
-        // Padding the data
-        for _ in data_size .. self.file_header.pixel_array_offset as usize {
-            result.push(0x00);
+        // NOTE(erick): Make sure the header (and its masks) are there
+        // before parsing it.
+        let mut data_walker = BytesWalker::new(data);
+        let info_header_size = data_walker.next_u32();
+        if info_header_size != INFO_HEADER_SIZE && info_header_size != V3_INFO_HEADER_SIZE {
+            return Err(BitmapError::UnsupportedInfoHeaderSize(info_header_size));
         }
+        let headers_size = info_header_size as usize + extra_masks_size(data);
+        if data.len() < headers_size {
+            return Err(BitmapError::TruncatedAt(data.len()));
+        }
+
+        let info_header = BitmapInfoHeader::from_data(data)?;
+        info_header.validate()?;
+
+        let pixel_array_offset = FILE_HEADER_SIZE + info_header.info_header_size +
+            info_header.extra_masks_size() +
+            info_header.palette_entries() as u32 * PALETTE_ENTRY_SIZE;
+        let file_size = FILE_HEADER_SIZE + data.len() as u32;
+        let file_header = BitmapFileHeader::new(file_size, pixel_array_offset);
+
+        let mut file_data = Vec::with_capacity(file_size as usize);
+        file_header.into_data(&mut file_data);
+        file_data.extend_from_slice(data);
+
+        Bitmap::from_data(file_data)
+    }
+
+    pub fn to_dib_data(&self) -> Vec<u8> {
+        let (_, info_header, pixels) = self.encode_normalized(&mut |_, _| true)
+            .expect("Only progress hooks can cancel encoding");
+
+        let mut result = Vec::new();
+        info_header.into_data(&mut result);
+        self.push_palette(&mut result);
+        result.extend_from_slice(&pixels);
+
+        result
+    }
 
-        pixels_into_data(&self.image_data, &mut result,
-                         &self.info_header, &self.palette);
+    // NOTE(erick): The whole file as a 'data:image/bmp;base64,...' URL,
+    // e.g., to inline small images in HTML reports.
+    pub fn to_data_url(&self) -> String {
+        let mut result = String::from(DATA_URL_PREFIX);
+        result.push_str(&base64::encode(&self.into_data()));
 
         result
     }
 
+    pub fn from_data_url(url: &str) -> BitmapResult<Bitmap> {
+        let url = url.trim();
+        // NOTE(erick): The scheme and the media type are case-insensitive.
+        let prefix = url.get(.. DATA_URL_PREFIX.len());
+        if !prefix.is_some_and(|prefix| prefix.eq_ignore_ascii_case(DATA_URL_PREFIX)) {
+            return Err(BitmapError::InvalidBitmap);
+        }
+
+        match base64::decode(&url[DATA_URL_PREFIX.len() ..]) {
+            Some(data) => Bitmap::from_data(data),
+            None       => Err(BitmapError::InvalidBitmap),
+        }
+    }
+
+    // NOTE(erick): None when the file we were loaded from had no CRC
+    // record (see EncodeOptions::append_crc32) or when the image was not
+    // loaded from a file.
+    pub fn verify_crc(&self) -> Option<bool> {
+        self.crc_check
+    }
+
+    // NOTE(erick): Prints both headers to stdout, which is what from_data
+    // used to do on every call.
+    pub fn dump_headers(&self) {
+        println!("{}", self.file_header);
+        println!("{}", self.info_header);
+    }
+
     pub fn into_file(&self, file: &mut File) -> BitmapResult<()> {
         let data = self.into_data();
 
-        // NOTE(erick): For some reason io::Error was not been
-        // converted to BitmapIOError(io_error).
-        if let Err(io_error) = file.write_all(data.as_slice()) {
-            return Err(BitmapError::BitmapIOError(io_error));
-        }
+        file.write_all(data.as_slice())?;
         Ok(())
     }
 
     pub fn color_to_alpha(&mut self, color: BitmapPixel) {
+        self.color_to_alpha_with(color, 0, AlphaFalloff::Hard);
+    }
+
+    // NOTE(erick): Same as color_to_alpha for every pixel within
+    // 'tolerance' (euclidean RGB distance, like flood_fill) of 'color'.
+    pub fn color_to_alpha_with(&mut self, color: BitmapPixel,
+                               tolerance: u32, falloff: AlphaFalloff) {
+        let max_distance_squared = tolerance.saturating_mul(tolerance);
+
         for pixel in &mut self.image_data {
-            if pixel.same_color_as(&color) {
-                pixel.alpha = 0x00;
+            let distance_squared = pixel.distance_squared(&color);
+            if distance_squared > max_distance_squared {
+                continue;
+            }
+
+            pixel.alpha = match falloff {
+                AlphaFalloff::Hard      => 0x00,
+                AlphaFalloff::Feathered => {
+                    // NOTE(erick): With no tolerance only exact matches
+                    // get here, and their distance is zero.
+                    let scale = (distance_squared as f32).sqrt() / tolerance.max(1) as f32;
+                    (pixel.alpha as f32 * scale).round() as u8
+                },
+            };
+        }
+    }
+
+    // NOTE(erick): Gives every pixel within 'tolerance' of 'from' the RGB
+    // of 'to', keeping its alpha. Matching palette entries are recolored
+    // too, so palette swaps survive writing palettized images.
+    pub fn replace_color(&mut self, from: BitmapPixel, to: BitmapPixel, tolerance: u32) {
+        let max_distance_squared = tolerance.saturating_mul(tolerance);
+        let recolor = |pixel: &mut BitmapPixel| {
+            if pixel.distance_squared(&from) <= max_distance_squared {
+                *pixel = BitmapPixel::rgba(to.red, to.green, to.blue, pixel.alpha);
+            }
+        };
+
+        self.image_data.iter_mut().for_each(recolor);
+        if let Some(ref mut palette) = self.palette {
+            palette.iter_mut().for_each(recolor);
+        }
+    }
+
+    // NOTE(erick): FNV-1a of the size and the RGBA of every pixel in
+    // screen order. Bitmaps that are == have the same hash, whatever their
+    // format, orientation or palette order.
+    pub fn pixel_hash(&self) -> u64 {
+        let width  = self.info_header.image_width as u32;
+        let height = self.info_header.image_height as u32;
+
+        let mut hasher = checksum::Fnv1a64::new();
+        hasher.write(&width.to_le_bytes());
+        hasher.write(&height.to_le_bytes());
+        for y in 0 .. height {
+            for x in 0 .. width {
+                let pixel = self.get_pixel(x, y);
+                hasher.write(&[pixel.red, pixel.green, pixel.blue, pixel.alpha]);
             }
         }
+
+        hasher.finish()
+    }
+
+    // NOTE(erick): Like ==, allowing every channel to be off by up to
+    // 'per_channel_tolerance', e.g., to compare an image with itself after
+    // going through a 16-bit format.
+    pub fn approx_eq(&self, other: &Bitmap, per_channel_tolerance: u8) -> bool {
+        let width  = self.info_header.image_width;
+        let height = self.info_header.image_height;
+        if width != other.info_header.image_width || height != other.info_header.image_height {
+            return false;
+        }
+
+        let close = |a: u8, b: u8| a.abs_diff(b) <= per_channel_tolerance;
+        (0 .. height as u32).all(|y| (0 .. width as u32).all(|x| {
+            let p0 = self.get_pixel(x, y);
+            let p1 = other.get_pixel(x, y);
+
+            close(p0.red, p1.red) && close(p0.green, p1.green) &&
+                close(p0.blue, p1.blue) && close(p0.alpha, p1.alpha)
+        }))
+    }
+
+    // NOTE(erick): Alpha is kept.
+    pub fn invert_colors(&mut self) {
+        self.map_channels(|value| 0xff - value);
+    }
+
+    // NOTE(erick): Calls 'f' with the screen coordinates of every pixel.
+    pub fn map_pixels<F>(&mut self, mut f: F)
+        where F: FnMut(u32, u32, BitmapPixel) -> BitmapPixel {
+        let width  = self.info_header.image_width as u32;
+        let height = self.info_header.image_height as u32;
+
+        for y in 0 .. height {
+            for x in 0 .. width {
+                let index = self.data_index(x, y);
+                self.image_data[index] = f(x, y, self.image_data[index]);
+            }
+        }
+    }
+
+    // NOTE(erick): Applies 'f' to red, green and blue. Alpha is kept.
+    pub fn map_channels<F>(&mut self, f: F)
+        where F: Fn(u8) -> u8 {
+        let mut table = [0u8; 256];
+        for (level, entry) in table.iter_mut().enumerate() {
+            *entry = f(level as u8);
+        }
+
+        self.map_color_levels(&table);
+    }
+
+    // NOTE(erick): Alpha is not touched by the adjustments below.
+    pub fn adjust_brightness(&mut self, offset: i16) {
+        self.add_channels([offset, offset, offset, 0]);
+    }
+
+    // NOTE(erick): Scales the distance of every channel to mid-gray, so
+    // 1.0 does nothing and 0.0 makes the image flat gray.
+    pub fn adjust_contrast(&mut self, factor: f32) {
+        let mut table = [0u8; 256];
+        for (level, entry) in table.iter_mut().enumerate() {
+            let value = (level as f32 - 128.0) * factor + 128.0;
+            *entry = value.round().clamp(0.0, 255.0) as u8;
+        }
+
+        self.map_color_levels(&table);
+    }
+
+    // NOTE(erick): out = in ^ (1 / gamma) on normalized channels, so
+    // values above 1.0 brighten the midtones. Non-positive values are
+    // ignored.
+    pub fn apply_gamma(&mut self, gamma: f32) {
+        if gamma <= 0.0 {
+            return;
+        }
+
+        let mut table = [0u8; 256];
+        for (level, entry) in table.iter_mut().enumerate() {
+            let value = (level as f32 / 255.0).powf(1.0 / gamma) * 255.0;
+            *entry = value.round().clamp(0.0, 255.0) as u8;
+        }
+
+        self.map_color_levels(&table);
+    }
+
+    // NOTE(erick): Rotates the hue of every pixel, keeping saturation,
+    // value and alpha.
+    pub fn adjust_hue(&mut self, degrees: f32) {
+        for pixel in &mut self.image_data {
+            let (hue, saturation, value) = pixel.to_hsv();
+            let alpha = pixel.alpha;
+
+            *pixel = BitmapPixel::from_hsv(hue + degrees, saturation, value);
+            pixel.alpha = alpha;
+        }
+    }
+
+    // NOTE(erick): Scales the HSV saturation, 0.0 gives a gray image.
+    pub fn adjust_saturation(&mut self, factor: f32) {
+        for pixel in &mut self.image_data {
+            let (hue, saturation, value) = pixel.to_hsv();
+            let alpha = pixel.alpha;
+
+            *pixel = BitmapPixel::from_hsv(hue, saturation * factor, value);
+            pixel.alpha = alpha;
+        }
+    }
+
+    // NOTE(erick): Factors are in [r, g, b, a] order. Results saturate
+    // at 0 and 0xff.
+    pub fn multiply_channels(&mut self, factors: [f32; 4]) {
+        let scale = |value: u8, factor: f32| {
+            (value as f32 * factor).round().clamp(0.0, 255.0) as u8
+        };
+
+        for pixel in &mut self.image_data {
+            pixel.red   = scale(pixel.red,   factors[0]);
+            pixel.green = scale(pixel.green, factors[1]);
+            pixel.blue  = scale(pixel.blue,  factors[2]);
+            pixel.alpha = scale(pixel.alpha, factors[3]);
+        }
+    }
+
+    pub fn add_channels(&mut self, offsets: [i16; 4]) {
+        let add = |value: u8, offset: i16| {
+            // NOTE(erick): i16 offsets can overflow an i16 sum.
+            (value as i32 + offset as i32).clamp(0, 0xff) as u8
+        };
+
+        for pixel in &mut self.image_data {
+            pixel.red   = add(pixel.red,   offsets[0]);
+            pixel.green = add(pixel.green, offsets[1]);
+            pixel.blue  = add(pixel.blue,  offsets[2]);
+            pixel.alpha = add(pixel.alpha, offsets[3]);
+        }
+    }
+
+    // NOTE(erick): Maps the luma of every pixel onto the gradient going
+    // from 'shadow' (black) to 'highlight' (white). Alpha is kept.
+    pub fn duotone(&mut self, shadow: BitmapPixel, highlight: BitmapPixel) {
+        for pixel in &mut self.image_data {
+            let t = pixel.luma() as f32 / 255.0;
+            let alpha = pixel.alpha;
+
+            *pixel = shadow.lerp(&highlight, t);
+            pixel.alpha = alpha;
+        }
+    }
+
+    // NOTE(erick): Replaces every color by its luma, keeping alpha and
+    // the current format.
+    pub fn to_grayscale(&mut self) {
+        for pixel in &mut self.image_data {
+            let luma = pixel.luma();
+            *pixel = BitmapPixel::rgba(luma, luma, luma, pixel.alpha);
+        }
+    }
+
+    // NOTE(erick): Same as 'to_grayscale' but converts the image to 8 bits
+    // with a 256 entries gray ramp as palette, so every luma level maps
+    // to itself.
+    #[cfg(feature = "encode-palettized")]
+    pub fn to_grayscale_8bit(&mut self) {
+        self.to_grayscale();
+        self.replace_headers(8, CompressionType::Uncompressed);
+
+        let palette = (0 ..= 255u8)
+            .map(|level| BitmapPixel::rgb(level, level, level))
+            .collect();
+        self.palette = Some(palette);
     }
 
     // NOTE(erick): We can probably have a lazy version of this function
@@ -861,6 +2307,18 @@ impl Bitmap {
         }
     }
 
+    // NOTE(erick): All the public geometry operations use screen
+    // coordinates, i.e., (0, 0) is the top-left pixel regardless of
+    // the image being stored top-down or bottom-up.
+    pub fn get_pixel(&self, x: u32, y: u32) -> BitmapPixel {
+        self.image_data[self.data_index(x, y)]
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, pixel: BitmapPixel) {
+        let data_index = self.data_index(x, y);
+        self.image_data[data_index] = pixel;
+    }
+
     pub fn crop_to_rect(&self, x0: u32, y0: u32,
                         width: u32, height: u32) -> BitmapResult<Bitmap> {
         if x0 >= self.info_header.image_width as u32 ||
@@ -873,101 +2331,161 @@ impl Bitmap {
                 return Err(BitmapError::InvalidOperation)
             }
 
+        Ok(self.cropped(x0, y0, width, height))
+    }
 
-        let mut result = Bitmap::lazy_new_default(width as i32, height as i32);
-        result.copy_rect_from(self, x0, y0, width, height);
+    // NOTE(erick): Headers for a 'width' x 'height' crop of this image.
+    // Only the layout changes, everything else is copied. RLE images
+    // become uncompressed, since we can't write RLE.
+    fn crop_headers(&self, width: u32, height: u32) -> (BitmapFileHeader, BitmapInfoHeader) {
+        let compression = match CompressionType::from(self.info_header.compression_type) {
+            CompressionType::Rle8 | CompressionType::Rle4 => CompressionType::Uncompressed,
+            compression                                   => compression,
+        };
+
+        let (mut file_header, mut info_header) =
+            Bitmap::create_headers(width as i32, height as i32,
+                                   self.info_header.bits_per_pixel, compression);
+        info_header.pixels_per_meter_x = self.info_header.pixels_per_meter_x;
+        info_header.pixels_per_meter_y = self.info_header.pixels_per_meter_y;
+        info_header.colors_used        = self.info_header.colors_used;
+        info_header.colors_important   = self.info_header.colors_important;
+        if compression == CompressionType::BitFields {
+            info_header.red_mask   = self.info_header.red_mask;
+            info_header.green_mask = self.info_header.green_mask;
+            info_header.blue_mask  = self.info_header.blue_mask;
+            info_header.alpha_mask = self.info_header.alpha_mask;
+        }
+
+        // NOTE(erick): create_headers reserves room for a full palette.
+        let pixel_array_offset = FILE_HEADER_SIZE + info_header.info_header_size +
+            info_header.extra_masks_size() +
+            self.palette_entries_to_write() as u32 * PALETTE_ENTRY_SIZE;
+        file_header.pixel_array_offset = pixel_array_offset;
+        file_header.file_size = pixel_array_offset + info_header.image_size;
+
+        (file_header, info_header)
+    }
+
+    // NOTE(erick): Same as crop_to_rect followed by convert_to, for when
+    // the crop should not keep the source's format.
+    pub fn crop_to_rect_as(&self, x0: u32, y0: u32, width: u32, height: u32,
+                           bits_per_pixel: u16,
+                           compression: CompressionType) -> BitmapResult<Bitmap> {
+        let mut result = self.crop_to_rect(x0, y0, width, height)?;
+        result.convert_to(bits_per_pixel, compression)?;
 
         Ok(result)
     }
 
-    pub fn merge_horizontally(image0: &Bitmap, image1: &Bitmap) -> Bitmap {
-        let result_width = image0.info_header.image_width +
-            image1.info_header.image_width;
-        let result_height = max(image0.info_header.image_height,
-                                image1.info_header.image_height);
+    // NOTE(erick): The crop keeps the bits per pixel, the masks and the
+    // palette (and the palette indices, so duplicated entries survive).
+    // The bounds are checked by the callers.
+    fn cropped(&self, x0: u32, y0: u32, width: u32, height: u32) -> Bitmap {
+        let (file_header, info_header) = self.crop_headers(width, height);
 
-        let mut result = Bitmap::new_default(result_width, result_height);
-        result.replace_rect_with_rect_from(image0,
-                                           0, 0,
-                                           0, 0,
-                                           image0.info_header.image_width as u32,
-                                           image0.info_header.image_height as u32);
-        result.replace_rect_with_rect_from(image1,
+        let mut result = Bitmap::new(width as i32, height as i32,
+                                     info_header.bits_per_pixel,
+                                     CompressionType::from(info_header.compression_type));
+        result.file_header = file_header;
+        result.info_header = info_header;
+        result.palette = self.palette.clone();
+
+        result.replace_rect_with_rect_from(self,
+                                           x0, y0,
                                            0, 0,
-                                           image0.info_header.image_width as u32, 0,
-                                           image1.info_header.image_width as u32,
-                                           image1.info_header.image_height as u32);
+                                           width, height);
+
+        if self.palette_indices.len() == self.image_data.len() {
+            let mut indices = vec![0; width as usize * height as usize];
+            for y in 0 .. height {
+                for x in 0 .. width {
+                    indices[result.data_index(x, y)] =
+                        self.palette_indices[self.data_index(x0 + x, y0 + y)];
+                }
+            }
+            result.palette_indices = indices;
+        }
 
         result
     }
 
-    pub fn merge_vertically(image0: &Bitmap, image1: &Bitmap) -> Bitmap {
-        let result_height = image0.info_header.image_height +
-            image1.info_header.image_height;
-        let result_width = max(image0.info_header.image_width,
-                               image1.info_header.image_width);
-
-        let mut result = Bitmap::new_default(result_width, result_height);
-        result.replace_rect_with_rect_from(image0,
-                                           0, 0,
-                                           0, 0,
-                                           image0.info_header.image_width as u32,
-                                           image0.info_header.image_height as u32);
-        result.replace_rect_with_rect_from(image1,
-                                           0, 0,
-                                           0, image0.info_header.image_height as u32,
-                                           image1.info_header.image_width as u32,
-                                           image1.info_header.image_height as u32);
+    // NOTE(erick): The smaller image is top aligned and the rest is left
+    // transparent. See merge_horizontally_with for other options.
+    pub fn merge_horizontally(image0: &Bitmap, image1: &Bitmap) -> Bitmap {
+        Bitmap::merge_horizontally_with(image0, image1, &MergeOptions::default())
+    }
 
-        result
+    // NOTE(erick): The smaller image is left aligned and the rest is left
+    // transparent. See merge_vertically_with for other options.
+    pub fn merge_vertically(image0: &Bitmap, image1: &Bitmap) -> Bitmap {
+        Bitmap::merge_vertically_with(image0, image1, &MergeOptions::default())
     }
 
     //
     // Private stuff.
     //
-    fn replace_rect_with_rect_from(&mut self, other: &Bitmap,
-                                   src_x0 : u32, src_y0 : u32,
-                                   dest_x0: u32, dest_y0: u32,
-                                   width: u32, height: u32) {
+    fn replace_headers(&mut self, bits_per_pixel: u16, compression: CompressionType) {
+        // NOTE(erick): New headers are always bottom-up.
+        if self.info_header.is_top_down {
+            self.mirror_vertically();
+        }
 
-        let src_stride  = other.info_header.image_width as usize;
-        let dest_stride = self.info_header.image_width  as usize;
+        // NOTE(erick): It's easier to create new header than to
+        // try to modify the existing ones.
+        let (file_header, info_header) =
+            Bitmap::create_headers(self.info_header.image_width,
+                                  self.info_header.image_height,
+                                  bits_per_pixel, compression);
 
-        let mut current_dest_y = dest_y0 as usize;
-        for current_src_y in src_y0 as usize .. (src_y0 + height) as usize {
-            let mut current_dest_x = dest_x0 as usize;
+        self.file_header = file_header;
+        self.info_header = info_header;
+    }
 
-            for current_src_x in src_x0 as usize .. (src_x0 + width) as usize {
-                let src_data_index  = current_src_y * src_stride + current_src_x;
-                let dest_data_index = current_dest_y * dest_stride + current_dest_x;
+    fn map_color_levels(&mut self, table: &[u8; 256]) {
+        for pixel in &mut self.image_data {
+            pixel.red   = table[pixel.red   as usize];
+            pixel.green = table[pixel.green as usize];
+            pixel.blue  = table[pixel.blue  as usize];
+        }
+    }
 
-                let data = other.image_data[src_data_index];
-                self.image_data[dest_data_index] = data;
+    fn data_index(&self, x: u32, y: u32) -> usize {
+        let stride = self.info_header.image_width as usize;
 
-                current_dest_x += 1;
-            }
+        // NOTE(erick): image_data is kept in file order, so the
+        // first row of a bottom-up image is the bottom one.
+        let row_index = if self.info_header.is_top_down {
+            y as usize
+        } else {
+            self.info_header.image_height as usize - y as usize - 1
+        };
 
-            current_dest_y += 1;
-        }
+        row_index * stride + x as usize
     }
 
-    fn copy_rect_from(&mut self, other: &Bitmap,
-                      x0: u32, y0: u32, width: u32, height: u32) {
-        assert_eq!(0, self.image_data.len());
-
-        let stride = other.info_header.image_width as usize;
+    #[allow(clippy::too_many_arguments, clippy::explicit_counter_loop)]
+    fn replace_rect_with_rect_from(&mut self, other: &Bitmap,
+                                   src_x0 : u32, src_y0 : u32,
+                                   dest_x0: u32, dest_y0: u32,
+                                   width: u32, height: u32) {
+        let mut current_dest_y = dest_y0;
+        for current_src_y in src_y0 .. src_y0 + height {
+            let mut current_dest_x = dest_x0;
 
-        for row_index in y0 as usize .. (y0 + height) as usize {
-            for column_index in x0 as usize .. (x0 + width) as usize {
-                let data_index = row_index * stride + column_index;
-                let data = other.image_data[data_index];
+            for current_src_x in src_x0 .. src_x0 + width {
+                let data = other.get_pixel(current_src_x, current_src_y);
+                self.set_pixel(current_dest_x, current_dest_y, data);
 
-                self.image_data.push(data);
+                current_dest_x += 1;
             }
+
+            current_dest_y += 1;
         }
     }
 }
 
+#[allow(clippy::manual_swap)]
 fn swap_slice_regions<T>(slice: &mut [T],
                          mut r0: Range<usize>,
                          mut r1: Range<usize>) where T: Copy {
@@ -988,6 +2506,7 @@ fn swap_slice_regions<T>(slice: &mut [T],
     }
 }
 
+#[allow(clippy::manual_swap)]
 fn mirror_slice<T>(slice: &mut [T]) where T: Copy {
     for index_left in 0 .. slice.len() / 2 {
         let index_right = slice.len() - index_left - 1;
@@ -999,14 +2518,82 @@ fn mirror_slice<T>(slice: &mut [T]) where T: Copy {
 }
 
 
-// TODO(erick): Floating-point is slow. We have enough
-// precision to do it using fixed-point math.
 pub fn map_zero_based(value: &mut u8, from: u32, to: u32) {
+    *value = scale_zero_based(*value as u32, from, to).min(0xff) as u8;
+}
+
+// NOTE(erick): Maps 'value' from [0, from] to [0, to] with integer math.
+// Rounding (instead of truncating) makes going to a smaller range and
+// back a no-op on values that came from it.
+fn scale_zero_based(value: u32, from: u32, to: u32) -> u32 {
     // Don't do useless work and don't divide by zero.
-    if from == to || from == 0 { return; }
+    if from == to || from == 0 { return value; }
 
-    let t = (*value as f32) / from as f32;
-    *value = (to as f32 * t) as u8;
+    ((value as u64 * to as u64 + from as u64 / 2) / from as u64) as u32
+}
+
+// NOTE(erick): scale_zero_based for every value a channel can have, so
+// converting a pixel costs a lookup per channel. The 5 bits channels of
+// 16 bits images get 32 entries tables. Values never go past 16 bits in
+// the formats we use it for, which also bounds the table size.
+struct ChannelLut {
+    table : Vec<u32>,
+}
+
+impl ChannelLut {
+    fn new(from: u32, to: u32) -> ChannelLut {
+        ChannelLut {
+            table : (0 ..= from.min(0xffff)).map(|value| scale_zero_based(value, from, to)).collect(),
+        }
+    }
+
+    fn get(&self, value: u32) -> u32 {
+        self.table[value as usize]
+    }
+}
+
+// NOTE(erick): One channel of a BitFields pixel. Channels can be any
+// width, so values are rescaled between the mask's range and the 8 bits
+// of BitmapPixel. Channels wider than 16 bits drop their low bits before
+// the lookup to keep the table small.
+struct BitfieldChannel {
+    mask        : u32,
+    offset      : u8,
+    extra_shift : u8,
+    lut         : ChannelLut,
+}
+
+impl BitfieldChannel {
+    fn for_reading(mask: u32) -> BitfieldChannel {
+        let (offset, shifted) = mask_offset_and_shifted(mask);
+        let extra_shift = (32 - shifted.leading_zeros()).saturating_sub(16) as u8;
+
+        BitfieldChannel {
+            mask        : mask,
+            offset      : offset,
+            extra_shift : extra_shift,
+            lut         : ChannelLut::new(shifted >> extra_shift, 0xff),
+        }
+    }
+
+    fn for_writing(mask: u32) -> BitfieldChannel {
+        let (offset, shifted) = mask_offset_and_shifted(mask);
+
+        BitfieldChannel {
+            mask        : mask,
+            offset      : offset,
+            extra_shift : 0,
+            lut         : ChannelLut::new(0xff, shifted),
+        }
+    }
+
+    fn read(&self, pixel_value: u32) -> u8 {
+        self.lut.get(((pixel_value & self.mask) >> self.offset) >> self.extra_shift) as u8
+    }
+
+    fn write(&self, value: u8) -> u32 {
+        (self.lut.get(value as u32) << self.offset) & self.mask
+    }
 }
 
 pub struct BytesWalker<'a> {
@@ -1015,7 +2602,7 @@ pub struct BytesWalker<'a> {
 }
 
 impl<'a> BytesWalker<'a> {
-    pub fn new(d: &[u8]) -> BytesWalker {
+    pub fn new(d: &[u8]) -> BytesWalker<'_> {
         BytesWalker {
             data          : d,
             current_index : 0,
@@ -1036,16 +2623,14 @@ impl<'a> BytesWalker<'a> {
     // NOTE(erick): It would be nice to use generics to
     // generate this functions, but I don't know of
     // a way to get the size of a type at compile time.
-    // WARNING(erick): Theses functions only work
-    // because the bitmap format uses little-endianness
-    // and we are running on an little-endian machine.
-    // Sooner or later this will have to be fixed.
+    // The bitmap format is little-endian, so we decode
+    // explicitly with from_le_bytes.
     pub fn next_u16(&mut self) -> u16 {
         let mut bytes = [0; 2];
         bytes.clone_from_slice(&self.data[self.current_index .. self.current_index + 2]);
         self.current_index += 2;
 
-        unsafe { transmute(bytes) }
+        u16::from_le_bytes(bytes)
     }
 
     pub fn next_u32(&mut self) -> u32 {
@@ -1053,7 +2638,7 @@ impl<'a> BytesWalker<'a> {
         bytes.clone_from_slice(&self.data[self.current_index .. self.current_index + 4]);
         self.current_index += 4;
 
-        unsafe { transmute(bytes) }
+        u32::from_le_bytes(bytes)
     }
 
     pub fn next_i32(&mut self) -> i32 {
@@ -1061,7 +2646,7 @@ impl<'a> BytesWalker<'a> {
         bytes.clone_from_slice(&self.data[self.current_index .. self.current_index + 4]);
         self.current_index += 4;
 
-        unsafe { transmute(bytes) }
+        i32::from_le_bytes(bytes)
     }
 
     pub fn align_with_u32(&mut self) {
@@ -1069,147 +2654,16 @@ impl<'a> BytesWalker<'a> {
 
         self.current_index += pad;
     }
-}
-
-#[allow(dead_code)]
-fn find_best_palette_median_cut(_colors: &Vec<BitmapPixel>,
-                                palette_desired_size: u16) -> BitmapPalette {
-    let mut colors = _colors.clone();
-
-    let mut colors_slice = colors.as_mut_slice();
-    let mut partitions = vec![(0, colors_slice.len())];
-
-    while partitions.len() < palette_desired_size as usize {
-        let mut new_partions = Vec::new();
-        for partition in partitions {
-            let partition_len = partition.1 - partition.0;
-            let partition_mid = partition.0 + (partition_len / 2);
-
-            let part = &mut colors_slice[partition.0 .. partition.1];
-            partition_divide(part);
-
-            let part0 = (partition.0, partition_mid);
-            let part1 = (partition_mid, partition.1);
-
-            new_partions.push(part0);
-            new_partions.push(part1);
-        }
-        partitions = new_partions;
-    }
-
-    let mut result = Vec::with_capacity(partitions.len());
-    for partition in partitions {
-        let part = &colors_slice[partition.0 .. partition.1];
-        result.push(pixels_mean(part));
-    }
-
-    result
-}
-
-#[allow(dead_code)]
-fn partition_divide(partition: &mut [BitmapPixel]) {
-    let mut min_r = 0;
-    let mut min_g = 0;
-    let mut min_b = 0;
-
-    let mut max_r = 0xff;
-    let mut max_g = 0xff;
-    let mut max_b = 0xff;
-
-    for pixel in partition.as_ref() {
-        if pixel.red < min_r { min_r = pixel.red; }
-        if pixel.red > max_r { max_r = pixel.red; }
-
-        if pixel.green < min_g { min_g = pixel.green; }
-        if pixel.green > max_g { max_g = pixel.green; }
-
-        if pixel.blue < min_b { min_b = pixel.blue; }
-        if pixel.blue > max_b { max_b = pixel.blue; }
-    }
-
-    let diff_r = max_r - min_r;
-    let diff_g = max_g - min_g;
-    let diff_b = max_b - min_b;
 
-    if diff_r > diff_g && diff_r > diff_b {
-        partition.sort_by(|a: &BitmapPixel, b: &BitmapPixel| a.red.cmp(&b.red));
-    } else if diff_g > diff_b {
-        partition.sort_by(|a: &BitmapPixel, b: &BitmapPixel| a.green.cmp(&b.green));
-    } else {
-        partition.sort_by(|a: &BitmapPixel, b: &BitmapPixel| a.blue.cmp(&b.blue));
+    pub fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.current_index)
     }
-}
 
-#[allow(dead_code)]
-fn pixels_mean(pixels: &[BitmapPixel]) -> BitmapPixel {
-    let mut accum_r = 0.0;
-    let mut accum_g = 0.0;
-    let mut accum_b = 0.0;
+    pub fn next_bytes(&mut self, n_bytes: usize) -> &'a [u8] {
+        let result = &self.data[self.current_index .. self.current_index + n_bytes];
+        self.current_index += n_bytes;
 
-    let weight = 1.0 / pixels.len() as f64;
-    for pixel in pixels {
-        accum_r += pixel.red   as f64 * weight;
-        accum_g += pixel.green as f64 * weight;
-        accum_b += pixel.blue  as f64 * weight;
+        result
     }
-
-    BitmapPixel::rgb(accum_r as u8,
-                    accum_g as u8,
-                    accum_b as u8)
-}
-
-
-struct KmeansPixel {
-    pixel      : BitmapPixel,
-    cluster_id : i32,
 }
 
-fn find_best_palette_k_means(_colors: &Vec<BitmapPixel>,
-                             palette_desired_size: u16) -> BitmapPalette {
-    let mut pixels : Vec<_> = _colors.into_iter().map(
-        |a| KmeansPixel{pixel: *a, cluster_id: -1}).collect();
-
-    let mut means = Vec::with_capacity(palette_desired_size as usize);
-    for _ in 0 .. palette_desired_size {
-        let rand_index = rand::random::<usize>() % _colors.len();
-        means.push(_colors[rand_index]);
-    }
-
-    for _ in 0 .. 20 {
-        // Assignment
-        let mut changes = 0;
-        for pixel in &mut pixels {
-            let p_index = pixel.pixel.find_closest_by_index(&means) as i32;
-            if p_index != pixel.cluster_id { changes += 1; }
-
-            pixel.cluster_id = p_index;
-        }
-
-        if changes == 0 { break; }
-
-        //Update
-        let mut means_counts : Vec<u64> = vec![0; palette_desired_size as usize];
-        let mut means_r : Vec<f64> = vec![0.0; palette_desired_size as usize];
-        let mut means_g : Vec<f64> = vec![0.0; palette_desired_size as usize];
-        let mut means_b : Vec<f64> = vec![0.0; palette_desired_size as usize];
-
-        for pixel in &pixels {
-            let index = pixel.cluster_id as usize;
-            let pix = pixel.pixel;
-
-            means_counts[index] += 1;
-            means_r[index] += pix.red   as f64;
-            means_g[index] += pix.green as f64;
-            means_b[index] += pix.blue  as f64;
-        }
-
-        for i in 0 .. means.len() {
-            let pixel = BitmapPixel::rgb((means_r[i] / (means_counts[i] as f64)) as u8,
-                                        (means_g[i] / (means_counts[i] as f64)) as u8,
-                                        (means_b[i] / (means_counts[i] as f64)) as u8);
-            means[i] = pixel;
-        }
-    }
-
-    means
-}
@@ -1,53 +1,301 @@
+#![allow(clippy::redundant_field_names)]
+
 extern crate bitmap_io;
 
 use bitmap_io::*;
 
 use std::fs::File;
+use std::process::exit;
 
-#[allow(unused_must_use)]
-fn main() {
+const USAGE : &str = "\
+Usage: bitmap_io <command> [arguments]
+
+Commands:
+  info <input.bmp> [<input.bmp> ...]
+  convert <input.bmp> <output.bmp> [--bpp <1|2|4|8|16|24|32>] [--compression <uncompressed|bitfields>]
+  crop <input.bmp> <output.bmp> <x> <y> <width> <height>
+  resize <input.bmp> <output.bmp> <width> <height> [--filter <nearest|bilinear|lanczos3>]
+  merge <output.bmp> <input.bmp> <input.bmp> [<input.bmp> ...]
+        [--layout <horizontal|vertical|grid:<columns>>] [--align <start|center|end>]
+  diff <a.bmp> <b.bmp> [<difference.bmp>]
+  pipeline <pipeline.json> <input.bmp> <output.bmp> [<input.bmp> <output.bmp> ...]
+
+diff exits with 1 when the images differ.";
+
+// NOTE(erick): What a command can fail with. Usage errors print the usage
+// and exit with 2, everything else exits with 1.
+enum CliError {
+    Usage(String),
+    Bitmap(BitmapError),
+}
+
+impl From<BitmapError> for CliError {
+    fn from(err: BitmapError) -> CliError {
+        CliError::Bitmap(err)
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(err: std::io::Error) -> CliError {
+        CliError::Bitmap(BitmapError::from(err))
+    }
+}
+
+type CliResult<T> = Result<T, CliError>;
+
+type CliOptions = Vec<(String, String)>;
+
+fn usage_error<T>(message: &str) -> CliResult<T> {
+    Err(CliError::Usage(message.to_string()))
+}
+
+// NOTE(erick): Splits '--name value' pairs from the positional arguments.
+// Every option takes exactly one value.
+fn split_options(args: &[String], known: &[&str]) -> CliResult<(Vec<String>, CliOptions)> {
+    let mut positional = Vec::new();
+    let mut options = Vec::new();
+
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        if let Some(name) = arg.strip_prefix("--") {
+            if !known.contains(&name) {
+                return usage_error(&format!("unknown option '{}'", arg));
+            }
+
+            match args_iter.next() {
+                Some(value) => options.push((name.to_string(), value.clone())),
+                None        => return usage_error(&format!("missing value for '{}'", arg)),
+            }
+        } else {
+            positional.push(arg.clone());
+        }
+    }
+
+    Ok((positional, options))
+}
+
+fn option<'a>(options: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    options.iter().rev()
+        .find(|option| option.0 == name)
+        .map(|option| option.1.as_str())
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, what: &str) -> CliResult<T> {
+    match value.parse() {
+        Ok(number) => Ok(number),
+        Err(_)     => usage_error(&format!("invalid {} '{}'", what, value)),
+    }
+}
+
+fn load(path: &str) -> CliResult<Bitmap> {
+    Ok(Bitmap::from_file(&mut File::open(path)?)?)
+}
+
+fn save(image: &Bitmap, path: &str) -> CliResult<()> {
+    Ok(image.into_file(&mut File::create(path)?)?)
+}
+
+// NOTE(erick): bitmap_io info <input.bmp> [<input.bmp> ...]
+fn run_info(args: &[String]) -> CliResult<()> {
+    if args.is_empty() {
+        return usage_error("info needs at least one file");
+    }
+
+    for path in args {
+        let info = Bitmap::probe(&mut File::open(path)?)?;
+        let file_size = std::fs::metadata(path)?.len();
+
+        println!("{}:", path);
+        println!("  size:         {} x {}", info.width, info.height);
+        println!("  bits/pixel:   {}", info.bits_per_pixel);
+        println!("  compression:  {:?}", info.compression);
+        println!("  palette:      {} entries", info.palette_size);
+        println!("  orientation:  {}", if info.is_top_down { "top-down" } else { "bottom-up" });
+        println!("  file size:    {} bytes", file_size);
+        println!("  decoded size: {} bytes", info.decoded_size);
+    }
+
+    Ok(())
+}
+
+// NOTE(erick): bitmap_io convert <input.bmp> <output.bmp> [--bpp <n>] [--compression <name>]
+fn run_convert(args: &[String]) -> CliResult<()> {
+    let (files, options) = split_options(args, &["bpp", "compression"])?;
+    if files.len() != 2 {
+        return usage_error("convert needs an input and an output file");
+    }
 
-    let mut bmp_file = File::open("test_24-uncompressed.bmp").unwrap();
-    let test_24_uncompressed = Bitmap::from_file(&mut bmp_file).unwrap();
+    let mut image = load(&files[0])?;
 
-    let mut bmp_file = File::open("test_32-uncompressed.bmp").unwrap();
-    let test_32_uncompressed = Bitmap::from_file(&mut bmp_file).unwrap();
+    let bits_per_pixel = match option(&options, "bpp") {
+        Some(value) => parse_number(value, "bits per pixel")?,
+        None        => image.info_header.bits_per_pixel,
+    };
+    let compression = match option(&options, "compression") {
+        Some("uncompressed") => CompressionType::Uncompressed,
+        Some("bitfields")    => CompressionType::BitFields,
+        Some(value)          => return usage_error(&format!("unknown compression '{}'", value)),
+        // NOTE(erick): Only 16 and 32 bits images can be BitFields.
+        None if bits_per_pixel == image.info_header.bits_per_pixel &&
+            image.info_header.compression_type == CompressionType::BitFields as u32 => {
+                CompressionType::BitFields
+            },
+        None => CompressionType::Uncompressed,
+    };
 
-    let mut bmp_file = File::open("test_32-bitfield.bmp").unwrap();
-    let test_32_bitfield = Bitmap::from_file(&mut bmp_file).unwrap();
+    image.convert_to(bits_per_pixel, compression)?;
+    save(&image, &files[1])
+}
+
+// NOTE(erick): bitmap_io crop <input.bmp> <output.bmp> <x> <y> <width> <height>
+fn run_crop(args: &[String]) -> CliResult<()> {
+    if args.len() != 6 {
+        return usage_error("crop needs an input, an output and a rectangle");
+    }
 
-    let mut bmp_file = File::open("test_16-bitfield.bmp").unwrap();
-    let test_16_bitfield = Bitmap::from_file(&mut bmp_file).unwrap();
+    let x      = parse_number(&args[2], "x")?;
+    let y      = parse_number(&args[3], "y")?;
+    let width  = parse_number(&args[4], "width")?;
+    let height = parse_number(&args[5], "height")?;
 
-    let mut bmp_file = File::open("8bpp.bmp").unwrap();
-    let test_8_uncompressed = Bitmap::from_file(&mut bmp_file).unwrap();
+    let mut image = load(&args[0])?;
+    image.crop_in_place(x, y, width, height)?;
+    save(&image, &args[1])
+}
 
-    // let mut bmp_file = File::open("test.bmp").unwrap();
-    // let bitmap = Bitmap::from_file(&mut bmp_file).unwrap();
+// NOTE(erick): bitmap_io resize <input.bmp> <output.bmp> <width> <height> [--filter <name>]
+fn run_resize(args: &[String]) -> CliResult<()> {
+    let (positional, options) = split_options(args, &["filter"])?;
+    if positional.len() != 4 {
+        return usage_error("resize needs an input, an output and the new size");
+    }
 
-    // println!("{}", bitmap.file_header);
-    // println!("{}", bitmap.info_header);
+    let width  = parse_number(&positional[2], "width")?;
+    let height = parse_number(&positional[3], "height")?;
+    let filter = match option(&options, "filter") {
+        Some("nearest")         => ResizeFilter::NearestNeighbor,
+        Some("bilinear") | None => ResizeFilter::Bilinear,
+        Some("lanczos3")        => ResizeFilter::Lanczos3,
+        Some(value)             => return usage_error(&format!("unknown filter '{}'", value)),
+    };
 
+    let mut image = load(&positional[0])?;
+    image.resize(width, height, filter)?;
+    save(&image, &positional[1])
+}
 
-    if let Ok(mut out_file) = File::create("test_24-uncompressed-result.bmp") {
-        test_24_uncompressed.into_file(&mut out_file);
+// NOTE(erick): bitmap_io merge <output.bmp> <input.bmp> <input.bmp> [...]
+//                              [--layout <name>] [--align <name>]
+fn run_merge(args: &[String]) -> CliResult<()> {
+    let (files, options) = split_options(args, &["layout", "align"])?;
+    if files.len() < 3 {
+        return usage_error("merge needs an output and at least two inputs");
     }
 
-    if let Ok(mut out_file) = File::create("test_32-uncompressed-result.bmp") {
-        test_32_uncompressed.into_file(&mut out_file);
+    let layout = match option(&options, "layout") {
+        Some("horizontal") | None => MergeLayout::Horizontal,
+        Some("vertical")          => MergeLayout::Vertical,
+        Some(value) if value.starts_with("grid:") => {
+            MergeLayout::Grid(parse_number(&value["grid:".len() ..], "number of columns")?)
+        },
+        Some(value) => return usage_error(&format!("unknown layout '{}'", value)),
+    };
+    let alignment = match option(&options, "align") {
+        Some("start") | None => Alignment::Start,
+        Some("center")       => Alignment::Center,
+        Some("end")          => Alignment::End,
+        Some(value)          => return usage_error(&format!("unknown alignment '{}'", value)),
+    };
+
+    let images = files[1 ..].iter()
+        .map(|path| load(path))
+        .collect::<CliResult<Vec<Bitmap>>>()?;
+    let image_refs: Vec<&Bitmap> = images.iter().collect();
+    let merge_options = MergeOptions { alignment : alignment, ..MergeOptions::default() };
+
+    let merged = Bitmap::merge(&image_refs, layout, &merge_options)?;
+    save(&merged, &files[0])
+}
+
+// NOTE(erick): bitmap_io diff <a.bmp> <b.bmp> [<difference.bmp>]
+// Returns whether the images are identical.
+fn run_diff(args: &[String]) -> CliResult<bool> {
+    if args.len() != 2 && args.len() != 3 {
+        return usage_error("diff needs two files (and optionally an output)");
     }
 
-    if let Ok(mut out_file) = File::create("test_32-bitfield-result.bmp") {
-        test_32_bitfield.into_file(&mut out_file);
+    let image0 = load(&args[0])?;
+    let image1 = load(&args[1])?;
+    let diff = image0.diff(&image1)?;
+
+    println!("differing pixels:   {}", diff.stats.differing_pixels);
+    println!("max channel delta:  {}", diff.stats.max_channel_delta);
+    println!("mean squared error: {:.4}", diff.stats.mean_squared_error);
+    println!("psnr:               {:.2} dB", diff.stats.psnr);
+
+    if let Some(path) = args.get(2) {
+        save(&diff.image, path)?;
     }
 
-    if let Ok(mut out_file) = File::create("test_16-bitfield-result.bmp") {
-        test_16_bitfield.into_file(&mut out_file);
+    Ok(diff.stats.is_identical())
+}
+
+// NOTE(erick): bitmap_io pipeline <pipeline.json> <input.bmp> <output.bmp> [<input.bmp> <output.bmp> ...]
+#[cfg(feature = "serde")]
+fn run_pipeline(args: &[String]) -> CliResult<()> {
+    use std::io::Read;
+
+    if args.len() < 3 || args.len().is_multiple_of(2) {
+        return usage_error("pipeline needs a pipeline file and pairs of input and output files");
     }
 
-    if let Ok(mut out_file) = File::create("test_8-uncompressed-result.bmp") {
-        test_8_uncompressed.into_file(&mut out_file);
+    let mut json = String::new();
+    File::open(&args[0])?.read_to_string(&mut json)?;
+    let pipeline = Pipeline::from_json(&json)?;
+
+    for files in args[1 ..].chunks(2) {
+        let mut image = load(&files[0])?;
+        pipeline.apply(&mut image)?;
+        save(&image, &files[1])?;
     }
 
-    println!("Hello world");
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+fn run_pipeline(_args: &[String]) -> CliResult<()> {
+    usage_error("pipeline needs the \"serde\" feature")
+}
+
+fn main() {
+    let args : Vec<String> = std::env::args().collect();
+    let command = args.get(1).map(|command| command.as_str()).unwrap_or("");
+    let command_args = if args.len() > 2 { &args[2 ..] } else { &[] };
+
+    let result = match command {
+        "info"     => run_info(command_args),
+        "convert"  => run_convert(command_args),
+        "crop"     => run_crop(command_args),
+        "resize"   => run_resize(command_args),
+        "merge"    => run_merge(command_args),
+        "pipeline" => run_pipeline(command_args),
+        "diff"     => run_diff(command_args).map(|identical| if !identical { exit(1) }),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            return;
+        },
+        _ => usage_error(&format!("unknown command '{}'", command)),
+    };
+
+    match result {
+        Ok(()) => {},
+        Err(CliError::Usage(message)) => {
+            eprintln!("bitmap_io: {}\n\n{}", message, USAGE);
+            exit(2);
+        },
+        Err(CliError::Bitmap(err)) => {
+            eprintln!("bitmap_io {} failed: {}", command, err);
+            exit(1);
+        },
+    }
 }
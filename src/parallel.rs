@@ -0,0 +1,33 @@
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+// NOTE(erick): Work made of independent items (rows, groups of rows)
+// goes through here. With the "rayon" feature the items are spread over
+// rayon's thread pool, otherwise they are processed in order on the
+// calling thread. Either way the results come back in item order.
+pub fn map_items<T, R, F>(items: &[T], f: F) -> Vec<R>
+    where T: Sync, R: Send, F: Fn(&T) -> R + Sync + Send {
+    #[cfg(feature = "rayon")]
+    {
+        items.par_iter().map(f).collect()
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    {
+        items.iter().map(f).collect()
+    }
+}
+
+// NOTE(erick): How many items to hand to map_items at once when the
+// caller has to do something (like reporting progress) between them.
+pub fn batch_size() -> usize {
+    #[cfg(feature = "rayon")]
+    {
+        rayon::current_num_threads()
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    {
+        1
+    }
+}
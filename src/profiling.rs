@@ -0,0 +1,88 @@
+// NOTE(erick): When the "profiling" feature is disabled DecodeTimer
+// is an empty struct and every call compiles down to nothing, so
+// from_data doesn't need to be littered with cfg attributes.
+
+#[cfg(feature = "profiling")]
+use std::cell::Cell;
+#[cfg(feature = "profiling")]
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "profiling")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DecodeTimings {
+    pub header        : Duration,
+    pub palette       : Duration,
+    pub pixel_convert : Duration,
+    pub total         : Duration,
+}
+
+#[cfg(feature = "profiling")]
+thread_local! {
+    static LAST_DECODE_TIMINGS : Cell<Option<DecodeTimings>> = const { Cell::new(None) };
+}
+
+#[cfg(feature = "profiling")]
+pub fn last_decode_timings() -> Option<DecodeTimings> {
+    LAST_DECODE_TIMINGS.with(|timings| timings.get())
+}
+
+#[cfg(feature = "profiling")]
+pub struct DecodeTimer {
+    start   : Instant,
+    last    : Instant,
+    timings : DecodeTimings,
+}
+
+#[cfg(feature = "profiling")]
+impl DecodeTimer {
+    pub fn start() -> DecodeTimer {
+        let now = Instant::now();
+        DecodeTimer {
+            start   : now,
+            last    : now,
+            timings : DecodeTimings::default(),
+        }
+    }
+
+    fn lap(&mut self) -> Duration {
+        let now = Instant::now();
+        let result = now - self.last;
+        self.last = now;
+
+        result
+    }
+
+    pub fn header_done(&mut self) {
+        self.timings.header = self.lap();
+    }
+
+    pub fn palette_done(&mut self) {
+        self.timings.palette = self.lap();
+    }
+
+    pub fn pixels_done(&mut self) {
+        self.timings.pixel_convert = self.lap();
+    }
+
+    pub fn finish(mut self) {
+        self.timings.total = self.last - self.start;
+        LAST_DECODE_TIMINGS.with(|timings| timings.set(Some(self.timings)));
+    }
+}
+
+#[cfg(not(feature = "profiling"))]
+pub struct DecodeTimer;
+
+#[cfg(not(feature = "profiling"))]
+impl DecodeTimer {
+    #[inline(always)]
+    pub fn start() -> DecodeTimer { DecodeTimer }
+    #[inline(always)]
+    pub fn header_done(&mut self) {}
+    #[inline(always)]
+    pub fn palette_done(&mut self) {}
+    #[inline(always)]
+    pub fn pixels_done(&mut self) {}
+    #[inline(always)]
+    pub fn finish(self) {}
+}
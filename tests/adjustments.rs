@@ -0,0 +1,51 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn test_image() -> Bitmap {
+    let mut result = Bitmap::new_default(4, 2);
+    result.map_pixels(|x, y, _| BitmapPixel::rgba(x as u8 * 80, y as u8 * 200, 0x7f, 0x40));
+
+    result
+}
+
+#[test]
+fn add_channels_saturates_on_extreme_offsets() {
+    let mut image = test_image();
+    image.add_channels([i16::MAX, i16::MIN, i16::MAX, i16::MIN]);
+    assert!(image.image_data.iter().all(|pixel| *pixel == BitmapPixel::rgba(0xff, 0x00, 0xff, 0x00)));
+
+    let mut image = test_image();
+    image.add_channels([i16::MIN, i16::MAX, 0, 0]);
+    assert!(image.image_data.iter().all(|pixel| pixel.red == 0x00 && pixel.green == 0xff &&
+                                        pixel.blue == 0x7f && pixel.alpha == 0x40));
+}
+
+#[test]
+fn add_channels_clamps_small_offsets() {
+    let mut image = test_image();
+    image.add_channels([100, -100, 1, -1]);
+
+    assert_eq!(image.get_pixel(0, 0), BitmapPixel::rgba(100, 0, 0x80, 0x3f));
+    assert_eq!(image.get_pixel(3, 1), BitmapPixel::rgba(0xff, 100, 0x80, 0x3f));
+}
+
+#[test]
+fn brightness_saturates_at_the_boundaries() {
+    let mut image = test_image();
+    image.adjust_brightness(i16::MAX);
+    assert!(image.image_data.iter().all(|pixel| *pixel == BitmapPixel::rgba(0xff, 0xff, 0xff, 0x40)));
+
+    let mut image = test_image();
+    image.adjust_brightness(i16::MIN);
+    assert!(image.image_data.iter().all(|pixel| *pixel == BitmapPixel::rgba(0x00, 0x00, 0x00, 0x40)));
+
+    // NOTE(erick): 0xff is the smallest offset that saturates every level.
+    let mut image = test_image();
+    image.adjust_brightness(0xff);
+    assert!(image.image_data.iter().all(|pixel| pixel.red == 0xff && pixel.green == 0xff));
+
+    let mut image = test_image();
+    image.adjust_brightness(0);
+    assert_eq!(image, test_image());
+}
@@ -0,0 +1,169 @@
+#![cfg(feature = "tokio")]
+#![allow(clippy::redundant_field_names)]
+
+extern crate bitmap_io;
+extern crate tokio;
+
+use bitmap_io::*;
+
+use tokio::io::{AsyncRead, ReadBuf};
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+// NOTE(erick): Just enough of an executor to drive the futures without
+// pulling tokio's runtime into the tests.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending       => thread::park(),
+        }
+    }
+}
+
+// NOTE(erick): Hands out a few bytes at a time and is not ready every
+// other poll, like a slow socket.
+struct SlowReader {
+    data     : Vec<u8>,
+    position : usize,
+    ready    : bool,
+    endless  : bool,
+    served   : usize,
+}
+
+impl SlowReader {
+    fn new(data: Vec<u8>) -> SlowReader {
+        SlowReader { data : data, position : 0, ready : false, endless : false, served : 0 }
+    }
+}
+
+impl AsyncRead for SlowReader {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context,
+                 buffer: &mut ReadBuf) -> Poll<std::io::Result<()>> {
+        self.ready = !self.ready;
+        if !self.ready {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        let start = self.position;
+        let end = (start + 7).min(self.data.len());
+        if start == end && self.endless {
+            buffer.put_slice(&[0; 7]);
+            self.served += 7;
+        } else {
+            buffer.put_slice(&self.data[start .. end]);
+            self.served += end - start;
+        }
+        self.position = end;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn gradient() -> Bitmap {
+    let mut result = Bitmap::new(7, 5, 24, CompressionType::Uncompressed);
+    result.map_pixels(|x, y, _| BitmapPixel::rgb((x * 30) as u8, (y * 40) as u8, 0x99));
+
+    result
+}
+
+#[test]
+fn async_reads_decode_like_from_data() {
+    let image = gradient();
+    let mut reader = SlowReader::new(image.into_data());
+
+    let decoded = block_on(Bitmap::from_async_reader(&mut reader)).unwrap();
+    assert_eq!(decoded, image);
+
+    let mut slice = &image.into_data()[..];
+    let decoded = block_on(Bitmap::from_async_reader(&mut slice)).unwrap();
+    assert_eq!(decoded, image);
+}
+
+#[test]
+fn async_writes_match_into_data() {
+    let image = gradient();
+
+    let mut data = Vec::new();
+    block_on(image.write_to_async(&mut data)).unwrap();
+    assert_eq!(data, image.into_data());
+}
+
+#[test]
+fn huge_headers_are_rejected_before_the_body() {
+    let mut header = Vec::new();
+    header.extend_from_slice(b"BM");
+    header.extend_from_slice(&54u32.to_le_bytes());
+    header.extend_from_slice(&[0; 4]);
+    header.extend_from_slice(&54u32.to_le_bytes());
+    header.extend_from_slice(&40u32.to_le_bytes());
+    header.extend_from_slice(&60000i32.to_le_bytes());
+    header.extend_from_slice(&60000i32.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&24u16.to_le_bytes());
+    header.extend_from_slice(&[0; 24]);
+
+    // NOTE(erick): The body never ends, only the limits can stop us.
+    let mut reader = SlowReader::new(header);
+    reader.endless = true;
+
+    let result = block_on(Bitmap::from_async_reader(&mut reader));
+    assert!(matches!(result, Err(BitmapError::DecodeLimitExceeded(_))));
+}
+
+fn small_header() -> Vec<u8> {
+    let mut header = gradient().into_data();
+    header.truncate(54);
+
+    header
+}
+
+#[test]
+fn endless_streams_stop_at_max_file_size() {
+    let options = DecodeOptions { max_file_size : 4096, ..DecodeOptions::default() };
+
+    // NOTE(erick): The headers are fine, the body just never ends.
+    let mut reader = SlowReader::new(small_header());
+    reader.endless = true;
+    let result = block_on(Bitmap::from_async_reader_with_options(&mut reader, &options));
+    assert!(matches!(result, Err(BitmapError::DecodeLimitExceeded(_))));
+    assert!(reader.served <= 4096 + 7);
+
+    let mut reader = SlowReader::new(b"BA".to_vec());
+    reader.endless = true;
+    let result = block_on(Bitmap::from_async_reader_with_options(&mut reader, &options));
+    assert!(matches!(result, Err(BitmapError::DecodeLimitExceeded(_))));
+
+    // NOTE(erick): Files right at the limit are still read.
+    let data = gradient().into_data();
+    let options = DecodeOptions { max_file_size : data.len() as u64, ..DecodeOptions::default() };
+    let mut reader = SlowReader::new(data);
+    assert!(block_on(Bitmap::from_async_reader_with_options(&mut reader, &options)).is_ok());
+}
+
+#[test]
+fn broken_streams_report_errors() {
+    let mut reader = SlowReader::new(b"BM\x10\x00".to_vec());
+    let result = block_on(Bitmap::from_async_reader(&mut reader));
+    assert!(matches!(result, Err(BitmapError::TruncatedAt(4))));
+
+    let mut reader = SlowReader::new(b"not a bitmap at all, not even close".to_vec());
+    assert!(block_on(Bitmap::from_async_reader(&mut reader)).is_err());
+}
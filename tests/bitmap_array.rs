@@ -0,0 +1,40 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn filled(width: i32, height: i32, color: BitmapPixel) -> Bitmap {
+    let mut result = Bitmap::new(width, height, 24, CompressionType::Uncompressed);
+    result.map_pixels(|_, _, _| color);
+
+    result
+}
+
+#[test]
+fn every_entry_is_read_back() {
+    let images = vec![filled(4, 3, BitmapPixel::rgb(0xff, 0, 0)),
+                      filled(2, 5, BitmapPixel::rgb(0, 0xff, 0))];
+
+    let data = Bitmap::array_into_data(&images).unwrap();
+    assert_eq!(&data[.. 2], b"BA");
+
+    let decoded = Bitmap::array_from_data(&data).unwrap();
+    assert_eq!(decoded.len(), 2);
+    for (image, original) in decoded.iter().zip(&images) {
+        assert_eq!(image.into_data(), original.into_data());
+    }
+
+    // NOTE(erick): Single image loaders get the first entry.
+    let first = Bitmap::from_data(data).unwrap();
+    assert_eq!(first.into_data(), images[0].into_data());
+}
+
+#[test]
+fn plain_files_are_a_single_entry_array() {
+    let image = filled(3, 3, BitmapPixel::rgb(0, 0, 0xff));
+
+    let decoded = Bitmap::array_from_data(&image.into_data()).unwrap();
+    assert_eq!(decoded.len(), 1);
+    assert_eq!(decoded[0].into_data(), image.into_data());
+
+    assert!(Bitmap::array_into_data(&[]).is_err());
+}
@@ -0,0 +1,85 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+#[test]
+fn defaults_match_new_default() {
+    let built = Bitmap::builder().size(5, 3).build().unwrap();
+    let expected = Bitmap::new_default(5, 3);
+
+    assert_eq!(built, expected);
+    assert_eq!(built.into_data(), expected.into_data());
+}
+
+#[test]
+fn builder_sets_the_headers() {
+    let image = BitmapBuilder::new()
+        .size(4, 2)
+        .bits_per_pixel(24)
+        .dpi(300)
+        .top_down(true)
+        .fill(BitmapPixel::rgb(0x10, 0x20, 0x30))
+        .build()
+        .unwrap();
+
+    assert_eq!(image.info_header.image_width, 4);
+    assert_eq!(image.info_header.image_height, 2);
+    assert_eq!(image.info_header.bits_per_pixel, 24);
+    assert_eq!(image.info_header.compression_type, CompressionType::Uncompressed as u32);
+    assert_eq!(image.info_header.pixels_per_meter_x, 11811);
+    assert_eq!(image.info_header.pixels_per_meter_y, 11811);
+    assert!(image.info_header.is_top_down);
+    assert_eq!(image.get_pixel(3, 1), BitmapPixel::rgb(0x10, 0x20, 0x30));
+
+    let decoded = Bitmap::from_slice(&image.into_data()).unwrap();
+    assert!(decoded.info_header.is_top_down);
+    assert_eq!(decoded.info_header.pixels_per_meter_y, 11811);
+    assert_eq!(decoded, image);
+}
+
+#[test]
+fn palettized_images_keep_their_palette() {
+    let palette = BitmapPaletteBuilder::black_and_white().build(8).unwrap();
+    let image = Bitmap::builder()
+        .size(3, 3)
+        .bits_per_pixel(8)
+        .palette(palette)
+        .fill(BitmapPixel::rgb(0xff, 0xff, 0xff))
+        .build()
+        .unwrap();
+
+    let palette = image.palette.as_ref().unwrap();
+    assert_eq!(palette.len(), 256);
+    assert_eq!(palette[0], BitmapPixel::black());
+    assert_eq!(palette[1], BitmapPixel::rgb(0xff, 0xff, 0xff));
+
+    let decoded = Bitmap::from_slice(&image.into_data()).unwrap();
+    assert_eq!(decoded.info_header.bits_per_pixel, 8);
+    assert_eq!(decoded.get_pixel(1, 1), BitmapPixel::rgb(0xff, 0xff, 0xff));
+}
+
+#[test]
+fn palettized_images_need_a_palette() {
+    for &bits_per_pixel in &[1, 2, 4, 8] {
+        let result = Bitmap::builder().size(2, 2).bits_per_pixel(bits_per_pixel).build();
+        assert!(matches!(result, Err(BitmapError::InvalidOperation)), "{} bpp", bits_per_pixel);
+    }
+}
+
+#[test]
+fn inconsistent_settings_fail_at_build() {
+    assert!(Bitmap::builder().build().is_err());
+    assert!(Bitmap::builder().size(0, 4).build().is_err());
+    assert!(Bitmap::builder().size(4, 4).bits_per_pixel(12).build().is_err());
+    assert!(Bitmap::builder().size(4, 4).bits_per_pixel(8)
+            .compression(CompressionType::BitFields).build().is_err());
+    assert!(Bitmap::builder().size(4, 4).bits_per_pixel(24)
+            .palette(vec![BitmapPixel::black()]).build().is_err());
+    assert!(Bitmap::builder().size(4, 4).bits_per_pixel(1)
+            .palette(vec![BitmapPixel::black(); 3]).build().is_err());
+
+    // NOTE(erick): We can read RLE files but not write them.
+    let result = Bitmap::builder().size(4, 4).bits_per_pixel(8)
+        .compression(CompressionType::Rle8).build();
+    assert!(matches!(result, Err(BitmapError::UnsupportedCompressionType(CompressionType::Rle8))));
+}
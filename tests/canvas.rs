@@ -0,0 +1,211 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+use common::{size_of, same_colors};
+
+fn test_image() -> Bitmap {
+    let mut result = Bitmap::new(4, 3, 24, CompressionType::Uncompressed);
+    result.map_pixels(|x, y, _| BitmapPixel::rgb((x * 50) as u8, (y * 60) as u8, 0x40));
+
+    result
+}
+
+fn check_placement(image: &Bitmap, original: &Bitmap, offset_x: u32, offset_y: u32,
+                   fill: BitmapPixel) {
+    let (width, height) = size_of(image);
+    let (original_width, original_height) = size_of(original);
+    for y in 0 .. height {
+        for x in 0 .. width {
+            let inside = x >= offset_x && x < offset_x + original_width &&
+                y >= offset_y && y < offset_y + original_height;
+            let expected = if inside {
+                original.get_pixel(x - offset_x, y - offset_y)
+            } else {
+                fill
+            };
+
+            assert!(image.get_pixel(x, y).same_color_as(&expected), "pixel ({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn extend_canvas_places_content_per_anchor() {
+    let original = test_image();
+    let fill = BitmapPixel::rgb(0xff, 0, 0xff);
+
+    let cases = [(Anchor::TopLeft, 0, 0), (Anchor::Top, 1, 0), (Anchor::TopRight, 3, 0),
+                 (Anchor::Left, 0, 2), (Anchor::Center, 1, 2), (Anchor::Right, 3, 2),
+                 (Anchor::BottomLeft, 0, 4), (Anchor::Bottom, 1, 4), (Anchor::BottomRight, 3, 4)];
+    for &(anchor, offset_x, offset_y) in cases.iter() {
+        let mut image = test_image();
+        image.extend_canvas(7, 7, anchor, fill).unwrap();
+
+        assert_eq!(size_of(&image), (7, 7));
+        check_placement(&image, &original, offset_x, offset_y, fill);
+    }
+}
+
+#[test]
+fn extend_canvas_keeps_format_and_round_trips() {
+    let original = test_image();
+    let fill = BitmapPixel::rgb(0x10, 0x20, 0x30);
+
+    let mut image = test_image();
+    image.extend_canvas(6, 5, Anchor::Center, fill).unwrap();
+    assert_eq!(image.info_header.bits_per_pixel, 24);
+
+    let decoded = Bitmap::from_slice(&image.into_data()).unwrap();
+    check_placement(&decoded, &original, 1, 1, fill);
+}
+
+#[test]
+fn pad_keeps_the_masks_and_the_resolution() {
+    let mut image = Bitmap::new(4, 3, 16, CompressionType::BitFields);
+    image.info_header.red_mask   = 0xf800;
+    image.info_header.green_mask = 0x07e0;
+    image.info_header.blue_mask  = 0x001f;
+    image.info_header.alpha_mask = 0x0000;
+    image.info_header.pixels_per_meter_x = 2835;
+    image.map_pixels(|x, y, _| BitmapPixel::rgb((x * 64) as u8, (y * 4) as u8, 0x80));
+    let original = Bitmap::from_slice(&image.into_data()).unwrap();
+
+    let fill = BitmapPixel::rgb(0, 0x04, 0);
+    image.pad(1, 2, 1, 0, fill).unwrap();
+    assert_eq!(size_of(&image), (7, 4));
+    assert_eq!((image.info_header.red_mask, image.info_header.green_mask,
+                image.info_header.blue_mask), (0xf800, 0x07e0, 0x001f));
+    assert_eq!(image.info_header.pixels_per_meter_x, 2835);
+
+    // NOTE(erick): 4 is only representable with a 6-bit green channel.
+    let decoded = Bitmap::from_slice(&image.into_data()).unwrap();
+    check_placement(&decoded, &original, 1, 1, fill);
+}
+
+#[test]
+fn extend_canvas_rejects_smaller_sizes() {
+    let mut image = test_image();
+    assert!(image.extend_canvas(3, 5, Anchor::Center, BitmapPixel::black()).is_err());
+    assert!(image.extend_canvas(5, 2, Anchor::Center, BitmapPixel::black()).is_err());
+    assert_eq!(size_of(&image), (4, 3));
+}
+
+#[test]
+fn pad_adds_borders_of_each_size() {
+    let original = test_image();
+    let fill = BitmapPixel::white();
+
+    let mut image = test_image();
+    image.pad(1, 2, 3, 0, fill).unwrap();
+
+    assert_eq!(size_of(&image), (7, 6));
+    check_placement(&image, &original, 1, 3, fill);
+}
+
+#[test]
+fn crop_in_place_matches_crop_to_rect() {
+    for &(bits_per_pixel, compression) in &[(24, CompressionType::Uncompressed),
+                                            (16, CompressionType::BitFields),
+                                            (32, CompressionType::BitFields)] {
+        let mut image = Bitmap::new(9, 7, bits_per_pixel, compression);
+        image.map_pixels(|x, y, _| BitmapPixel::rgb((x * 25) as u8, (y * 35) as u8, 0x80));
+        // NOTE(erick): Decoded, so the pixels already went through the format.
+        let mut image = Bitmap::from_slice(&image.into_data()).unwrap();
+
+        for &(x0, y0, width, height) in &[(2, 1, 5, 4), (0, 0, 9, 7), (8, 6, 1, 1), (0, 3, 9, 2)] {
+            let expected = image.crop_to_rect(x0, y0, width, height).unwrap();
+
+            let mut cropped = Bitmap::from_slice(&image.into_data()).unwrap();
+            cropped.crop_in_place(x0, y0, width, height).unwrap();
+            assert!(same_colors(&cropped, &expected));
+            assert_eq!(cropped.info_header.bits_per_pixel, bits_per_pixel);
+
+            let decoded = Bitmap::from_slice(&cropped.into_data()).unwrap();
+            assert!(same_colors(&decoded, &expected));
+        }
+
+        assert!(image.crop_in_place(5, 0, 5, 1).is_err());
+        assert!(image.crop_in_place(0, 3, 1, 5).is_err());
+    }
+}
+
+#[test]
+fn crop_in_place_keeps_the_orientation() {
+    let mut image = test_image();
+    image.info_header.is_top_down = true;
+    image.map_pixels(|x, y, _| BitmapPixel::rgb((x * 50) as u8, (y * 60) as u8, 0x40));
+    let expected = image.crop_to_rect(1, 1, 2, 2).unwrap();
+
+    image.crop_in_place(1, 1, 2, 2).unwrap();
+    assert!(image.info_header.is_top_down);
+    assert!(same_colors(&image, &expected));
+
+    let decoded = Bitmap::from_slice(&image.into_data()).unwrap();
+    assert!(same_colors(&decoded, &expected));
+}
+
+#[test]
+fn trim_removes_uniform_borders() {
+    let original = test_image();
+    let fill = BitmapPixel::rgb(0xf0, 0xf0, 0xf0);
+
+    let mut image = test_image();
+    image.pad(2, 1, 0, 3, fill).unwrap();
+    image.set_pixel(0, 0, BitmapPixel::rgb(0xf8, 0xe8, 0xf0));
+
+    assert_eq!(image.trim(BitmapPixel::rgb(0xf0, 0xf0, 0xf0), 16), Some((2, 0, 4, 3)));
+    assert!(same_colors(&image, &original));
+
+    let mut blank = Bitmap::new_default(3, 3);
+    blank.map_pixels(|_, _, _| fill);
+    assert_eq!(blank.trim(fill, 0), None);
+    assert_eq!(size_of(&blank), (3, 3));
+}
+
+fn checker() -> Bitmap {
+    let mut result = Bitmap::new_default(3, 2);
+    result.map_pixels(|x, y, _| BitmapPixel::rgb(x as u8 * 10, y as u8 * 10, 0));
+
+    result
+}
+
+#[test]
+fn tiled_repeats_the_pattern() {
+    let pattern = checker();
+    let image = Bitmap::tiled(&pattern, 8, 5).unwrap();
+
+    assert_eq!(size_of(&image), (8, 5));
+    for y in 0 .. 5 {
+        for x in 0 .. 8 {
+            assert!(image.get_pixel(x, y).same_color_as(&pattern.get_pixel(x % 3, y % 2)));
+        }
+    }
+
+    assert!(Bitmap::tiled(&Bitmap::new_default(0, 2), 8, 5).is_err());
+}
+
+#[test]
+fn fill_tiled_shifts_the_pattern_by_the_offset() {
+    let pattern = checker();
+
+    let mut image = test_image();
+    image.fill_tiled_with_offset(&pattern, 1, -1).unwrap();
+    for y in 0 .. 3 {
+        for x in 0 .. 4 {
+            let expected = pattern.get_pixel((x + 2) % 3, (y + 1) % 2);
+            assert!(image.get_pixel(x, y).same_color_as(&expected));
+        }
+    }
+
+    let mut shifted = test_image();
+    shifted.fill_tiled_with_offset(&pattern, 7, -3).unwrap();
+    let decoded = Bitmap::from_slice(&shifted.into_data()).unwrap();
+    for y in 0 .. 3 {
+        for x in 0 .. 4 {
+            assert!(decoded.get_pixel(x, y).same_color_as(&image.get_pixel(x, y)));
+        }
+    }
+}
@@ -0,0 +1,106 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn rounded_reference(value: u8, from: u32, to: u32) -> u8 {
+    ((value as f64 * to as f64 / from as f64).round() as u32).min(0xff) as u8
+}
+
+#[test]
+fn fixed_point_scaling_rounds() {
+    for &(from, to) in &[(0x1f, 0xff), (0x3f, 0xff), (0x0f, 0xff), (0x01, 0xff),
+                         (0xff, 0x1f), (0xff, 0x3f), (0xff, 0x0f), (0xff, 0x01)] {
+        for value in 0 ..= from.min(0xff) as u8 {
+            let mut scaled = value;
+            map_zero_based(&mut scaled, from, to);
+            assert_eq!(scaled, rounded_reference(value, from, to), "{} {} {}", value, from, to);
+        }
+    }
+
+    let mut value = 0x42;
+    map_zero_based(&mut value, 0, 0xff);
+    assert_eq!(value, 0x42);
+}
+
+#[test]
+fn rgb565_channels_are_scaled() {
+    let mut image = Bitmap::new(2, 1, 16, CompressionType::BitFields);
+    image.info_header.red_mask   = 0xf800;
+    image.info_header.green_mask = 0x07e0;
+    image.info_header.blue_mask  = 0x001f;
+    image.info_header.alpha_mask = 0x0000;
+    image.set_pixel(0, 0, BitmapPixel::rgb(0xff, 0x80, 0x10));
+    image.set_pixel(1, 0, BitmapPixel::rgb(0x00, 0xff, 0x84));
+
+    let decoded = Bitmap::from_data(image.into_data()).unwrap();
+    let first = decoded.get_pixel(0, 0);
+    let second = decoded.get_pixel(1, 0);
+    assert_eq!((first.red, first.green, first.blue, first.alpha), (0xff, 0x82, 0x10, 0xff));
+    assert_eq!((second.red, second.green, second.blue), (0x00, 0xff, 0x84));
+}
+
+fn pixel_array(data: &[u8]) -> &[u8] {
+    let offset = u32::from_le_bytes([data[10], data[11], data[12], data[13]]) as usize;
+    &data[offset ..]
+}
+
+fn with_masks(bits_per_pixel: u16, masks: (u32, u32, u32, u32), pixels: &[BitmapPixel]) -> Bitmap {
+    let mut image = Bitmap::new(pixels.len() as i32, 1, bits_per_pixel, CompressionType::BitFields);
+    image.info_header.red_mask   = masks.0;
+    image.info_header.green_mask = masks.1;
+    image.info_header.blue_mask  = masks.2;
+    image.info_header.alpha_mask = masks.3;
+    for (x, &pixel) in pixels.iter().enumerate() {
+        image.set_pixel(x as u32, 0, pixel);
+    }
+
+    image
+}
+
+#[test]
+fn ten_bit_channels_are_scaled() {
+    let masks = (0x3ff00000, 0x000ffc00, 0x000003ff, 0xc0000000);
+    let image = with_masks(32, masks, &[BitmapPixel::rgba(0xff, 0x80, 0x00, 0xff),
+                                        BitmapPixel::rgba(0x01, 0x00, 0x10, 0x55)]);
+
+    let data = image.into_data();
+    let first = u32::from_le_bytes([pixel_array(&data)[0], pixel_array(&data)[1],
+                                    pixel_array(&data)[2], pixel_array(&data)[3]]);
+    // NOTE(erick): 0x80 is 514 out of 1023.
+    assert_eq!(first, 0xc0000000 | 0x3ff << 20 | 514 << 10);
+
+    let decoded = Bitmap::from_data(data).unwrap();
+    assert_eq!(decoded.get_pixel(0, 0), BitmapPixel::rgba(0xff, 0x80, 0x00, 0xff));
+    assert_eq!(decoded.get_pixel(1, 0), BitmapPixel::rgba(0x01, 0x00, 0x10, 0x55));
+}
+
+#[test]
+fn narrow_and_wide_32_bit_channels_are_scaled() {
+    // NOTE(erick): 4 bits of blue, 4 of alpha, 4 of green and 20 of red.
+    let masks = (0xfffff000, 0x00000f00, 0x0000000f, 0x000000f0);
+    let image = with_masks(32, masks, &[BitmapPixel::rgba(0x12, 0x80, 0xff, 0x33)]);
+
+    let decoded = Bitmap::from_data(image.into_data()).unwrap();
+    assert_eq!(decoded.get_pixel(0, 0), BitmapPixel::rgba(0x12, 0x88, 0xff, 0x33));
+}
+
+#[test]
+fn custom_16_bit_layouts_are_scaled() {
+    // NOTE(erick): BGR565, blue on the high bits.
+    let masks = (0x001f, 0x07e0, 0xf800, 0x0000);
+    let image = with_masks(16, masks, &[BitmapPixel::rgb(0xff, 0x00, 0x84)]);
+
+    let data = image.into_data();
+    let value = u16::from_le_bytes([pixel_array(&data)[0], pixel_array(&data)[1]]);
+    assert_eq!(value, 0x10 << 11 | 0x1f);
+
+    let decoded = Bitmap::from_data(data).unwrap();
+    assert_eq!(decoded.get_pixel(0, 0), BitmapPixel::rgb(0xff, 0x00, 0x84));
+
+    // NOTE(erick): ARGB4444.
+    let masks = (0x0f00, 0x00f0, 0x000f, 0xf000);
+    let image = with_masks(16, masks, &[BitmapPixel::rgba(0x11, 0x77, 0xee, 0x99)]);
+
+    let decoded = Bitmap::from_data(image.into_data()).unwrap();
+    assert_eq!(decoded.get_pixel(0, 0), BitmapPixel::rgba(0x11, 0x77, 0xee, 0x99));
+}
@@ -0,0 +1,187 @@
+#![cfg(feature = "cli")]
+
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+mod common;
+
+// NOTE(erick): Every test gets its own directory, tests run in parallel.
+fn work_dir(test: &str) -> PathBuf {
+    let result = std::env::temp_dir().join(format!("bitmap_io_cli_{}_{}", test, std::process::id()));
+    std::fs::create_dir_all(&result).unwrap();
+
+    result
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bitmap_io")).args(args).output().unwrap()
+}
+
+fn write_gradient(path: &PathBuf, width: i32, height: i32) -> Bitmap {
+    let mut result = Bitmap::new(width, height, 24, CompressionType::Uncompressed);
+    result.map_pixels(|x, y, _| BitmapPixel::rgb((x * 30) as u8, (y * 30) as u8, 0x55));
+    std::fs::write(path, result.into_data()).unwrap();
+
+    result
+}
+
+fn read(path: &PathBuf) -> Bitmap {
+    Bitmap::from_slice(&std::fs::read(path).unwrap()).unwrap()
+}
+
+#[test]
+fn info_describes_the_file() {
+    let dir = work_dir("info");
+    let input = dir.join("input.bmp");
+    write_gradient(&input, 5, 3);
+
+    let output = run(&["info", input.to_str().unwrap()]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("5 x 3"));
+    assert!(stdout.contains("bits/pixel:   24"));
+}
+
+#[test]
+fn convert_crop_and_resize_write_new_files() {
+    let dir = work_dir("convert");
+    let input = dir.join("input.bmp");
+    let image = write_gradient(&input, 6, 4);
+
+    let converted = dir.join("converted.bmp");
+    let output = run(&["convert", input.to_str().unwrap(), converted.to_str().unwrap(),
+                       "--bpp", "32", "--compression", "bitfields"]);
+    assert!(output.status.success());
+    let result = read(&converted);
+    assert_eq!(result.info_header.bits_per_pixel, 32);
+    assert_eq!(result, image);
+
+    let cropped = dir.join("cropped.bmp");
+    let output = run(&["crop", input.to_str().unwrap(), cropped.to_str().unwrap(), "1", "2", "3", "2"]);
+    assert!(output.status.success());
+    assert_eq!(read(&cropped), image.crop_to_rect(1, 2, 3, 2).unwrap());
+
+    let resized = dir.join("resized.bmp");
+    let output = run(&["resize", input.to_str().unwrap(), resized.to_str().unwrap(),
+                       "12", "8", "--filter", "nearest"]);
+    assert!(output.status.success());
+    let result = read(&resized);
+    assert_eq!((result.info_header.image_width, result.info_header.image_height), (12, 8));
+}
+
+#[test]
+fn merge_and_diff_work_on_several_files() {
+    let dir = work_dir("merge");
+    let input0 = dir.join("input0.bmp");
+    let input1 = dir.join("input1.bmp");
+    let image0 = write_gradient(&input0, 3, 2);
+    let image1 = write_gradient(&input1, 2, 2);
+
+    let merged = dir.join("merged.bmp");
+    let output = run(&["merge", merged.to_str().unwrap(),
+                       input0.to_str().unwrap(), input1.to_str().unwrap(), "--layout", "vertical"]);
+    assert!(output.status.success());
+    assert_eq!(read(&merged), Bitmap::merge_vertically(&image0, &image1));
+
+    let output = run(&["diff", input0.to_str().unwrap(), input0.to_str().unwrap()]);
+    assert!(output.status.success());
+
+    let difference = dir.join("difference.bmp");
+    let output = run(&["diff", merged.to_str().unwrap(), merged.to_str().unwrap(),
+                       difference.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert!(difference.exists());
+
+    let changed = dir.join("changed.bmp");
+    let mut image = image0.clone();
+    image.set_pixel(2, 1, BitmapPixel::black());
+    std::fs::write(&changed, image.into_data()).unwrap();
+
+    let output = run(&["diff", input0.to_str().unwrap(), changed.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stdout).unwrap().contains("differing pixels:   1"));
+}
+
+#[test]
+fn bad_arguments_print_the_usage() {
+    let output = run(&["crop", "input.bmp"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Usage:"));
+
+    let output = run(&["resize", "a.bmp", "b.bmp", "1", "1", "--filter", "cubic"]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = run(&["frobnicate"]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = run(&["info", "/this/file/does/not/exist.bmp"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn rle_inputs_are_written_uncompressed() {
+    let dir = work_dir("rle");
+    let input = dir.join("input.bmp");
+    std::fs::write(&input, common::rle8_file()).unwrap();
+    let original = read(&input);
+
+    let resized = dir.join("resized.bmp");
+    let output = run(&["resize", input.to_str().unwrap(), resized.to_str().unwrap(), "2", "2"]);
+    assert_eq!(output.status.code(), Some(0));
+    let result = read(&resized);
+    assert_eq!(result.info_header.compression_type, CompressionType::Uncompressed as u32);
+    assert_eq!(common::size_of(&result), (2, 2));
+
+    let cropped = dir.join("cropped.bmp");
+    let output = run(&["crop", input.to_str().unwrap(), cropped.to_str().unwrap(), "1", "0", "3", "2"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(read(&cropped), original.crop_to_rect(1, 0, 3, 2).unwrap());
+
+    let converted = dir.join("converted.bmp");
+    let output = run(&["convert", input.to_str().unwrap(), converted.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    let result = read(&converted);
+    assert_eq!(result.info_header.compression_type, CompressionType::Uncompressed as u32);
+    assert_eq!(result, original);
+
+    // NOTE(erick): Pipeline steps keep the format, RLE included.
+    if cfg!(feature = "serde") {
+        let pipeline = dir.join("pipeline.json");
+        let json = "{\"steps\": [{\"Brightness\": {\"offset\": 1}}], \"dither\": \"None\"}";
+        std::fs::write(&pipeline, json).unwrap();
+
+        let brightened = dir.join("brightened.bmp");
+        let output = run(&["pipeline", pipeline.to_str().unwrap(),
+                           input.to_str().unwrap(), brightened.to_str().unwrap()]);
+        assert_eq!(output.status.code(), Some(0));
+        let result = read(&brightened);
+        assert_eq!(result.info_header.compression_type, CompressionType::Uncompressed as u32);
+        assert_eq!(common::size_of(&result), (4, 2));
+    }
+}
+
+#[test]
+fn palettized_inputs_keep_their_format() {
+    let dir = work_dir("palettized");
+    let input = dir.join("input.bmp");
+    let mut image = common::gradient(4, 3);
+    image.convert_to(8, CompressionType::Uncompressed).unwrap();
+    std::fs::write(&input, image.into_data()).unwrap();
+
+    let cropped = dir.join("cropped.bmp");
+    let output = run(&["crop", input.to_str().unwrap(), cropped.to_str().unwrap(), "0", "0", "1", "1"]);
+    assert_eq!(output.status.code(), Some(0));
+    let result = read(&cropped);
+    assert_eq!(result.info_header.bits_per_pixel, 8);
+    assert_eq!(result, image.crop_to_rect(0, 0, 1, 1).unwrap());
+
+    let converted = dir.join("converted.bmp");
+    let output = run(&["convert", input.to_str().unwrap(), converted.to_str().unwrap(), "--bpp", "4"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(read(&converted).info_header.bits_per_pixel, 4);
+}
@@ -0,0 +1,56 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn strip(colors: &[BitmapPixel]) -> Bitmap {
+    let mut result = Bitmap::new_default(colors.len() as i32, 1);
+    result.map_pixels(|x, _, _| colors[x as usize]);
+
+    result
+}
+
+fn alphas(image: &Bitmap) -> Vec<u8> {
+    (0 .. image.info_header.image_width as u32).map(|x| image.get_pixel(x, 0).alpha).collect()
+}
+
+fn colors() -> Vec<BitmapPixel> {
+    vec![BitmapPixel::rgb(0, 0xff, 0),
+         BitmapPixel::rgb(3, 0xfb, 0),
+         BitmapPixel::rgb(0, 0xf6, 0),
+         BitmapPixel::rgb(0x80, 0x80, 0x80)]
+}
+
+#[test]
+fn exact_color_to_alpha_is_unchanged() {
+    let mut image = strip(&colors());
+    image.color_to_alpha(BitmapPixel::rgb(0, 0xff, 0));
+
+    assert_eq!(alphas(&image), vec![0, 0xff, 0xff, 0xff]);
+}
+
+#[test]
+fn tolerance_catches_near_colors() {
+    let green = BitmapPixel::rgb(0, 0xff, 0);
+
+    let mut image = strip(&colors());
+    image.color_to_alpha_with(green, 5, AlphaFalloff::Hard);
+    assert_eq!(alphas(&image), vec![0, 0, 0xff, 0xff]);
+
+    let mut image = strip(&colors());
+    image.color_to_alpha_with(green, 10, AlphaFalloff::Hard);
+    assert_eq!(alphas(&image), vec![0, 0, 0, 0xff]);
+}
+
+#[test]
+fn feathering_scales_alpha_by_distance() {
+    let green = BitmapPixel::rgb(0, 0xff, 0);
+
+    let mut image = strip(&colors());
+    image.color_to_alpha_with(green, 10, AlphaFalloff::Feathered);
+    // NOTE(erick): Distances are 0, 5 and 9.
+    assert_eq!(alphas(&image), vec![0, 128, 230, 0xff]);
+
+    let mut image = strip(&colors());
+    image.color_to_alpha_with(green, 0, AlphaFalloff::Feathered);
+    assert_eq!(alphas(&image), vec![0, 0xff, 0xff, 0xff]);
+}
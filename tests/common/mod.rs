@@ -0,0 +1,108 @@
+// NOTE(erick): Fixtures shared by the integration tests. Every test file
+// is its own crate and uses only some of them, hence the allow.
+#![allow(dead_code)]
+
+use bitmap_io::*;
+
+pub fn size_of(image: &Bitmap) -> (u32, u32) {
+    (image.info_header.image_width as u32, image.info_header.image_height as u32)
+}
+
+pub fn gradient(width: u32, height: u32) -> Bitmap {
+    let mut result = Bitmap::new_default(width as i32, height as i32);
+    result.map_pixels(|x, y, _| {
+        BitmapPixel::rgba((x * 255 / width) as u8, (y * 255 / height) as u8,
+                          ((x + y) * 13) as u8, 0xff)
+    });
+
+    result
+}
+
+// NOTE(erick): Both compare in screen order, so images stored with
+// different orientations can still be the same.
+pub fn same_pixels(image: &Bitmap, other: &Bitmap) -> bool {
+    let (width, height) = size_of(image);
+    size_of(other) == (width, height) &&
+        (0 .. height).all(|y| (0 .. width).all(|x| {
+            image.get_pixel(x, y) == other.get_pixel(x, y)
+        }))
+}
+
+// NOTE(erick): Ignores alpha, for formats that don't store it.
+pub fn same_colors(image: &Bitmap, other: &Bitmap) -> bool {
+    let (width, height) = size_of(image);
+    size_of(other) == (width, height) &&
+        (0 .. height).all(|y| (0 .. width).all(|x| {
+            image.get_pixel(x, y).same_color_as(&other.get_pixel(x, y))
+        }))
+}
+
+pub fn assert_same_pixels(image: &Bitmap, other: &Bitmap) {
+    assert_eq!(size_of(image), size_of(other));
+
+    let (width, height) = size_of(image);
+    for y in 0 .. height {
+        for x in 0 .. width {
+            let p0 = image.get_pixel(x, y);
+            let p1 = other.get_pixel(x, y);
+            assert_eq!((p0.red, p0.green, p0.blue, p0.alpha),
+                       (p1.red, p1.green, p1.blue, p1.alpha), "pixel ({}, {})", x, y);
+        }
+    }
+}
+
+pub fn assert_same_colors(image: &Bitmap, other: &Bitmap, context: &str) {
+    assert_eq!(size_of(image), size_of(other), "{}", context);
+
+    let (width, height) = size_of(image);
+    for y in 0 .. height {
+        for x in 0 .. width {
+            let p0 = image.get_pixel(x, y);
+            let p1 = other.get_pixel(x, y);
+            assert_eq!((p0.red, p0.green, p0.blue), (p1.red, p1.green, p1.blue),
+                       "{} at ({}, {})", context, x, y);
+        }
+    }
+}
+
+// NOTE(erick): Wraps an RLE pixel array in the headers of a bottom-up
+// 'width' x 'height' file. The palette entries are BGRX.
+pub fn rle_file(width: i32, height: i32, bits_per_pixel: u16,
+                palette: &[[u8; 4]], pixels: &[u8]) -> Vec<u8> {
+    let compression = if bits_per_pixel == 4 { CompressionType::Rle4 } else { CompressionType::Rle8 };
+    let pixel_offset = 14 + 40 + palette.len() * 4;
+
+    let mut data = Vec::new();
+    data.extend_from_slice(b"BM");
+    data.extend_from_slice(&((pixel_offset + pixels.len()) as u32).to_le_bytes());
+    data.extend_from_slice(&[0; 4]);
+    data.extend_from_slice(&(pixel_offset as u32).to_le_bytes());
+
+    data.extend_from_slice(&40u32.to_le_bytes());
+    data.extend_from_slice(&width.to_le_bytes());
+    data.extend_from_slice(&height.to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&bits_per_pixel.to_le_bytes());
+    data.extend_from_slice(&(compression as u32).to_le_bytes());
+    data.extend_from_slice(&(pixels.len() as u32).to_le_bytes());
+    data.extend_from_slice(&[0; 8]);
+    data.extend_from_slice(&(palette.len() as u32).to_le_bytes());
+    data.extend_from_slice(&[0; 4]);
+
+    for entry in palette {
+        data.extend_from_slice(entry);
+    }
+    data.extend_from_slice(pixels);
+
+    data
+}
+
+// NOTE(erick): A 4x2 RLE8 file with a two colors palette (red and white).
+// The bottom row is a run of red, the top one two whites followed by two
+// reds.
+pub fn rle8_file() -> Vec<u8> {
+    rle_file(4, 2, 8, &[[0x00, 0x00, 0xff, 0x00], [0xff, 0xff, 0xff, 0x00]],
+             &[0x04, 0x00, 0x00, 0x00,
+               0x02, 0x01, 0x02, 0x00, 0x00, 0x00,
+               0x00, 0x01])
+}
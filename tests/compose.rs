@@ -0,0 +1,142 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+use common::size_of;
+
+fn filled(width: i32, height: i32, color: BitmapPixel) -> Bitmap {
+    let mut result = Bitmap::new_default(width, height);
+    result.map_pixels(|_, _, _| color);
+
+    result
+}
+
+fn overlay_pixel(src: BitmapPixel, dest: BitmapPixel, mode: BlendMode) -> BitmapPixel {
+    let mut image = filled(1, 1, dest);
+    image.overlay_with_mode(&filled(1, 1, src), 0, 0, mode);
+
+    image.get_pixel(0, 0)
+}
+
+#[test]
+fn blend_modes_on_opaque_pixels() {
+    let src  = BitmapPixel::rgb(200, 100, 50);
+    let dest = BitmapPixel::rgb(100, 200, 50);
+
+    // NOTE(erick): Multiply is (s * d + 127) / 255 and Screen is
+    // s + d - Multiply.
+    let expected = [(BlendMode::Normal,   BitmapPixel::rgb(200, 100,  50)),
+                    (BlendMode::Multiply, BitmapPixel::rgb( 78,  78,  10)),
+                    (BlendMode::Screen,   BitmapPixel::rgb(222, 222,  90)),
+                    (BlendMode::Add,      BitmapPixel::rgb(255, 255, 100)),
+                    (BlendMode::Subtract, BitmapPixel::rgb(  0, 100,   0)),
+                    (BlendMode::Darken,   BitmapPixel::rgb(100, 100,  50)),
+                    (BlendMode::Lighten,  BitmapPixel::rgb(200, 200,  50))];
+    for &(mode, result) in &expected {
+        assert_eq!(overlay_pixel(src, dest, mode), result, "{:?}", mode);
+    }
+}
+
+#[test]
+fn blend_modes_keep_the_source_over_transparent_pixels() {
+    let src  = BitmapPixel::rgb(200, 100, 50);
+    let dest = BitmapPixel::rgba(100, 200, 50, 0);
+
+    for &mode in &[BlendMode::Normal, BlendMode::Multiply, BlendMode::Screen,
+                   BlendMode::Add, BlendMode::Subtract, BlendMode::Darken,
+                   BlendMode::Lighten] {
+        assert_eq!(overlay_pixel(src, dest, mode), src, "{:?}", mode);
+    }
+
+    // NOTE(erick): A half transparent source is mixed with the
+    // blended color.
+    let half = BitmapPixel::rgba(200, 100, 50, 0x80);
+    let result = overlay_pixel(half, BitmapPixel::rgb(100, 200, 50), BlendMode::Darken);
+    assert_eq!(result, BitmapPixel::rgb(100, 150, 50));
+}
+
+#[test]
+fn overlays_are_clipped() {
+    let red = BitmapPixel::rgb(0xff, 0, 0);
+    let mut image = filled(4, 4, BitmapPixel::rgb(0, 0, 0));
+    image.overlay_with_mode(&filled(3, 3, red), 2, -1, BlendMode::Add);
+
+    for y in 0 .. 4 {
+        for x in 0 .. 4 {
+            let expected = if x >= 2 && y < 2 { 0xff } else { 0 };
+            assert_eq!(image.get_pixel(x, y).red, expected, "({}, {})", x, y);
+        }
+    }
+
+    image.overlay_with_mode(&filled(3, 3, red), i32::MIN, i32::MAX, BlendMode::Add);
+}
+
+#[test]
+fn stitches_share_the_overlapping_columns() {
+    let image0 = common::gradient(6, 4);
+    let image1 = common::gradient(5, 3);
+
+    let stitched = Bitmap::stitch_horizontally(&image0, &image1, 2, Blend::Linear).unwrap();
+    assert_eq!(size_of(&stitched), (9, 4));
+
+    let stitched = Bitmap::stitch_horizontally(&image0, &image1, 0, Blend::None).unwrap();
+    assert_eq!(size_of(&stitched), (11, 4));
+    let stitched = Bitmap::stitch_horizontally(&image0, &image1, 5, Blend::None).unwrap();
+    assert_eq!(size_of(&stitched), (6, 4));
+}
+
+#[test]
+fn linear_stitches_ramp_across_the_overlap() {
+    let black = filled(6, 2, BitmapPixel::rgb(0, 0, 0));
+    let white = filled(6, 2, BitmapPixel::rgb(0xff, 0xff, 0xff));
+
+    // NOTE(erick): The overlap is columns 2 to 5, sampled at their
+    // centers: 1/8, 3/8, 5/8 and 7/8 of the way to white.
+    let stitched = Bitmap::stitch_horizontally(&black, &white, 4, Blend::Linear).unwrap();
+    let expected = [0, 0, 32, 96, 159, 223, 0xff, 0xff];
+    for y in 0 .. 2 {
+        for (x, &value) in expected.iter().enumerate() {
+            assert_eq!(stitched.get_pixel(x as u32, y).red, value, "({}, {})", x, y);
+        }
+    }
+
+    let stitched = Bitmap::stitch_horizontally(&black, &white, 4, Blend::None).unwrap();
+    let expected = [0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+    for (x, &value) in expected.iter().enumerate() {
+        assert_eq!(stitched.get_pixel(x as u32, 0).red, value, "({}, 0)", x);
+    }
+}
+
+#[test]
+fn stitches_of_different_heights_use_the_taller_image() {
+    let black = filled(4, 4, BitmapPixel::rgb(0, 0, 0));
+    let white = filled(4, 2, BitmapPixel::rgb(0xff, 0xff, 0xff));
+
+    let stitched = Bitmap::stitch_horizontally(&black, &white, 2, Blend::Linear).unwrap();
+    assert_eq!(size_of(&stitched), (6, 4));
+
+    // NOTE(erick): Below the short image the overlap is just 'black'.
+    for y in 2 .. 4 {
+        for x in 0 .. 4 {
+            assert_eq!(stitched.get_pixel(x, y).red, 0, "({}, {})", x, y);
+        }
+    }
+    for y in 0 .. 2 {
+        assert_eq!(stitched.get_pixel(5, y).red, 0xff);
+        assert!(stitched.get_pixel(2, y).red > 0 && stitched.get_pixel(2, y).red < 0xff);
+    }
+}
+
+#[test]
+fn stitches_cant_overlap_more_than_an_image() {
+    let image0 = common::gradient(6, 4);
+    let image1 = common::gradient(3, 4);
+
+    assert!(matches!(Bitmap::stitch_horizontally(&image0, &image1, 4, Blend::Linear),
+                     Err(BitmapError::InvalidOperation)));
+    assert!(matches!(Bitmap::stitch_horizontally(&image1, &image0, 4, Blend::None),
+                     Err(BitmapError::InvalidOperation)));
+    assert!(Bitmap::stitch_horizontally(&image0, &image1, 3, Blend::Linear).is_ok());
+}
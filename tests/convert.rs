@@ -0,0 +1,131 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+use common::{gradient, assert_same_colors};
+
+fn formats() -> Vec<(u16, CompressionType)> {
+    let mut result = vec![(16, CompressionType::Uncompressed),
+                          (16, CompressionType::BitFields),
+                          (24, CompressionType::Uncompressed),
+                          (32, CompressionType::Uncompressed),
+                          (32, CompressionType::BitFields)];
+
+    if cfg!(feature = "encode-palettized") {
+        for &bits_per_pixel in &[1, 2, 4, 8] {
+            result.push((bits_per_pixel, CompressionType::Uncompressed));
+        }
+    }
+
+    result
+}
+
+#[test]
+fn converted_pixels_survive_a_round_trip() {
+    for (source_bpp, source_compression) in formats() {
+        for (target_bpp, target_compression) in formats() {
+            let context = format!("{} bpp {:?} -> {} bpp {:?}",
+                                  source_bpp, source_compression,
+                                  target_bpp, target_compression);
+
+            let mut image = gradient(7, 5);
+            image.convert_to(source_bpp, source_compression).unwrap();
+            image.convert_to(target_bpp, target_compression).unwrap();
+
+            assert_eq!(image.info_header.bits_per_pixel, target_bpp, "{}", context);
+
+            let decoded = Bitmap::from_data(image.into_data()).unwrap();
+            assert_same_colors(&image, &decoded, &context);
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "encode-palettized")]
+fn palettized_conversion_keeps_few_colors_exact() {
+    let mut image = Bitmap::new(4, 4, 24, CompressionType::Uncompressed);
+    let colors = [BitmapPixel::rgb(0xff, 0, 0), BitmapPixel::rgb(0, 0xff, 0),
+                  BitmapPixel::rgb(0, 0, 0xff)];
+    image.map_pixels(|x, y, _| colors[((x + y) % 3) as usize]);
+
+    let original = image.clone();
+    image.convert_to(4, CompressionType::Uncompressed).unwrap();
+
+    assert_eq!(image.palette.as_ref().unwrap().len(), 16);
+    assert_same_colors(&image, &original, "4 bpp");
+}
+
+#[test]
+fn unsupported_targets_are_rejected() {
+    let mut image = gradient(3, 3);
+
+    assert!(image.convert_to(24, CompressionType::BitFields).is_err());
+    assert!(image.convert_to(8, CompressionType::Rle8).is_err());
+    assert!(image.convert_to(12, CompressionType::Uncompressed).is_err());
+}
+
+#[cfg(feature = "encode-palettized")]
+fn flat_gray_with_black_and_white_palette() -> Bitmap {
+    let mut result = Bitmap::new(8, 8, 24, CompressionType::Uncompressed);
+    result.map_pixels(|_, _, _| BitmapPixel::rgb(0x80, 0x80, 0x80));
+    result.palette = Some(vec![BitmapPixel::black(), BitmapPixel::rgb(0xff, 0xff, 0xff)]);
+
+    result
+}
+
+#[cfg(feature = "encode-palettized")]
+fn white_pixels(image: &Bitmap) -> Vec<bool> {
+    let mut result = Vec::new();
+    for y in 0 .. 8 {
+        for x in 0 .. 8 {
+            result.push(image.get_pixel(x, y).red == 0xff);
+        }
+    }
+
+    result
+}
+
+#[test]
+#[cfg(feature = "encode-palettized")]
+fn conversions_can_use_ordered_dithering() {
+    let mut plain = flat_gray_with_black_and_white_palette();
+    plain.convert_to(1, CompressionType::Uncompressed).unwrap();
+    let plain_whites = white_pixels(&plain).iter().filter(|&&white| white).count();
+    assert!(plain_whites == 0 || plain_whites == 64);
+
+    let mut dithered = flat_gray_with_black_and_white_palette();
+    dithered.convert_to_with_dither(1, CompressionType::Uncompressed, Dither::Bayer4x4).unwrap();
+    assert_eq!(dithered.info_header.bits_per_pixel, 1);
+
+    // NOTE(erick): Half of the 4x4 thresholds are below 50% gray, and the
+    // pattern repeats every 4 pixels in both directions.
+    let whites = white_pixels(&dithered);
+    assert_eq!(whites.iter().filter(|&&white| white).count(), 32);
+    for y in 0 .. 8 {
+        for x in 0 .. 8 {
+            assert_eq!(whites[y * 8 + x], whites[(y % 4) * 8 + x % 4], "pixel ({}, {})", x, y);
+        }
+    }
+
+    let decoded = Bitmap::from_data(dithered.into_data()).unwrap();
+    assert_same_colors(&dithered, &decoded, "1 bpp Bayer4x4");
+
+    let mut piped = flat_gray_with_black_and_white_palette();
+    Pipeline::new().dither(Dither::Bayer4x4)
+        .convert_to(1, CompressionType::Uncompressed)
+        .apply(&mut piped).unwrap();
+    assert_same_colors(&piped, &dithered, "pipeline");
+}
+
+#[test]
+#[cfg(feature = "encode-palettized")]
+fn dithered_conversions_reject_unsupported_targets_untouched() {
+    let mut image = flat_gray_with_black_and_white_palette();
+    let original = image.clone();
+
+    assert!(image.convert_to_with_dither(8, CompressionType::BitFields, Dither::Bayer8x8).is_err());
+    assert_same_colors(&image, &original, "rejected");
+    assert_eq!(image.info_header.bits_per_pixel, 24);
+}
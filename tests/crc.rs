@@ -0,0 +1,59 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn with_crc() -> EncodeOptions {
+    EncodeOptions { append_crc32 : true }
+}
+
+// NOTE(erick): A 4x1 24 bits image whose pixel array is the ASCII text
+// "123456789abc" (pixels are stored BGR).
+fn ascii_image() -> Bitmap {
+    let mut result = Bitmap::new(4, 1, 24, CompressionType::Uncompressed);
+    for (x, bgr) in b"123456789abc".chunks(3).enumerate() {
+        result.set_pixel(x as u32, 0, BitmapPixel::rgb(bgr[2], bgr[1], bgr[0]));
+    }
+
+    result
+}
+
+#[test]
+fn crc_records_match_zlib() {
+    let data = ascii_image().into_data_with_options(&with_crc());
+
+    let record = &data[data.len() - 8 ..];
+    assert_eq!(&record[.. 4], b"bCRC");
+    // NOTE(erick): zlib.crc32(b"123456789abc").
+    assert_eq!(u32::from_le_bytes([record[4], record[5], record[6], record[7]]), 0xbdb0c0e4);
+    assert_eq!(&data[54 .. data.len() - 8], b"123456789abc");
+}
+
+#[test]
+fn crc_records_round_trip() {
+    let image = ascii_image();
+
+    let decoded = Bitmap::from_data(image.into_data_with_options(&with_crc())).unwrap();
+    assert_eq!(decoded.verify_crc(), Some(true));
+    assert!(decoded.trailer.is_empty());
+    assert_eq!(decoded, image);
+
+    // NOTE(erick): The record is only written when asked for.
+    let plain = Bitmap::from_data(image.into_data()).unwrap();
+    assert_eq!(plain.verify_crc(), None);
+    assert_eq!(decoded.into_data(), image.into_data());
+}
+
+#[test]
+fn corrupted_pixels_fail_the_crc() {
+    let mut data = ascii_image().into_data_with_options(&with_crc());
+    data[60] ^= 0x01;
+
+    let decoded = Bitmap::from_data(data).unwrap();
+    assert_eq!(decoded.verify_crc(), Some(false));
+
+    // NOTE(erick): So does a corrupted record.
+    let mut data = ascii_image().into_data_with_options(&with_crc());
+    let last = data.len() - 1;
+    data[last] ^= 0x80;
+    assert_eq!(Bitmap::from_data(data).unwrap().verify_crc(), Some(false));
+}
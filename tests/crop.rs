@@ -0,0 +1,83 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+use common::gradient;
+
+fn assert_cropped_pixels(cropped: &Bitmap, image: &Bitmap, x0: u32, y0: u32) {
+    let width  = cropped.info_header.image_width as u32;
+    let height = cropped.info_header.image_height as u32;
+
+    for y in 0 .. height {
+        for x in 0 .. width {
+            assert!(cropped.get_pixel(x, y).same_color_as(&image.get_pixel(x0 + x, y0 + y)),
+                    "pixel ({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn crop_keeps_the_source_format() {
+    let mut image = gradient(6, 5);
+    image.convert_to(16, CompressionType::BitFields).unwrap();
+
+    let cropped = image.crop_to_rect(1, 1, 4, 3).unwrap();
+    assert_eq!(cropped.info_header.bits_per_pixel, 16);
+    assert_eq!(cropped.info_header.compression_type, CompressionType::BitFields as u32);
+    assert_eq!(cropped.info_header.red_mask, image.info_header.red_mask);
+    assert_eq!(cropped.info_header.green_mask, image.info_header.green_mask);
+    assert_eq!(cropped.info_header.blue_mask, image.info_header.blue_mask);
+    assert_cropped_pixels(&cropped, &image, 1, 1);
+
+    let data = cropped.into_data();
+    assert_eq!(data.len(), cropped.file_header.file_size as usize);
+
+    let decoded = Bitmap::from_slice(&data).unwrap();
+    assert_cropped_pixels(&decoded, &image, 1, 1);
+}
+
+#[test]
+fn crop_to_rect_as_converts() {
+    let image = gradient(6, 5);
+
+    let cropped = image.crop_to_rect_as(2, 0, 3, 5, 24, CompressionType::Uncompressed).unwrap();
+    assert_eq!(cropped.info_header.bits_per_pixel, 24);
+    assert_cropped_pixels(&cropped, &image, 2, 0);
+
+    assert!(image.crop_to_rect_as(2, 0, 3, 5, 24, CompressionType::BitFields).is_err());
+    assert!(image.crop_to_rect_as(2, 0, 5, 5, 24, CompressionType::Uncompressed).is_err());
+}
+
+#[cfg(feature = "encode-palettized")]
+#[test]
+fn crop_keeps_the_palette() {
+    let mut image = gradient(8, 8);
+    image.convert_to(8, CompressionType::Uncompressed).unwrap();
+
+    let cropped = image.crop_to_rect(3, 2, 4, 5).unwrap();
+    assert_eq!(cropped.info_header.bits_per_pixel, 8);
+    assert_eq!(cropped.palette.as_ref().map(|palette| palette.len()),
+               image.palette.as_ref().map(|palette| palette.len()));
+    assert_cropped_pixels(&cropped, &image, 3, 2);
+
+    let data = cropped.into_data();
+    assert_eq!(data.len(), cropped.file_header.file_size as usize);
+    assert_eq!(cropped.file_header.pixel_array_offset as usize + cropped.info_header.image_size as usize,
+               data.len());
+
+    let decoded = Bitmap::from_slice(&data).unwrap();
+    assert_eq!(decoded.info_header.bits_per_pixel, 8);
+    assert_cropped_pixels(&decoded, &image, 3, 2);
+}
+
+#[test]
+fn views_copy_in_the_parent_format() {
+    let mut image = gradient(6, 5);
+    image.convert_to(24, CompressionType::Uncompressed).unwrap();
+
+    let copy = image.view(1, 2, 3, 2).unwrap().to_bitmap();
+    assert_eq!(copy.info_header.bits_per_pixel, 24);
+    assert_cropped_pixels(&copy, &image, 1, 2);
+}
@@ -0,0 +1,26 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+#[test]
+fn data_urls_round_trip() {
+    // NOTE(erick): Odd sizes exercise both padding lengths.
+    for &(width, height) in &[(1, 1), (2, 3), (5, 2)] {
+        let mut image = Bitmap::new_default(width, height);
+        image.map_pixels(|x, y, _| BitmapPixel::rgb(x as u8 * 40, y as u8 * 60, 0x7f));
+
+        let url = image.to_data_url();
+        assert!(url.starts_with("data:image/bmp;base64,Qk"));
+
+        let decoded = Bitmap::from_data_url(&url).unwrap();
+        assert_eq!(decoded.into_data(), image.into_data());
+    }
+}
+
+#[test]
+fn malformed_data_urls_are_rejected() {
+    assert!(Bitmap::from_data_url("data:image/png;base64,Qk0=").is_err());
+    assert!(Bitmap::from_data_url("data:image/bmp;base64,Qk0").is_err());
+    assert!(Bitmap::from_data_url("data:image/bmp;base64,Qk*=").is_err());
+    assert!(Bitmap::from_data_url("data:image/bmp;base64,Q===").is_err());
+}
@@ -0,0 +1,64 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn test_image(bits_per_pixel: u16, compression: CompressionType) -> Bitmap {
+    let mut result = Bitmap::new(5, 3, 24, CompressionType::Uncompressed);
+    result.map_pixels(|x, y, _| BitmapPixel::rgb(x as u8 * 50, y as u8 * 100, 0x40));
+    result.convert_to(bits_per_pixel, compression).unwrap();
+
+    result
+}
+
+#[test]
+fn dib_data_is_the_file_without_its_header() {
+    let mut formats = vec![(24, CompressionType::Uncompressed),
+                           (32, CompressionType::BitFields)];
+    if cfg!(feature = "encode-palettized") {
+        formats.push((8, CompressionType::Uncompressed));
+    }
+
+    for (bits_per_pixel, compression) in formats {
+        let image = test_image(bits_per_pixel, compression);
+        let dib = image.to_dib_data();
+        assert_eq!(dib, &image.into_data()[14 ..]);
+
+        let decoded = Bitmap::from_dib_data(&dib).unwrap();
+        assert_eq!(decoded.into_data(), image.into_data());
+    }
+}
+
+// NOTE(erick): What the clipboard usually hands out: a basic header with
+// BitFields compression followed by three masks.
+#[test]
+fn basic_headers_with_bitfields_read_their_masks() {
+    let mut dib = Vec::new();
+    dib.extend_from_slice(&40u32.to_le_bytes());
+    dib.extend_from_slice(&2i32.to_le_bytes());
+    dib.extend_from_slice(&(-1i32).to_le_bytes());
+    dib.extend_from_slice(&1u16.to_le_bytes());
+    dib.extend_from_slice(&32u16.to_le_bytes());
+    dib.extend_from_slice(&3u32.to_le_bytes());
+    dib.extend_from_slice(&8u32.to_le_bytes());
+    dib.extend_from_slice(&[0; 16]);
+    dib.extend_from_slice(&0x00ff0000u32.to_le_bytes());
+    dib.extend_from_slice(&0x0000ff00u32.to_le_bytes());
+    dib.extend_from_slice(&0x000000ffu32.to_le_bytes());
+    dib.extend_from_slice(&[0x30, 0x20, 0x10, 0x00, 0x03, 0x02, 0x01, 0x00]);
+
+    let image = Bitmap::from_dib_data(&dib).unwrap();
+    let left  = image.get_pixel(0, 0);
+    let right = image.get_pixel(1, 0);
+    assert_eq!((left.red, left.green, left.blue), (0x10, 0x20, 0x30));
+    assert_eq!((right.red, right.green, right.blue), (0x01, 0x02, 0x03));
+
+    assert_eq!(image.to_dib_data(), dib);
+}
+
+#[test]
+fn truncated_headers_are_rejected() {
+    let dib = test_image(24, CompressionType::Uncompressed).to_dib_data();
+
+    assert!(Bitmap::from_dib_data(&dib[.. 20]).is_err());
+    assert!(Bitmap::from_dib_data(&[]).is_err());
+}
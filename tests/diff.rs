@@ -0,0 +1,116 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn gradient() -> Bitmap {
+    let mut result = Bitmap::new_default(4, 4);
+    result.map_pixels(|x, y, _| BitmapPixel::rgb((x * 60) as u8, (y * 60) as u8, 0x80));
+
+    result
+}
+
+#[test]
+fn identical_images_have_no_difference() {
+    let image = gradient();
+    let diff = image.diff(&gradient()).unwrap();
+
+    assert!(diff.stats.is_identical());
+    assert_eq!(diff.stats.max_channel_delta, 0);
+    assert_eq!(diff.stats.mean_squared_error, 0.0);
+    assert!(diff.stats.psnr.is_infinite());
+    assert!(diff.image.get_pixel(2, 3).same_color_as(&BitmapPixel::black()));
+}
+
+#[test]
+fn diff_reports_the_changes() {
+    let image = gradient();
+    let mut other = gradient();
+    other.set_pixel(1, 2, BitmapPixel::rgb(60 + 10, 120, 0x80 - 20));
+    let mut faded = other.get_pixel(3, 0);
+    faded.alpha = 0xff - 8;
+    other.set_pixel(3, 0, faded);
+
+    let diff = image.diff(&other).unwrap();
+    assert_eq!(diff.stats.differing_pixels, 2);
+    assert_eq!(diff.stats.max_channel_delta, 20);
+    assert!(diff.image.get_pixel(1, 2).same_color_as(&BitmapPixel::rgb(10, 0, 20)));
+    assert_eq!(diff.image.get_pixel(1, 2).alpha, 0xff);
+    assert!(diff.image.get_pixel(3, 0).same_color_as(&BitmapPixel::black()));
+
+    // NOTE(erick): (10^2 + 20^2 + 8^2) over 4 * 4 * 4 samples.
+    let mean_squared_error = 564.0 / 64.0;
+    assert!((diff.stats.mean_squared_error - mean_squared_error).abs() < 1e-9);
+    let psnr = 10.0 * (255.0f64 * 255.0 / mean_squared_error).log10();
+    assert!((diff.stats.psnr - psnr).abs() < 1e-9);
+}
+
+#[test]
+fn diff_requires_the_same_size() {
+    let image = gradient();
+    assert!(image.diff(&Bitmap::new_default(4, 3)).is_err());
+}
+
+#[test]
+fn heatmaps_of_identical_images_are_the_image() {
+    let image = gradient();
+    for &color_map in &[ColorMap::Grayscale, ColorMap::Heat, ColorMap::Jet] {
+        let heatmap = image.diff_heatmap(&gradient(), color_map).unwrap();
+        for y in 0 .. 4 {
+            for x in 0 .. 4 {
+                assert_eq!(heatmap.get_pixel(x, y), image.get_pixel(x, y));
+            }
+        }
+    }
+}
+
+#[test]
+fn heatmaps_tint_only_the_differing_pixels() {
+    let image = gradient();
+    let mut other = gradient();
+    // NOTE(erick): Red goes from 60 to 255, 195 / (2 * 255) of the
+    // largest difference.
+    other.set_pixel(1, 2, BitmapPixel::rgb(255, 120, 0x80));
+    let magnitude = 195.0 / 510.0;
+
+    let original = image.get_pixel(1, 2);
+    for &color_map in &[ColorMap::Grayscale, ColorMap::Heat, ColorMap::Jet] {
+        let heatmap = image.diff_heatmap(&other, color_map).unwrap();
+
+        let expected = original.lerp(&color_map.color(magnitude), 0.6);
+        let tinted = heatmap.get_pixel(1, 2);
+        assert!(tinted.same_color_as(&expected), "{:?}: {:?}", color_map, tinted);
+        assert_eq!(tinted.alpha, original.alpha);
+        assert!(!tinted.same_color_as(&original));
+
+        for y in 0 .. 4 {
+            for x in 0 .. 4 {
+                if (x, y) != (1, 2) {
+                    assert_eq!(heatmap.get_pixel(x, y), image.get_pixel(x, y));
+                }
+            }
+        }
+    }
+
+    // NOTE(erick): Grayscale is (97, 97, 97) at that magnitude.
+    let heatmap = image.diff_heatmap(&other, ColorMap::Grayscale).unwrap();
+    assert!(heatmap.get_pixel(1, 2).same_color_as(&BitmapPixel::rgb(82, 106, 109)));
+}
+
+#[test]
+fn color_maps_span_their_ranges() {
+    assert!(ColorMap::Grayscale.color(0.0).same_color_as(&BitmapPixel::rgb(0, 0, 0)));
+    assert!(ColorMap::Grayscale.color(1.0).same_color_as(&BitmapPixel::rgb(0xff, 0xff, 0xff)));
+    assert!(ColorMap::Heat.color(0.0).same_color_as(&BitmapPixel::rgb(0, 0, 0)));
+    assert!(ColorMap::Heat.color(1.0).same_color_as(&BitmapPixel::rgb(0xff, 0xff, 0xff)));
+    assert!(ColorMap::Jet.color(0.0).same_color_as(&BitmapPixel::rgb(0, 0, 127)));
+    assert!(ColorMap::Jet.color(1.0).same_color_as(&BitmapPixel::rgb(127, 0, 0)));
+}
+
+#[test]
+fn heatmaps_require_the_same_size() {
+    let image = gradient();
+    assert!(matches!(image.diff_heatmap(&Bitmap::new_default(4, 3), ColorMap::Heat),
+                     Err(BitmapError::InvalidOperation)));
+    assert!(matches!(image.diff_heatmap(&Bitmap::new_default(3, 4), ColorMap::Jet),
+                     Err(BitmapError::InvalidOperation)));
+}
@@ -0,0 +1,321 @@
+#![cfg(feature = "draw")]
+
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+use common::size_of;
+
+fn blank(width: i32, height: i32) -> Bitmap {
+    let mut result = Bitmap::new(width, height, 24, CompressionType::Uncompressed);
+    result.map_pixels(|_, _, _| BitmapPixel::rgb(0, 0, 0));
+
+    result
+}
+
+fn painted(image: &Bitmap) -> Vec<(u32, u32)> {
+    let (width, height) = size_of(image);
+    let mut result = Vec::new();
+    for y in 0 .. height {
+        for x in 0 .. width {
+            if image.get_pixel(x, y).red != 0 {
+                result.push((x, y));
+            }
+        }
+    }
+
+    result
+}
+
+#[test]
+fn lines_inside_the_image_include_both_end_points() {
+    let mut image = blank(8, 8);
+    image.draw_line(1, 1, 6, 4, BitmapPixel::rgb(0xff, 0, 0));
+
+    let pixels = painted(&image);
+    assert!(pixels.contains(&(1, 1)));
+    assert!(pixels.contains(&(6, 4)));
+    assert_eq!(pixels.len(), 6);
+}
+
+#[test]
+fn off_canvas_lines_are_clipped() {
+    let red = BitmapPixel::rgb(0xff, 0, 0);
+
+    let mut image = blank(8, 8);
+    image.draw_line(i32::MIN, 3, i32::MAX, 3, red);
+    assert_eq!(painted(&image), (0 .. 8).map(|x| (x, 3)).collect::<Vec<_>>());
+
+    let mut image = blank(8, 8);
+    image.draw_line(-1_000_000_000, -1_000_000_000, 1_000_000_000, 1_000_000_000, red);
+    assert_eq!(painted(&image), (0 .. 8).map(|i| (i, i)).collect::<Vec<_>>());
+
+    let mut image = blank(8, 8);
+    image.draw_line(i32::MIN, i32::MIN, i32::MIN, i32::MAX, red);
+    image.draw_line(-10, 20, 20, -10 + 40, red);
+    assert!(painted(&image).is_empty());
+}
+
+#[test]
+fn off_canvas_rectangles_are_clipped() {
+    let red = BitmapPixel::rgb(0xff, 0, 0);
+
+    let mut image = blank(4, 4);
+    image.fill_rect(i32::MAX, i32::MAX, u32::MAX, u32::MAX, red);
+    image.draw_rect(i32::MAX, 0, u32::MAX, 4, red);
+    assert!(painted(&image).is_empty());
+
+    let mut image = blank(4, 4);
+    image.fill_rect(2, i32::MIN, u32::MAX, u32::MAX, red);
+    let expected: Vec<_> = (0 .. 4).flat_map(|y| vec![(2, y), (3, y)]).collect();
+    assert_eq!(painted(&image), expected);
+
+    let mut image = blank(4, 4);
+    image.draw_rect(1, 1, u32::MAX, u32::MAX, red);
+    let expected = vec![(1, 1), (2, 1), (3, 1), (1, 2), (1, 3)];
+    assert_eq!(painted(&image), expected);
+}
+
+#[test]
+fn small_ellipses_touch_their_extremes() {
+    let mut image = blank(9, 9);
+    image.draw_ellipse(4, 4, 3, 2, BitmapPixel::rgb(0xff, 0, 0));
+
+    let pixels = painted(&image);
+    for point in [(1, 4), (7, 4), (4, 2), (4, 6)].iter() {
+        assert!(pixels.contains(point), "{:?}", point);
+    }
+    assert!(!pixels.contains(&(4, 4)));
+    assert!(pixels.iter().all(|&(x, y)| (1 ..= 7).contains(&x) && (2 ..= 6).contains(&y)));
+
+    let mut image = blank(9, 9);
+    image.fill_ellipse(4, 4, 3, 2, BitmapPixel::rgb(0xff, 0, 0));
+    let pixels = painted(&image);
+    assert!(pixels.contains(&(4, 4)));
+    assert!(pixels.contains(&(1, 4)) && pixels.contains(&(7, 4)));
+    assert!(!pixels.contains(&(0, 4)) && !pixels.contains(&(4, 1)));
+}
+
+#[test]
+fn off_canvas_ellipses_are_clipped() {
+    let red = BitmapPixel::rgb(0xff, 0, 0);
+
+    let mut image = blank(8, 8);
+    image.draw_ellipse(5, 5, 3_000_000, 3_000_000, red);
+    image.draw_ellipse(i32::MAX, 5, 3, 3, red);
+    image.draw_ellipse(i32::MIN, i32::MIN, 3, 3, red);
+    image.fill_ellipse(i32::MAX, 5, 3, 3, red);
+    image.fill_ellipse(5, i32::MIN, u32::MAX, 3, red);
+    assert!(painted(&image).is_empty());
+
+    let mut image = blank(4, 4);
+    image.fill_ellipse(2, 2, u32::MAX, u32::MAX, red);
+    assert_eq!(painted(&image).len(), 16);
+
+    let mut image = blank(4, 4);
+    image.fill_ellipse(-1_000_000_000, 3, 1_000_000_001, 0, red);
+    assert_eq!(painted(&image), vec![(0, 3), (1, 3)]);
+}
+
+#[test]
+fn anti_aliased_lines_split_coverage_between_rows() {
+    let white = BitmapPixel::rgb(0xff, 0xff, 0xff);
+    let mut image = blank(8, 8);
+    image.draw_line_aa(0.0, 2.5, 7.0, 2.5, white);
+
+    for x in 1 .. 7 {
+        for y in 0 .. 8 {
+            let red = image.get_pixel(x, y).red;
+            if y == 2 || y == 3 {
+                assert!((126 ..= 129).contains(&red), "({}, {}) is {}", x, y, red);
+            } else {
+                assert_eq!(red, 0, "({}, {})", x, y);
+            }
+        }
+    }
+    // NOTE(erick): The end points cover half of their pixel along x.
+    let red = image.get_pixel(0, 2).red;
+    assert!((62 ..= 66).contains(&red), "{}", red);
+
+    let mut image = blank(8, 8);
+    image.draw_line_aa(3.0, 0.0, 3.0, 7.0, white);
+    assert!((0 .. 8).all(|y| image.get_pixel(3, y).red > 0));
+    assert!((0 .. 8).all(|y| image.get_pixel(2, y).red == 0 && image.get_pixel(4, y).red == 0));
+}
+
+#[test]
+fn off_canvas_anti_aliased_lines_are_clipped() {
+    let white = BitmapPixel::rgb(0xff, 0xff, 0xff);
+
+    let mut image = blank(8, 8);
+    image.draw_line_aa(0.0, 3e9, 10.0, 3e9, white);
+    image.draw_line_aa(-3e9, -3e9, -3e9, 3e9, white);
+    image.draw_line_aa(f32::NAN, 0.0, 4.0, 4.0, white);
+    assert!(painted(&image).is_empty());
+
+    let mut image = blank(8, 8);
+    image.draw_line_aa(-3e9, 2.5, 3e9, 2.5, white);
+    for x in 0 .. 8 {
+        let red = image.get_pixel(x, 2).red;
+        assert!((126 ..= 129).contains(&red), "({}, 2) is {}", x, red);
+        assert_eq!(image.get_pixel(x, 3).red, red);
+    }
+}
+
+#[test]
+fn anti_aliased_circles_fade_with_the_distance_to_the_ring() {
+    let white = BitmapPixel::rgb(0xff, 0xff, 0xff);
+    let mut image = blank(9, 9);
+    image.draw_circle_aa(4.0, 4.0, 2.0, white);
+
+    for &(x, y) in [(6, 4), (2, 4), (4, 6), (4, 2)].iter() {
+        assert_eq!(image.get_pixel(x, y).red, 0xff);
+    }
+    assert_eq!(image.get_pixel(4, 4).red, 0);
+    assert_eq!(image.get_pixel(5, 4).red, 0);
+    assert_eq!(image.get_pixel(0, 0).red, 0);
+
+    // NOTE(erick): sqrt(5) is about 0.236 away from the ring.
+    let red = image.get_pixel(6, 5).red;
+    assert!((192 ..= 197).contains(&red), "{}", red);
+
+    let mut image = blank(4, 4);
+    image.draw_circle_aa(1e9, 1e9, 1e3, white);
+    assert!(painted(&image).is_empty());
+}
+
+// NOTE(erick): The pixels of the builtin glyph for 'c' placed at (x, y).
+fn glyph_pixels(c: char, x: u32, y: u32) -> Vec<(u32, u32)> {
+    let font = BitmapFont::builtin();
+    let mask = font.glyph(c).unwrap();
+    let mut result = Vec::new();
+    for glyph_y in 0 .. BUILTIN_GLYPH_HEIGHT {
+        for glyph_x in 0 .. BUILTIN_GLYPH_WIDTH {
+            if mask[(glyph_y * BUILTIN_GLYPH_WIDTH + glyph_x) as usize] {
+                result.push((x + glyph_x, y + glyph_y));
+            }
+        }
+    }
+
+    result.sort_by_key(|&(x, y)| (y, x));
+    result
+}
+
+#[test]
+fn text_renders_the_builtin_glyphs() {
+    let red = BitmapPixel::rgb(0xff, 0, 0);
+
+    let mut image = blank(8, 8);
+    image.draw_text(0, 0, "A", red);
+    let pixels = painted(&image);
+    assert_eq!(pixels, glyph_pixels('A', 0, 0));
+    // NOTE(erick): The top row of 'A' is 0x0C, the least significant
+    // bit is the leftmost pixel.
+    assert_eq!(pixels.iter().filter(|&&(_, y)| y == 0).collect::<Vec<_>>(),
+               vec![&(2, 0), &(3, 0)]);
+}
+
+#[test]
+fn text_handles_new_lines_and_unknown_characters() {
+    let red = BitmapPixel::rgb(0xff, 0, 0);
+
+    let mut image = blank(16, 16);
+    image.draw_text(0, 0, "A\nA", red);
+    let mut expected = glyph_pixels('A', 0, 0);
+    expected.extend(glyph_pixels('A', 0, 8));
+    assert_eq!(painted(&image), expected);
+
+    let mut image = blank(16, 8);
+    image.draw_text(0, 0, "\u{263a}A", red);
+    assert_eq!(painted(&image), glyph_pixels('A', 8, 0));
+}
+
+#[test]
+fn off_canvas_text_is_clipped() {
+    let red = BitmapPixel::rgb(0xff, 0, 0);
+
+    let mut image = blank(8, 8);
+    image.draw_text(i32::MAX - 4, 0, "ab", red);
+    image.draw_text(0, i32::MAX - 4, "a\nb", red);
+    image.draw_text(i32::MIN, i32::MIN, "a\nb", red);
+    assert!(painted(&image).is_empty());
+
+    let mut image = blank(8, 8);
+    image.draw_text(-8, 0, "xA", red);
+    assert_eq!(painted(&image), glyph_pixels('A', 0, 0));
+}
+
+#[test]
+fn flood_fill_ignores_seeds_outside_of_the_image() {
+    let mut image = blank(4, 4);
+    image.flood_fill(4, 0, BitmapPixel::rgb(0xff, 0, 0), 0);
+    image.flood_fill(0, u32::MAX, BitmapPixel::rgb(0xff, 0, 0), 0);
+    assert!(painted(&image).is_empty());
+}
+
+#[test]
+fn flood_fill_tolerance_is_inclusive() {
+    let red = BitmapPixel::rgb(0xff, 0, 0);
+    let make = || {
+        let mut image = blank(2, 1);
+        // NOTE(erick): (0, 3, 4) is exactly 5 away from black.
+        image.set_pixel(1, 0, BitmapPixel::rgb(0, 3, 4));
+        image
+    };
+
+    let mut image = make();
+    image.flood_fill(0, 0, red, 4);
+    assert_eq!(painted(&image), vec![(0, 0)]);
+
+    let mut image = make();
+    image.flood_fill(0, 0, red, 5);
+    assert_eq!(painted(&image), vec![(0, 0), (1, 0)]);
+}
+
+#[test]
+fn flood_fill_does_not_cross_diagonal_gaps() {
+    let red = BitmapPixel::rgb(0xff, 0, 0);
+    let wall = BitmapPixel::rgb(0, 0xff, 0);
+
+    let mut image = blank(2, 2);
+    image.set_pixel(1, 0, wall);
+    image.set_pixel(0, 1, wall);
+    image.flood_fill(0, 0, red, 0);
+
+    assert_eq!(painted(&image), vec![(0, 0)]);
+    assert_eq!(image.get_pixel(1, 1).red, 0);
+}
+
+#[test]
+fn flood_fill_with_a_similar_color_terminates() {
+    let similar = BitmapPixel::rgb(1, 1, 1);
+    let mut image = blank(16, 16);
+    image.flood_fill(3, 7, similar, 10);
+
+    for y in 0 .. 16 {
+        for x in 0 .. 16 {
+            assert!(image.get_pixel(x, y).same_color_as(&similar), "({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn flood_fill_handles_large_regions() {
+    let mut image = blank(1024, 1024);
+    // NOTE(erick): A serpentine wall makes the region as deep as possible.
+    for x in (1 .. 1024).step_by(2) {
+        let gap_y = if x % 4 == 1 { 1023 } else { 0 };
+        for y in 0 .. 1024 {
+            if y != gap_y {
+                image.set_pixel(x, y, BitmapPixel::rgb(0, 0xff, 0));
+            }
+        }
+    }
+
+    image.flood_fill(0, 0, BitmapPixel::rgb(0xff, 0, 0), 0);
+    assert_eq!(image.get_pixel(1022, 512).red, 0xff);
+    assert_eq!(image.get_pixel(1023, 5).red, 0);
+    assert_eq!(painted(&image).len(), 512 * 1024 + 512);
+}
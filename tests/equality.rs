@@ -0,0 +1,70 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn gradient() -> Bitmap {
+    let mut result = Bitmap::new_default(5, 4);
+    result.map_pixels(|x, y, _| BitmapPixel::rgb((x * 50) as u8, (y * 70) as u8, 0x33));
+
+    result
+}
+
+#[test]
+fn equal_bitmaps_have_the_same_pixels() {
+    let image = gradient();
+    assert_eq!(image, gradient());
+
+    let mut other = gradient();
+    other.set_pixel(4, 3, BitmapPixel::white());
+    assert_ne!(image, other);
+
+    let mut faded = gradient();
+    let mut pixel = faded.get_pixel(0, 0);
+    pixel.alpha = 0x80;
+    faded.set_pixel(0, 0, pixel);
+    assert_ne!(image, faded);
+
+    assert_ne!(image, Bitmap::new_default(5, 3));
+}
+
+#[test]
+fn equality_ignores_the_format() {
+    let image = gradient();
+
+    let mut converted = gradient();
+    converted.convert_to(24, CompressionType::Uncompressed).unwrap();
+    assert_eq!(image, converted);
+
+    let mut flipped = gradient();
+    flipped.info_header.is_top_down = true;
+    flipped.map_pixels(|x, y, _| image.get_pixel(x, y));
+    assert_eq!(image, flipped);
+
+    assert_eq!(image, Bitmap::from_slice(&image.into_data()).unwrap());
+}
+
+#[test]
+fn approx_eq_allows_lossy_conversions() {
+    let image = gradient();
+
+    let mut converted = gradient();
+    converted.convert_to(16, CompressionType::BitFields).unwrap();
+    assert_ne!(image, converted);
+    assert!(image.approx_eq(&converted, 8));
+    assert!(!image.approx_eq(&converted, 0));
+
+    assert!(!image.approx_eq(&Bitmap::new_default(4, 4), 0xff));
+}
+
+#[test]
+fn pixels_compare_all_channels() {
+    assert_eq!(BitmapPixel::rgb(1, 2, 3), BitmapPixel::rgba(1, 2, 3, 0xff));
+    assert_ne!(BitmapPixel::rgb(1, 2, 3), BitmapPixel::rgba(1, 2, 3, 0));
+}
+
+#[test]
+fn debug_shows_the_layout() {
+    let description = format!("{:?}", gradient());
+    assert!(description.contains("width: 5"));
+    assert!(description.contains("BitFields"));
+}
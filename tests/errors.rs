@@ -0,0 +1,111 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+use std::error::Error;
+
+mod common;
+
+fn small_file() -> Vec<u8> {
+    let mut image = Bitmap::new(3, 2, 24, CompressionType::Uncompressed);
+    image.map_pixels(|x, y, _| BitmapPixel::rgb(x as u8, y as u8, 0));
+
+    image.into_data()
+}
+
+#[test]
+fn truncated_headers_report_where_the_data_ends() {
+    let data = small_file();
+
+    match Bitmap::from_slice(&[]) {
+        Err(BitmapError::TruncatedAt(0)) => {},
+        other => panic!("unexpected result: {:?}", other.err()),
+    }
+    match Bitmap::from_slice(&data[.. 30]) {
+        Err(BitmapError::TruncatedAt(30)) => {},
+        other => panic!("unexpected result: {:?}", other.err()),
+    }
+    match Bitmap::from_dib_data(&data[14 .. 30]) {
+        Err(BitmapError::TruncatedAt(16)) => {},
+        other => panic!("unexpected result: {:?}", other.err()),
+    }
+}
+
+#[test]
+fn inconsistent_offsets_name_the_field() {
+    let mut data = small_file();
+    data[10 .. 14].copy_from_slice(&20u32.to_le_bytes());
+
+    match Bitmap::decode_with(&mut data.as_slice(), &DecodeOptions::default(), |_, _| {}) {
+        Err(BitmapError::InconsistentField { name, expected, found }) => {
+            assert_eq!((name, expected, found), ("pixel_array_offset", 54, 20));
+        },
+        other => panic!("unexpected result: {:?}", other.err()),
+    }
+}
+
+#[test]
+fn errors_have_messages() {
+    assert_eq!(BitmapError::TruncatedAt(30).to_string(), "data truncated at byte 30");
+    assert_eq!(BitmapError::InconsistentField { name : "file_size", expected : 60, found : 4 }.to_string(),
+               "inconsistent file_size: expected 60, found 4");
+    assert_eq!(BitmapError::UnsupportedInfoHeaderSize(12).to_string(),
+               "unsupported info header size: 12 bytes");
+}
+
+#[test]
+fn io_errors_are_kept_as_the_source() {
+    let io_error = std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "short read");
+    let err = BitmapError::from(io_error);
+
+    assert!(err.to_string().contains("short read"));
+    let source = err.source().expect("I/O errors have a source");
+    assert_eq!(source.to_string(), "short read");
+    assert!(BitmapError::InvalidBitmap.source().is_none());
+}
+
+fn decode_boxed(data: &[u8]) -> Result<Bitmap, Box<dyn Error>> {
+    Ok(Bitmap::from_slice(data)?)
+}
+
+#[test]
+fn errors_convert_into_boxed_errors() {
+    assert!(decode_boxed(&small_file()).is_ok());
+
+    let err = decode_boxed(b"BM").err().unwrap();
+    assert!(err.downcast_ref::<BitmapError>().is_some());
+}
+
+#[test]
+fn rle_files_are_rejected_without_decode_rle() {
+    let result = Bitmap::from_slice(&common::rle8_file());
+    if cfg!(feature = "decode-rle") {
+        assert!(result.is_ok());
+    } else {
+        assert!(matches!(result, Err(BitmapError::UnsupportedCompressionType(CompressionType::Rle8))));
+    }
+}
+
+#[test]
+fn unsupported_depths_are_rejected_by_every_decoder() {
+    let with_format = |bits_per_pixel: u16, compression: CompressionType| {
+        let mut data = small_file();
+        data[28 .. 30].copy_from_slice(&bits_per_pixel.to_le_bytes());
+        data[30 .. 34].copy_from_slice(&(compression as u32).to_le_bytes());
+        data
+    };
+
+    for data in &[with_format(12, CompressionType::Uncompressed),
+                  with_format(0, CompressionType::Uncompressed),
+                  with_format(24, CompressionType::BitFields),
+                  with_format(8, CompressionType::BitFields)] {
+        assert!(matches!(Bitmap::from_slice(data), Err(BitmapError::InvalidBitmap)));
+        assert!(matches!(Bitmap::decode_region(data, 0, 0, 1, 1), Err(BitmapError::InvalidBitmap)));
+        assert!(matches!(Bitmap::from_dib_data(&data[14 ..]), Err(BitmapError::InvalidBitmap)));
+        assert!(matches!(Bitmap::decode_with(&mut data.as_slice(), &DecodeOptions::default(), |_, _| {}),
+                         Err(BitmapError::InvalidBitmap)));
+    }
+
+    assert!(matches!(Bitmap::from_slice(&with_format(24, CompressionType::Jpeg)),
+                     Err(BitmapError::UnsupportedCompressionType(CompressionType::Jpeg))));
+}
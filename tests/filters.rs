@@ -0,0 +1,214 @@
+#![cfg(feature = "filters")]
+
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+use common::{gradient, size_of, assert_same_pixels};
+
+type Filter = fn(&mut Bitmap);
+
+fn flat(width: i32, height: i32, color: BitmapPixel) -> Bitmap {
+    let mut result = Bitmap::new_default(width, height);
+    result.map_pixels(|_, _, _| color);
+
+    result
+}
+
+// NOTE(erick): Black on the left half, white on the right one.
+fn vertical_step(width: i32, height: i32) -> Bitmap {
+    let mut result = Bitmap::new_default(width, height);
+    result.map_pixels(|x, _, _| {
+        if x < width as u32 / 2 { BitmapPixel::black() } else { BitmapPixel::white() }
+    });
+
+    result
+}
+
+#[test]
+fn identity_kernels_keep_the_image() {
+    let identity = [0.0, 0.0, 0.0,
+                    0.0, 1.0, 0.0,
+                    0.0, 0.0, 0.0];
+
+    for &edge in &[EdgeMode::Clamp, EdgeMode::Wrap, EdgeMode::Mirror] {
+        let original = gradient(9, 7);
+        let mut image = original.clone();
+        image.convolve(&identity, 3, 3, edge).unwrap();
+
+        assert_same_pixels(&image, &original);
+    }
+}
+
+#[test]
+fn kernels_without_a_center_are_rejected() {
+    let mut image = gradient(4, 4);
+
+    assert!(image.convolve(&[0.5, 0.5], 2, 1, EdgeMode::Clamp).is_err());
+    assert!(image.convolve(&[1.0, 0.0, 0.0], 3, 3, EdgeMode::Clamp).is_err());
+}
+
+#[test]
+fn edge_modes_pick_the_pixels_past_the_border() {
+    let original = gradient(5, 1);
+    // NOTE(erick): Each pixel becomes its left neighbor.
+    let left_neighbor = [1.0, 0.0, 0.0];
+
+    let cases = [(EdgeMode::Clamp, 0), (EdgeMode::Wrap, 4), (EdgeMode::Mirror, 1)];
+    for &(edge, source_x) in &cases {
+        let mut image = original.clone();
+        image.convolve(&left_neighbor, 3, 1, edge).unwrap();
+
+        assert!(image.get_pixel(0, 0).same_color_as(&original.get_pixel(source_x, 0)),
+                "{:?}", edge);
+        for x in 1 .. 5 {
+            assert!(image.get_pixel(x, 0).same_color_as(&original.get_pixel(x - 1, 0)),
+                    "{:?} at {}", edge, x);
+        }
+    }
+}
+
+#[test]
+fn flat_images_stay_flat() {
+    let color = BitmapPixel::rgba(0x30, 0x80, 0xc0, 0x7f);
+    let original = flat(8, 6, color);
+
+    let filters : [(&str, Filter); 3] = [
+        ("box_blur", |image| image.box_blur(2)),
+        ("sharpen",  |image| image.sharpen()),
+        ("emboss",   |image| image.emboss()),
+    ];
+    for &(name, filter) in &filters {
+        let mut image = original.clone();
+        filter(&mut image);
+
+        // NOTE(erick): Alpha included, filters only touch the colors.
+        for y in 0 .. 6 {
+            for x in 0 .. 8 {
+                assert_eq!(image.get_pixel(x, y), color, "{} at ({}, {})", name, x, y);
+            }
+        }
+    }
+
+    let mut image = original.clone();
+    image.sobel_edges();
+    let (width, height) = size_of(&image);
+    for y in 0 .. height {
+        for x in 0 .. width {
+            assert!(image.get_pixel(x, y).same_color_as(&BitmapPixel::black()),
+                    "sobel_edges at ({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn steps_give_an_edge_response() {
+    let mut image = vertical_step(8, 4);
+    image.sobel_edges();
+
+    for y in 0 .. 4 {
+        for x in 0 .. 8 {
+            let pixel = image.get_pixel(x, y);
+            if x == 3 || x == 4 {
+                assert!(pixel.same_color_as(&BitmapPixel::white()), "edge at ({}, {})", x, y);
+            } else {
+                assert!(pixel.same_color_as(&BitmapPixel::black()), "flat at ({}, {})", x, y);
+            }
+        }
+    }
+
+    // NOTE(erick): Blurring spreads the step over the radius, sharpening
+    // makes it overshoot (and clip) on both sides.
+    let mut blurred = vertical_step(8, 4);
+    blurred.box_blur(1);
+    let row : Vec<u8> = (0 .. 8).map(|x| blurred.get_pixel(x, 1).red).collect();
+    assert_eq!(row, vec![0, 0, 0, 0x55, 0xaa, 0xff, 0xff, 0xff]);
+
+    let dark  = BitmapPixel::rgb(0x40, 0x40, 0x40);
+    let light = BitmapPixel::rgb(0xc0, 0xc0, 0xc0);
+    let mut sharpened = Bitmap::new_default(8, 4);
+    sharpened.map_pixels(|x, _, _| if x < 4 { dark } else { light });
+    sharpened.sharpen();
+    let row : Vec<u8> = (0 .. 8).map(|x| sharpened.get_pixel(x, 1).red).collect();
+    assert_eq!(row, vec![0x40, 0x40, 0x40, 0, 0xff, 0xc0, 0xc0, 0xc0]);
+
+    let mut embossed = vertical_step(8, 4);
+    embossed.emboss();
+    assert!(embossed.get_pixel(3, 1).red > 0);
+    assert_eq!(embossed.get_pixel(0, 1).red, 0);
+    assert_eq!(embossed.get_pixel(7, 1).red, 0xff);
+}
+
+fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let radius = (3.0 * sigma).ceil() as i32;
+    let kernel : Vec<f32> = (-radius ..= radius)
+        .map(|offset| (-(offset * offset) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum : f32 = kernel.iter().sum();
+
+    kernel.iter().map(|weight| weight / sum).collect()
+}
+
+#[test]
+fn gaussian_blur_matches_the_full_kernel() {
+    for &sigma in &[0.5, 1.0, 1.7] {
+        let kernel_1d = gaussian_kernel(sigma);
+        let size = kernel_1d.len();
+        let mut kernel_2d = Vec::with_capacity(size * size);
+        for weight_y in &kernel_1d {
+            for weight_x in &kernel_1d {
+                kernel_2d.push(weight_x * weight_y);
+            }
+        }
+
+        let mut separable = gradient(13, 9);
+        separable.gaussian_blur(sigma);
+        let mut full = gradient(13, 9);
+        full.convolve(&kernel_2d, size, size, EdgeMode::Clamp).unwrap();
+
+        // NOTE(erick): Both passes are summed in a different order, so
+        // the rounding may differ by one.
+        for y in 0 .. 9 {
+            for x in 0 .. 13 {
+                let p0 = separable.get_pixel(x, y);
+                let p1 = full.get_pixel(x, y);
+                for &(c0, c1) in &[(p0.red, p1.red), (p0.green, p1.green), (p0.blue, p1.blue)] {
+                    assert!((c0 as i32 - c1 as i32).abs() <= 1,
+                            "sigma {} at ({}, {}): {:?} {:?}", sigma, x, y, p0, p1);
+                }
+                assert_eq!(p0.alpha, p1.alpha);
+            }
+        }
+    }
+}
+
+#[test]
+fn non_positive_sigmas_do_nothing() {
+    let original = gradient(6, 5);
+
+    for &sigma in &[0.0, -1.0] {
+        let mut image = original.clone();
+        image.gaussian_blur(sigma);
+        assert_same_pixels(&image, &original);
+    }
+}
+
+#[test]
+fn huge_sigmas_are_capped_at_the_image_size() {
+    let mut flat = Bitmap::new_default(6, 5);
+    flat.map_pixels(|_, _, _| BitmapPixel::rgba(0x40, 0x80, 0xc0, 0xff));
+    let original = flat.clone();
+    flat.gaussian_blur(20000.0);
+    assert_same_pixels(&flat, &original);
+
+    // NOTE(erick): A very wide Gaussian is almost a box filter, so the
+    // red ramp (0 to 212) gets much flatter.
+    let mut image = gradient(6, 5);
+    image.gaussian_blur(1e30);
+    let (min, max) = (0 .. 5).flat_map(|y| (0 .. 6).map(move |x| (x, y)))
+        .map(|(x, y)| image.get_pixel(x, y).red)
+        .fold((0xff, 0), |(min, max), red| (red.min(min), red.max(max)));
+    assert!(max - min < 0x60, "{} {}", min, max);
+}
@@ -0,0 +1,135 @@
+#![cfg(feature = "filters")]
+
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+use common::{gradient, size_of, assert_same_pixels};
+
+fn filled(width: i32, height: i32, color: BitmapPixel) -> Bitmap {
+    let mut result = Bitmap::new_default(width, height);
+    result.map_pixels(|_, _, _| color);
+
+    result
+}
+
+// NOTE(erick): A checkerboard of 'dark' and 'light' on the columns in
+// 'sharp_columns' and flat 'dark' everywhere else.
+fn partly_sharp(width: i32, height: i32, sharp_columns: std::ops::Range<u32>,
+                dark: BitmapPixel, light: BitmapPixel) -> Bitmap {
+    let mut result = Bitmap::new_default(width, height);
+    result.map_pixels(|x, y, _| {
+        if sharp_columns.contains(&x) && (x + y) % 2 == 1 { light } else { dark }
+    });
+
+    result
+}
+
+fn is_invalid_operation(result: Result<Bitmap, BitmapError>) -> bool {
+    matches!(result, Err(BitmapError::InvalidOperation))
+}
+
+#[test]
+fn exposure_stacks_must_be_non_empty_and_the_same_size() {
+    let small = gradient(4, 4);
+    let wide  = gradient(5, 4);
+
+    assert!(is_invalid_operation(Bitmap::exposure_fuse(&[])));
+    assert!(is_invalid_operation(Bitmap::exposure_fuse(&[&small, &wide])));
+}
+
+#[test]
+fn fusing_a_single_capture_keeps_it() {
+    let image = gradient(7, 5);
+
+    assert_same_pixels(&Bitmap::exposure_fuse(&[&image]).unwrap(), &image);
+}
+
+#[test]
+fn exposure_fusion_prefers_the_better_exposed_capture() {
+    // NOTE(erick): The same colored, textured patch captured around
+    // mid-gray and nearly blown out. Flat gray patches have no contrast
+    // or saturation, so every capture would get the same weight.
+    let texture = |base: [u8; 3], amplitude: u8| {
+        let mut result = Bitmap::new_default(8, 8);
+        result.map_pixels(|x, y, _| {
+            let offset = if (x + y) % 2 == 0 { 0 } else { amplitude };
+            BitmapPixel::rgb(base[0] + offset, base[1] + offset, base[2] + offset)
+        });
+        result
+    };
+    let well_exposed = texture([120, 90, 70], 40);
+    let over_exposed = texture([235, 225, 215], 10);
+
+    let fused = Bitmap::exposure_fuse(&[&well_exposed, &over_exposed]).unwrap();
+    assert_eq!(size_of(&fused), (8, 8));
+    for y in 0 .. 8 {
+        for x in 0 .. 8 {
+            let distance_to_well = fused.get_pixel(x, y)
+                .distance_squared(&well_exposed.get_pixel(x, y));
+            let distance_to_over = fused.get_pixel(x, y)
+                .distance_squared(&over_exposed.get_pixel(x, y));
+            assert!(distance_to_well < distance_to_over, "({}, {})", x, y);
+        }
+    }
+
+    // NOTE(erick): The order of the captures doesn't matter.
+    let swapped = Bitmap::exposure_fuse(&[&over_exposed, &well_exposed]).unwrap();
+    assert!(swapped.approx_eq(&fused, 1));
+}
+
+#[test]
+fn focus_stacks_must_be_non_empty_and_the_same_size() {
+    let small = gradient(4, 4);
+    let wide  = gradient(5, 4);
+
+    assert!(is_invalid_operation(Bitmap::focus_stack(&[])));
+    assert!(is_invalid_operation(Bitmap::focus_stack(&[&small, &wide])));
+}
+
+#[test]
+fn focus_stacking_a_single_frame_keeps_it() {
+    let image = gradient(7, 5);
+
+    assert_same_pixels(&Bitmap::focus_stack(&[&image]).unwrap(), &image);
+}
+
+#[test]
+fn focus_stacking_takes_each_half_from_the_sharp_frame() {
+    let left_sharp = partly_sharp(24, 8, 0 .. 12, BitmapPixel::rgb(0, 0, 0),
+                                  BitmapPixel::rgb(0xff, 0xff, 0xff));
+    let right_sharp = partly_sharp(24, 8, 12 .. 24, BitmapPixel::rgb(0, 0, 0x80),
+                                   BitmapPixel::rgb(0xff, 0x80, 0));
+
+    let stacked = Bitmap::focus_stack(&[&left_sharp, &right_sharp]).unwrap();
+
+    // NOTE(erick): Skip the columns whose window sees both halves.
+    for y in 0 .. 8 {
+        for x in 0 .. 8 {
+            assert_eq!(stacked.get_pixel(x, y), left_sharp.get_pixel(x, y), "({}, {})", x, y);
+        }
+        for x in 16 .. 24 {
+            assert_eq!(stacked.get_pixel(x, y), right_sharp.get_pixel(x, y), "({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn focus_stacking_ties_go_to_the_first_frame() {
+    let red  = filled(6, 6, BitmapPixel::rgb(0xff, 0, 0));
+    let blue = filled(6, 6, BitmapPixel::rgb(0, 0, 0xff));
+    assert_same_pixels(&Bitmap::focus_stack(&[&red, &blue]).unwrap(), &red);
+    assert_same_pixels(&Bitmap::focus_stack(&[&blue, &red]).unwrap(), &blue);
+
+    // NOTE(erick): The same texture in two colors of the same luma is
+    // just as sharp.
+    let gray  = partly_sharp(6, 6, 0 .. 6, BitmapPixel::rgb(0, 0, 0),
+                             BitmapPixel::rgb(0x80, 0x80, 0x80));
+    let green = partly_sharp(6, 6, 0 .. 6, BitmapPixel::rgb(0, 0, 0),
+                             BitmapPixel::rgb(0, 218, 0));
+    assert_eq!(BitmapPixel::rgb(0, 218, 0).luma(), 0x80);
+    assert_same_pixels(&Bitmap::focus_stack(&[&gray, &green]).unwrap(), &gray);
+    assert_same_pixels(&Bitmap::focus_stack(&[&green, &gray]).unwrap(), &green);
+}
@@ -0,0 +1,118 @@
+#![cfg(feature = "draw")]
+
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+use common::same_pixels;
+
+fn channels(pixel: BitmapPixel) -> (u8, u8, u8, u8) {
+    (pixel.red, pixel.green, pixel.blue, pixel.alpha)
+}
+
+#[test]
+fn linear_gradients_span_the_image() {
+    let black = BitmapPixel::rgb(0, 0, 0);
+    let white = BitmapPixel::rgb(0xff, 0xff, 0xff);
+
+    let horizontal = Bitmap::linear_gradient(6, 3, black, white, 0.0).unwrap();
+    assert_eq!(horizontal.info_header.image_width, 6);
+    for y in 0 .. 3 {
+        assert_eq!(channels(horizontal.get_pixel(0, y)), (0, 0, 0, 0xff));
+        assert_eq!(channels(horizontal.get_pixel(5, y)), (0xff, 0xff, 0xff, 0xff));
+        assert_eq!(horizontal.get_pixel(2, y).red, 102);
+    }
+
+    let vertical = Bitmap::linear_gradient(3, 5, black, white, 90.0).unwrap();
+    for x in 0 .. 3 {
+        assert_eq!(vertical.get_pixel(x, 0).red, 0);
+        assert_eq!(vertical.get_pixel(x, 2).red, 128);
+        assert_eq!(vertical.get_pixel(x, 4).red, 0xff);
+    }
+
+    let reversed = Bitmap::linear_gradient(6, 3, black, white, 180.0).unwrap();
+    assert_eq!(reversed.get_pixel(0, 1).red, 0xff);
+    assert_eq!(reversed.get_pixel(5, 1).red, 0);
+
+    let diagonal = Bitmap::linear_gradient(4, 4, black, white, 45.0).unwrap();
+    assert_eq!(diagonal.get_pixel(0, 0).red, 0);
+    assert_eq!(diagonal.get_pixel(3, 3).red, 0xff);
+    assert_eq!(diagonal.get_pixel(3, 0).red, diagonal.get_pixel(0, 3).red);
+}
+
+#[test]
+fn linear_gradients_interpolate_alpha() {
+    let clear = BitmapPixel::rgba(0xff, 0, 0, 0);
+    let solid = BitmapPixel::rgba(0xff, 0, 0, 0xff);
+
+    let image = Bitmap::linear_gradient(3, 1, clear, solid, 0.0).unwrap();
+    assert_eq!(image.get_pixel(1, 0).alpha, 128);
+
+    let single = Bitmap::linear_gradient(1, 1, clear, solid, 30.0).unwrap();
+    assert_eq!(single.get_pixel(0, 0).alpha, 0);
+}
+
+#[test]
+fn radial_gradients_fade_with_distance() {
+    let inner = BitmapPixel::rgb(0xff, 0xff, 0xff);
+    let outer = BitmapPixel::rgb(0, 0, 0x40);
+
+    let image = Bitmap::radial_gradient(9, 9, inner, outer, 4.0, 4.0, 4.0).unwrap();
+    assert_eq!(channels(image.get_pixel(4, 4)), (0xff, 0xff, 0xff, 0xff));
+    assert_eq!(image.get_pixel(6, 4).red, 128);
+    assert!(image.get_pixel(4, 2).same_color_as(&image.get_pixel(2, 4)));
+    assert!(image.get_pixel(0, 4).same_color_as(&outer));
+    assert!(image.get_pixel(0, 0).same_color_as(&outer));
+}
+
+#[test]
+fn noise_is_reproducible() {
+    let image = Bitmap::noise(16, 8, 0).unwrap();
+    // NOTE(erick): The first SplitMix64 output for seed 0 is
+    // 0xe220a8397b1dcdaf. Fixtures rely on this never changing.
+    assert_eq!(channels(image.get_pixel(0, 0)), (0xaf, 0xcd, 0x1d, 0xff));
+
+    assert!(same_pixels(&image, &Bitmap::noise(16, 8, 0).unwrap()));
+    assert!(!same_pixels(&image, &Bitmap::noise(16, 8, 1).unwrap()));
+
+    let decoded = Bitmap::from_slice(&image.into_data()).unwrap();
+    assert!(same_pixels(&image, &decoded));
+}
+
+#[test]
+fn noise_covers_the_whole_range() {
+    let image = Bitmap::noise(64, 64, 42).unwrap();
+
+    let mut seen = [false; 256];
+    for y in 0 .. 64 {
+        for x in 0 .. 64 {
+            seen[image.get_pixel(x, y).red as usize] = true;
+        }
+    }
+    assert!(seen.iter().filter(|&&seen| seen).count() > 240);
+}
+
+#[cfg(feature = "noise")]
+#[test]
+fn value_noise_is_smooth_and_reproducible() {
+    let image = Bitmap::value_noise(32, 32, 7, 8.0, 1).unwrap();
+    assert!(same_pixels(&image, &Bitmap::value_noise(32, 32, 7, 8.0, 1).unwrap()));
+    assert!(!same_pixels(&image, &Bitmap::value_noise(32, 32, 8, 8.0, 1).unwrap()));
+
+    for y in 0 .. 32 {
+        for x in 0 .. 31 {
+            let p0 = image.get_pixel(x, y);
+            let p1 = image.get_pixel(x + 1, y);
+            assert_eq!((p0.red, p0.red), (p0.green, p0.blue));
+            assert!((p0.red as i32 - p1.red as i32).abs() <= 48);
+        }
+    }
+
+    let detailed = Bitmap::value_noise(32, 32, 7, 8.0, 4).unwrap();
+    assert!(!same_pixels(&image, &detailed));
+
+    assert!(Bitmap::value_noise(4, 4, 7, 0.0, 1).is_err());
+    assert!(Bitmap::value_noise(4, 4, 7, 8.0, 0).is_err());
+}
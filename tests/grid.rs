@@ -0,0 +1,83 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+// NOTE(erick): 3 x 2 tiles of 4 x 3 pixels, 1 pixel apart, 2 pixels from
+// the corner and one extra pixel after the last ones. Every pixel of a
+// tile is colored with the tile's position.
+fn sprite_sheet() -> Bitmap {
+    let mut result = Bitmap::new(17, 11, 24,
+                                 CompressionType::Uncompressed);
+    result.map_pixels(|x, y, _| {
+        let in_tile = x >= 2 && y >= 2 && (x - 2) % 5 < 4 && (y - 2) % 4 < 3;
+        if in_tile {
+            BitmapPixel::rgb(((x - 2) / 5) as u8 * 40, ((y - 2) / 4) as u8 * 40, (x + y) as u8)
+        } else {
+            BitmapPixel::white()
+        }
+    });
+
+    result
+}
+
+#[test]
+fn split_grid_slices_every_tile() {
+    let sheet = sprite_sheet();
+    let tiles = sheet.split_grid(4, 3, 1, 2);
+    assert_eq!(tiles.len(), 6);
+
+    for (index, tile) in tiles.iter().enumerate() {
+        let column = index as u32 % 3;
+        let row    = index as u32 / 3;
+
+        assert_eq!((tile.info_header.image_width, tile.info_header.image_height), (4, 3));
+        assert_eq!(tile.info_header.bits_per_pixel, 24);
+        for y in 0 .. 3 {
+            for x in 0 .. 4 {
+                let expected = sheet.get_pixel(2 + column * 5 + x, 2 + row * 4 + y);
+                assert!(tile.get_pixel(x, y).same_color_as(&expected));
+                assert_eq!((expected.red, expected.green), (column as u8 * 40, row as u8 * 40));
+            }
+        }
+    }
+}
+
+#[test]
+fn grid_tiles_is_lazy_split_grid() {
+    let sheet = sprite_sheet();
+
+    let mut tiles = sheet.grid_tiles(4, 3, 1, 2);
+    assert_eq!((tiles.columns(), tiles.rows()), (3, 2));
+    assert_eq!(tiles.len(), 6);
+
+    tiles.next();
+    assert_eq!(tiles.len(), 5);
+
+    let collected: Vec<Vec<u8>> = sheet.grid_tiles(4, 3, 1, 2).map(|tile| tile.into_data()).collect();
+    let split: Vec<Vec<u8>> = sheet.split_grid(4, 3, 1, 2).iter().map(|tile| tile.into_data()).collect();
+    assert_eq!(collected, split);
+}
+
+#[test]
+fn partial_tiles_are_left_out() {
+    let sheet = Bitmap::new_default(10, 7);
+
+    assert_eq!(sheet.split_grid(3, 3, 0, 0).len(), 3 * 2);
+    assert_eq!(sheet.split_grid(3, 3, 1, 0).len(), 2 * 2);
+    assert_eq!(sheet.split_grid(3, 3, 1, 1).len(), 2);
+    assert_eq!(sheet.split_grid(11, 3, 0, 0).len(), 0);
+    assert_eq!(sheet.split_grid(0, 3, 0, 0).len(), 0);
+    assert_eq!(sheet.split_grid(3, 3, 0, 8).len(), 0);
+}
+
+#[test]
+fn huge_spacings_leave_a_single_tile() {
+    let sheet = sprite_sheet();
+
+    let tiles = sheet.split_grid(4, 3, u32::MAX, 2);
+    assert_eq!(tiles.len(), 1);
+    assert_eq!(tiles[0].into_data(), sheet.crop_to_rect(2, 2, 4, 3).unwrap().into_data());
+
+    assert_eq!(sheet.split_grid(17, 11, u32::MAX, 0).len(), 1);
+    assert_eq!(sheet.grid_tiles(4, 3, u32::MAX - 3, 2).len(), 1);
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "filters")]
+
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+use common::{gradient, assert_same_pixels};
+
+#[test]
+fn matching_an_image_against_itself_keeps_it() {
+    let mut image = gradient(13, 9);
+    image.match_histogram(&gradient(13, 9));
+
+    assert_same_pixels(&image, &gradient(13, 9));
+}
+
+#[test]
+fn matched_histograms_follow_the_reference() {
+    // NOTE(erick): 64 gray levels, one pixel each, with varying alpha.
+    let mut image = Bitmap::new_default(8, 8);
+    image.map_pixels(|x, y, _| {
+        let level = (y * 8 + x) as u8;
+        BitmapPixel::rgba(level, level, level, 0x80 + level)
+    });
+
+    // NOTE(erick): Three quarters at 100 and one quarter at 200, in an
+    // image of a different size.
+    let mut reference = Bitmap::new_default(4, 2);
+    reference.map_pixels(|x, _, _| {
+        let level = if x < 3 { 100 } else { 200 };
+        BitmapPixel::rgb(level, level, level)
+    });
+
+    image.match_histogram(&reference);
+
+    let mut n_low = 0;
+    let mut n_high = 0;
+    for y in 0 .. 8 {
+        for x in 0 .. 8 {
+            let pixel = image.get_pixel(x, y);
+            let level = (y * 8 + x) as u8;
+            assert_eq!(pixel.alpha, 0x80 + level);
+            assert!(pixel.red == pixel.green && pixel.green == pixel.blue);
+
+            match pixel.red {
+                100 => n_low += 1,
+                200 => n_high += 1,
+                other => panic!("unexpected level {} at ({}, {})", other, x, y),
+            }
+            // NOTE(erick): The order of the levels is kept.
+            assert_eq!(pixel.red == 100, level < 48);
+        }
+    }
+    assert_eq!((n_low, n_high), (48, 16));
+}
+
+#[test]
+fn empty_references_do_nothing() {
+    let mut image = gradient(4, 4);
+    image.match_histogram(&Bitmap::new_default(0, 0));
+
+    assert_same_pixels(&image, &gradient(4, 4));
+}
@@ -0,0 +1,95 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn is_limit_error<T>(result: Result<T, BitmapError>) -> bool {
+    matches!(result, Err(BitmapError::DecodeLimitExceeded(_)))
+}
+
+// NOTE(erick): Just the headers of a 24 bits image claiming to be huge.
+fn huge_header(width: i32, height: i32) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(b"BM");
+    data.extend_from_slice(&54u32.to_le_bytes());
+    data.extend_from_slice(&[0; 4]);
+    data.extend_from_slice(&54u32.to_le_bytes());
+    data.extend_from_slice(&40u32.to_le_bytes());
+    data.extend_from_slice(&width.to_le_bytes());
+    data.extend_from_slice(&height.to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&24u16.to_le_bytes());
+    data.extend_from_slice(&[0; 24]);
+
+    data
+}
+
+#[test]
+fn default_limits_reject_huge_headers() {
+    assert!(is_limit_error(Bitmap::from_data(huge_header(0x7fff_ffff, 1))));
+    assert!(is_limit_error(Bitmap::from_data(huge_header(60000, 60000))));
+    assert!(is_limit_error(Bitmap::decode_with(&mut huge_header(60000, 60000).as_slice(),
+                                               &DecodeOptions::default(), |_, _| {})));
+    assert!(Bitmap::from_data(huge_header(-5, 1)).is_err());
+}
+
+#[test]
+fn regions_check_the_limits() {
+    let data = huge_header(60000, 60000);
+    assert!(is_limit_error(Bitmap::decode_region(&data, 0, 0, 1, 1)));
+
+    let data = Bitmap::new(20, 10, 24, CompressionType::Uncompressed).into_data();
+    let options = DecodeOptions { max_width : 16, ..DecodeOptions::default() };
+    assert!(is_limit_error(Bitmap::decode_region_with_options(&data, 0, 0, 1, 1, &options)));
+    assert!(Bitmap::decode_region(&data, 0, 0, 1, 1).is_ok());
+}
+
+#[test]
+fn most_negative_height_is_invalid() {
+    let data = huge_header(1, i32::MIN);
+    assert_eq!(&data[22 .. 26], &[0x00, 0x00, 0x00, 0x80]);
+
+    assert!(matches!(Bitmap::from_data(data.clone()), Err(BitmapError::InvalidBitmap)));
+    assert!(matches!(Bitmap::probe(&mut data.as_slice()), Err(BitmapError::InvalidBitmap)));
+}
+
+#[test]
+fn custom_limits_are_applied() {
+    let data = Bitmap::new(20, 10, 24, CompressionType::Uncompressed).into_data();
+
+    let options = DecodeOptions { max_width : 16, ..DecodeOptions::default() };
+    assert!(is_limit_error(Bitmap::from_data_with_options(data.clone(), &options)));
+
+    let mut options = DecodeOptions { max_pixels : 199, ..DecodeOptions::default() };
+    assert!(is_limit_error(Bitmap::from_reader_with_options(&mut data.as_slice(), &options)));
+
+    options.max_pixels = 200;
+    assert!(Bitmap::from_reader_with_options(&mut data.as_slice(), &options).is_ok());
+    assert!(Bitmap::from_reader(&mut data.as_slice()).is_ok());
+}
+
+#[test]
+#[cfg(feature = "encode-palettized")]
+fn palette_limit_is_applied() {
+    let mut image = Bitmap::new(4, 4, 8, CompressionType::Uncompressed);
+    image.palette = Some(vec![BitmapPixel::black(); 256]);
+    let data = image.into_data();
+
+    let options = DecodeOptions { max_palette_size : 16, ..DecodeOptions::default() };
+    assert!(is_limit_error(Bitmap::from_data_with_options(data.clone(), &options)));
+
+    // NOTE(erick): Also inside bitmap arrays.
+    let array = Bitmap::array_into_data(&[image]).unwrap();
+    assert!(is_limit_error(Bitmap::from_data_with_options(array, &options)));
+}
+
+#[test]
+fn readers_stop_at_max_file_size() {
+    let options = DecodeOptions { max_file_size : 1000, ..DecodeOptions::default() };
+
+    let result = Bitmap::from_reader_with_options(&mut std::io::repeat(0), &options);
+    assert!(matches!(result, Err(BitmapError::DecodeLimitExceeded(_))));
+
+    let data = Bitmap::new_default(4, 4).into_data();
+    let options = DecodeOptions { max_file_size : data.len() as u64, ..DecodeOptions::default() };
+    assert!(Bitmap::from_reader_with_options(&mut &data[..], &options).is_ok());
+}
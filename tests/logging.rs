@@ -0,0 +1,68 @@
+#![cfg(feature = "log")]
+
+extern crate bitmap_io;
+extern crate log;
+
+use bitmap_io::*;
+use log::{Log, Metadata, Record, Level};
+use std::sync::{Mutex, Once};
+
+struct CapturingLogger {
+    messages : Mutex<Vec<(Level, String)>>,
+}
+
+impl Log for CapturingLogger {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let message = format!("{}", record.args());
+        self.messages.lock().unwrap().push((record.level(), message));
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER : CapturingLogger = CapturingLogger { messages : Mutex::new(Vec::new()) };
+static LOGGER_INIT : Once = Once::new();
+
+fn logged(expected_level: Level, text: &str) -> bool {
+    LOGGER.messages.lock().unwrap().iter().any(|&(level, ref message)| {
+        level == expected_level && message.contains(text)
+    })
+}
+
+fn init_logger() {
+    LOGGER_INIT.call_once(|| {
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+    });
+}
+
+#[test]
+fn headers_go_to_the_logger() {
+    init_logger();
+
+    let data = Bitmap::new(3, 2, 24, CompressionType::Uncompressed).into_data();
+    Bitmap::from_data(data).unwrap();
+
+    assert!(logged(Level::Debug, "image_width: 3"));
+}
+
+#[test]
+#[cfg(feature = "encode-palettized")]
+fn decode_warnings_go_to_the_logger() {
+    init_logger();
+
+    // NOTE(erick): A 2 entries palette with an index of 5.
+    let mut image = Bitmap::new(2, 1, 8, CompressionType::Uncompressed);
+    image.palette = Some(vec![BitmapPixel::black(); 256]);
+    let mut data = image.into_data();
+    let pixels_offset = BytesWalker::new(&data[10 ..]).next_u32() as usize;
+    data[46] = 2;
+    data[pixels_offset] = 5;
+    Bitmap::from_data(data).unwrap();
+
+    assert!(logged(Level::Warn, "PaletteIndexOutOfRange"));
+}
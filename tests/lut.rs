@@ -0,0 +1,111 @@
+#![cfg(feature = "filters")]
+
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+fn invert_cube(header: &str, max: f32) -> String {
+    let mut result = String::from(header);
+    for b in 0 .. 2 {
+        for g in 0 .. 2 {
+            for r in 0 .. 2 {
+                result.push_str(&format!("{} {} {}\n", (1 - r) as f32 * max,
+                                         (1 - g) as f32 * max, (1 - b) as f32 * max));
+            }
+        }
+    }
+
+    result
+}
+
+#[test]
+fn identity_luts_keep_the_image() {
+    let image = common::gradient(9, 7);
+
+    for &size in &[2, 17, 33] {
+        let mut mapped = image.clone();
+        mapped.apply_lut3d(&Lut3d::identity(size).unwrap());
+        assert!(mapped.approx_eq(&image, 1), "size {}", size);
+    }
+}
+
+#[test]
+fn cube_files_are_parsed() {
+    let lut = Lut3d::from_cube_str(&invert_cube("TITLE \"invert\"\n# comment\nLUT_3D_SIZE 2\n\n", 1.0))
+        .unwrap();
+    assert_eq!(lut.size(), 2);
+    assert_eq!(lut.lookup([0.0, 0.25, 1.0]), [1.0, 0.75, 0.0]);
+
+    let mut image = Bitmap::new_default(1, 1);
+    image.set_pixel(0, 0, BitmapPixel::rgba(0x10, 0x80, 0xff, 0x40));
+    image.apply_lut3d(&lut);
+    assert_eq!(image.get_pixel(0, 0), BitmapPixel::rgba(0xef, 0x7f, 0x00, 0x40));
+}
+
+#[test]
+fn input_ranges_set_the_domain() {
+    // NOTE(erick): The entries are output colors and stay in [0, 1], only
+    // the inputs are in the domain.
+    let header = "LUT_3D_SIZE 2\nLUT_3D_INPUT_RANGE 0 1023\n";
+    let lut = Lut3d::from_cube_str(&invert_cube(header, 1.0)).unwrap();
+    assert_eq!(lut.domain(), ([0.0; 3], [1023.0; 3]));
+    assert_eq!(lut.lookup([1023.0, 0.0, 511.5]), [0.0, 1.0, 0.5]);
+    assert_eq!(lut.lookup([2000.0, -5.0, 0.0]), [0.0, 1.0, 1.0]);
+
+    let header = "LUT_3D_SIZE 2\nDOMAIN_MIN -1 0 0\nDOMAIN_MAX 1 4 4\n";
+    let lut = Lut3d::from_cube_str(&invert_cube(header, 1.0)).unwrap();
+    assert_eq!(lut.lookup([1.0, 0.0, 2.0]), [0.0, 1.0, 0.5]);
+    assert_eq!(lut.lookup([0.0, 1.0, 4.0]), [0.5, 0.75, 0.0]);
+
+    // NOTE(erick): Pixels are in [0, 1], so a white pixel is a quarter of
+    // the way into a [0, 4] domain.
+    let header = "LUT_3D_SIZE 2\nDOMAIN_MAX 4 4 4\n";
+    let lut = Lut3d::from_cube_str(&invert_cube(header, 1.0)).unwrap();
+    let mut image = Bitmap::new_default(1, 1);
+    image.set_pixel(0, 0, BitmapPixel::rgba(0xff, 0x00, 0xff, 0xff));
+    image.apply_lut3d(&lut);
+    assert_eq!(image.get_pixel(0, 0), BitmapPixel::rgba(0xbf, 0xff, 0xbf, 0xff));
+
+    assert!(Lut3d::from_cube_str("LUT_3D_SIZE 2\nLUT_3D_INPUT_RANGE 0\n").is_err());
+    let header = "LUT_3D_SIZE 2\nDOMAIN_MIN 1 0 0\nDOMAIN_MAX 1 1 1\n";
+    assert!(matches!(Lut3d::from_cube_str(&invert_cube(header, 1.0)),
+                     Err(BitmapError::InvalidLut(_))));
+}
+
+#[test]
+fn broken_luts_are_rejected() {
+    assert!(matches!(Lut3d::from_cube_str("LUT_1D_SIZE 4\n"), Err(BitmapError::InvalidLut(_))));
+    assert!(matches!(Lut3d::from_cube_str("LUT_3D_SIZE 2\n0 0 0\n"), Err(BitmapError::InvalidLut(_))));
+    assert!(matches!(Lut3d::from_cube_str("LUT_3D_SIZE 2\n0 zero 0\n"), Err(BitmapError::InvalidLut(_))));
+
+    // NOTE(erick): size^3 doesn't fit a usize.
+    let huge = 1 << (usize::BITS / 2);
+    assert!(matches!(Lut3d::new(huge, Vec::new()), Err(BitmapError::InvalidLut(_))));
+    assert!(matches!(Lut3d::identity(huge), Err(BitmapError::InvalidLut(_))));
+    let text = format!("LUT_3D_SIZE {}\n0 0 0\n", huge);
+    assert!(matches!(Lut3d::from_cube_str(&text), Err(BitmapError::InvalidLut(_))));
+}
+
+#[test]
+fn hald_images_are_read() {
+    // NOTE(erick): A level 2 Hald CLUT is 8x8 and holds a 4x4x4 cube.
+    let lut = Lut3d::identity(4).unwrap();
+    let mut hald = Bitmap::new_default(8, 8);
+    hald.map_pixels(|x, y, _| {
+        let index = (y * 8 + x) as usize;
+        let rgb = lut.lookup([(index % 4) as f32 / 3.0, (index / 4 % 4) as f32 / 3.0,
+                              (index / 16) as f32 / 3.0]);
+        BitmapPixel::rgb((rgb[0] * 255.0).round() as u8, (rgb[1] * 255.0).round() as u8,
+                         (rgb[2] * 255.0).round() as u8)
+    });
+
+    let from_hald = Lut3d::from_hald(&hald).unwrap();
+    assert_eq!(from_hald.size(), 4);
+    let mut image = common::gradient(6, 6);
+    image.apply_lut3d(&from_hald);
+    assert!(image.approx_eq(&common::gradient(6, 6), 1));
+
+    assert!(Lut3d::from_hald(&Bitmap::new_default(8, 4)).is_err());
+}
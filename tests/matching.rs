@@ -0,0 +1,89 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+use common::size_of;
+
+fn texture(width: i32, height: i32) -> Bitmap {
+    let mut result = Bitmap::new_default(width, height);
+    result.map_pixels(|x, y, _| {
+        let value = ((x * 73 + y * 151) ^ (x * y * 7)) as u8;
+        BitmapPixel::rgb(value, value, value)
+    });
+
+    result
+}
+
+#[test]
+fn translation_is_found() {
+    let image = texture(64, 48);
+    let mut shifted = texture(64, 48);
+    shifted.shift(-5, 3, BitmapPixel::black());
+
+    assert_eq!(image.estimate_translation(&shifted, 16), (5, -3));
+}
+
+#[test]
+fn huge_max_shifts_are_clamped_to_the_image() {
+    let image = texture(40, 32);
+    let mut shifted = texture(40, 32);
+    shifted.shift(2, 1, BitmapPixel::black());
+
+    // NOTE(erick): Without the clamp the coarse search alone would try
+    // billions of shifts.
+    assert_eq!(image.estimate_translation(&shifted, u32::MAX), (-2, -1));
+}
+
+fn naive_best_match(haystack: &Bitmap, needle: &Bitmap) -> (u32, u32, u64) {
+    let (width, height) = size_of(haystack);
+    let (needle_width, needle_height) = size_of(needle);
+
+    let mut best = (0, 0, u64::MAX);
+    for y in 0 ..= height - needle_height {
+        for x in 0 ..= width - needle_width {
+            let mut sad = 0u64;
+            for needle_y in 0 .. needle_height {
+                for needle_x in 0 .. needle_width {
+                    let p0 = haystack.get_pixel(x + needle_x, y + needle_y);
+                    let p1 = needle.get_pixel(needle_x, needle_y);
+                    sad += (p0.red   as i32 - p1.red   as i32).unsigned_abs() as u64 +
+                        (p0.green as i32 - p1.green as i32).unsigned_abs() as u64 +
+                        (p0.blue  as i32 - p1.blue  as i32).unsigned_abs() as u64;
+                }
+            }
+            if sad < best.2 {
+                best = (x, y, sad);
+            }
+        }
+    }
+
+    best
+}
+
+#[test]
+fn subimages_are_found_exactly() {
+    let image = texture(48, 40);
+    let needle = image.crop_to_rect(17, 9, 8, 6).unwrap();
+
+    assert_eq!(image.find_subimage(&needle, 0.0), Some((17, 9, 0.0)));
+}
+
+#[test]
+fn approximate_matches_agree_with_a_full_search() {
+    let image = texture(40, 32);
+    let mut needle = image.crop_to_rect(21, 5, 7, 5).unwrap();
+    needle.map_pixels(|x, y, pixel| {
+        let noise = ((x * 5 + y * 3) % 7) as u8;
+        BitmapPixel::rgb(pixel.red.saturating_add(noise), pixel.green, pixel.blue.saturating_sub(noise))
+    });
+
+    let (x, y, sad) = naive_best_match(&image, &needle);
+    let max_sad = (7 * 5 * 3 * 255) as f32;
+    let (found_x, found_y, score) = image.find_subimage(&needle, 1.0).unwrap();
+
+    assert_eq!((found_x, found_y), (x, y));
+    assert!((score - sad as f32 / max_sad).abs() < 1e-6);
+    assert_eq!(image.find_subimage(&needle, score * 0.5), None);
+}
@@ -0,0 +1,88 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn solid(width: i32, height: i32, color: BitmapPixel) -> Bitmap {
+    let mut result = Bitmap::new_default(width, height);
+    result.map_pixels(|_, _, _| color);
+
+    result
+}
+
+fn size_of(image: &Bitmap) -> (u32, u32) {
+    (image.info_header.image_width as u32, image.info_header.image_height as u32)
+}
+
+// NOTE(erick): One character per pixel, 'r', 'g', 'b' or '.' for anything
+// else, rows top to bottom.
+fn layout_of(image: &Bitmap) -> Vec<String> {
+    let (width, height) = size_of(image);
+    (0 .. height).map(|y| {
+        (0 .. width).map(|x| {
+            let pixel = image.get_pixel(x, y);
+            match (pixel.red, pixel.green, pixel.blue) {
+                (0xff, 0, 0) => 'r',
+                (0, 0xff, 0) => 'g',
+                (0, 0, 0xff) => 'b',
+                _            => '.',
+            }
+        }).collect()
+    }).collect()
+}
+
+fn red() -> BitmapPixel { BitmapPixel::rgb(0xff, 0, 0) }
+fn green() -> BitmapPixel { BitmapPixel::rgb(0, 0xff, 0) }
+fn blue() -> BitmapPixel { BitmapPixel::rgb(0, 0, 0xff) }
+
+#[test]
+fn default_merges_are_top_left_aligned_and_transparent() {
+    let image0 = solid(2, 3, red());
+    let image1 = solid(1, 1, green());
+
+    let merged = Bitmap::merge_horizontally(&image0, &image1);
+    assert_eq!(layout_of(&merged), vec!["rrg", "rr.", "rr."]);
+    assert_eq!(merged.get_pixel(2, 2).alpha, 0);
+
+    let merged = Bitmap::merge_vertically(&image0, &image1);
+    assert_eq!(layout_of(&merged), vec!["rr", "rr", "rr", "g."]);
+}
+
+#[test]
+fn merges_align_the_smaller_image() {
+    let image0 = solid(2, 3, red());
+    let image1 = solid(1, 1, green());
+
+    let center = MergeOptions { alignment : Alignment::Center, background : blue() };
+    let end    = MergeOptions { alignment : Alignment::End, ..MergeOptions::default() };
+
+    let merged = Bitmap::merge_horizontally_with(&image0, &image1, &center);
+    assert_eq!(layout_of(&merged), vec!["rrb", "rrg", "rrb"]);
+
+    let merged = Bitmap::merge_vertically_with(&image0, &image1, &end);
+    assert_eq!(layout_of(&merged), vec!["rr", "rr", "rr", ".g"]);
+}
+
+#[test]
+fn merge_builds_strips_and_grids() {
+    let image0 = solid(2, 2, red());
+    let image1 = solid(1, 1, green());
+    let image2 = solid(1, 2, red());
+    let images = [&image0, &image1, &image2];
+
+    let options = MergeOptions { alignment : Alignment::End, background : blue() };
+    let strip = Bitmap::merge(&images, MergeLayout::Horizontal, &options).unwrap();
+    assert_eq!(layout_of(&strip), vec!["rrbr", "rrgr"]);
+
+    let options = MergeOptions { alignment : Alignment::Center, background : blue() };
+    let strip = Bitmap::merge(&images, MergeLayout::Vertical, &options).unwrap();
+    assert_eq!(layout_of(&strip), vec!["rr", "rr", "gb", "rb", "rb"]);
+
+    let grid = Bitmap::merge(&images, MergeLayout::Grid(2), &MergeOptions::default()).unwrap();
+    assert_eq!(layout_of(&grid), vec!["rrg.", "rr..", "r...", "r..."]);
+
+    let grid = Bitmap::merge(&images, MergeLayout::Grid(5), &MergeOptions::default()).unwrap();
+    assert_eq!(size_of(&grid), (6, 2));
+
+    assert!(Bitmap::merge(&[], MergeLayout::Horizontal, &MergeOptions::default()).is_err());
+    assert!(Bitmap::merge(&images, MergeLayout::Grid(0), &MergeOptions::default()).is_err());
+}
@@ -0,0 +1,123 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+// NOTE(erick): 3x2, top row red, white, black and bottom row
+// green, blue, gray.
+fn test_image() -> Bitmap {
+    let colors = [BitmapPixel::rgb(0xff, 0, 0), BitmapPixel::rgb(0xff, 0xff, 0xff),
+                  BitmapPixel::rgb(0, 0, 0), BitmapPixel::rgb(0, 0xff, 0),
+                  BitmapPixel::rgb(0, 0, 0xff), BitmapPixel::rgb(0x80, 0x80, 0x80)];
+
+    let mut result = Bitmap::new(3, 2, 24, CompressionType::Uncompressed);
+    result.map_pixels(|x, y, _| colors[(y * 3 + x) as usize]);
+
+    result
+}
+
+fn luma_samples(image: &Bitmap) -> Vec<u8> {
+    (0 .. 6).map(|index| image.get_pixel(index % 3, index / 3).luma()).collect()
+}
+
+#[test]
+fn ppm_is_written_top_down() {
+    let mut ascii = Vec::new();
+    test_image().write_ppm(&mut ascii, NetpbmEncoding::Ascii).unwrap();
+    assert_eq!(String::from_utf8(ascii).unwrap(),
+               "P3\n3 2\n255\n255 0 0 255 255 255 0 0 0\n0 255 0 0 0 255 128 128 128\n");
+
+    let mut binary = Vec::new();
+    test_image().write_ppm(&mut binary, NetpbmEncoding::Binary).unwrap();
+    let mut expected = b"P6\n3 2\n255\n".to_vec();
+    expected.extend_from_slice(&[255, 0, 0, 255, 255, 255, 0, 0, 0,
+                                 0, 255, 0, 0, 0, 255, 128, 128, 128]);
+    assert_eq!(binary, expected);
+}
+
+#[test]
+fn pgm_stores_luma() {
+    let image = test_image();
+    let luma = luma_samples(&image);
+
+    let mut binary = Vec::new();
+    image.write_pgm(&mut binary, NetpbmEncoding::Binary).unwrap();
+    let mut expected = b"P5\n3 2\n255\n".to_vec();
+    expected.extend_from_slice(&luma);
+    assert_eq!(binary, expected);
+
+    let mut ascii = Vec::new();
+    image.write_pgm(&mut ascii, NetpbmEncoding::Ascii).unwrap();
+    let ascii = String::from_utf8(ascii).unwrap();
+    let values : Vec<u8> = ascii.split_whitespace().skip(4)
+        .map(|value| value.parse().unwrap())
+        .collect();
+    assert!(ascii.starts_with("P2\n3 2\n255\n"));
+    assert_eq!(values, luma);
+}
+
+#[test]
+fn pbm_marks_dark_pixels() {
+    let image = test_image();
+    let bits : Vec<u8> = luma_samples(&image).iter().map(|&luma| (luma < 128) as u8).collect();
+
+    let mut ascii = Vec::new();
+    image.write_pbm(&mut ascii, NetpbmEncoding::Ascii).unwrap();
+    let expected = format!("P1\n3 2\n{} {} {}\n{} {} {}\n",
+                           bits[0], bits[1], bits[2], bits[3], bits[4], bits[5]);
+    assert_eq!(String::from_utf8(ascii).unwrap(), expected);
+
+    let mut binary = Vec::new();
+    image.write_pbm(&mut binary, NetpbmEncoding::Binary).unwrap();
+    let pack = |row: &[u8]| row[0] << 7 | row[1] << 6 | row[2] << 5;
+    let mut expected = b"P4\n3 2\n".to_vec();
+    expected.extend_from_slice(&[pack(&bits[.. 3]), pack(&bits[3 ..])]);
+    assert_eq!(binary, expected);
+}
+
+#[test]
+fn long_ascii_rows_are_wrapped() {
+    let image = Bitmap::new(40, 1, 24, CompressionType::Uncompressed);
+
+    let mut ascii = Vec::new();
+    image.write_ppm(&mut ascii, NetpbmEncoding::Ascii).unwrap();
+    let ascii = String::from_utf8(ascii).unwrap();
+    assert!(ascii.lines().all(|line| line.len() <= 70));
+    assert_eq!(ascii.split_whitespace().count(), 4 + 40 * 3);
+}
+
+#[test]
+fn ppm_files_read_back() {
+    let image = test_image();
+
+    for &encoding in &[NetpbmEncoding::Ascii, NetpbmEncoding::Binary] {
+        let mut data = Vec::new();
+        image.write_ppm(&mut data, encoding).unwrap();
+
+        let decoded = Bitmap::from_ppm_reader(&mut data.as_slice()).unwrap();
+        assert_eq!(decoded.into_data(), image.into_data(), "{:?}", encoding);
+    }
+}
+
+#[test]
+fn ppm_comments_and_maxval_are_handled() {
+    let ascii = b"P3 # comment\n2 1\n# another one\n15\n15 0 0  0 7 15\n";
+    let image = Bitmap::from_ppm_data(ascii).unwrap();
+    let left  = image.get_pixel(0, 0);
+    let right = image.get_pixel(1, 0);
+    assert_eq!((left.red, left.green, left.blue), (255, 0, 0));
+    assert_eq!((right.red, right.green, right.blue), (0, 119, 255));
+
+    let mut binary = b"P6\n1 1\n65535\n".to_vec();
+    binary.extend_from_slice(&[0xff, 0xff, 0x80, 0x00, 0x00, 0x00]);
+    let pixel = Bitmap::from_ppm_data(&binary).unwrap().get_pixel(0, 0);
+    assert_eq!((pixel.red, pixel.green, pixel.blue), (255, 128, 0));
+}
+
+#[test]
+fn malformed_ppm_files_are_rejected() {
+    assert!(Bitmap::from_ppm_data(b"P5\n1 1\n255\n\0").is_err());
+    assert!(Bitmap::from_ppm_data(b"P3\n1 1\n255\n1 2").is_err());
+    assert!(Bitmap::from_ppm_data(b"P3\n1 1\n15\n1 2 16").is_err());
+    assert!(Bitmap::from_ppm_data(b"P6\n2 2\n255\n\0\0\0").is_err());
+    assert!(Bitmap::from_ppm_data(b"P6\n0 2\n255\n").is_err());
+}
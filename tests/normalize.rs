@@ -0,0 +1,67 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+use common::same_colors;
+
+fn test_image(bits_per_pixel: u16, compression: CompressionType) -> Bitmap {
+    let mut result = Bitmap::new(5, 3, bits_per_pixel, compression);
+    result.map_pixels(|x, y, _| BitmapPixel::rgb((x * 40) as u8, (y * 80) as u8, 0x33));
+
+    result
+}
+
+#[test]
+fn edited_sizes_are_recomputed_on_write() {
+    let mut image = test_image(24, CompressionType::Uncompressed);
+    let expected = image.into_data();
+
+    image.file_header.file_size = 7;
+    image.file_header.pixel_array_offset = 1000;
+    image.info_header.image_size = 3;
+    image.info_header.colors_used = 12;
+
+    assert_eq!(image.into_data(), expected);
+
+    image.normalize_headers();
+    assert_eq!(image.file_header.file_size as usize, expected.len());
+    assert_eq!(image.file_header.pixel_array_offset, 54);
+    assert_eq!(image.info_header.image_size, 3 * 16);
+    assert_eq!(image.info_header.colors_used, 0);
+}
+
+#[test]
+#[cfg(feature = "encode-palettized")]
+fn shorter_palettes_update_colors_used() {
+    let mut image = Bitmap::new(4, 2, 8, CompressionType::Uncompressed);
+    image.palette = Some(vec![BitmapPixel::black(), BitmapPixel::rgb(0xff, 0, 0)]);
+    image.map_pixels(|x, _, _| if x % 2 == 0 { BitmapPixel::black() }
+                               else { BitmapPixel::rgb(0xff, 0, 0) });
+
+    image.normalize_headers();
+    assert_eq!(image.info_header.colors_used, 2);
+    assert_eq!(image.file_header.pixel_array_offset, 54 + 2 * 4);
+
+    let decoded = Bitmap::from_data(image.into_data()).unwrap();
+    assert_eq!(decoded.palette.as_ref().map(|palette| palette.len()), Some(2));
+    assert!(same_colors(&image, &decoded));
+}
+
+#[test]
+fn missing_masks_get_defaults() {
+    for &bits_per_pixel in &[16, 32] {
+        let mut image = test_image(bits_per_pixel, CompressionType::BitFields);
+        let expected = Bitmap::from_data(image.into_data()).unwrap();
+
+        image.info_header.red_mask   = 0;
+        image.info_header.green_mask = 0;
+        image.info_header.blue_mask  = 0;
+        image.info_header.alpha_mask = 0;
+
+        let decoded = Bitmap::from_data(image.into_data()).unwrap();
+        assert!(decoded.info_header.red_mask != 0);
+        assert!(same_colors(&expected, &decoded), "{} bpp", bits_per_pixel);
+    }
+}
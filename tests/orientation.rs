@@ -0,0 +1,113 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+// NOTE(erick): Builds an image whose pixel at screen position (x, y)
+// has red = x and green = y, so we can tell where every pixel came from.
+fn coordinates_image(width: i32, height: i32, top_down: bool) -> Bitmap {
+    let mut result = Bitmap::new_default(width, height);
+    result.info_header.is_top_down = top_down;
+
+    for y in 0 .. height as u32 {
+        for x in 0 .. width as u32 {
+            result.set_pixel(x, y, BitmapPixel::rgb(x as u8, y as u8, 0));
+        }
+    }
+
+    result
+}
+
+fn assert_pixel(image: &Bitmap, x: u32, y: u32, red: u8, green: u8) {
+    let pixel = image.get_pixel(x, y);
+    assert_eq!((pixel.red, pixel.green), (red, green),
+               "pixel at ({}, {})", x, y);
+}
+
+#[test]
+fn storage_order_follows_orientation() {
+    let bottom_up = coordinates_image(2, 3, false);
+    let top_down  = coordinates_image(2, 3, true);
+
+    // The first stored row is the bottom one for bottom-up images.
+    assert_eq!(bottom_up.image_data[0].green, 2);
+    assert_eq!(top_down.image_data[0].green, 0);
+
+    for y in 0 .. 3 {
+        for x in 0 .. 2 {
+            assert_pixel(&bottom_up, x, y, x as u8, y as u8);
+            assert_pixel(&top_down, x, y, x as u8, y as u8);
+        }
+    }
+}
+
+#[test]
+fn crop_uses_screen_coordinates() {
+    for &top_down in &[false, true] {
+        let image = coordinates_image(5, 4, top_down);
+        let cropped = image.crop_to_rect(1, 2, 3, 2).unwrap();
+
+        assert_eq!(cropped.info_header.image_width, 3);
+        assert_eq!(cropped.info_header.image_height, 2);
+        for y in 0 .. 2 {
+            for x in 0 .. 3 {
+                assert_pixel(&cropped, x, y, x as u8 + 1, y as u8 + 2);
+            }
+        }
+    }
+}
+
+#[test]
+fn merge_horizontally_mixed_orientations() {
+    let left  = coordinates_image(2, 3, true);
+    let right = coordinates_image(3, 2, false);
+
+    let merged = Bitmap::merge_horizontally(&left, &right);
+    assert_eq!(merged.info_header.image_width, 5);
+    assert_eq!(merged.info_header.image_height, 3);
+
+    for y in 0 .. 3 {
+        for x in 0 .. 2 {
+            assert_pixel(&merged, x, y, x as u8, y as u8);
+        }
+    }
+    for y in 0 .. 2 {
+        for x in 0 .. 3 {
+            assert_pixel(&merged, x + 2, y, x as u8, y as u8);
+        }
+    }
+
+    // The area not covered by the shorter image is at the bottom.
+    assert_eq!(merged.get_pixel(4, 2).alpha, 0x00);
+}
+
+#[test]
+fn merge_vertically_mixed_orientations() {
+    let top    = coordinates_image(3, 2, false);
+    let bottom = coordinates_image(2, 2, true);
+
+    let merged = Bitmap::merge_vertically(&top, &bottom);
+    assert_eq!(merged.info_header.image_width, 3);
+    assert_eq!(merged.info_header.image_height, 4);
+
+    for y in 0 .. 2 {
+        for x in 0 .. 3 {
+            assert_pixel(&merged, x, y, x as u8, y as u8);
+        }
+        for x in 0 .. 2 {
+            assert_pixel(&merged, x, y + 2, x as u8, y as u8);
+        }
+    }
+}
+
+#[test]
+fn top_down_survives_a_round_trip() {
+    let image = coordinates_image(3, 2, true);
+    let decoded = Bitmap::from_data(image.into_data()).unwrap();
+
+    assert!(decoded.info_header.is_top_down);
+    for y in 0 .. 2 {
+        for x in 0 .. 3 {
+            assert_pixel(&decoded, x, y, x as u8, y as u8);
+        }
+    }
+}
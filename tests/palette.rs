@@ -0,0 +1,67 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+#[test]
+fn palettes_must_fit_the_bit_depth() {
+    assert!(BitmapPaletteBuilder::black_and_white().build(1).is_ok());
+    assert!(BitmapPaletteBuilder::grayscale(4).build(2).is_ok());
+    assert!(BitmapPaletteBuilder::grayscale(16).build(4).is_ok());
+    assert!(BitmapPaletteBuilder::grayscale(256).build(8).is_ok());
+
+    assert!(matches!(BitmapPaletteBuilder::grayscale(3).build(1),
+                     Err(BitmapError::InvalidOperation)));
+    assert!(matches!(BitmapPaletteBuilder::grayscale(17).build(4),
+                     Err(BitmapError::InvalidOperation)));
+    assert!(matches!(BitmapPaletteBuilder::web_safe().build(4),
+                     Err(BitmapError::InvalidOperation)));
+    assert!(matches!(BitmapPaletteBuilder::grayscale(257).build(8),
+                     Err(BitmapError::InvalidOperation)));
+}
+
+#[test]
+fn empty_palettes_are_rejected() {
+    assert!(BitmapPaletteBuilder::new().is_empty());
+    assert!(matches!(BitmapPaletteBuilder::new().build(8),
+                     Err(BitmapError::InvalidOperation)));
+    assert!(matches!(BitmapPaletteBuilder::grayscale(0).build(1),
+                     Err(BitmapError::InvalidOperation)));
+    assert!(matches!(BitmapPaletteBuilder::from_colors(&[]).build(4),
+                     Err(BitmapError::InvalidOperation)));
+}
+
+#[test]
+fn depths_without_a_palette_are_rejected() {
+    for &bits_per_pixel in &[16, 24, 32] {
+        assert!(matches!(BitmapPaletteBuilder::black_and_white().build(bits_per_pixel),
+                         Err(BitmapError::InvalidOperation)), "{} bpp", bits_per_pixel);
+    }
+}
+
+#[test]
+fn built_palettes_are_opaque_and_keep_their_order() {
+    let colors = [BitmapPixel::rgba(0x10, 0x20, 0x30, 0x00), BitmapPixel::rgba(0xff, 0, 0, 0x80)];
+    let palette = BitmapPaletteBuilder::from_colors(&colors)
+        .push(BitmapPixel::rgb(0, 0xff, 0))
+        .build(2).unwrap();
+
+    assert_eq!(palette, vec![BitmapPixel::rgb(0x10, 0x20, 0x30), BitmapPixel::rgb(0xff, 0, 0),
+                             BitmapPixel::rgb(0, 0xff, 0)]);
+}
+
+#[test]
+fn set_palette_checks_and_pads_the_palette() {
+    let mut image = Bitmap::new(4, 4, 4, CompressionType::Uncompressed);
+    let palette = BitmapPaletteBuilder::black_and_white().build(4).unwrap();
+    image.set_palette(palette).unwrap();
+
+    let stored = image.palette.as_ref().unwrap();
+    assert_eq!(stored.len(), 16);
+    assert!(stored[2 ..].iter().all(|&color| color == BitmapPixel::rgb(0xff, 0xff, 0xff)));
+
+    assert!(image.set_palette(Vec::new()).is_err());
+    assert!(image.set_palette(BitmapPaletteBuilder::grayscale(17).build(8).unwrap()).is_err());
+
+    let mut true_color = Bitmap::new(4, 4, 24, CompressionType::Uncompressed);
+    assert!(true_color.set_palette(vec![BitmapPixel::black()]).is_err());
+}
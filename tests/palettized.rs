@@ -0,0 +1,267 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+const BITS_PER_PIXEL : [u16; 4] = [1, 2, 4, 8];
+const MAX_WIDTH      : u32 = 17;
+const HEIGHT         : u32 = 3;
+
+fn test_palette(bits_per_pixel: u16) -> Vec<BitmapPixel> {
+    (0 .. palette_capacity(bits_per_pixel))
+        .map(|index| BitmapPixel::rgb(index as u8, 0xff - index as u8, 0x80))
+        .collect()
+}
+
+// NOTE(erick): Palette index of the pixel at screen position (x, y).
+fn test_index(x: u32, y: u32, bits_per_pixel: u16) -> usize {
+    (x as usize * 7 + y as usize * 3 + 1) % palette_capacity(bits_per_pixel)
+}
+
+fn padded_row_size(width: u32, bits_per_pixel: u16) -> usize {
+    (width as usize * bits_per_pixel as usize).div_ceil(32) * 4
+}
+
+// NOTE(erick): Packs a bottom-up image by hand, independently of the
+// crate's writer.
+fn handmade_file(width: u32, bits_per_pixel: u16) -> Vec<u8> {
+    let palette = test_palette(bits_per_pixel);
+    let row_size = padded_row_size(width, bits_per_pixel);
+    let pixel_offset = 14 + 40 + palette.len() * 4;
+    let file_size = pixel_offset + row_size * HEIGHT as usize;
+
+    let mut data = Vec::new();
+    data.extend_from_slice(b"BM");
+    data.extend_from_slice(&(file_size as u32).to_le_bytes());
+    data.extend_from_slice(&[0; 4]);
+    data.extend_from_slice(&(pixel_offset as u32).to_le_bytes());
+
+    data.extend_from_slice(&40u32.to_le_bytes());
+    data.extend_from_slice(&(width as i32).to_le_bytes());
+    data.extend_from_slice(&(HEIGHT as i32).to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&bits_per_pixel.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&((row_size * HEIGHT as usize) as u32).to_le_bytes());
+    data.extend_from_slice(&[0; 16]);
+
+    for color in &palette {
+        data.extend_from_slice(&[color.blue, color.green, color.red, 0]);
+    }
+
+    for row in 0 .. HEIGHT {
+        let y = HEIGHT - row - 1;
+        let mut row_data = vec![0u8; row_size];
+        for x in 0 .. width {
+            let bit_offset = x as usize * bits_per_pixel as usize;
+            let shift = 8 - bits_per_pixel as usize - bit_offset % 8;
+            row_data[bit_offset / 8] |= (test_index(x, y, bits_per_pixel) << shift) as u8;
+        }
+        data.extend_from_slice(&row_data);
+    }
+
+    data
+}
+
+fn test_image(width: u32, bits_per_pixel: u16) -> Bitmap {
+    let palette = test_palette(bits_per_pixel);
+
+    let mut result = Bitmap::new(width as i32, HEIGHT as i32,
+                                 bits_per_pixel, CompressionType::Uncompressed);
+    for y in 0 .. HEIGHT {
+        for x in 0 .. width {
+            result.set_pixel(x, y, palette[test_index(x, y, bits_per_pixel)]);
+        }
+    }
+    result.palette = Some(palette);
+
+    result
+}
+
+fn assert_test_pixels(image: &Bitmap, width: u32, bits_per_pixel: u16) {
+    let palette = test_palette(bits_per_pixel);
+
+    assert_eq!(image.image_data.len(), (width * HEIGHT) as usize);
+    for y in 0 .. HEIGHT {
+        for x in 0 .. width {
+            let expected = palette[test_index(x, y, bits_per_pixel)];
+            let pixel = image.get_pixel(x, y);
+            assert_eq!((pixel.red, pixel.green, pixel.blue),
+                       (expected.red, expected.green, expected.blue),
+                       "{} bpp, width {}, pixel ({}, {})", bits_per_pixel, width, x, y);
+        }
+    }
+}
+
+#[test]
+fn decodes_every_width() {
+    for &bits_per_pixel in &BITS_PER_PIXEL {
+        for width in 1 ..= MAX_WIDTH {
+            let image = Bitmap::from_data(handmade_file(width, bits_per_pixel)).unwrap();
+            assert_test_pixels(&image, width, bits_per_pixel);
+            assert!(image.decode_warnings().is_empty());
+        }
+    }
+}
+
+#[test]
+fn encodes_every_width() {
+    for &bits_per_pixel in &BITS_PER_PIXEL {
+        for width in 1 ..= MAX_WIDTH {
+            let data = test_image(width, bits_per_pixel).into_data();
+            assert_eq!(data, handmade_file(width, bits_per_pixel),
+                       "{} bpp, width {}", bits_per_pixel, width);
+        }
+    }
+}
+
+#[test]
+fn round_trips_every_width() {
+    for &bits_per_pixel in &BITS_PER_PIXEL {
+        for width in 1 ..= MAX_WIDTH {
+            let data = test_image(width, bits_per_pixel).into_data();
+            let image = Bitmap::from_data(data).unwrap();
+            assert_test_pixels(&image, width, bits_per_pixel);
+        }
+    }
+}
+
+#[test]
+fn streams_every_width() {
+    for &bits_per_pixel in &BITS_PER_PIXEL {
+        for width in 1 ..= MAX_WIDTH {
+            let data = handmade_file(width, bits_per_pixel);
+            let expected = Bitmap::from_data(data.clone()).unwrap();
+
+            let mut rows = 0;
+            Bitmap::decode_with(&mut data.as_slice(), &DecodeOptions::default(), |y, row| {
+                for x in 0 .. width {
+                    assert_eq!(row[x as usize].red, expected.get_pixel(x, y).red);
+                }
+                rows += 1;
+            }).unwrap();
+            assert_eq!(rows, HEIGHT);
+        }
+    }
+}
+
+// NOTE(erick): An 8 bpp file that only stores two colors (colors_used = 2)
+// followed by some gap bytes before the pixel data. Its last pixel uses
+// an index past the end of the palette.
+fn short_palette_file() -> Vec<u8> {
+    let gap = [0xaa; 6];
+    let pixel_offset = 14 + 40 + 2 * 4 + gap.len();
+    let file_size = pixel_offset + 4;
+
+    let mut data = Vec::new();
+    data.extend_from_slice(b"BM");
+    data.extend_from_slice(&(file_size as u32).to_le_bytes());
+    data.extend_from_slice(&[0; 4]);
+    data.extend_from_slice(&(pixel_offset as u32).to_le_bytes());
+
+    data.extend_from_slice(&40u32.to_le_bytes());
+    data.extend_from_slice(&4i32.to_le_bytes());
+    data.extend_from_slice(&1i32.to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&8u16.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&4u32.to_le_bytes());
+    data.extend_from_slice(&[0; 8]);
+    data.extend_from_slice(&2u32.to_le_bytes());
+    data.extend_from_slice(&[0; 4]);
+
+    data.extend_from_slice(&[0x00, 0x00, 0xff, 0]);
+    data.extend_from_slice(&[0xff, 0x00, 0x00, 0]);
+    data.extend_from_slice(&gap);
+    data.extend_from_slice(&[0, 1, 1, 3]);
+
+    data
+}
+
+#[test]
+fn short_palettes_ignore_gap_bytes() {
+    let image = Bitmap::from_data(short_palette_file()).unwrap();
+    assert_eq!(image.palette.as_ref().unwrap().len(), 2);
+
+    let reds : Vec<_> = (0 .. 4).map(|x| image.get_pixel(x, 0).red).collect();
+    let blues : Vec<_> = (0 .. 4).map(|x| image.get_pixel(x, 0).blue).collect();
+    assert_eq!(reds,  vec![0xff, 0x00, 0x00, 0x00]);
+    assert_eq!(blues, vec![0x00, 0xff, 0xff, 0x00]);
+
+    assert_eq!(image.decode_warnings(), &[DecodeWarning::PaletteIndexOutOfRange { count : 1 }]);
+}
+
+#[test]
+fn warnings_stay_with_the_decoded_image() {
+    let expected = [DecodeWarning::PaletteIndexOutOfRange { count : 1 }];
+
+    // NOTE(erick): Decoding on another thread (or decoding something
+    // else afterwards) doesn't change what an image reports.
+    let image = std::thread::spawn(|| Bitmap::from_data(short_palette_file()).unwrap())
+        .join().unwrap();
+    let clean = Bitmap::from_data(handmade_file(3, 8)).unwrap();
+    assert_eq!(image.decode_warnings(), &expected);
+    assert!(clean.decode_warnings().is_empty());
+    assert!(Bitmap::new_default(2, 2).decode_warnings().is_empty());
+
+    let region = Bitmap::decode_region(&short_palette_file(), 2, 0, 2, 1).unwrap();
+    assert_eq!(region.decode_warnings(), &expected);
+
+    let data = short_palette_file();
+    let (info_header, warnings) =
+        Bitmap::decode_with_warnings(&mut data.as_slice(), &DecodeOptions::default(), |_, _| {})
+        .unwrap();
+    assert_eq!(info_header.image_width, 4);
+    assert_eq!(warnings, expected);
+}
+
+// NOTE(erick): With every palette entry holding the same color the pixels
+// alone can't tell the indices apart, so only the kept indices can give
+// back the original file.
+#[test]
+fn repeated_palette_colors_keep_their_indices() {
+    for &bits_per_pixel in &BITS_PER_PIXEL {
+        for width in 1 ..= MAX_WIDTH {
+            let mut data = handmade_file(width, bits_per_pixel);
+            for entry in data[54 .. 54 + palette_capacity(bits_per_pixel) * 4].chunks_mut(4) {
+                entry.copy_from_slice(&[0x10, 0x20, 0x30, 0x00]);
+            }
+
+            let mut image = Bitmap::from_data(data.clone()).unwrap();
+            assert_eq!(image.into_data(), data, "{} bpp, width {}", bits_per_pixel, width);
+
+            // NOTE(erick): Edited pixels get a matching entry, the others
+            // keep their index.
+            let last_index = palette_capacity(bits_per_pixel) - 1;
+            image.palette.as_mut().unwrap()[last_index] = BitmapPixel::rgb(0xff, 0xff, 0xff);
+            image.set_pixel(0, 0, BitmapPixel::rgb(0xff, 0xff, 0xff));
+
+            let image = Bitmap::from_data(image.into_data()).unwrap();
+            let expected = Bitmap::from_data(data).unwrap();
+            assert_eq!(image.get_pixel(0, 0).red, 0xff);
+            for y in 0 .. HEIGHT {
+                for x in 0 .. width {
+                    if (x, y) != (0, 0) {
+                        assert_eq!(image.get_pixel(x, y).red, expected.get_pixel(x, y).red);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn images_without_a_palette_are_written_as_24_bit() {
+    for &bits_per_pixel in &BITS_PER_PIXEL {
+        let mut image = Bitmap::new(3, 2, bits_per_pixel, CompressionType::Uncompressed);
+        image.set_pixel(1, 1, BitmapPixel::rgb(0x10, 0x20, 0x30));
+
+        let decoded = Bitmap::from_data(image.into_data()).unwrap();
+        assert_eq!(decoded.info_header.bits_per_pixel, 24);
+        assert!(decoded.palette.is_none());
+        assert_eq!(decoded.get_pixel(1, 1), BitmapPixel::rgb(0x10, 0x20, 0x30));
+
+        image.palette = Some(Vec::new());
+        let decoded = Bitmap::from_data(image.into_data()).unwrap();
+        assert_eq!(decoded.info_header.bits_per_pixel, 24);
+    }
+}
@@ -0,0 +1,65 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+// NOTE(erick): Large enough to be decoded and encoded in several steps,
+// which run in parallel with the rayon feature.
+const WIDTH  : i32 = 1000;
+const HEIGHT : i32 = 300;
+
+#[test]
+fn large_images_round_trip() {
+    for &(bits_per_pixel, compression) in &[(16, CompressionType::BitFields),
+                                            (24, CompressionType::Uncompressed),
+                                            (32, CompressionType::BitFields)] {
+        let mut image = Bitmap::new(WIDTH, HEIGHT, bits_per_pixel, compression);
+        image.map_pixels(|x, y, _| BitmapPixel::rgb((x * 8) as u8, (y * 8) as u8, (x ^ y) as u8));
+
+        let data = image.into_data();
+        let decoded = Bitmap::from_data(data.clone()).unwrap();
+        assert_eq!(decoded.into_data(), data, "{} bpp", bits_per_pixel);
+    }
+}
+
+#[test]
+#[cfg(feature = "encode-palettized")]
+fn palette_warnings_add_up_across_steps() {
+    let mut image = Bitmap::new(WIDTH, HEIGHT, 8, CompressionType::Uncompressed);
+    image.palette = Some((0 .. 256).map(|value| BitmapPixel::rgb(value as u8, 0, 0)).collect());
+    image.map_pixels(|x, y, _| BitmapPixel::rgb(((x + y) % 4) as u8, 0, 0));
+    let mut data = image.into_data();
+
+    // NOTE(erick): Keep 3 palette entries, so every fourth index is out
+    // of range.
+    data[46] = 3;
+    let decoded = Bitmap::from_data(data).unwrap();
+
+    let expected = (WIDTH * HEIGHT / 4) as usize;
+    assert_eq!(decoded.decode_warnings(),
+               &[DecodeWarning::PaletteIndexOutOfRange { count : expected }]);
+    assert_eq!(decoded.get_pixel(1, 0).red, 1);
+    assert_eq!(decoded.get_pixel(1, (HEIGHT - 1) as u32).red, 0);
+}
+
+#[test]
+#[cfg(feature = "filters")]
+fn convolution_matches_per_pixel_reference() {
+    let mut image = Bitmap::new_default(64, 48);
+    image.map_pixels(|x, y, _| BitmapPixel::rgb((x * 4) as u8, (y * 5) as u8, (x * y) as u8));
+    let original = image.clone();
+
+    let kernel = [0.0, 1.0, 0.0,
+                  1.0, 0.0, 1.0,
+                  0.0, 1.0, 0.0];
+    image.convolve(&kernel, 3, 3, EdgeMode::Clamp).unwrap();
+
+    for y in 0 .. 48i64 {
+        for x in 0 .. 64i64 {
+            let at = |dx: i64, dy: i64| {
+                original.get_pixel((x + dx).clamp(0, 63) as u32, (y + dy).clamp(0, 47) as u32).red as u32
+            };
+            let expected = (at(0, -1) + at(-1, 0) + at(1, 0) + at(0, 1)).min(255);
+            assert_eq!(image.get_pixel(x as u32, y as u32).red as u32, expected);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn test_image() -> Bitmap {
+    let mut result = Bitmap::new_default(9, 6);
+    result.map_pixels(|x, y, _| BitmapPixel::rgb(x as u8 * 28, y as u8 * 40, 0x90));
+
+    result
+}
+
+#[test]
+fn steps_match_the_bitmap_methods() {
+    let pipeline = Pipeline::new()
+        .resize(6, 4, ResizeFilter::Bilinear)
+        .brightness(10)
+        .grayscale()
+        .mirror_horizontally();
+    assert_eq!(pipeline.steps().len(), 4);
+
+    let mut expected = test_image();
+    expected.resize(6, 4, ResizeFilter::Bilinear).unwrap();
+    expected.adjust_brightness(10);
+    expected.to_grayscale();
+    expected.mirror_horizontally();
+
+    let mut images = vec![test_image(), test_image()];
+    pipeline.apply_all(&mut images).unwrap();
+    for image in &images {
+        assert_eq!(image.into_data(), expected.into_data());
+    }
+}
+
+#[test]
+#[cfg(feature = "encode-palettized")]
+fn quantize_uses_the_pipeline_dither() {
+    let pipeline = Pipeline::new().quantize(4).dither(Dither::FloydSteinberg);
+
+    let mut image = test_image();
+    pipeline.apply(&mut image).unwrap();
+
+    let mut expected = test_image();
+    PaletteQuantizer::train(&[&expected], 4).unwrap()
+        .remap(&mut expected, Dither::FloydSteinberg);
+
+    assert_eq!(image.info_header.bits_per_pixel, 2);
+    assert_eq!(image.into_data(), expected.into_data());
+}
+
+#[test]
+fn failing_steps_are_reported() {
+    let pipeline = Pipeline::new().convert_to(12, CompressionType::Uncompressed);
+    assert!(pipeline.apply(&mut test_image()).is_err());
+}
+
+#[test]
+fn extreme_brightness_offsets_saturate() {
+    let mut image = test_image();
+    Pipeline::new().brightness(i16::MAX).apply(&mut image).unwrap();
+    assert!(image.image_data.iter().all(|pixel| *pixel == BitmapPixel::rgb(0xff, 0xff, 0xff)));
+
+    let mut image = test_image();
+    Pipeline::new().brightness(i16::MIN).apply(&mut image).unwrap();
+    assert!(image.image_data.iter().all(|pixel| *pixel == BitmapPixel::black()));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn extreme_brightness_offsets_from_json_saturate() {
+    let json = "{\"steps\": [{\"Brightness\": {\"offset\": 32767}}], \"dither\": \"None\"}";
+    let pipeline = Pipeline::from_json(json).unwrap();
+
+    let mut image = test_image();
+    pipeline.apply(&mut image).unwrap();
+    assert!(image.image_data.iter().all(|pixel| *pixel == BitmapPixel::rgb(0xff, 0xff, 0xff)));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn pipelines_round_trip_through_json() {
+    let pipeline = Pipeline::new()
+        .resize(3, 3, ResizeFilter::Lanczos3)
+        .contrast(1.5)
+        .convert_to(16, CompressionType::BitFields);
+
+    let decoded = Pipeline::from_json(&pipeline.to_json()).unwrap();
+    assert_eq!(decoded, pipeline);
+
+    assert!(Pipeline::from_json("{\"steps\": [\"Explode\"]}").is_err());
+}
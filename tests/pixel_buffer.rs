@@ -0,0 +1,74 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn test_image() -> Bitmap {
+    let mut result = Bitmap::new_default(7, 5);
+    result.map_pixels(|x, y, _| BitmapPixel::rgba((x * 30) as u8, (y * 50) as u8,
+                                                  (x + y) as u8, (0x80 + x) as u8));
+
+    result
+}
+
+fn same_rgba(a: &BitmapPixel, b: &BitmapPixel) -> bool {
+    a.same_color_as(b) && a.alpha == b.alpha
+}
+
+#[test]
+fn buffers_follow_the_declared_format() {
+    let image = test_image();
+
+    for &format in &[PixelFormat::Bgra8, PixelFormat::Rgba8, PixelFormat::Bgr8, PixelFormat::Rgb8] {
+        let buffer = image.to_pixel_buffer(format);
+        assert_eq!(buffer.as_bytes().len(), 7 * 5 * format.bytes_per_pixel());
+        assert_eq!(buffer.stride(), 7 * format.bytes_per_pixel());
+
+        let pixel = image.get_pixel(3, 1);
+        let bytes = &buffer.row(1)[3 * format.bytes_per_pixel() ..];
+        match format {
+            PixelFormat::Bgra8 => assert_eq!(&bytes[.. 4], &[pixel.blue, pixel.green, pixel.red, pixel.alpha]),
+            PixelFormat::Rgba8 => assert_eq!(&bytes[.. 4], &[pixel.red, pixel.green, pixel.blue, pixel.alpha]),
+            PixelFormat::Bgr8  => assert_eq!(&bytes[.. 3], &[pixel.blue, pixel.green, pixel.red]),
+            PixelFormat::Rgb8  => assert_eq!(&bytes[.. 3], &[pixel.red, pixel.green, pixel.blue]),
+        }
+
+        let back = Bitmap::from_pixel_buffer(&buffer);
+        for y in 0 .. 5 {
+            for x in 0 .. 7 {
+                assert!(back.get_pixel(x, y).same_color_as(&image.get_pixel(x, y)));
+            }
+        }
+    }
+
+    let rgba = image.to_pixel_buffer(PixelFormat::Rgba8);
+    assert_eq!(rgba.convert_to(PixelFormat::Bgra8), image.to_pixel_buffer(PixelFormat::Bgra8));
+    assert!(PixelBuffer::from_bytes(7, 5, PixelFormat::Rgb8, vec![0; 7 * 5 * 4]).is_err());
+}
+
+#[test]
+fn bmp_data_round_trips() {
+    let image = test_image();
+    let buffer = image.to_pixel_buffer(PixelFormat::Bgra8);
+
+    // NOTE(erick): Copied rows in both directions.
+    let data = buffer.to_bmp_data();
+    assert_eq!(PixelBuffer::from_bmp_data(&data).unwrap(), buffer);
+
+    let decoded = Bitmap::from_data(data).unwrap();
+    for y in 0 .. 5 {
+        for x in 0 .. 7 {
+            assert!(same_rgba(&decoded.get_pixel(x, y), &image.get_pixel(x, y)));
+        }
+    }
+
+    // NOTE(erick): Files in other layouts go through Bitmap.
+    for &(bits_per_pixel, compression) in &[(24, CompressionType::Uncompressed),
+                                            (32, CompressionType::Uncompressed),
+                                            (32, CompressionType::BitFields)] {
+        let mut converted = image.clone();
+        converted.convert_to(bits_per_pixel, compression).unwrap();
+
+        let from_file = PixelBuffer::from_bmp_data(&converted.into_data()).unwrap();
+        assert_eq!(from_file, converted.to_pixel_buffer(PixelFormat::Bgra8), "{} bpp", bits_per_pixel);
+    }
+}
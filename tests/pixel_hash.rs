@@ -0,0 +1,70 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn gradient() -> Bitmap {
+    let mut result = Bitmap::new_default(6, 5);
+    result.map_pixels(|x, y, _| BitmapPixel::rgb((x * 40) as u8, (y * 50) as u8, 0x10));
+
+    result
+}
+
+#[test]
+fn pixel_hash_is_stable() {
+    // NOTE(erick): Stored cache keys depend on these values.
+    assert_eq!(Bitmap::new_default(0, 0).pixel_hash(), 0xa8c7f832281a39c5);
+
+    let mut single = Bitmap::new_default(1, 1);
+    single.set_pixel(0, 0, BitmapPixel::rgb(0x12, 0x34, 0x56));
+    assert_eq!(single.pixel_hash(), 0x3ca17cceef0de6b0);
+}
+
+#[test]
+fn pixel_hash_ignores_the_encoding() {
+    let image = gradient();
+    let hash = image.pixel_hash();
+
+    let mut converted = gradient();
+    converted.convert_to(24, CompressionType::Uncompressed).unwrap();
+    assert_eq!(converted.pixel_hash(), hash);
+
+    let decoded = Bitmap::from_slice(&converted.into_data()).unwrap();
+    assert_eq!(decoded.pixel_hash(), hash);
+
+    let mut flipped = gradient();
+    flipped.info_header.is_top_down = true;
+    flipped.map_pixels(|x, y, _| image.get_pixel(x, y));
+    assert_eq!(flipped.pixel_hash(), hash);
+}
+
+#[cfg(feature = "encode-palettized")]
+#[test]
+fn pixel_hash_ignores_the_palette_order() {
+    let mut image = Bitmap::new_default(4, 1);
+    image.map_pixels(|x, _, _| if x % 2 == 0 { BitmapPixel::red() } else { BitmapPixel::blue() });
+    image.convert_to(8, CompressionType::Uncompressed).unwrap();
+    let hash = image.pixel_hash();
+
+    let mut reordered = image.clone();
+    reordered.palette.as_mut().unwrap().reverse();
+    let data = reordered.into_data();
+    let decoded = Bitmap::from_slice(&data).unwrap();
+    assert_eq!(decoded.pixel_hash(), hash);
+}
+
+#[test]
+fn pixel_hash_depends_on_pixels_and_size() {
+    let image = gradient();
+
+    let mut changed = gradient();
+    changed.set_pixel(5, 4, BitmapPixel::black());
+    assert_ne!(changed.pixel_hash(), image.pixel_hash());
+
+    let mut transparent = gradient();
+    let mut pixel = transparent.get_pixel(0, 0);
+    pixel.alpha = 0;
+    transparent.set_pixel(0, 0, pixel);
+    assert_ne!(transparent.pixel_hash(), image.pixel_hash());
+
+    assert_ne!(Bitmap::new_default(2, 3).pixel_hash(), Bitmap::new_default(3, 2).pixel_hash());
+}
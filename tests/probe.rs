@@ -0,0 +1,79 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+#[test]
+fn probe_reads_the_headers_only() {
+    let image = Bitmap::new(7, 3, 24, CompressionType::Uncompressed);
+    let data = image.into_data();
+
+    let mut reader = data.as_slice();
+    let info = Bitmap::probe(&mut reader).unwrap();
+    assert_eq!(info, BitmapInfo {
+        width          : 7,
+        height         : 3,
+        bits_per_pixel : 24,
+        compression    : CompressionType::Uncompressed,
+        palette_size   : 0,
+        is_top_down    : false,
+        decoded_size   : 7 * 3 * 4,
+    });
+
+    // NOTE(erick): The pixels were not touched.
+    assert_eq!(reader.len(), data.len() - 14 - 40);
+}
+
+#[test]
+#[cfg(feature = "encode-palettized")]
+fn probe_reports_palettes_and_orientation() {
+    let mut image = Bitmap::new(5, 5, 4, CompressionType::Uncompressed);
+    image.palette = Some(vec![BitmapPixel::black(); 16]);
+    image.info_header.is_top_down = true;
+
+    let info = Bitmap::probe(&mut image.into_data().as_slice()).unwrap();
+    assert_eq!(info.bits_per_pixel, 4);
+    assert_eq!(info.palette_size, 16);
+    assert!(info.is_top_down);
+}
+
+// NOTE(erick): Basic header with BitFields compression, which has its
+// masks right after it.
+#[test]
+fn masks_after_basic_headers_are_read() {
+    let mut dib = Vec::new();
+    dib.extend_from_slice(&40u32.to_le_bytes());
+    dib.extend_from_slice(&1i32.to_le_bytes());
+    dib.extend_from_slice(&1i32.to_le_bytes());
+    dib.extend_from_slice(&1u16.to_le_bytes());
+    dib.extend_from_slice(&32u16.to_le_bytes());
+    dib.extend_from_slice(&3u32.to_le_bytes());
+    dib.extend_from_slice(&4u32.to_le_bytes());
+    dib.extend_from_slice(&[0; 16]);
+    dib.extend_from_slice(&0x00ff0000u32.to_le_bytes());
+    dib.extend_from_slice(&0x0000ff00u32.to_le_bytes());
+    dib.extend_from_slice(&0x000000ffu32.to_le_bytes());
+    dib.extend_from_slice(&[0x30, 0x20, 0x10, 0x00]);
+
+    let data = Bitmap::from_dib_data(&dib).unwrap().into_data();
+
+    let info = Bitmap::probe(&mut data.as_slice()).unwrap();
+    assert_eq!(info.compression, CompressionType::BitFields);
+
+    let mut red = 0;
+    Bitmap::decode_with(&mut data.as_slice(), &DecodeOptions::default(), |_, row| {
+        red = row[0].red;
+    }).unwrap();
+    assert_eq!(red, 0x10);
+}
+
+#[test]
+fn probe_rejects_bad_headers() {
+    let mut data = Bitmap::new(2, 2, 24, CompressionType::Uncompressed).into_data();
+    assert!(Bitmap::probe(&mut &data[.. 20]).is_err());
+
+    data[14] = 12;
+    assert!(Bitmap::probe(&mut data.as_slice()).is_err());
+
+    data[0] = b'X';
+    assert!(Bitmap::probe(&mut data.as_slice()).is_err());
+}
@@ -0,0 +1,74 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+use common::same_pixels;
+
+fn test_image(width: i32, height: i32, bits_per_pixel: u16) -> Bitmap {
+    let mut result = Bitmap::new(width, height, bits_per_pixel, CompressionType::Uncompressed);
+    result.map_pixels(|x, y, _| BitmapPixel::rgb(x as u8, y as u8, (x ^ y) as u8));
+
+    result
+}
+
+#[test]
+fn progress_goes_up_to_the_height() {
+    // NOTE(erick): Big enough to take several steps.
+    let image = test_image(300, 400, 24);
+
+    let mut encode_reports = Vec::new();
+    let data = image.into_data_with_progress(&EncodeOptions::default(), |done, total| {
+        encode_reports.push((done, total));
+        true
+    }).unwrap();
+    assert_eq!(data, image.into_data());
+
+    let mut decode_reports = Vec::new();
+    let decoded = Bitmap::from_slice_with_progress(&data, &DecodeOptions::default(), |done, total| {
+        decode_reports.push((done, total));
+        true
+    }).unwrap();
+    assert!(same_pixels(&decoded, &image));
+
+    for reports in &[encode_reports, decode_reports] {
+        assert!(reports.len() > 1);
+        assert!(reports.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(reports.iter().all(|&(_, total)| total == 400));
+        assert_eq!(reports.last(), Some(&(400, 400)));
+    }
+}
+
+#[test]
+fn returning_false_cancels() {
+    let image = test_image(300, 400, 32);
+    let data = image.into_data();
+
+    let mut calls = 0;
+    let result = Bitmap::from_slice_with_progress(&data, &DecodeOptions::default(), |_, _| {
+        calls += 1;
+        calls < 2
+    });
+    assert!(matches!(result, Err(BitmapError::Cancelled)));
+    assert_eq!(calls, 2);
+
+    let result = image.into_data_with_progress(&EncodeOptions::default(), |_, _| false);
+    assert!(matches!(result, Err(BitmapError::Cancelled)));
+}
+
+#[test]
+fn truncated_files_still_finish() {
+    let mut data = test_image(300, 400, 24).into_data();
+    let new_length = data.len() / 2 + 5;
+    data.truncate(new_length);
+
+    let mut last_report = None;
+    let decoded = Bitmap::from_slice_with_progress(&data, &DecodeOptions::default(), |done, total| {
+        last_report = Some((done, total));
+        true
+    }).unwrap();
+
+    assert!(same_pixels(&decoded, &Bitmap::from_data(data).unwrap()));
+    assert_eq!(last_report, Some((400, 400)));
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "qoi")]
+
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+use common::assert_same_pixels;
+
+#[test]
+fn images_round_trip() {
+    // NOTE(erick): Runs longer than 62 pixels, small and large steps and
+    // repeated colors to go through every op.
+    let mut opaque = Bitmap::new(100, 4, 24, CompressionType::Uncompressed);
+    opaque.map_pixels(|x, y, _| match y {
+        0 => BitmapPixel::rgb(0x20, 0x40, 0x60),
+        1 => BitmapPixel::rgb(x as u8, x as u8 / 2, 0xff - x as u8),
+        2 => BitmapPixel::rgb((x * 37) as u8, (x * 91) as u8, (x * 13) as u8),
+        _ => if x % 2 == 0 { BitmapPixel::red() } else { BitmapPixel::blue() },
+    });
+
+    let mut translucent = opaque.clone();
+    translucent.map_pixels(|x, _, pixel| BitmapPixel::rgba(pixel.red, pixel.green,
+                                                           pixel.blue, (x * 5) as u8));
+
+    for image in &[opaque, translucent] {
+        let decoded = Bitmap::from_qoi(&image.to_qoi()).unwrap();
+        assert_same_pixels(&decoded, image);
+    }
+}
+
+#[test]
+fn encoding_follows_the_spec() {
+    let mut image = Bitmap::new(2, 1, 24, CompressionType::Uncompressed);
+    image.map_pixels(|_, _, _| BitmapPixel::red());
+
+    // NOTE(erick): Red is a wrapping -1 step from the initial black
+    // (QOI_OP_DIFF), then a run of one.
+    let mut expected = b"qoif".to_vec();
+    expected.extend_from_slice(&[0, 0, 0, 2, 0, 0, 0, 1, 3, 0, 0x5a, 0xc0]);
+    expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+    assert_eq!(image.to_qoi(), expected);
+}
+
+#[test]
+fn broken_files_are_rejected() {
+    let mut image = Bitmap::new(3, 3, 24, CompressionType::Uncompressed);
+    image.map_pixels(|x, y, _| BitmapPixel::rgb(x as u8 * 90, y as u8 * 90, 7));
+    let data = image.to_qoi();
+
+    let mut truncated = data[.. data.len() - 10].to_vec();
+    truncated.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+    assert!(Bitmap::from_qoi(&truncated).is_err());
+
+    let mut huge = data.clone();
+    huge[4 .. 8].copy_from_slice(&0x7fffffffu32.to_be_bytes());
+    assert!(Bitmap::from_qoi(&huge).is_err());
+
+    assert!(Bitmap::from_qoi(b"qoif").is_err());
+}
@@ -0,0 +1,92 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn test_image(top_down: bool) -> Bitmap {
+    let mut result = Bitmap::new(11, 7, 24, CompressionType::Uncompressed);
+    result.map_pixels(|x, y, _| BitmapPixel::rgb(x as u8 * 20, y as u8 * 30, 0x55));
+    result.info_header.is_top_down = top_down;
+
+    result
+}
+
+fn assert_region(data: &[u8], region: &Bitmap, x0: u32, y0: u32) {
+    let full = Bitmap::from_slice(data).unwrap();
+
+    for y in 0 .. region.info_header.image_height as u32 {
+        for x in 0 .. region.info_header.image_width as u32 {
+            let expected = full.get_pixel(x0 + x, y0 + y);
+            let pixel = region.get_pixel(x, y);
+            assert_eq!((pixel.red, pixel.green, pixel.blue),
+                       (expected.red, expected.green, expected.blue));
+        }
+    }
+}
+
+#[test]
+fn regions_match_the_full_decode() {
+    for &top_down in &[false, true] {
+        let mut images = vec![test_image(top_down)];
+        if cfg!(feature = "encode-palettized") {
+            let mut palettized = test_image(top_down);
+            palettized.convert_to(4, CompressionType::Uncompressed).unwrap();
+            images.push(palettized);
+        }
+
+        for image in &images {
+            let data = image.into_data();
+            let region = Bitmap::decode_region(&data, 3, 2, 5, 4).unwrap();
+            assert_eq!(region.info_header.image_width, 5);
+            assert_eq!(region.info_header.image_height, 4);
+            assert_region(&data, &region, 3, 2);
+        }
+    }
+}
+
+#[test]
+fn regions_outside_of_the_image_are_rejected() {
+    let data = test_image(false).into_data();
+
+    assert!(Bitmap::decode_region(&data, 8, 0, 4, 1).is_err());
+    assert!(Bitmap::decode_region(&data, 0, 7, 1, 1).is_err());
+    assert!(Bitmap::decode_region(&data, 0, 0, 0, 1).is_err());
+    assert!(Bitmap::decode_region(&data[.. 100], 0, 0, 11, 7).is_err());
+}
+
+#[test]
+fn regions_need_their_rows_in_the_file() {
+    let data = test_image(false).into_data();
+    let row_size = 36;
+
+    // NOTE(erick): The file is bottom-up, so the last rows of the file
+    // are the first rows on screen.
+    let truncated = &data[.. data.len() - 2 * row_size];
+    assert!(Bitmap::decode_region(truncated, 0, 2, 11, 5).is_ok());
+    assert!(matches!(Bitmap::decode_region(truncated, 0, 1, 11, 1),
+                     Err(BitmapError::TruncatedAt(_))));
+
+    // NOTE(erick): Only the headers of a large image, the region has to
+    // be rejected before its pixels are allocated.
+    let mut large = data[.. 54].to_vec();
+    large[18 .. 22].copy_from_slice(&8000i32.to_le_bytes());
+    large[22 .. 26].copy_from_slice(&8000i32.to_le_bytes());
+    assert!(matches!(Bitmap::decode_region(&large, 0, 0, 8000, 8000),
+                     Err(BitmapError::TruncatedAt(54))));
+}
+
+#[test]
+#[cfg(feature = "mmap")]
+fn memory_mapped_files_decode() {
+    let image = test_image(false);
+    let data = image.into_data();
+
+    let path = std::env::temp_dir().join(format!("bitmap_io_mmap_{}.bmp", std::process::id()));
+    std::fs::write(&path, &data).unwrap();
+
+    let mapped = Bitmap::from_mmap(&path).unwrap();
+    let region = Bitmap::region_from_mmap(&path, 1, 1, 2, 2).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(mapped.into_data(), data);
+    assert_region(&data, &region, 1, 1);
+}
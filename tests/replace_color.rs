@@ -0,0 +1,57 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn sprite() -> Bitmap {
+    let mut result = Bitmap::new_default(4, 2);
+    result.map_pixels(|x, y, _| match (x, y) {
+        (0, 0) => BitmapPixel::rgba(0xff, 0, 0, 0x80),
+        (1, 0) => BitmapPixel::rgb(0xfa, 4, 0),
+        (2, _) => BitmapPixel::rgb(0xe0, 0x20, 0),
+        _      => BitmapPixel::rgb(0, 0, 0xff),
+    });
+
+    result
+}
+
+#[test]
+fn replace_color_keeps_alpha() {
+    let mut image = sprite();
+    image.replace_color(BitmapPixel::rgb(0xff, 0, 0), BitmapPixel::rgb(0, 0xff, 0), 0);
+
+    let pixel = image.get_pixel(0, 0);
+    assert_eq!((pixel.red, pixel.green, pixel.blue, pixel.alpha), (0, 0xff, 0, 0x80));
+    assert!(image.get_pixel(1, 0).same_color_as(&BitmapPixel::rgb(0xfa, 4, 0)));
+}
+
+#[test]
+fn replace_color_uses_the_tolerance() {
+    let green = BitmapPixel::rgb(0, 0xff, 0);
+
+    let mut image = sprite();
+    image.replace_color(BitmapPixel::rgb(0xff, 0, 0), green, 8);
+    assert!(image.get_pixel(0, 0).same_color_as(&green));
+    assert!(image.get_pixel(1, 0).same_color_as(&green));
+    assert!(image.get_pixel(2, 0).same_color_as(&BitmapPixel::rgb(0xe0, 0x20, 0)));
+    assert!(image.get_pixel(3, 1).same_color_as(&BitmapPixel::rgb(0, 0, 0xff)));
+
+    let mut image = sprite();
+    image.replace_color(BitmapPixel::rgb(0xff, 0, 0), green, 60);
+    assert!(image.get_pixel(2, 1).same_color_as(&green));
+    assert_eq!(image.get_pixel(2, 1).alpha, 0xff);
+}
+
+#[cfg(feature = "encode-palettized")]
+#[test]
+fn replace_color_swaps_palette_entries() {
+    let mut image = sprite();
+    image.convert_to(4, CompressionType::Uncompressed).unwrap();
+
+    let yellow = BitmapPixel::rgb(0xff, 0xff, 0);
+    image.replace_color(BitmapPixel::rgb(0, 0, 0xff), yellow, 0);
+
+    let decoded = Bitmap::from_slice(&image.into_data()).unwrap();
+    assert!(decoded.get_pixel(3, 0).same_color_as(&yellow));
+    assert!(decoded.get_pixel(0, 1).same_color_as(&yellow));
+    assert!(decoded.get_pixel(2, 0).same_color_as(&BitmapPixel::rgb(0xe0, 0x20, 0)));
+}
@@ -0,0 +1,154 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+use common::size_of;
+
+#[test]
+fn resize_keeps_the_format() {
+    let mut image = Bitmap::new(4, 4, 16, CompressionType::BitFields);
+    image.info_header.red_mask   = 0x001f;
+    image.info_header.green_mask = 0x07e0;
+    image.info_header.blue_mask  = 0xf800;
+    image.info_header.pixels_per_meter_x = 2835;
+
+    image.resize(3, 5, ResizeFilter::Bilinear).unwrap();
+    assert_eq!(size_of(&image), (3, 5));
+    assert_eq!(image.info_header.bits_per_pixel, 16);
+    assert_eq!(image.info_header.compression_type, CompressionType::BitFields as u32);
+    assert_eq!(image.info_header.red_mask, 0x001f);
+    assert_eq!(image.info_header.pixels_per_meter_x, 2835);
+
+    assert!(image.resize(0, 5, ResizeFilter::Bilinear).is_err());
+}
+
+#[test]
+#[cfg(all(feature = "decode-rle", feature = "encode-palettized"))]
+fn resized_rle_images_are_written_uncompressed() {
+    let mut image = Bitmap::from_slice(&common::rle8_file()).unwrap();
+    assert_eq!(image.info_header.compression_type, CompressionType::Rle8 as u32);
+
+    image.resize(2, 2, ResizeFilter::NearestNeighbor).unwrap();
+    assert_eq!(image.info_header.compression_type, CompressionType::Uncompressed as u32);
+    assert_eq!(image.info_header.bits_per_pixel, 8);
+
+    let red   = BitmapPixel::rgb(0xff, 0x00, 0x00);
+    let white = BitmapPixel::rgb(0xff, 0xff, 0xff);
+
+    let decoded = Bitmap::from_slice(&image.into_data()).unwrap();
+    assert_eq!(size_of(&decoded), (2, 2));
+    assert!(decoded.get_pixel(0, 0).same_color_as(&white));
+    assert!(decoded.get_pixel(1, 0).same_color_as(&red));
+    assert!(decoded.get_pixel(0, 1).same_color_as(&red));
+    assert!(decoded.get_pixel(1, 1).same_color_as(&red));
+}
+
+fn checkerboard(size: i32) -> Bitmap {
+    let mut result = Bitmap::new_default(size, size);
+    result.map_pixels(|x, y, _| {
+        if (x + y) % 2 == 0 { BitmapPixel::white() } else { BitmapPixel::black() }
+    });
+
+    result
+}
+
+#[test]
+fn nearest_neighbor_picks_single_pixels() {
+    // NOTE(erick): Destination pixel i samples source pixel 2i + 1, which
+    // is always white on this checkerboard. Averaging would give gray.
+    let mut image = checkerboard(8);
+    image.resize(4, 4, ResizeFilter::NearestNeighbor).unwrap();
+    assert!(image.image_data.iter().all(|pixel| *pixel == BitmapPixel::white()));
+
+    let mut image = checkerboard(9);
+    image.resize(3, 3, ResizeFilter::NearestNeighbor).unwrap();
+    assert!(image.image_data.iter().all(|pixel| *pixel == BitmapPixel::white() ||
+                                        *pixel == BitmapPixel::black()));
+    assert_eq!(image.get_pixel(0, 0), checkerboard(9).get_pixel(1, 1));
+    assert_eq!(image.get_pixel(1, 0), checkerboard(9).get_pixel(4, 1));
+
+    // NOTE(erick): Enlarging repeats every pixel.
+    let mut image = checkerboard(2);
+    image.resize(4, 6, ResizeFilter::NearestNeighbor).unwrap();
+    for y in 0 .. 6 {
+        for x in 0 .. 4 {
+            assert_eq!(image.get_pixel(x, y), checkerboard(2).get_pixel(x / 2, y / 3));
+        }
+    }
+}
+
+fn filled(width: i32, height: i32, color: BitmapPixel) -> Bitmap {
+    let mut result = Bitmap::new_default(width, height);
+    result.map_pixels(|_, _, _| color);
+
+    result
+}
+
+#[test]
+fn thumbnails_keep_the_aspect_ratio() {
+    let wide = common::gradient(40, 20);
+    assert_eq!(size_of(&wide.thumbnail(10, 10, None).unwrap()), (10, 5));
+    assert_eq!(size_of(&wide.thumbnail(30, 5, None).unwrap()), (10, 5));
+
+    let tall = common::gradient(20, 40);
+    assert_eq!(size_of(&tall.thumbnail(10, 10, None).unwrap()), (5, 10));
+}
+
+#[test]
+fn thumbnails_are_not_enlarged() {
+    let image = common::gradient(4, 3);
+    let thumb = image.thumbnail(100, 100, None).unwrap();
+    common::assert_same_pixels(&thumb, &image);
+}
+
+#[test]
+fn letterboxed_thumbnails_are_centered() {
+    let blue = BitmapPixel::rgb(0, 0, 0xff);
+    let red  = BitmapPixel::rgb(0xff, 0, 0);
+
+    // NOTE(erick): 40x20 becomes 10x5, two rows of letterbox above and
+    // three below.
+    let thumb = filled(40, 20, blue).thumbnail(10, 10, Some(red)).unwrap();
+    assert_eq!(size_of(&thumb), (10, 10));
+    for y in 0 .. 10 {
+        let expected = if (2 .. 7).contains(&y) { blue } else { red };
+        for x in 0 .. 10 {
+            assert!(thumb.get_pixel(x, y).same_color_as(&expected), "({}, {})", x, y);
+        }
+    }
+
+    // NOTE(erick): Small images are centered as they are.
+    let thumb = filled(4, 2, blue).thumbnail(10, 10, Some(red)).unwrap();
+    assert_eq!(size_of(&thumb), (10, 10));
+    for y in 0 .. 10 {
+        for x in 0 .. 10 {
+            let inside = (3 .. 7).contains(&x) && (4 .. 6).contains(&y);
+            let expected = if inside { blue } else { red };
+            assert!(thumb.get_pixel(x, y).same_color_as(&expected), "({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn thumbnails_need_a_non_empty_box() {
+    let image = common::gradient(4, 3);
+    assert!(matches!(image.thumbnail(0, 10, None), Err(BitmapError::InvalidOperation)));
+    assert!(matches!(image.thumbnail(10, 0, None), Err(BitmapError::InvalidOperation)));
+    assert!(matches!(image.thumbnail(0, 10, Some(BitmapPixel::black())),
+                     Err(BitmapError::InvalidOperation)));
+}
+
+#[test]
+fn empty_images_cant_be_resized() {
+    for &(width, height) in &[(0, 0), (0, 3), (4, 0)] {
+        let mut image = Bitmap::new_default(width, height);
+        assert!(matches!(image.resize(2, 2, ResizeFilter::Bilinear),
+                         Err(BitmapError::InvalidOperation)), "{} x {}", width, height);
+        assert!(matches!(image.resize(2, 2, ResizeFilter::NearestNeighbor),
+                         Err(BitmapError::InvalidOperation)), "{} x {}", width, height);
+        assert!(matches!(image.thumbnail(8, 8, Some(BitmapPixel::black())),
+                         Err(BitmapError::InvalidOperation)), "{} x {}", width, height);
+    }
+}
@@ -0,0 +1,77 @@
+#![cfg(feature = "decode-rle")]
+
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+const RED   : [u8; 4] = [0x00, 0x00, 0xff, 0x00];
+const WHITE : [u8; 4] = [0xff, 0xff, 0xff, 0x00];
+const BLUE  : [u8; 4] = [0xff, 0x00, 0x00, 0x00];
+
+// NOTE(erick): The colors above as get_pixel returns them.
+const R : (u8, u8, u8) = (0xff, 0x00, 0x00);
+const W : (u8, u8, u8) = (0xff, 0xff, 0xff);
+const B : (u8, u8, u8) = (0x00, 0x00, 0xff);
+
+fn row(image: &Bitmap, y: u32) -> Vec<(u8, u8, u8)> {
+    (0 .. image.info_header.image_width as u32)
+        .map(|x| image.get_pixel(x, y))
+        .map(|pixel| (pixel.red, pixel.green, pixel.blue))
+        .collect()
+}
+
+#[test]
+fn rle8_runs_are_decoded() {
+    let image = Bitmap::from_slice(&common::rle8_file()).unwrap();
+
+    assert_eq!(image.info_header.compression_type, CompressionType::Rle8 as u32);
+    assert_eq!(row(&image, 0), vec![W, W, R, R]);
+    assert_eq!(row(&image, 1), vec![R, R, R, R]);
+    assert!(image.decode_warnings().is_empty());
+}
+
+#[test]
+fn rle8_absolute_mode_and_deltas_are_decoded() {
+    // NOTE(erick): Bottom row: three literal indices (padded to an even
+    // number of bytes) and a run of one. The delta then skips a pixel
+    // and moves to the top row, which is left with the first color.
+    let pixels = [0x00, 0x03, 0x02, 0x01, 0x02, 0x00,  0x01, 0x00,
+                  0x00, 0x00,
+                  0x00, 0x02, 0x01, 0x00,
+                  0x01, 0x02,
+                  0x00, 0x01];
+    let image = Bitmap::from_slice(&common::rle_file(4, 3, 8, &[RED, WHITE, BLUE], &pixels)).unwrap();
+
+    assert_eq!(row(&image, 2), vec![B, W, B, R]);
+    assert_eq!(row(&image, 1), vec![R, B, R, R]);
+    assert_eq!(row(&image, 0), vec![R, R, R, R]);
+}
+
+#[test]
+fn rle4_runs_alternate_their_two_indices() {
+    // NOTE(erick): A run of five alternating 1 and 2, then an absolute
+    // run of three nibbles (2, 0, 1) padded to two bytes.
+    let pixels = [0x05, 0x12,  0x00, 0x00,
+                  0x00, 0x03, 0x20, 0x10,  0x00, 0x00,
+                  0x00, 0x01];
+    let image = Bitmap::from_slice(&common::rle_file(5, 2, 4, &[RED, WHITE, BLUE], &pixels)).unwrap();
+
+    assert_eq!(image.info_header.compression_type, CompressionType::Rle4 as u32);
+    assert_eq!(row(&image, 1), vec![W, B, W, B, W]);
+    assert_eq!(row(&image, 0), vec![B, R, W, R, R]);
+}
+
+#[test]
+fn broken_rle_data_is_contained() {
+    // NOTE(erick): A run longer than the row and an index past the end
+    // of the palette, with no end of bitmap marker.
+    let pixels = [0x09, 0x01,  0x00, 0x00,
+                  0x02, 0x07];
+    let image = Bitmap::from_slice(&common::rle_file(3, 2, 8, &[RED, WHITE], &pixels)).unwrap();
+
+    assert_eq!(row(&image, 1), vec![W, W, W]);
+    assert_eq!(row(&image, 0), vec![(0, 0, 0), (0, 0, 0), R]);
+    assert_eq!(image.decode_warnings(), &[DecodeWarning::PaletteIndexOutOfRange { count : 2 }]);
+}
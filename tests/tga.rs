@@ -0,0 +1,92 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+mod common;
+
+use common::assert_same_pixels;
+
+fn tga_header(image_type: u8, color_map: (u16, u8), width: u16, height: u16,
+              pixel_depth: u8, descriptor: u8) -> Vec<u8> {
+    let mut data = vec![0, (color_map.0 > 0) as u8, image_type, 0, 0];
+    data.extend_from_slice(&color_map.0.to_le_bytes());
+    data.push(color_map.1);
+    data.extend_from_slice(&[0; 4]);
+    data.extend_from_slice(&width.to_le_bytes());
+    data.extend_from_slice(&height.to_le_bytes());
+    data.push(pixel_depth);
+    data.push(descriptor);
+
+    data
+}
+
+#[test]
+fn written_files_read_back() {
+    // NOTE(erick): Long runs and unique pixels to exercise both packets.
+    let mut opaque = Bitmap::new(150, 3, 24, CompressionType::Uncompressed);
+    opaque.map_pixels(|x, y, _| if x < 130 {
+        BitmapPixel::rgb(0x10, 0x20, y as u8)
+    } else {
+        BitmapPixel::rgb(x as u8, y as u8, 0x40)
+    });
+
+    let mut translucent = Bitmap::new_default(4, 2);
+    translucent.map_pixels(|x, y, _| BitmapPixel::rgba(x as u8 * 60, y as u8 * 90, 0x33, 0x80));
+
+    for image in &[opaque, translucent] {
+        for &compression in &[TgaCompression::Uncompressed, TgaCompression::Rle] {
+            let mut data = Vec::new();
+            image.write_tga(&mut data, compression).unwrap();
+
+            let decoded = Bitmap::from_tga_reader(&mut data.as_slice()).unwrap();
+            assert_same_pixels(&decoded, image);
+        }
+    }
+}
+
+#[test]
+fn bottom_up_true_color_files_are_flipped() {
+    let mut data = tga_header(2, (0, 0), 2, 2, 24, 0);
+    data.extend_from_slice(&[0, 0, 0xff,  0, 0xff, 0]);   // bottom: red, green
+    data.extend_from_slice(&[0xff, 0, 0,  0xff, 0xff, 0xff]); // top: blue, white
+
+    let image = Bitmap::from_tga_data(&data).unwrap();
+    let colors : Vec<_> = [(0, 0), (1, 0), (0, 1), (1, 1)].iter()
+        .map(|&(x, y)| {
+            let pixel = image.get_pixel(x, y);
+            (pixel.red, pixel.green, pixel.blue)
+        })
+        .collect();
+    assert_eq!(colors, vec![(0, 0, 0xff), (0xff, 0xff, 0xff), (0xff, 0, 0), (0, 0xff, 0)]);
+}
+
+#[test]
+fn color_mapped_and_grayscale_rle_files_are_read() {
+    let mut data = tga_header(9, (2, 24), 3, 1, 8, 0x20);
+    data.extend_from_slice(&[0, 0, 0xff,  0xff, 0, 0]);
+    data.extend_from_slice(&[0x81, 1, 0x00, 0]);
+
+    let image = Bitmap::from_tga_data(&data).unwrap();
+    let blues : Vec<_> = (0 .. 3).map(|x| image.get_pixel(x, 0).blue).collect();
+    assert_eq!(blues, vec![0xff, 0xff, 0]);
+
+    let mut data = tga_header(11, (0, 0), 2, 1, 8, 0x20);
+    data.extend_from_slice(&[0x81, 0x7f]);
+
+    let image = Bitmap::from_tga_data(&data).unwrap();
+    assert_eq!(image.get_pixel(1, 0).green, 0x7f);
+}
+
+#[test]
+fn broken_files_are_rejected() {
+    let mut data = tga_header(2, (0, 0), 2, 2, 24, 0);
+    data.extend_from_slice(&[0; 11]);
+    assert!(Bitmap::from_tga_data(&data).is_err());
+
+    let mut data = tga_header(10, (0, 0), 2, 2, 24, 0);
+    data.extend_from_slice(&[0x83, 1, 2]);
+    assert!(Bitmap::from_tga_data(&data).is_err());
+
+    assert!(Bitmap::from_tga_data(&tga_header(2, (0, 0), 2, 2, 12, 0)).is_err());
+    assert!(Bitmap::from_tga_data(&[0; 10]).is_err());
+}
@@ -0,0 +1,48 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn test_image(bits_per_pixel: u16, compression: CompressionType) -> Bitmap {
+    let mut result = Bitmap::new(5, 3, bits_per_pixel, compression);
+    result.map_pixels(|x, y, _| BitmapPixel::rgb((x * 40) as u8, (y * 80) as u8, 0x33));
+
+    result
+}
+
+const FORMATS : [(u16, CompressionType); 5] = [(16, CompressionType::Uncompressed),
+                                               (16, CompressionType::BitFields),
+                                               (24, CompressionType::Uncompressed),
+                                               (32, CompressionType::Uncompressed),
+                                               (32, CompressionType::BitFields)];
+
+#[test]
+fn trailing_bytes_are_kept_as_trailer() {
+    let junk = [0xde, 0xad, 0xbe, 0xef, 0x01, 0x02, 0x03];
+
+    for &(bits_per_pixel, compression) in &FORMATS {
+        let mut data = test_image(bits_per_pixel, compression).into_data();
+        data.extend_from_slice(&junk);
+
+        let image = Bitmap::from_data(data.clone()).unwrap();
+        assert_eq!(image.image_data.len(), 15, "{} bpp", bits_per_pixel);
+        assert_eq!(image.trailer, junk);
+        assert_eq!(image.into_data(), data);
+    }
+}
+
+#[test]
+fn truncated_files_leave_missing_rows_transparent() {
+    for &(bits_per_pixel, compression) in &FORMATS {
+        let mut data = test_image(bits_per_pixel, compression).into_data();
+        let complete = Bitmap::from_data(data.clone()).unwrap();
+        let new_length = data.len() - 3;
+        data.truncate(new_length);
+
+        // NOTE(erick): The images are bottom-up, so the top row is lost.
+        let image = Bitmap::from_data(data).unwrap();
+        assert_eq!(image.image_data.len(), 15, "{} bpp", bits_per_pixel);
+        assert_eq!(image.get_pixel(1, 0).alpha, 0x00);
+        assert_eq!(image.get_pixel(1, 1).green, complete.get_pixel(1, 1).green);
+        assert!(image.trailer.is_empty());
+    }
+}
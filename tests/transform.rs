@@ -0,0 +1,171 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn coordinates(width: i32, height: i32) -> Bitmap {
+    let mut result = Bitmap::new_default(width, height);
+    result.map_pixels(|x, y, _| BitmapPixel::rgb(x as u8 * 20, y as u8 * 20, 0x77));
+
+    result
+}
+
+fn background() -> BitmapPixel {
+    BitmapPixel::rgba(0, 0, 0, 0)
+}
+
+#[test]
+fn identity_keeps_the_image() {
+    let mut image = coordinates(5, 4);
+    image.transform([1.0, 0.0, 0.0, 0.0, 1.0, 0.0], ResizeFilter::Bilinear, background()).unwrap();
+
+    assert_eq!(image, coordinates(5, 4));
+}
+
+#[test]
+fn translation_and_scale_move_pixels() {
+    let original = coordinates(6, 6);
+
+    let mut translated = coordinates(6, 6);
+    translated.transform([1.0, 0.0, 2.0, 0.0, 1.0, -1.0],
+                         ResizeFilter::NearestNeighbor, background()).unwrap();
+    assert_eq!(translated.get_pixel(3, 1), original.get_pixel(1, 2));
+    assert_eq!(translated.get_pixel(1, 2), background());
+    assert_eq!(translated.get_pixel(4, 5), background());
+
+    let mut scaled = coordinates(6, 6);
+    scaled.transform([2.0, 0.0, 0.0, 0.0, 2.0, 0.0],
+                     ResizeFilter::NearestNeighbor, background()).unwrap();
+    assert_eq!(scaled.get_pixel(4, 2), original.get_pixel(2, 1));
+
+    let mut sheared = coordinates(6, 6);
+    sheared.transform([1.0, 1.0, 0.0, 0.0, 1.0, 0.0],
+                      ResizeFilter::NearestNeighbor, background()).unwrap();
+    assert_eq!(sheared.get_pixel(4, 3), original.get_pixel(1, 3));
+}
+
+#[test]
+fn rotate_turns_clockwise_around_the_center() {
+    let original = coordinates(5, 5);
+
+    let mut rotated = coordinates(5, 5);
+    rotated.rotate(90.0, ResizeFilter::NearestNeighbor, background());
+    for y in 0 .. 5 {
+        for x in 0 .. 5 {
+            assert_eq!(rotated.get_pixel(x, y), original.get_pixel(y, 4 - x));
+        }
+    }
+
+    let mut turned = coordinates(5, 5);
+    turned.rotate(180.0, ResizeFilter::NearestNeighbor, background());
+    turned.rotate(180.0, ResizeFilter::NearestNeighbor, background());
+    assert_eq!(turned, original);
+
+    let mut tilted = coordinates(5, 5);
+    tilted.rotate(45.0, ResizeFilter::Bilinear, background());
+    assert_eq!(tilted.get_pixel(0, 0), background());
+    assert!(tilted.get_pixel(2, 2).same_color_as(&original.get_pixel(2, 2)));
+}
+
+#[test]
+fn singular_matrices_are_rejected() {
+    let mut image = coordinates(4, 4);
+    assert!(image.transform([1.0, 2.0, 0.0, 2.0, 4.0, 0.0],
+                            ResizeFilter::Bilinear, background()).is_err());
+    assert!(image.transform([0.0; 6], ResizeFilter::Bilinear, background()).is_err());
+    assert_eq!(image, coordinates(4, 4));
+}
+
+// NOTE(erick): Smooth enough that the polar grid can represent it.
+fn soft_blobs(width: i32, height: i32) -> Bitmap {
+    let mut result = Bitmap::new_default(width, height);
+    result.map_pixels(|x, y, _| {
+        let (x, y) = (x as f32 / width as f32, y as f32 / height as f32);
+        BitmapPixel::rgba((128.0 + 100.0 * (x * 3.0).sin()) as u8,
+                          (128.0 + 100.0 * (y * 2.5).cos()) as u8,
+                          (128.0 + 80.0 * ((x + y) * 2.0).sin()) as u8, 0xff)
+    });
+
+    result
+}
+
+#[test]
+fn polar_round_trips_keep_the_image() {
+    let original = soft_blobs(64, 64);
+    let center = (31.5, 31.5);
+
+    let mut image = original.clone();
+    image.to_polar(center);
+    image.from_polar(center);
+
+    // NOTE(erick): Only the inscribed disc, near the corners the columns
+    // are more than a pixel apart and some samples fall outside.
+    let mut total_error = 0;
+    let mut n_pixels = 0;
+    for y in 0 .. 64 {
+        for x in 0 .. 64 {
+            if (x as f32 - center.0).hypot(y as f32 - center.1) > 30.0 {
+                continue;
+            }
+
+            let p0 = image.get_pixel(x, y);
+            let p1 = original.get_pixel(x, y);
+            let error = (p0.red as i32 - p1.red as i32).abs()
+                .max((p0.green as i32 - p1.green as i32).abs())
+                .max((p0.blue as i32 - p1.blue as i32).abs());
+
+            assert!(error <= 6, "({}, {}): {:?} {:?}", x, y, p0, p1);
+            total_error += error;
+            n_pixels += 1;
+        }
+    }
+    assert!(total_error as f32 / n_pixels as f32 <= 1.0, "total error {}", total_error);
+}
+
+#[test]
+fn polar_center_maps_to_row_zero() {
+    let red  = BitmapPixel::rgb(0xff, 0, 0);
+    let blue = BitmapPixel::rgb(0, 0, 0xff);
+    // NOTE(erick): A red disc of radius 2 in the middle of a blue image.
+    let mut image = Bitmap::new_default(33, 33);
+    image.map_pixels(|x, y, _| {
+        let (dx, dy) = (x as i32 - 16, y as i32 - 16);
+        if dx * dx + dy * dy <= 4 { red } else { blue }
+    });
+
+    image.to_polar((16.0, 16.0));
+
+    for x in 0 .. 33 {
+        assert!(image.get_pixel(x, 0).same_color_as(&red), "column {}", x);
+        assert!(!image.get_pixel(x, 32).same_color_as(&red), "column {}", x);
+    }
+}
+
+#[test]
+fn undistort_without_coefficients_keeps_the_image() {
+    let mut image = coordinates(7, 5);
+    image.undistort(0.0, 0.0, (3.0, 2.0));
+
+    assert_eq!(image, coordinates(7, 5));
+}
+
+#[test]
+fn undistort_keeps_the_center_fixed() {
+    for &(k1, k2) in &[(0.3, 0.1), (-0.25, 0.05), (1.0, 0.0)] {
+        let mut image = coordinates(7, 7);
+        image.undistort(k1, k2, (3.0, 3.0));
+
+        assert_eq!(image.get_pixel(3, 3), coordinates(7, 7).get_pixel(3, 3),
+                   "k1 {} k2 {}", k1, k2);
+    }
+
+    // NOTE(erick): A strong pincushion correction reads the corners from
+    // outside of the capture, a barrel one from inside of it.
+    let mut pincushion = coordinates(7, 7);
+    pincushion.undistort(1.0, 0.0, (3.0, 3.0));
+    assert_eq!(pincushion.get_pixel(0, 0).alpha, 0);
+
+    let mut barrel = coordinates(7, 7);
+    barrel.undistort(-0.25, 0.0, (3.0, 3.0));
+    assert_eq!(barrel.get_pixel(0, 0).alpha, 0xff);
+    assert!(barrel.get_pixel(0, 0).red > 0);
+}
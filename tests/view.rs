@@ -0,0 +1,84 @@
+extern crate bitmap_io;
+
+use bitmap_io::*;
+
+fn test_image() -> Bitmap {
+    let mut result = Bitmap::new(10, 8, 24, CompressionType::Uncompressed);
+    result.map_pixels(|x, y, _| BitmapPixel::rgb((x * 20) as u8, (y * 30) as u8, 0x40));
+
+    result
+}
+
+#[test]
+fn views_read_relative_to_their_corner() {
+    let image = test_image();
+    let view = image.view(2, 3, 5, 4).unwrap();
+
+    assert_eq!((view.width(), view.height(), view.stride()), (5, 4, 10));
+    assert!(view.get_pixel(0, 0).same_color_as(&image.get_pixel(2, 3)));
+    assert!(view.get_pixel(4, 3).same_color_as(&image.get_pixel(6, 6)));
+
+    let inner = view.view(1, 1, 2, 2).unwrap();
+    assert_eq!((inner.x(), inner.y()), (3, 4));
+    assert!(inner.get_pixel(1, 1).same_color_as(&image.get_pixel(4, 5)));
+
+    let cropped = image.crop_to_rect(2, 3, 5, 4).unwrap();
+    assert_eq!(view.into_data(), cropped.into_data());
+
+    assert!(image.view(6, 0, 5, 1).is_err());
+    assert!(view.view(0, 0, 6, 1).is_err());
+}
+
+#[test]
+fn mutable_views_write_into_the_parent() {
+    let mut image = test_image();
+    let red = BitmapPixel::rgb(0xff, 0, 0);
+
+    {
+        let mut view = image.view_mut(1, 1, 3, 2).unwrap();
+        view.fill(red);
+        view.set_pixel(2, 1, BitmapPixel::black());
+    }
+
+    for y in 0 .. 8 {
+        for x in 0 .. 10 {
+            let inside = (1 .. 4).contains(&x) && (1 .. 3).contains(&y);
+            let expected = if (x, y) == (3, 2) {
+                BitmapPixel::black()
+            } else if inside {
+                red
+            } else {
+                test_image().get_pixel(x, y)
+            };
+            assert!(image.get_pixel(x, y).same_color_as(&expected), "{} {}", x, y);
+        }
+    }
+}
+
+#[test]
+fn blits_are_clipped_to_the_view() {
+    let mut image = test_image();
+    let mut source = Bitmap::new(4, 4, 24, CompressionType::Uncompressed);
+    source.map_pixels(|_, _, _| BitmapPixel::rgb(1, 2, 3));
+
+    {
+        let mut view = image.view_mut(5, 5, 3, 3).unwrap();
+        view.blit(&source.as_view(), 1, -2);
+    }
+
+    for y in 0 .. 8 {
+        for x in 0 .. 10 {
+            let blitted = (6 .. 8).contains(&x) && (5 .. 7).contains(&y);
+            let pixel = image.get_pixel(x, y);
+            assert_eq!(pixel.same_color_as(&BitmapPixel::rgb(1, 2, 3)), blitted, "{} {}", x, y);
+        }
+    }
+
+    let mut view = image.view_mut(0, 0, 2, 2).unwrap();
+    let transparent = BitmapPixel::rgba(0xff, 0xff, 0xff, 0);
+    let mut overlay = Bitmap::new_default(2, 2);
+    overlay.map_pixels(|_, _, _| transparent);
+    let before = view.get_pixel(1, 1);
+    view.overlay(&overlay.as_view(), 0, 0, BlendMode::Normal);
+    assert!(view.get_pixel(1, 1).same_color_as(&before));
+}